@@ -0,0 +1,11 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        // protoc comes with the build, so `--features grpc` needs nothing
+        // installed.
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::compile_protos("proto/compass.proto").expect("proto/compass.proto");
+    }
+}
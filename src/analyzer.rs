@@ -1,27 +1,112 @@
-use serde_json::{json, Value};
-use tree_sitter::{Language, Parser, Query, QueryCursor, StreamingIterator};
+use crate::cancel::CancellationToken;
+use crate::hotspots;
+use crate::metrics::{self, LineCounts};
+use crate::pairing::Counterpart;
+use crate::position::Columns;
+use crate::ratings::Ratings;
+use crate::scope::{RuleScope, Scopes};
+use crate::snippet::{self, Context, SnippetOptions};
+use crate::structural::{self, PairSide};
+use crate::timing::{self, FileTimings, RuleTiming};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+use tree_sitter::{
+    Language, Node, ParseOptions, ParseState, Parser, Query, QueryCursor, QueryCursorOptions,
+    QueryCursorState, StreamingIterator, Tree,
+};
+use web_time::Instant;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AnalysisResult {
     pub rule_name: String,
     pub severity: Severity,
     pub message: String,
     pub line: usize,
+    /// 1-based, in UTF-8 bytes; see `columns` for other units.
     pub column: usize,
+    pub columns: Columns,
+    /// Line and columns just past the flagged text.
+    pub end_line: usize,
+    pub end_columns: Columns,
+    /// Byte range of the flagged text in the source.
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// The flagged text, cut to its first [`MAX_TEXT_LEN`] bytes so a
+    /// finding on a huge node doesn't hold a copy of it, then to the
+    /// analyzer's [`SnippetOptions`].
     pub text: String,
+    /// Lines around the finding, when `context_lines` asks for them.
+    pub context: Option<Context>,
     pub suggestion: Option<String>,
     pub score_impact: f64,
+    pub confidence: Confidence,
+    pub labels: BTreeMap<String, String>,
+    /// Snippets from the rule showing code it flags and the preferred form.
+    pub example_bad: Option<String>,
+    pub example_good: Option<String>,
+    /// Replacement for the flagged node, from a rule with a `fix`.
+    pub fix: Option<Fix>,
 }
 
-#[derive(Debug, Clone)]
+/// Longest flagged text, in bytes, kept in [`AnalysisResult::text`].
+pub const MAX_TEXT_LEN: usize = 1024;
+
+/// The first [`MAX_TEXT_LEN`] bytes of `text`, cut at a character boundary.
+fn excerpt(text: &str) -> &str {
+    if text.len() <= MAX_TEXT_LEN {
+        return text;
+    }
+    let mut end = MAX_TEXT_LEN;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Replaces the source bytes `start_byte..end_byte` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct Fix {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub replacement: String,
+}
+
+/// Ordered from least to most severe, so `severity >= Severity::Warning`
+/// reads as "at least a warning".
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
-    Error,
-    Warning,
-    Info,
+    #[default]
     Style,
+    Info,
+    Warning,
+    Error,
 }
 
 impl Severity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "info" => Some(Severity::Info),
+            "style" => Some(Severity::Style),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Style => "style",
+        }
+    }
+
     pub fn base_score_impact(&self) -> f64 {
         match self {
             Severity::Error => -3.0,
@@ -32,6 +117,270 @@ impl Severity {
     }
 }
 
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How likely a rule's matches are to be genuine problems.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Confidence {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl Confidence {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "high" => Some(Confidence::High),
+            "medium" => Some(Confidence::Medium),
+            "low" => Some(Confidence::Low),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Confidence::High => "high",
+            Confidence::Medium => "medium",
+            Confidence::Low => "low",
+        }
+    }
+
+    /// Score multiplier applied when confidence weighting is enabled.
+    pub fn score_factor(&self) -> f64 {
+        match self {
+            Confidence::High => 1.0,
+            Confidence::Medium => 0.7,
+            Confidence::Low => 0.4,
+        }
+    }
+}
+
+/// A node captured by a rule, with 1-based line and column positions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleCapture {
+    /// Index of the query pattern that matched; 0 for structural checks.
+    pub pattern: usize,
+    /// Capture name without the `@`, or the check name for structural checks.
+    pub capture: String,
+    pub kind: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub text: String,
+}
+
+/// Longest source text quoted in a syntax error message.
+const MAX_ERROR_TEXT: usize = 40;
+
+/// Where the parser could not make sense of a file, see
+/// [`structural::syntax_errors`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    /// `unexpected `...`` or `missing `...``.
+    pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// The syntax errors of `source_code`, in document order.
+pub fn syntax_errors(
+    source_code: &str,
+    language: &Language,
+) -> Result<Vec<SyntaxError>, Box<dyn std::error::Error>> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+    let tree = parse(&mut parser, source_code, &CancellationToken::default())?;
+    Ok(syntax_errors_in(tree.root_node(), source_code))
+}
+
+/// The syntax errors of the tree under `root`, parsed from `source_code`.
+pub fn syntax_errors_in(root: Node<'_>, source_code: &str) -> Vec<SyntaxError> {
+    structural::syntax_errors(root, source_code.as_bytes())
+        .into_iter()
+        .map(|node| {
+            let start = node.start_position();
+            let message = if node.is_missing() {
+                format!("missing `{}`", node.kind())
+            } else {
+                let first_line = source_code[node.byte_range()]
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim();
+                match first_line.char_indices().nth(MAX_ERROR_TEXT) {
+                    Some((cut, _)) => format!("unexpected `{}...`", &first_line[..cut]),
+                    None => format!("unexpected `{}`", first_line),
+                }
+            };
+            SyntaxError {
+                line: start.row + 1,
+                column: Columns::at(source_code, node.start_byte()).byte,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Parses `source_code` until `cancel` stops it; tree-sitter only gives
+/// up when cancelled, never on invalid syntax.
+pub(crate) fn parse(
+    parser: &mut Parser,
+    source_code: &str,
+    cancel: &CancellationToken,
+) -> Result<Tree, Box<dyn std::error::Error>> {
+    let bytes = source_code.as_bytes();
+    let mut progress = |_: &ParseState| cancel.is_cancelled();
+    let options = ParseOptions::new().progress_callback(&mut progress);
+    let tree = parser.parse_with_options(
+        &mut |offset, _| bytes.get(offset..).unwrap_or_default(),
+        None,
+        Some(options),
+    );
+    cancel.check()?;
+    tree.ok_or_else(|| "tree-sitter could not parse the source".into())
+}
+
+/// Pattern index, capture name, and node of one capture.
+type CapturedNode<'tree> = (usize, String, Node<'tree>);
+
+/// Findings, timings, line counts, and tree of one file.
+type CountedAnalysis = (Vec<AnalysisResult>, FileTimings, LineCounts, Tree);
+
+/// What [`CodeAnalyzer::analyze_with_tree`] returns: findings, score,
+/// timings, and the tree the rules ran on.
+pub type TreeAnalysis = (Vec<AnalysisResult>, CodeScore, FileTimings, Tree);
+
+/// Where a flagged node is: its bytes, and its 0-based first and last rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub start_row: usize,
+    pub end_row: usize,
+}
+
+impl Span {
+    pub fn of(node: Node<'_>) -> Self {
+        Span {
+            start_byte: node.start_byte(),
+            end_byte: node.end_byte(),
+            start_row: node.start_position().row,
+            end_row: node.end_position().row,
+        }
+    }
+}
+
+/// The node each match of `query` in the tree under `root` flags: its
+/// widest capture, which for the usual `(...) @whole` pattern is the
+/// matched construct, so captures used only by predicates aren't reported
+/// on their own. A node flagged by several matches is listed once. With a
+/// `byte_range`, only matches touching it. Matching stops early once
+/// `cancel` is cancelled, leaving the nodes incomplete.
+pub(crate) fn query_nodes<'tree>(
+    query: &Query,
+    root: Node<'tree>,
+    source_code: &str,
+    byte_range: Option<std::ops::Range<usize>>,
+    cancel: &CancellationToken,
+) -> Vec<CapturedNode<'tree>> {
+    query_captures(query, root, source_code, byte_range, cancel, true)
+}
+
+/// Every capture of `query`'s matches as [`query_nodes`] finds them, or
+/// only the widest of each match with `widest_only`.
+fn query_captures<'tree>(
+    query: &Query,
+    root: Node<'tree>,
+    source_code: &str,
+    byte_range: Option<std::ops::Range<usize>>,
+    cancel: &CancellationToken,
+    widest_only: bool,
+) -> Vec<CapturedNode<'tree>> {
+    let names = query.capture_names();
+    let mut cursor = QueryCursor::new();
+    if let Some(byte_range) = byte_range {
+        cursor.set_byte_range(byte_range);
+    }
+    let mut nodes = Vec::new();
+    let mut flagged = HashSet::new();
+    let mut progress = |_: &QueryCursorState| cancel.is_cancelled();
+    let options = QueryCursorOptions::new().progress_callback(&mut progress);
+    let mut matches = cursor.matches_with_options(query, root, source_code.as_bytes(), options);
+    while let Some(match_) = matches.next() {
+        let widest = match_
+            .captures
+            .iter()
+            .min_by_key(|capture| Reverse(capture.node.byte_range().len()));
+        let captures = match widest {
+            Some(widest) if widest_only && !flagged.insert(widest.node.id()) => &[],
+            Some(widest) if widest_only => std::slice::from_ref(widest),
+            _ => match_.captures,
+        };
+        nodes.extend(captures.iter().map(|capture| {
+            (
+                match_.pattern_index,
+                names[capture.index as usize].to_string(),
+                capture.node,
+            )
+        }));
+    }
+    nodes
+}
+
+/// A parsed counterpart, as pair checks see it.
+#[derive(Clone, Copy)]
+pub(crate) struct Pair<'a> {
+    root: Node<'a>,
+    source: &'a [u8],
+    side: PairSide,
+}
+
+pub(crate) fn parse_counterpart<'a>(
+    parser: &mut Parser,
+    counterpart: Option<&'a Counterpart>,
+) -> Option<(Tree, &'a Counterpart)> {
+    let counterpart = counterpart?;
+    let tree = parse(
+        parser,
+        &counterpart.source_code,
+        &CancellationToken::default(),
+    )
+    .ok()?;
+    Some((tree, counterpart))
+}
+
+pub(crate) fn pair<'a>(parsed: Option<&'a (Tree, &'a Counterpart)>) -> Option<Pair<'a>> {
+    parsed.map(|(tree, counterpart)| Pair {
+        root: tree.root_node(),
+        source: counterpart.source_code.as_bytes(),
+        side: counterpart.side,
+    })
+}
+
+/// Whether `rule` keeps a finding on `node`, given the file's `scopes`
+/// (computed when any rule is scoped).
+pub(crate) fn in_scope(rule: &AnalysisRule, scopes: Option<&Scopes>, node: Node<'_>) -> bool {
+    match (rule.scope, scopes) {
+        (Some(scope), Some(scopes)) => scopes.contains(scope, node.byte_range()),
+        _ => true,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AnalysisRule {
     pub name: String,
@@ -40,6 +389,35 @@ pub struct AnalysisRule {
     pub message_template: String,
     pub suggestion: Option<String>,
     pub weight_multiplier: f64,
+    pub confidence: Confidence,
+    pub labels: BTreeMap<String, String>,
+    /// Built-in structural check run instead of `query`, see
+    /// [`structural`].
+    pub check: Option<String>,
+    /// Regex matched against each comment instead of running `query`.
+    pub comment: Option<Regex>,
+    /// Modules whose imports are flagged instead of running `query`.
+    pub banned_imports: Vec<String>,
+    /// Functions with more parameters or `return`s than these are flagged
+    /// instead of running `query`.
+    pub max_parameters: Option<usize>,
+    pub max_returns: Option<usize>,
+    /// Findings on lines `git blame` dates more recently than this are
+    /// dropped, see [`blame`](crate::blame).
+    pub older_than: Option<Duration>,
+    /// Cap on the total score deduction from this rule within one file.
+    pub max_total_impact: Option<f64>,
+    /// Line counts of the files the rule applies to; it is not run on
+    /// shorter or longer files.
+    pub min_lines: Option<usize>,
+    pub max_lines: Option<usize>,
+    pub example_bad: Option<String>,
+    pub example_good: Option<String>,
+    /// Text replacing each flagged node, applied by `compass fix`.
+    pub fix: Option<String>,
+    /// The part of the file whose nodes the rule flags; `None` for all of
+    /// it.
+    pub scope: Option<RuleScope>,
 }
 
 impl AnalysisRule {
@@ -57,6 +435,21 @@ impl AnalysisRule {
             message_template: message,
             suggestion,
             weight_multiplier: 1.0,
+            confidence: Confidence::High,
+            labels: BTreeMap::new(),
+            check: None,
+            comment: None,
+            banned_imports: Vec::new(),
+            max_parameters: None,
+            max_returns: None,
+            older_than: None,
+            max_total_impact: None,
+            min_lines: None,
+            max_lines: None,
+            example_bad: None,
+            example_good: None,
+            fix: None,
+            scope: None,
         }
     }
 
@@ -64,9 +457,233 @@ impl AnalysisRule {
         self.weight_multiplier = weight;
         self
     }
+
+    pub fn with_confidence(mut self, confidence: Confidence) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub fn with_labels(mut self, labels: BTreeMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_check(mut self, check: Option<String>) -> Self {
+        self.check = check;
+        self
+    }
+
+    pub fn with_comment(mut self, comment: Option<Regex>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    pub fn with_banned_imports(mut self, banned_imports: Vec<String>) -> Self {
+        self.banned_imports = banned_imports;
+        self
+    }
+
+    pub fn with_function_limits(
+        mut self,
+        max_parameters: Option<usize>,
+        max_returns: Option<usize>,
+    ) -> Self {
+        self.max_parameters = max_parameters;
+        self.max_returns = max_returns;
+        self
+    }
+
+    /// Whether the rule runs its tree-sitter `query`, rather than a
+    /// structural check, a comment regex, or another built-in pass.
+    pub fn runs_query(&self) -> bool {
+        self.check.is_none()
+            && self.comment.is_none()
+            && self.banned_imports.is_empty()
+            && self.max_parameters.is_none()
+            && self.max_returns.is_none()
+    }
+
+    pub fn with_older_than(mut self, older_than: Option<Duration>) -> Self {
+        self.older_than = older_than;
+        self
+    }
+
+    /// What a comment rule's regex matched in `text`: its first group, or
+    /// else the whole match.
+    pub fn marker(&self, text: &str) -> Option<String> {
+        let captures = self.comment.as_ref()?.captures(text)?;
+        let matched = captures.get(1).or_else(|| captures.get(0))?;
+        Some(matched.as_str().to_string())
+    }
+
+    pub fn with_max_total_impact(mut self, max_total_impact: Option<f64>) -> Self {
+        self.max_total_impact = max_total_impact;
+        self
+    }
+
+    pub fn with_line_limits(mut self, min: Option<usize>, max: Option<usize>) -> Self {
+        self.min_lines = min;
+        self.max_lines = max;
+        self
+    }
+
+    /// Whether the rule runs on a file of `line_count` lines.
+    pub fn applies_to_lines(&self, line_count: usize) -> bool {
+        self.min_lines.is_none_or(|min| line_count >= min)
+            && self.max_lines.is_none_or(|max| line_count <= max)
+    }
+
+    pub fn with_examples(mut self, bad: Option<String>, good: Option<String>) -> Self {
+        self.example_bad = bad;
+        self.example_good = good;
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Option<String>) -> Self {
+        self.fix = fix;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: Option<RuleScope>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Every node the rule captures in `source_code`, in match order,
+    /// including captures of a match that don't become findings. Fails
+    /// if the query does not compile for `language`. Pair checks need the
+    /// file's `counterpart`.
+    pub fn captures(
+        &self,
+        source_code: &str,
+        language: &Language,
+        counterpart: Option<&Counterpart>,
+    ) -> Result<Vec<RuleCapture>, Box<dyn std::error::Error>> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        let cancel = CancellationToken::default();
+        let tree = parse(&mut parser, source_code, &cancel)?;
+        let parsed = parse_counterpart(&mut parser, counterpart);
+
+        let nodes = if self.runs_query() {
+            let query = Query::new(language, &self.query)?;
+            query_captures(&query, tree.root_node(), source_code, None, &cancel, false)
+        } else {
+            self.captured_nodes(
+                tree.root_node(),
+                source_code,
+                language,
+                pair(parsed.as_ref()),
+                &mut RuleTiming::default(),
+                &cancel,
+            )?
+        };
+        Ok(nodes
+            .into_iter()
+            .map(|(pattern, capture, node)| {
+                let (start, end) = (node.start_position(), node.end_position());
+                RuleCapture {
+                    pattern,
+                    capture,
+                    kind: node.kind().to_string(),
+                    start_line: start.row + 1,
+                    start_column: Columns::at(source_code, node.start_byte()).byte,
+                    end_line: end.row + 1,
+                    end_column: Columns::at(source_code, node.end_byte()).byte,
+                    text: source_code[node.byte_range()].to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// (pattern index, capture name, node) for each node the rule flags,
+    /// recording how long that took in `timing`. Queries stop matching
+    /// once `cancel` is cancelled.
+    pub(crate) fn captured_nodes<'tree>(
+        &self,
+        root: Node<'tree>,
+        source_code: &str,
+        language: &Language,
+        pair: Option<Pair<'_>>,
+        timing: &mut RuleTiming,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<CapturedNode<'tree>>, Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        if let Some(name) = &self.check {
+            let nodes = if let Some(check) = structural::lookup(name) {
+                check(root, source_code.as_bytes())
+            } else if let Some((side, check)) = structural::lookup_pair(name) {
+                match pair {
+                    Some(pair) if pair.side == side => {
+                        check(root, source_code.as_bytes(), pair.root, pair.source)
+                    }
+                    _ => Vec::new(),
+                }
+            } else {
+                return Err(format!("rule '{}': unknown check '{}'", self.name, name).into());
+            };
+            timing.matching = started.elapsed();
+            timing.matches = nodes.len();
+            return Ok(nodes
+                .into_iter()
+                .map(|node| (0, name.clone(), node))
+                .collect());
+        }
+        if let Some(pattern) = &self.comment {
+            let nodes: Vec<_> = structural::comments(root)
+                .into_iter()
+                .filter(|node| pattern.is_match(&source_code[node.byte_range()]))
+                .map(|node| (0, "comment".to_string(), node))
+                .collect();
+            timing.matching = started.elapsed();
+            timing.matches = nodes.len();
+            return Ok(nodes);
+        }
+        if !self.banned_imports.is_empty() {
+            // Once per module of a statement, not per name it imports.
+            let mut seen = HashSet::new();
+            let nodes: Vec<_> = structural::imports(root, source_code.as_bytes())
+                .into_iter()
+                .filter(|import| {
+                    self.banned_imports
+                        .iter()
+                        .any(|module| import.is_of(module))
+                })
+                .filter(|import| seen.insert((import.declaration.id(), import.path.clone())))
+                .map(|import| (0, "import".to_string(), import.node))
+                .collect();
+            timing.matching = started.elapsed();
+            timing.matches = nodes.len();
+            return Ok(nodes);
+        }
+        if self.max_parameters.is_some() || self.max_returns.is_some() {
+            let source = source_code.as_bytes();
+            let nodes: Vec<_> = hotspots::functions(root)
+                .into_iter()
+                .filter(|function| {
+                    self.max_parameters
+                        .is_some_and(|max| hotspots::parameter_count(*function, source) > max)
+                        || self
+                            .max_returns
+                            .is_some_and(|max| hotspots::return_count(*function) > max)
+                })
+                .map(|function| (0, "function".to_string(), function))
+                .collect();
+            timing.matching = started.elapsed();
+            timing.matches = nodes.len();
+            return Ok(nodes);
+        }
+
+        let query = Query::new(language, &self.query)?;
+        timing.compile = started.elapsed();
+        let nodes = query_nodes(&query, root, source_code, None, cancel);
+        timing.matching = started.elapsed() - timing.compile;
+        timing.matches = nodes.len();
+        Ok(nodes)
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CodeScore {
     pub overall_score: f64,
     pub max_score: f64,
@@ -74,9 +691,11 @@ pub struct CodeScore {
     pub breakdown: ScoreBreakdown,
     pub rating: String,
     pub summary: String,
+    /// Code, comment, and blank lines of the file.
+    pub lines: LineCounts,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ScoreBreakdown {
     pub errors: usize,
     pub warnings: usize,
@@ -87,21 +706,155 @@ pub struct ScoreBreakdown {
     pub info_deduction: f64,
     pub style_deduction: f64,
     pub size_bonus: f64,
+    /// Rules whose `max_total_impact` was reached, with the deduction they
+    /// would have caused without the cap.
+    pub capped_rules: BTreeMap<String, CappedRule>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CappedRule {
+    pub uncapped_impact: f64,
+    pub max_total_impact: f64,
+}
+
+/// How repeated findings of the same rule within one file are scored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatDamping {
+    /// Every finding deducts its full impact.
+    #[default]
+    None,
+    /// The n-th repeat deducts `ln(n) - ln(n - 1)` of its impact, so a rule
+    /// hit n times costs `1 + ln(n)` findings' worth in total.
+    Logarithmic,
+}
+
+impl RepeatDamping {
+    fn factor(&self, occurrence: usize) -> f64 {
+        match self {
+            RepeatDamping::None => 1.0,
+            RepeatDamping::Logarithmic if occurrence <= 1 => 1.0,
+            RepeatDamping::Logarithmic => (occurrence as f64).ln() - ((occurrence - 1) as f64).ln(),
+        }
+    }
 }
 
 pub struct CodeAnalyzer {
     rules: Vec<AnalysisRule>,
+    repeat_damping: RepeatDamping,
+    weight_by_confidence: bool,
+    score_min_severity: Severity,
+    counterpart: Option<Counterpart>,
+    snippets: SnippetOptions,
+    ratings: Ratings,
+    test_file: bool,
+    cancellation: CancellationToken,
+}
+
+impl Default for CodeAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CodeAnalyzer {
     pub fn new() -> Self {
-        CodeAnalyzer { rules: Vec::new() }
+        CodeAnalyzer {
+            rules: Vec::new(),
+            repeat_damping: RepeatDamping::None,
+            weight_by_confidence: false,
+            score_min_severity: Severity::Style,
+            counterpart: None,
+            snippets: SnippetOptions::default(),
+            ratings: Ratings::default(),
+            test_file: false,
+            cancellation: CancellationToken::default(),
+        }
+    }
+
+    pub fn with_confidence_weighting(mut self, enabled: bool) -> Self {
+        self.weight_by_confidence = enabled;
+        self
+    }
+
+    pub fn with_repeat_damping(mut self, repeat_damping: RepeatDamping) -> Self {
+        self.repeat_damping = repeat_damping;
+        self
+    }
+
+    /// Findings below `severity` are still reported but no longer lower
+    /// the score or count towards its issues.
+    pub fn with_score_min_severity(mut self, severity: Severity) -> Self {
+        self.score_min_severity = severity;
+        self
+    }
+
+    /// How much of the flagged code findings quote, and of the code around.
+    pub fn with_snippets(mut self, snippets: SnippetOptions) -> Self {
+        self.snippets = snippets;
+        self
+    }
+
+    /// Rating bands and summary templates of scores.
+    pub fn with_ratings(mut self, ratings: Ratings) -> Self {
+        self.ratings = ratings;
+        self
+    }
+
+    /// Stops analyses with [`Cancelled`](crate::cancel::Cancelled) once `cancellation` is cancelled
+    /// or times out.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// The other file of a C/C++ header/source pair, for pair checks.
+    pub(crate) fn with_counterpart(mut self, counterpart: Option<Counterpart>) -> Self {
+        self.counterpart = counterpart;
+        self
+    }
+
+    pub(crate) fn counterpart(&self) -> Option<&Counterpart> {
+        self.counterpart.as_ref()
+    }
+
+    /// Marks the analyzed file as test code as a whole, for rules scoped
+    /// to `test` or `non_test`.
+    pub fn with_test_file(mut self, test_file: bool) -> Self {
+        self.test_file = test_file;
+        self
+    }
+
+    /// The function and test ranges of a parsed file, when a rule is
+    /// scoped.
+    pub(crate) fn scopes(&self, root: Node<'_>, source_code: &str) -> Option<Scopes> {
+        self.rules
+            .iter()
+            .any(|rule| rule.scope.is_some())
+            .then(|| Scopes::of(root, source_code.as_bytes(), self.test_file))
+    }
+
+    /// Whether any rule is a pair check, which needs a counterpart.
+    pub fn has_pair_checks(&self) -> bool {
+        self.rules.iter().any(|rule| {
+            rule.check
+                .as_deref()
+                .is_some_and(|check| structural::lookup_pair(check).is_some())
+        })
     }
 
     pub fn add_rule(&mut self, rule: AnalysisRule) {
         self.rules.push(rule);
     }
 
+    pub(crate) fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn rules(&self) -> &[AnalysisRule] {
+        &self.rules
+    }
+
     pub fn has_rules(&self) -> bool {
         !self.rules.is_empty()
     }
@@ -111,40 +864,152 @@ impl CodeAnalyzer {
         source_code: &str,
         language: &Language,
     ) -> Result<Vec<AnalysisResult>, Box<dyn std::error::Error>> {
+        self.analyze_timed(source_code, language)
+            .map(|(results, _)| results)
+    }
+
+    /// [`analyze`](Self::analyze), and how long parsing and each rule took.
+    pub fn analyze_timed(
+        &self,
+        source_code: &str,
+        language: &Language,
+    ) -> Result<(Vec<AnalysisResult>, FileTimings), Box<dyn std::error::Error>> {
+        self.analyze_counted(source_code, language)
+            .map(|(results, timings, _, _)| (results, timings))
+    }
+
+    /// [`analyze_timed`](Self::analyze_timed), the file's line counts, and
+    /// its tree.
+    fn analyze_counted(
+        &self,
+        source_code: &str,
+        language: &Language,
+    ) -> Result<CountedAnalysis, Box<dyn std::error::Error>> {
         let mut parser = Parser::new();
         parser.set_language(language)?;
 
-        let tree = parser.parse(source_code, None).unwrap();
+        let started = Instant::now();
+        let tree = parse(&mut parser, source_code, &self.cancellation)?;
+        let mut timings = FileTimings {
+            parse: started.elapsed(),
+            rules: Vec::new(),
+        };
+        tracing::debug!(parse_ms = timing::millis(timings.parse), "parsed");
+        let parsed = parse_counterpart(&mut parser, self.counterpart.as_ref());
         let mut results = Vec::new();
+        let lines: Vec<&str> = if self.snippets.context_lines > 0 {
+            source_code.lines().collect()
+        } else {
+            Vec::new()
+        };
+        let line_count = source_code.lines().count();
+        let scopes = self.scopes(tree.root_node(), source_code);
 
-        for rule in &self.rules {
-            let query = Query::new(language, &rule.query)?;
-            let mut cursor = QueryCursor::new();
-
-            let mut matches = cursor.matches(&query, tree.root_node(), source_code.as_bytes());
-            while let Some(match_) = matches.next() {
-                for capture in match_.captures {
-                    let node = capture.node;
-                    let start = node.start_position();
-                    let text = node.utf8_text(source_code.as_bytes()).unwrap_or("");
-
-                    let score_impact = rule.severity.base_score_impact() * rule.weight_multiplier;
-
-                    results.push(AnalysisResult {
-                        rule_name: rule.name.clone(),
-                        severity: rule.severity.clone(),
-                        message: rule.message_template.clone(),
-                        line: start.row + 1,
-                        column: start.column + 1,
-                        text: text.to_string(),
-                        suggestion: rule.suggestion.clone(),
-                        score_impact,
-                    });
-                }
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to_lines(line_count))
+        {
+            let mut timing = RuleTiming {
+                rule: rule.name.clone(),
+                ..RuleTiming::default()
+            };
+            let nodes = rule.captured_nodes(
+                tree.root_node(),
+                source_code,
+                language,
+                pair(parsed.as_ref()),
+                &mut timing,
+                &self.cancellation,
+            )?;
+            // A cancelled query's matches are incomplete.
+            self.cancellation.check()?;
+            tracing::debug!(
+                rule = %timing.rule,
+                compile_ms = timing::millis(timing.compile),
+                match_ms = timing::millis(timing.matching),
+                matches = timing.matches,
+                "ran rule"
+            );
+            timings.rules.push(timing);
+
+            let nodes = nodes
+                .into_iter()
+                .filter(|(_, _, node)| in_scope(rule, scopes.as_ref(), *node));
+            for (occurrence, (_, _, node)) in nodes.enumerate() {
+                results.push(self.result(
+                    rule,
+                    source_code,
+                    &lines,
+                    Span::of(node),
+                    occurrence + 1,
+                ));
             }
         }
 
-        Ok(results)
+        let lines = metrics::line_counts(tree.root_node(), source_code);
+        Ok((results, timings, lines, tree))
+    }
+
+    /// What `rule` reports at `span`, its `occurrence`th (from 1) finding
+    /// in the file. `lines` are the source lines when findings take
+    /// context.
+    pub(crate) fn result(
+        &self,
+        rule: &AnalysisRule,
+        source_code: &str,
+        lines: &[&str],
+        span: Span,
+        occurrence: usize,
+    ) -> AnalysisResult {
+        let confidence_factor = if self.weight_by_confidence {
+            rule.confidence.score_factor()
+        } else {
+            1.0
+        };
+        let score_impact = rule.severity.base_score_impact()
+            * rule.weight_multiplier
+            * self.repeat_damping.factor(occurrence)
+            * confidence_factor;
+        let columns = Columns::at(source_code, span.start_byte);
+        let mut labels = rule.labels.clone();
+        if let Some(marker) = rule.marker(&source_code[span.start_byte..span.end_byte]) {
+            labels.insert("marker".to_string(), marker);
+        }
+        // Secrets stay out of reports and everything made from them.
+        let redacted = rule.check.as_deref().is_some_and(structural::redacts);
+        let text = &source_code[span.start_byte..span.end_byte];
+
+        AnalysisResult {
+            rule_name: rule.name.clone(),
+            severity: rule.severity.clone(),
+            message: rule.message_template.clone(),
+            line: span.start_row + 1,
+            column: columns.byte,
+            columns,
+            end_line: span.end_row + 1,
+            end_columns: Columns::at(source_code, span.end_byte),
+            start_byte: span.start_byte,
+            end_byte: span.end_byte,
+            text: if redacted {
+                snippet::redact(text)
+            } else {
+                self.snippets.extract(excerpt(text))
+            },
+            context: (self.snippets.context_lines > 0 && !redacted)
+                .then(|| snippet::context(lines, span.start_row + 1, self.snippets.context_lines)),
+            suggestion: rule.suggestion.clone(),
+            score_impact,
+            confidence: rule.confidence,
+            labels,
+            example_bad: rule.example_bad.clone(),
+            example_good: rule.example_good.clone(),
+            fix: rule.fix.as_ref().map(|replacement| Fix {
+                start_byte: span.start_byte,
+                end_byte: span.end_byte,
+                replacement: replacement.clone(),
+            }),
+        }
     }
 
     pub fn analyze_with_score(
@@ -152,44 +1017,96 @@ impl CodeAnalyzer {
         source_code: &str,
         language: &Language,
     ) -> Result<(Vec<AnalysisResult>, CodeScore), Box<dyn std::error::Error>> {
-        let results = self.analyze(source_code, language)?;
-        let score = self.calculate_score(&results, source_code);
-        Ok((results, score))
+        self.analyze_with_score_timed(source_code, language)
+            .map(|(results, score, _)| (results, score))
+    }
+
+    /// [`analyze_with_score`](Self::analyze_with_score), with
+    /// [`analyze_timed`](Self::analyze_timed)'s timings.
+    pub fn analyze_with_score_timed(
+        &self,
+        source_code: &str,
+        language: &Language,
+    ) -> Result<(Vec<AnalysisResult>, CodeScore, FileTimings), Box<dyn std::error::Error>> {
+        self.analyze_with_tree(source_code, language)
+            .map(|(results, score, timings, _)| (results, score, timings))
+    }
+
+    /// [`analyze_with_score_timed`](Self::analyze_with_score_timed), and the
+    /// tree the rules ran on, for measuring the file without parsing it
+    /// again.
+    pub fn analyze_with_tree(
+        &self,
+        source_code: &str,
+        language: &Language,
+    ) -> Result<TreeAnalysis, Box<dyn std::error::Error>> {
+        let (results, timings, lines, tree) = self.analyze_counted(source_code, language)?;
+        let score = self.calculate_score(&results, lines);
+        Ok((results, score, timings, tree))
     }
 
-    fn calculate_score(&self, results: &[AnalysisResult], source_code: &str) -> CodeScore {
+    /// Scores `results` for a file of `lines`; larger files, counted in
+    /// code lines, get more lenient deductions.
+    pub(crate) fn calculate_score(
+        &self,
+        results: &[AnalysisResult],
+        lines: LineCounts,
+    ) -> CodeScore {
+        let results: Vec<&AnalysisResult> = results
+            .iter()
+            .filter(|result| result.severity >= self.score_min_severity)
+            .collect();
         let base_score = 10.0;
-        let line_count = source_code.lines().count();
+        let line_count = lines.code;
 
-        let mut breakdown = ScoreBreakdown {
-            errors: 0,
-            warnings: 0,
-            info_issues: 0,
-            style_issues: 0,
-            error_deduction: 0.0,
-            warning_deduction: 0.0,
-            info_deduction: 0.0,
-            style_deduction: 0.0,
-            size_bonus: 0.0,
-        };
+        let mut breakdown = ScoreBreakdown::default();
+
+        // Scale each capped rule's findings so their deductions sum to the cap.
+        let mut rule_totals: BTreeMap<&str, f64> = BTreeMap::new();
+        for result in &results {
+            *rule_totals.entry(result.rule_name.as_str()).or_default() += result.score_impact.abs();
+        }
+        let mut rule_factors: BTreeMap<&str, f64> = BTreeMap::new();
+        for rule in &self.rules {
+            let (Some(cap), Some(&total)) =
+                (rule.max_total_impact, rule_totals.get(rule.name.as_str()))
+            else {
+                continue;
+            };
+            if total > cap {
+                rule_factors.insert(&rule.name, cap / total);
+                breakdown.capped_rules.insert(
+                    rule.name.clone(),
+                    CappedRule {
+                        uncapped_impact: total,
+                        max_total_impact: cap,
+                    },
+                );
+            }
+        }
 
-        for result in results {
+        for result in &results {
+            let factor = rule_factors
+                .get(result.rule_name.as_str())
+                .copied()
+                .unwrap_or(1.0);
+            let deduction = result.score_impact.abs() * factor;
             match result.severity {
                 Severity::Error => {
                     breakdown.errors += 1;
-                    breakdown.error_deduction += result.score_impact.abs();
+                    breakdown.error_deduction += deduction;
                 }
                 Severity::Warning => {
                     breakdown.warnings += 1;
-                    breakdown.warning_deduction += result.score_impact.abs();
+                    breakdown.warning_deduction += deduction;
                 }
                 Severity::Info => {
                     breakdown.info_issues += 1;
-                    breakdown.info_deduction += result.score_impact.abs();
+                    breakdown.info_deduction += deduction;
                 }
                 Severity::Style => {
                     breakdown.style_issues += 1;
-                    breakdown.style_deduction += result.score_impact.abs();
+                    breakdown.style_deduction += deduction;
                 }
             }
         }
@@ -214,7 +1131,7 @@ impl CodeAnalyzer {
         let overall_score = (base_score - adjusted_deduction).max(0.0);
         let rounded_score = (overall_score * 10.0).round() / 10.0;
 
-        let (rating, summary) = self.get_rating_and_summary(rounded_score, &breakdown);
+        let (rating, summary) = self.ratings.rate(rounded_score, &breakdown);
 
         CodeScore {
             overall_score: rounded_score,
@@ -223,69 +1140,13 @@ impl CodeAnalyzer {
             breakdown,
             rating,
             summary,
+            lines,
         }
     }
+}
 
-    fn get_rating_and_summary(&self, score: f64, breakdown: &ScoreBreakdown) -> (String, String) {
-        let rating = match score {
-            9.0..=10.0 => "Excellent",
-            7.5..=8.9 => "Good",
-            6.0..=7.4 => "Fair",
-            4.0..=5.9 => "Poor",
-            _ => "Critical",
-        }
-        .to_string();
-
-        let summary = if breakdown.errors > 0 {
-            format!(
-                "Code has {} critical errors that need immediate attention",
-                breakdown.errors
-            )
-        } else if breakdown.warnings > 5 {
-            "Multiple warnings detected - consider addressing them".to_string()
-        } else if breakdown.info_issues > 10 {
-            "Many minor issues found - good opportunity for cleanup".to_string()
-        } else if score >= 9.0 {
-            "Excellent code quality with minimal issues".to_string()
-        } else if score >= 7.5 {
-            "Good code quality with room for minor improvements".to_string()
-        } else {
-            "Code needs improvement in several areas".to_string()
-        };
-
-        (rating, summary)
-    }
-
-    pub fn format_score_as_json(&self, results: &[AnalysisResult], score: &CodeScore) -> Value {
-        json!({
-            "score": score.overall_score,
-            "max_score": score.max_score,
-            "rating": score.rating,
-            "summary": score.summary,
-            "total_issues": score.total_issues,
-            "breakdown": {
-                "errors": score.breakdown.errors,
-                "warnings": score.breakdown.warnings,
-                "info_issues": score.breakdown.info_issues,
-                "style_issues": score.breakdown.style_issues,
-                "deductions": {
-                    "from_errors": score.breakdown.error_deduction,
-                    "from_warnings": score.breakdown.warning_deduction,
-                    "from_info": score.breakdown.info_deduction,
-                    "from_style": score.breakdown.style_deduction
-                },
-                "size_bonus": score.breakdown.size_bonus
-            },
-            "issues": results.iter().map(|r| json!({
-                "rule": r.rule_name,
-                "severity": format!("{:?}", r.severity),
-                "message": r.message,
-                "line": r.line,
-                "column": r.column,
-                "text": r.text,
-                "suggestion": r.suggestion,
-                "score_impact": r.score_impact
-            })).collect::<Vec<_>>()
-        })
-    }
+/// Rating label and one-line summary for a score and its breakdown, with
+/// the default [`Ratings`].
+pub fn rating_and_summary(score: f64, breakdown: &ScoreBreakdown) -> (String, String) {
+    Ratings::default().rate(score, breakdown)
 }
@@ -0,0 +1,565 @@
+//! High-level entry point for embedding compass: analyze a file or a
+//! directory the way the `compass` binary does, without reimplementing
+//! language detection and config resolution.
+
+use crate::analyzer::CodeAnalyzer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::blame;
+use crate::cancel::CancellationToken;
+use crate::config::AnalyzerConfig;
+use crate::discovery;
+use crate::error::Error;
+use crate::language::{LanguageDefinition, LanguageRegistry};
+use crate::pairing;
+use crate::paths;
+use crate::progress::{FileCounter, Listener, Progress};
+use crate::ratings::Ratings;
+use crate::report::{project_report_with, FileReport, ProjectScore, ProjectWeighting};
+use crate::scope;
+use crate::source::{self, SourceFile, DEFAULT_MAX_FILE_SIZE};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How [`analyze_path`] finds languages and rules.
+pub struct AnalyzeOptions {
+    config: Option<AnalyzerConfig>,
+    registry: LanguageRegistry,
+    /// Absolute path to the in-memory contents that replace the file.
+    overlays: BTreeMap<PathBuf, String>,
+    progress: Option<Listener>,
+    cancellation: CancellationToken,
+    file_timeout: Option<Duration>,
+}
+
+impl Default for AnalyzeOptions {
+    /// The built-in languages, each with its built-in rules.
+    fn default() -> Self {
+        AnalyzeOptions {
+            config: None,
+            registry: LanguageRegistry::builtin(),
+            overlays: BTreeMap::new(),
+            progress: None,
+            cancellation: CancellationToken::default(),
+            file_timeout: None,
+        }
+    }
+}
+
+impl AnalyzeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `config` for every language instead of the built-in rules.
+    /// Grammars it declares are resolved against the working directory.
+    pub fn with_config(mut self, config: AnalyzerConfig) -> Result<Self, Error> {
+        self.registry
+            .register_from_config(&config, Path::new("."))
+            .map_err(Error::config)?;
+        self.config = Some(config);
+        Ok(self)
+    }
+
+    /// Like [`with_config`](Self::with_config), loading the config from a
+    /// file and resolving its grammars relative to it.
+    pub fn with_config_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let config = AnalyzerConfig::from_file(path).map_err(Error::config)?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        self.registry
+            .register_from_config(&config, base_dir)
+            .map_err(Error::config)?;
+        self.config = Some(config);
+        Ok(self)
+    }
+
+    pub fn with_registry(mut self, registry: LanguageRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Analyzes `contents` in place of the file at `path`, such as an
+    /// editor's unsaved buffer. The file need not exist yet; nothing is
+    /// written to disk.
+    pub fn with_overlay<P: AsRef<Path>>(mut self, path: P, contents: impl Into<String>) -> Self {
+        self.overlays
+            .insert(paths::absolute(path.as_ref()), contents.into());
+        self
+    }
+
+    /// Calls `listener` as each file of a directory starts, with how many
+    /// of the directory's files are done, to show progress.
+    pub fn with_progress(mut self, listener: impl Fn(&Progress) + 'static) -> Self {
+        self.progress = Some(Box::new(listener));
+        self
+    }
+
+    /// Stops the run with [`Error::Cancelled`] once `token` is cancelled or
+    /// times out.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    /// Gives each file `timeout` to be analyzed. In a directory, files that
+    /// take longer are skipped and listed in [`Report::timed_out`]; a single
+    /// file fails with [`Error::Cancelled`].
+    pub fn with_file_timeout(mut self, timeout: Duration) -> Self {
+        self.file_timeout = Some(timeout);
+        self
+    }
+
+    /// The token one file's analysis stops on.
+    fn file_cancellation(&self) -> CancellationToken {
+        match self.file_timeout {
+            Some(timeout) => self.cancellation.with_timeout(timeout),
+            None => self.cancellation.clone(),
+        }
+    }
+
+    /// Whether `file`, found under `root`, matches the config's `exclude`.
+    fn excludes(&self, root: &Path, file: &Path) -> bool {
+        let relative = file.strip_prefix(root).unwrap_or(file);
+        self.config
+            .as_ref()
+            .is_some_and(|config| config.excludes(relative))
+    }
+
+    pub fn registry(&self) -> &LanguageRegistry {
+        &self.registry
+    }
+
+    /// Project score weighting from the config's `[scoring]` section.
+    pub fn weighting(&self) -> ProjectWeighting {
+        self.config
+            .as_ref()
+            .map(|config| config.scoring.project_weighting)
+            .unwrap_or_default()
+    }
+
+    /// Rating bands and summaries from the config's `[ratings]` section.
+    pub fn ratings(&self) -> Ratings {
+        self.config
+            .as_ref()
+            .map(|config| config.ratings.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Rules for [`Compass::with_rules`], parsed from a config's TOML.
+#[derive(Debug)]
+pub struct RuleSet(AnalyzerConfig);
+
+impl RuleSet {
+    /// Parses the contents of a `compass.toml`. Configs it extends are
+    /// resolved against the working directory.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> Result<Self, Error> {
+        AnalyzerConfig::from_str(content)
+            .map(RuleSet)
+            .map_err(Error::config)
+    }
+
+    /// Loads the config at `path`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        AnalyzerConfig::from_file(path)
+            .map(RuleSet)
+            .map_err(Error::config)
+    }
+
+    /// The config the rules were parsed from.
+    pub fn config(&self) -> &AnalyzerConfig {
+        &self.0
+    }
+}
+
+impl From<AnalyzerConfig> for RuleSet {
+    fn from(config: AnalyzerConfig) -> Self {
+        RuleSet(config)
+    }
+}
+
+/// The embedding entry point: the languages and rules to use, and
+/// analysis of paths or in-memory sources with them.
+///
+/// ```no_run
+/// use compass::prelude::*;
+///
+/// let compass = Compass::new().with_config_file("compass.toml")?;
+/// let report = compass.analyze_path("src/")?;
+/// println!("{:?}", report.score.overall_score);
+/// # Ok::<(), compass::Error>(())
+/// ```
+#[derive(Default)]
+pub struct Compass {
+    options: AnalyzeOptions,
+}
+
+impl Compass {
+    /// The built-in languages, each with its built-in rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `rules` for every language instead of the built-in rules.
+    pub fn with_rules(self, rules: RuleSet) -> Result<Self, Error> {
+        Ok(Compass {
+            options: self.options.with_config(rules.0)?,
+        })
+    }
+
+    /// Like [`with_rules`](Self::with_rules), loading them from a config file.
+    pub fn with_config_file<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        Ok(Compass {
+            options: self.options.with_config_file(path)?,
+        })
+    }
+
+    pub fn with_registry(self, registry: LanguageRegistry) -> Self {
+        Compass {
+            options: self.options.with_registry(registry),
+        }
+    }
+
+    /// See [`AnalyzeOptions::with_overlay`].
+    pub fn with_overlay<P: AsRef<Path>>(self, path: P, contents: impl Into<String>) -> Self {
+        Compass {
+            options: self.options.with_overlay(path, contents),
+        }
+    }
+
+    /// See [`AnalyzeOptions::with_progress`].
+    pub fn with_progress(self, listener: impl Fn(&Progress) + 'static) -> Self {
+        Compass {
+            options: self.options.with_progress(listener),
+        }
+    }
+
+    /// See [`AnalyzeOptions::with_cancellation`].
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        Compass {
+            options: self.options.with_cancellation(token),
+        }
+    }
+
+    /// See [`AnalyzeOptions::with_file_timeout`].
+    pub fn with_file_timeout(self, timeout: Duration) -> Self {
+        Compass {
+            options: self.options.with_file_timeout(timeout),
+        }
+    }
+
+    pub fn options(&self) -> &AnalyzeOptions {
+        &self.options
+    }
+
+    /// See [`analyze_path`].
+    pub fn analyze_path<P: AsRef<Path>>(&self, path: P) -> Result<Report, Error> {
+        analyze_path(path, &self.options)
+    }
+
+    /// Analyzes `source` as if it lived at `label`, in the language its
+    /// extension selects. `None` when no enabled rule applies to it.
+    pub fn analyze_source(&self, label: &str, source: &str) -> Result<Option<FileReport>, Error> {
+        let language = self.options.registry.detect(label).ok_or_else(|| {
+            Error::UnsupportedFile(format!(
+                "unsupported file extension for '{}'. Supported extensions: {}",
+                label,
+                self.options.registry.extensions_label()
+            ))
+        })?;
+        analyze_source_until(
+            label,
+            source,
+            language,
+            self.options.config.as_ref(),
+            &self.options.overlays,
+            &self.options.file_cancellation(),
+        )
+    }
+}
+
+/// Every analyzed file of a run and the combined project score.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub files: Vec<FileReport>,
+    pub score: ProjectScore,
+    pub weighting: ProjectWeighting,
+    pub ratings: Ratings,
+    /// Files of a directory skipped for running past the file timeout.
+    pub timed_out: Vec<String>,
+}
+
+impl Report {
+    /// The aggregated JSON schema printed for directories.
+    pub fn to_json(&self) -> Value {
+        project_report_with(&self.files, &self.score)
+    }
+
+    /// Re-analyzes only the file at `path` after it changed, was created, or
+    /// was deleted (along with its C/C++ header or source counterpart), then
+    /// updates the project score from the cached reports of every other
+    /// file. Watchers call this instead of a full rescan.
+    pub fn update<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        options: &AnalyzeOptions,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        self.reanalyze(path, options)?;
+        // Pair checks of a C/C++ file depend on its header or source.
+        if let Some(side) = pairing::side_of(path) {
+            let partners: Vec<PathBuf> = self
+                .files
+                .iter()
+                .map(|file| PathBuf::from(&file.path))
+                .filter(|file| file.file_stem() == path.file_stem())
+                .filter(|file| pairing::side_of(file).is_some_and(|other| other != side))
+                .collect();
+            for partner in partners {
+                self.reanalyze(&partner, options)?;
+            }
+        }
+        self.score =
+            ProjectScore::from_files(&self.files, self.weighting).with_ratings(&self.ratings);
+        Ok(())
+    }
+
+    fn reanalyze(&mut self, path: &Path, options: &AnalyzeOptions) -> Result<(), Error> {
+        let key = paths::absolute(path);
+        let cached = self
+            .files
+            .iter()
+            .position(|file| paths::absolute(Path::new(&file.path)) == key);
+        let present = path.is_file() || options.overlays.contains_key(&key);
+        let report = if present && options.registry.detect(path).is_some() {
+            analyze_file(path, options, true)?
+        } else {
+            None
+        };
+
+        match (cached, report) {
+            (Some(index), Some(report)) => self.files[index] = report,
+            (Some(index), None) => {
+                self.files.remove(index);
+            }
+            (None, Some(report)) => {
+                let index = self.files.partition_point(|file| file.path < report.path);
+                self.files.insert(index, report);
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Analyzes a file, or every supported file under a directory.
+///
+/// A single file must have a supported extension and rules for its
+/// language and fit in `max_file_size`; in a directory, other files are
+/// skipped.
+pub fn analyze_path<P: AsRef<Path>>(path: P, options: &AnalyzeOptions) -> Result<Report, Error> {
+    let path = path.as_ref();
+    if let Some(config) = &options.config {
+        for requirement in &config.require_grammar {
+            options
+                .registry
+                .check_grammar(requirement)
+                .map_err(|e| Error::Config(format!("grammar requirement {}", e)))?;
+        }
+    }
+    let mut timed_out = Vec::new();
+    let files = if path.is_dir() {
+        let mut paths = discovery::collect_files(path, |file| {
+            options.registry.detect(file).is_some() && !options.excludes(path, file)
+        })?;
+        // Overlays for files not yet saved under the directory.
+        let root = paths::absolute(path);
+        for overlay in options.overlays.keys() {
+            if let Ok(relative) = overlay.strip_prefix(&root) {
+                let file = path.join(relative);
+                if options.registry.detect(&file).is_some() && !paths.contains(&file) {
+                    paths.push(file);
+                }
+            }
+        }
+        paths.sort();
+        tracing::info!(root = %path.display(), files = paths.len(), "discovered files");
+
+        let mut files = Vec::new();
+        let mut counter = FileCounter::new(paths.len());
+        for file in paths {
+            if let Some(listener) = &options.progress {
+                listener(&counter.start(&file.to_string_lossy()));
+            }
+            match analyze_file(&file, options, true) {
+                Ok(report) => files.extend(report),
+                // Only this file ran out of time; the run goes on.
+                Err(Error::Cancelled) if !options.cancellation.is_cancelled() => {
+                    tracing::warn!(path = %file.display(), "skipped file: analysis timed out");
+                    timed_out.push(file.to_string_lossy().into_owned());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        files
+    } else {
+        let report = analyze_file(path, options, false)?;
+        vec![report.ok_or_else(|| {
+            Error::Other(format!("no enabled rules apply to '{}'", path.display()))
+        })?]
+    };
+
+    let weighting = options.weighting();
+    let ratings = options.ratings();
+    Ok(Report {
+        score: ProjectScore::from_files(&files, weighting).with_ratings(&ratings),
+        files,
+        weighting,
+        ratings,
+        timed_out,
+    })
+}
+
+/// `scanned` files were found in a directory rather than named directly.
+fn analyze_file(
+    path: &Path,
+    options: &AnalyzeOptions,
+    scanned: bool,
+) -> Result<Option<FileReport>, Error> {
+    let language = options.registry.detect(path).ok_or_else(|| {
+        Error::UnsupportedFile(format!(
+            "unsupported file extension for '{}'. Supported extensions: {}",
+            path.display(),
+            options.registry.extensions_label()
+        ))
+    })?;
+    let source = match options.overlays.get(&paths::absolute(path)) {
+        Some(contents) => SourceFile {
+            text: contents.clone(),
+            invalid_at: None,
+            binary: false,
+        },
+        None => {
+            let max_file_size = options
+                .config
+                .as_ref()
+                .map_or(DEFAULT_MAX_FILE_SIZE, AnalyzerConfig::max_file_size);
+            if let Some(size) = source::oversized(path, max_file_size) {
+                if scanned {
+                    tracing::info!(path = %path.display(), size, "skipped file: over max_file_size");
+                    return Ok(None);
+                }
+                return Err(Error::Other(format!(
+                    "'{}' is {} bytes, over max_file_size ({} bytes)",
+                    path.display(),
+                    size,
+                    max_file_size
+                )));
+            }
+            source::read(path).map_err(|e| {
+                Error::Io(std::io::Error::new(
+                    e.kind(),
+                    format!("failed to read '{}': {}", path.display(), e),
+                ))
+            })?
+        }
+    };
+    let skips_undecodable = options
+        .config
+        .as_ref()
+        .is_some_and(|config| config.skip_undecodable);
+    if scanned && skips_undecodable && source.is_undecodable() {
+        tracing::info!(path = %path.display(), "skipped file: binary or not valid UTF-8");
+        return Ok(None);
+    }
+    let report = analyze_source_until(
+        &path.to_string_lossy(),
+        &source.text,
+        language,
+        options.config.as_ref(),
+        &options.overlays,
+        &options.file_cancellation(),
+    )?;
+    Ok(report.map(|mut report| {
+        report.results.splice(0..0, source.decoding_warning());
+        report
+    }))
+}
+
+/// Analyzes source text as if it lived at `label`. `None` when no enabled
+/// rule applies to the language.
+pub fn analyze_source(
+    label: &str,
+    source: &str,
+    language: &LanguageDefinition,
+    config: Option<&AnalyzerConfig>,
+) -> Result<Option<FileReport>, Error> {
+    analyze_source_until(
+        label,
+        source,
+        language,
+        config,
+        &BTreeMap::new(),
+        &CancellationToken::default(),
+    )
+}
+
+/// [`analyze_source`] with pair checks seeing `overlays`, failing with
+/// [`Error::Cancelled`] once `cancel` is.
+fn analyze_source_until(
+    label: &str,
+    source: &str,
+    language: &LanguageDefinition,
+    config: Option<&AnalyzerConfig>,
+    overlays: &BTreeMap<PathBuf, String>,
+    cancel: &CancellationToken,
+) -> Result<Option<FileReport>, Error> {
+    let _file = tracing::debug_span!("file", path = label).entered();
+    let analyzer =
+        analyzer_for(label, language, config, overlays)?.with_cancellation(cancel.clone());
+    if !analyzer.has_rules() {
+        tracing::debug!(language = %language.name, "skipped file: no enabled rules for its language");
+        return Ok(None);
+    }
+    let (results, score) = analyzer.analyze_with_score(source, &language.language)?;
+    // Browsers have no git to date findings with.
+    #[cfg(not(target_arch = "wasm32"))]
+    let (results, score) = blame::date_findings(Path::new(label), &analyzer, results, score);
+    Ok(Some(FileReport {
+        path: label.to_string(),
+        language: language.name.clone(),
+        line_count: source.lines().count(),
+        results,
+        score,
+        grammar: Some(language.grammar.clone()),
+    }))
+}
+
+/// The analyzer for a file at `label`: rules from `config` scoped to the
+/// language and source set with its path overrides applied, or the
+/// language's built-in rules. Pair checks get the file's header or source
+/// counterpart from `overlays` or else from disk, and scoped rules learn
+/// whether `label` names a test file.
+pub fn analyzer_for(
+    label: &str,
+    language: &LanguageDefinition,
+    config: Option<&AnalyzerConfig>,
+    overlays: &BTreeMap<PathBuf, String>,
+) -> Result<CodeAnalyzer, Box<dyn std::error::Error>> {
+    let analyzer = match config {
+        Some(config) => config.to_analyzer_for_path(Some(&language.name), label),
+        None => {
+            let builtin =
+                AnalyzerConfig::from_str(language.default_config.as_deref().unwrap_or_default())?;
+            builtin.to_analyzer_for_path(None, label)
+        }
+    }
+    .with_test_file(scope::is_test_file(label));
+    if !analyzer.has_pair_checks() {
+        return Ok(analyzer);
+    }
+    Ok(analyzer.with_counterpart(pairing::counterpart(Path::new(label), overlays)))
+}
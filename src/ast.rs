@@ -0,0 +1,200 @@
+//! Indented S-expression dumps of syntax trees, for rule authors looking
+//! up node kinds and field names. Nodes captured by a query are marked with
+//! their capture names.
+
+use std::collections::BTreeMap;
+use tree_sitter::{Language, Node, Parser, Point, Query, QueryCursor, StreamingIterator};
+
+/// Longest leaf text printed before it is cut off.
+const MAX_LEAF_TEXT: usize = 40;
+
+/// What [`dump`] prints.
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    /// Query whose captures are marked with `@name`.
+    pub query: Option<String>,
+    /// 1-based line and column; only the smallest named node spanning it is
+    /// printed, with its ancestors' kinds as a breadcrumb.
+    pub at: Option<(usize, usize)>,
+    /// Also print anonymous nodes such as punctuation and keywords.
+    pub anonymous: bool,
+}
+
+/// The dumped tree, and how many query matches it contains.
+#[derive(Debug, Clone)]
+pub struct Dump {
+    pub text: String,
+    pub matches: usize,
+}
+
+/// Parses `source_code` and dumps its tree. Fails if the query does not
+/// compile for `language`.
+pub fn dump(
+    source_code: &str,
+    language: &Language,
+    options: &DumpOptions,
+) -> Result<Dump, Box<dyn std::error::Error>> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+    let tree = parser
+        .parse(source_code, None)
+        .ok_or("tree-sitter could not parse the source")?;
+    let source = source_code.as_bytes();
+
+    let mut root = tree.root_node();
+    let mut text = String::new();
+    if let Some((line, column)) = options.at {
+        let point = Point::new(line.saturating_sub(1), column.saturating_sub(1));
+        root = root
+            .named_descendant_for_point_range(point, point)
+            .unwrap_or(root);
+        let mut ancestors = Vec::new();
+        let mut node = root;
+        while let Some(parent) = node.parent() {
+            ancestors.push(parent.kind());
+            node = parent;
+        }
+        if !ancestors.is_empty() {
+            ancestors.reverse();
+            text.push_str(&format!("; in {}\n", ancestors.join(" > ")));
+        }
+    }
+
+    let mut captures: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    let mut matches = 0;
+    if let Some(query) = &options.query {
+        let query = Query::new(language, query)?;
+        let names = query.capture_names();
+        let mut cursor = QueryCursor::new();
+        let mut found = cursor.matches(&query, root, source);
+        while let Some(match_) = found.next() {
+            matches += 1;
+            for capture in match_.captures {
+                captures
+                    .entry(capture.node.id())
+                    .or_default()
+                    .push(format!("@{}", names[capture.index as usize]));
+            }
+        }
+    }
+
+    write_node(root, None, 0, source, options, &captures, &mut text);
+    Ok(Dump { text, matches })
+}
+
+fn write_node(
+    node: Node<'_>,
+    field: Option<&str>,
+    depth: usize,
+    source: &[u8],
+    options: &DumpOptions,
+    captures: &BTreeMap<usize, Vec<String>>,
+    out: &mut String,
+) {
+    let start = node.start_position();
+    let end = node.end_position();
+    out.push_str(&"  ".repeat(depth));
+    if let Some(field) = field {
+        out.push_str(field);
+        out.push_str(": ");
+    }
+    if node.is_named() {
+        out.push('(');
+        out.push_str(node.kind());
+    } else {
+        out.push_str(&format!("{:?}", node.kind()));
+    }
+    out.push_str(&format!(
+        " [{}:{}-{}:{}]",
+        start.row + 1,
+        start.column + 1,
+        end.row + 1,
+        end.column + 1
+    ));
+    if node.is_named() && node.child_count() == 0 {
+        out.push_str(&format!(" {:?}", leaf_text(node, source)));
+    }
+    if node.is_missing() {
+        out.push_str(" MISSING");
+    }
+    if let Some(names) = captures.get(&node.id()) {
+        out.push(' ');
+        out.push_str(&names.join(" "));
+    }
+    out.push('\n');
+
+    let mut cursor = node.walk();
+    let mut children = Vec::new();
+    if cursor.goto_first_child() {
+        loop {
+            children.push((cursor.node(), cursor.field_name()));
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+    for (child, field) in children {
+        if child.is_named() || options.anonymous {
+            write_node(child, field, depth + 1, source, options, captures, out);
+        }
+    }
+    if node.is_named() {
+        // Close the node on its last line, as tree-sitter's own S-expressions do.
+        out.pop();
+        out.push_str(")\n");
+    }
+}
+
+fn leaf_text(node: Node<'_>, source: &[u8]) -> String {
+    let text = node.utf8_text(source).unwrap_or_default();
+    match text.char_indices().nth(MAX_LEAF_TEXT) {
+        Some((cut, _)) => format!("{}...", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "lang-rust"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_marks_captures_and_narrows_to_a_position() {
+        let language: Language = tree_sitter_rust::LANGUAGE.into();
+        let source = "fn main() {\n    let x = foo().unwrap();\n}\n";
+
+        let options = DumpOptions {
+            query: Some("(call_expression function: (field_expression field: (field_identifier) @method)) @call".to_string()),
+            ..DumpOptions::default()
+        };
+        let full = dump(source, &language, &options).unwrap();
+        assert_eq!(full.matches, 1);
+        assert!(full.text.starts_with(
+            "(source_file [1:1-4:1]\n  (function_item [1:1-3:2]\n    name: (identifier [1:4-1:8] \"main\")"
+        ));
+        assert!(full
+            .text
+            .contains("value: (call_expression [2:13-2:27] @call\n"));
+        assert!(full
+            .text
+            .contains("field: (field_identifier [2:19-2:25] \"unwrap\" @method))\n"));
+
+        let options = DumpOptions {
+            at: Some((2, 20)),
+            anonymous: true,
+            ..DumpOptions::default()
+        };
+        let narrowed = dump(source, &language, &options).unwrap();
+        assert_eq!(narrowed.matches, 0);
+        assert_eq!(
+            narrowed.text,
+            "; in source_file > function_item > block > let_declaration > call_expression > field_expression\n(field_identifier [2:19-2:25] \"unwrap\")\n"
+        );
+
+        let options = DumpOptions {
+            query: Some("(no_such_node) @x".to_string()),
+            ..DumpOptions::default()
+        };
+        let error = dump(source, &language, &options).unwrap_err();
+        assert!(error.to_string().contains("Invalid node type no_such_node"));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::exemption::Exemption;
+use crate::history::current_commit;
+use crate::report::ProjectScore;
+use crate::update::CURRENT_VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Evidence of one gate decision, written as a line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    /// `HEAD` of the enclosing git repository, if any.
+    pub commit: Option<String>,
+    pub compass_version: String,
+    /// Analyzed path to the SHA-256 of its contents.
+    pub inputs: BTreeMap<String, String>,
+    /// SHA-256 over the effective rule configs.
+    pub config_fingerprint: String,
+    pub policy: Option<PolicyFingerprint>,
+    pub score: Option<f64>,
+    pub rating: String,
+    pub total_issues: usize,
+    /// `pass`, `fail`, or `exempted` when a failing gate was let through.
+    pub decision: String,
+    pub violations: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exemption: Option<Exemption>,
+    /// Severity overrides from the config that were in effect.
+    pub overrides: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyFingerprint {
+    pub path: String,
+    pub sha256: String,
+}
+
+impl AuditRecord {
+    /// A record stamped with the current time, git commit, and compass
+    /// version; the decision follows from `violations` and `exemption`.
+    pub fn record(
+        score: &ProjectScore,
+        inputs: BTreeMap<String, String>,
+        config_fingerprint: String,
+        policy: Option<PolicyFingerprint>,
+        violations: Vec<String>,
+        exemption: Option<Exemption>,
+        overrides: BTreeMap<String, String>,
+    ) -> Self {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let decision = match (violations.is_empty(), &exemption) {
+            (true, _) => "pass",
+            (false, Some(_)) => "exempted",
+            (false, None) => "fail",
+        };
+
+        AuditRecord {
+            recorded_at,
+            commit: current_commit(),
+            compass_version: CURRENT_VERSION.to_string(),
+            inputs,
+            config_fingerprint,
+            policy,
+            score: score.overall_score,
+            rating: score.rating.clone(),
+            total_issues: score.total_issues,
+            decision: decision.to_string(),
+            violations,
+            exemption,
+            overrides,
+        }
+    }
+}
+
+/// Writes `record` to `destination`: an `http(s)://` webhook receives it as
+/// a JSON POST, anything else is a file the record is appended to. Files
+/// are only ever opened for appending, never rewritten.
+pub fn write(destination: &str, record: &AuditRecord) -> Result<(), Box<dyn std::error::Error>> {
+    let line = serde_json::to_string(record)?;
+    if destination.starts_with("https://") || destination.starts_with("http://") {
+        return post(destination, &line);
+    }
+
+    let path = Path::new(destination);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+fn post(url: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new("curl")
+        .args([
+            "-fsS",
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "-H",
+            "User-Agent: compass",
+            "--data-binary",
+            "@-",
+            url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to run curl (required for webhooks): {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or("failed to open curl stdin")?
+        .write_all(body.as_bytes())?;
+    if !child.wait()?.success() {
+        return Err(format!("webhook rejected the audit record: {}", url).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! `compass badge`: the project score and rating as an SVG badge, or as
+//! the JSON a shields.io endpoint badge reads, for READMEs.
+
+use crate::ratings::Ratings;
+use crate::report::ProjectScore;
+use serde_json::{json, Value};
+
+/// Text on the left half of a badge.
+const LABEL: &str = "compass";
+
+/// Shields.io colors from the best rating band to the worst, with the hex
+/// values shields.io draws them in.
+const COLORS: [(&str, &str); 6] = [
+    ("brightgreen", "#4c1"),
+    ("green", "#97ca00"),
+    ("yellowgreen", "#a4a61d"),
+    ("yellow", "#dfb317"),
+    ("orange", "#fe7d37"),
+    ("red", "#e05d44"),
+];
+const NO_SCORE_COLOR: (&str, &str) = ("lightgrey", "#9f9f9f");
+
+/// Approximate width of a character of 11px Verdana, and the padding on
+/// either side of a half's text.
+const CHAR_WIDTH: usize = 7;
+const PADDING: usize = 10;
+
+/// Shields.io name and hex value of `rating`'s color: the best band is
+/// bright green, the worst red, and the bands between spread over the
+/// colors between. Ratings outside the bands, like `N/A`, are grey.
+pub fn color(ratings: &Ratings, rating: &str) -> (&'static str, &'static str) {
+    let bands = ratings.ranked();
+    let Some(rank) = bands.iter().position(|(label, _)| *label == rating) else {
+        return NO_SCORE_COLOR;
+    };
+    if bands.len() == 1 {
+        return COLORS[0];
+    }
+    let last = COLORS.len() - 1;
+    let index = (rank as f64 * last as f64 / (bands.len() - 1) as f64).round() as usize;
+    COLORS[index.min(last)]
+}
+
+/// Right half of the badge, like `8.4 Good`.
+fn message(score: &ProjectScore) -> String {
+    match score.overall_score {
+        Some(overall) => format!("{:.1} {}", overall, score.rating),
+        None => score.rating.clone(),
+    }
+}
+
+/// A flat shields.io-style SVG badge of the project score.
+pub fn svg(score: &ProjectScore, ratings: &Ratings) -> String {
+    let message = message(score);
+    let (_, fill) = color(ratings, &score.rating);
+    let label_width = LABEL.chars().count() * CHAR_WIDTH + PADDING;
+    let message_width = message.chars().count() * CHAR_WIDTH + PADDING;
+    let width = label_width + message_width;
+    let title = format!("{}: {}", LABEL, message);
+    let (label, message, title) = (escape(LABEL), escape(&message), escape(&title));
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{title}">
+  <title>{title}</title>
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <clipPath id="r">
+    <rect width="{width}" height="20" rx="3" fill="#fff"/>
+  </clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{message_width}" height="20" fill="{fill}"/>
+    <rect width="{width}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// The JSON of a shields.io endpoint badge
+/// (`https://img.shields.io/endpoint?url=...`).
+pub fn shields_endpoint(score: &ProjectScore, ratings: &Ratings) -> Value {
+    json!({
+        "schemaVersion": 1,
+        "label": LABEL,
+        "message": message(score),
+        "color": color(ratings, &score.rating).0,
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ProjectWeighting;
+
+    #[test]
+    fn test_colors_follow_rating_bands() {
+        let ratings = Ratings::default();
+        let colors: Vec<&str> = ["Excellent", "Good", "Fair", "Poor", "Critical", "N/A"]
+            .iter()
+            .map(|rating| color(&ratings, rating).0)
+            .collect();
+        assert_eq!(
+            colors,
+            [
+                "brightgreen",
+                "green",
+                "yellow",
+                "orange",
+                "red",
+                "lightgrey"
+            ]
+        );
+
+        let pass_fail: Ratings = toml::from_str("[bands]\nPass = 5.0\nFail = 0.0\n").unwrap();
+        assert_eq!(color(&pass_fail, "Pass").0, "brightgreen");
+        assert_eq!(color(&pass_fail, "Fail").0, "red");
+    }
+
+    #[test]
+    fn test_badge_shows_score_and_escaped_rating() {
+        let ratings: Ratings = toml::from_str("[bands]\n\"A&B\" = 0.0\n").unwrap();
+        let empty = ProjectScore::from_files(&[], ProjectWeighting::default());
+        let mut score = empty.clone();
+        score.overall_score = Some(8.44);
+        score = score.with_ratings(&ratings);
+
+        let badge = svg(&score, &ratings);
+        assert!(badge.contains(">8.4 A&amp;B</text>"));
+        assert!(badge.contains("fill=\"#4c1\""));
+        assert_eq!(
+            shields_endpoint(&empty, &ratings),
+            json!({ "schemaVersion": 1, "label": "compass", "message": "N/A", "color": "lightgrey" })
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! `cargo compass`: analyze every Rust source in the current Cargo workspace
+//! and report scores per crate.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use clap::Parser;
+use compass::analyzer::CodeScore;
+use compass::config::AnalyzerConfig;
+use compass::discovery;
+use compass::language::LanguageRegistry;
+use compass::source;
+use serde_json::{json, to_string_pretty, Value};
+
+struct Package {
+    name: String,
+    root: PathBuf,
+}
+
+struct FileScore {
+    path: PathBuf,
+    score: CodeScore,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "cargo compass",
+    bin_name = "cargo compass",
+    version,
+    about = "Analyzes every .rs file in the workspace and reports scores per crate"
+)]
+struct Cli {
+    /// Config file (default: the built-in Rust config)
+    #[arg(value_name = "CONFIG")]
+    config_path: Option<String>,
+    /// Cargo.toml of the workspace (default: the one cargo finds)
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<String>,
+    /// Include per-file scores in each crate's report
+    #[arg(long = "files")]
+    include_files: bool,
+}
+
+fn main() {
+    // Cargo invokes subcommands as `cargo-compass compass <args>`.
+    let mut args: Vec<String> = env::args().collect();
+    if args.get(1).is_some_and(|arg| arg == "compass") {
+        args.remove(1);
+    }
+    let Cli {
+        config_path,
+        manifest_path,
+        include_files,
+    } = Cli::parse_from(args);
+
+    let (workspace_root, packages) = workspace_packages(manifest_path.as_deref());
+
+    let registry = LanguageRegistry::builtin();
+    let rust = registry.get("rust").expect("rust is a built-in language");
+    let config = match &config_path {
+        Some(path) => AnalyzerConfig::from_file(path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to load config '{}': {}", path, e);
+            process::exit(1);
+        }),
+        None => AnalyzerConfig::from_str(rust.default_config.as_deref().unwrap_or_default())
+            .expect("embedded config should parse"),
+    };
+    let analyzer = config.to_analyzer_for_language("rust");
+    if !analyzer.has_rules() {
+        eprintln!("Error: config contains no enabled rules for language 'rust'");
+        process::exit(1);
+    }
+
+    let files = discovery::collect_files(&workspace_root, |path| {
+        path.extension().is_some_and(|ext| ext == "rs")
+    })
+    .unwrap_or_else(|e| {
+        eprintln!(
+            "Error: failed to scan '{}': {}",
+            workspace_root.display(),
+            e
+        );
+        process::exit(1);
+    });
+
+    let mut per_package: Vec<Vec<FileScore>> = packages.iter().map(|_| Vec::new()).collect();
+    for path in files {
+        // Nested workspace members live under the root package's directory,
+        // so attribute each file to the deepest package that contains it.
+        let Some(index) = packages
+            .iter()
+            .enumerate()
+            .filter(|(_, package)| path.starts_with(&package.root))
+            .max_by_key(|(_, package)| package.root.components().count())
+            .map(|(index, _)| index)
+        else {
+            continue;
+        };
+
+        let source = source::read(&path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read '{}': {}", path.display(), e);
+            process::exit(1);
+        });
+        let (_, score) = analyzer
+            .analyze_with_score(&source.text, &rust.language)
+            .unwrap_or_else(|e| {
+                eprintln!("Error: analysis of '{}' failed: {}", path.display(), e);
+                process::exit(1);
+            });
+        per_package[index].push(FileScore { path, score });
+    }
+
+    let crates: Vec<Value> = packages
+        .iter()
+        .zip(&per_package)
+        .map(|(package, files)| crate_report(package, files, &workspace_root, include_files))
+        .collect();
+    let all_files: Vec<&FileScore> = per_package.iter().flatten().collect();
+
+    let output = json!({
+        "workspace_root": workspace_root.display().to_string(),
+        "files_analyzed": all_files.len(),
+        "average_score": average(all_files.iter().map(|file| file.score.overall_score)),
+        "total_issues": all_files.iter().map(|file| file.score.total_issues).sum::<usize>(),
+        "crates": crates,
+    });
+    match to_string_pretty(&output) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error: failed to format analysis result: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn crate_report(
+    package: &Package,
+    files: &[FileScore],
+    workspace_root: &Path,
+    include_files: bool,
+) -> Value {
+    let mut report = json!({
+        "name": package.name,
+        "path": relative(&package.root, workspace_root),
+        "files_analyzed": files.len(),
+        "average_score": average(files.iter().map(|file| file.score.overall_score)),
+        "lowest_score": files
+            .iter()
+            .map(|file| file.score.overall_score)
+            .reduce(f64::min),
+        "total_issues": files.iter().map(|file| file.score.total_issues).sum::<usize>(),
+        "errors": files.iter().map(|file| file.score.breakdown.errors).sum::<usize>(),
+        "warnings": files.iter().map(|file| file.score.breakdown.warnings).sum::<usize>(),
+        "info_issues": files.iter().map(|file| file.score.breakdown.info_issues).sum::<usize>(),
+        "style_issues": files.iter().map(|file| file.score.breakdown.style_issues).sum::<usize>(),
+    });
+
+    if include_files {
+        report["files"] = files
+            .iter()
+            .map(|file| {
+                json!({
+                    "path": relative(&file.path, workspace_root),
+                    "score": file.score.overall_score,
+                    "rating": file.score.rating,
+                    "total_issues": file.score.total_issues,
+                })
+            })
+            .collect();
+    }
+    report
+}
+
+fn average(scores: impl Iterator<Item = f64>) -> Option<f64> {
+    let scores: Vec<f64> = scores.collect();
+    if scores.is_empty() {
+        return None;
+    }
+    let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+    Some((mean * 10.0).round() / 10.0)
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(stripped) if stripped.as_os_str().is_empty() => ".".to_string(),
+        Ok(stripped) => stripped.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+/// Asks cargo for the workspace layout rather than parsing manifests, so
+/// `members`/`exclude` globs and path dependencies are resolved exactly as
+/// cargo resolves them.
+fn workspace_packages(manifest_path: Option<&str>) -> (PathBuf, Vec<Package>) {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(path) = manifest_path {
+        command.args(["--manifest-path", path]);
+    }
+
+    let output = command.output().unwrap_or_else(|e| {
+        eprintln!("Error: failed to run cargo metadata: {}", e);
+        process::exit(1);
+    });
+    if !output.status.success() {
+        eprintln!(
+            "Error: cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        process::exit(1);
+    }
+
+    let metadata: Value = serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        eprintln!("Error: failed to parse cargo metadata: {}", e);
+        process::exit(1);
+    });
+
+    let workspace_root = PathBuf::from(metadata["workspace_root"].as_str().unwrap_or("."));
+    let packages = metadata["packages"]
+        .as_array()
+        .map(|packages| {
+            packages
+                .iter()
+                .filter_map(|package| {
+                    let manifest = Path::new(package["manifest_path"].as_str()?);
+                    Some(Package {
+                        name: package["name"].as_str()?.to_string(),
+                        root: manifest.parent()?.to_path_buf(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (workspace_root, packages)
+}
@@ -0,0 +1,139 @@
+//! Ages of source lines from `git blame`, for rules with `older_than`:
+//! their findings on lines changed more recently are dropped, and the rest
+//! are labelled with `age_days`.
+
+use crate::analyzer::{AnalysisResult, AnalysisRule, CodeAnalyzer, CodeScore};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Unix time each line of `path` was authored, by 0-based line; lines not
+/// committed yet date from now. `None` when git is missing or the file is
+/// not tracked.
+pub fn line_times(path: &Path) -> Option<Vec<u64>> {
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["blame", "--porcelain", "--"])
+        .arg(path.file_name()?)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Author times by final line from `git blame --porcelain` output, which
+/// gives a commit's `author-time` only the first time the commit appears.
+fn parse_porcelain(output: &str) -> Vec<u64> {
+    let mut times = Vec::new();
+    let mut commit_times: HashMap<&str, u64> = HashMap::new();
+    let mut commit = "";
+    let mut line = 0;
+    for row in output.lines() {
+        if row.starts_with('\t') && line > 0 {
+            let time = commit_times.get(commit).copied().unwrap_or_else(now);
+            if times.len() < line {
+                times.resize(line, time);
+            }
+            times[line - 1] = time;
+        } else if let Some(time) = row.strip_prefix("author-time ") {
+            if let Ok(time) = time.parse() {
+                commit_times.insert(commit, time);
+            }
+        } else {
+            let mut fields = row.split(' ');
+            let (Some(sha), Some(_), Some(final_line)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if sha.len() == 40 && sha.bytes().all(|b| b.is_ascii_hexdigit()) {
+                commit = sha;
+                line = final_line.parse().unwrap_or(line);
+            }
+        }
+    }
+    times
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Drops findings of rules with `older_than` whose line was authored more
+/// recently, given each line's author time, and labels the others with
+/// their `age_days`. Findings of other rules are kept as they are.
+pub fn apply_ages(rules: &[AnalysisRule], results: &mut Vec<AnalysisResult>, line_times: &[u64]) {
+    let now = now();
+    results.retain_mut(|result| {
+        let Some(older_than) = rules
+            .iter()
+            .find(|rule| rule.name == result.rule_name)
+            .and_then(|rule| rule.older_than)
+        else {
+            return true;
+        };
+        let Some(&time) = line_times.get(result.line - 1) else {
+            return true;
+        };
+        let age = now.saturating_sub(time);
+        result
+            .labels
+            .insert("age_days".to_string(), (age / SECONDS_PER_DAY).to_string());
+        age >= older_than.as_secs()
+    });
+}
+
+/// The findings `analyzer` made on the file at `path`, with [`apply_ages`]
+/// applied, and their score, when one of its rules has `older_than` and
+/// git can blame the file.
+pub(crate) fn date_findings(
+    path: &Path,
+    analyzer: &CodeAnalyzer,
+    mut results: Vec<AnalysisResult>,
+    score: CodeScore,
+) -> (Vec<AnalysisResult>, CodeScore) {
+    if !analyzer
+        .rules()
+        .iter()
+        .any(|rule| rule.older_than.is_some())
+    {
+        return (results, score);
+    }
+    let Some(times) = line_times(path) else {
+        return (results, score);
+    };
+    apply_ages(analyzer.rules(), &mut results, &times);
+    let score = analyzer.calculate_score(&results, score.lines);
+    (results, score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_porcelain_times_follow_each_line_commit() {
+        let first = "1".repeat(40);
+        let second = "2".repeat(40);
+        let output = format!(
+            "{first} 1 1 2\nauthor Ann\nauthor-time 1000\nfilename a.rs\n\tfn a() {{}}\n\
+             {first} 2 2\n\t// TODO\n\
+             {second} 3 3 1\nauthor Bo\nauthor-time 5000\nfilename a.rs\n\tlet b = 1;\n"
+        );
+
+        assert_eq!(parse_porcelain(&output), [1000, 1000, 5000]);
+    }
+}
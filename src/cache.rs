@@ -0,0 +1,139 @@
+//! Results of earlier runs, stored under `.compass/cache` so a directory
+//! scan only re-analyzes files whose content or config changed.
+
+use crate::analyzer::{AnalysisResult, CodeScore, Severity, SyntaxError};
+use crate::fingerprint::sha256_hex;
+use crate::hotspots::FunctionMetrics;
+use crate::metrics::FileMetrics;
+use crate::update::CURRENT_VERSION;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_CACHE_DIR: &str = ".compass/cache";
+
+/// Everything a run keeps of one analyzed file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CachedAnalysis {
+    pub results: Vec<AnalysisResult>,
+    pub score: CodeScore,
+    pub line_count: usize,
+    pub functions: Vec<FunctionMetrics>,
+    pub metrics: Option<FileMetrics>,
+    pub rules: Vec<String>,
+    pub rule_severities: BTreeMap<String, Severity>,
+    pub syntax_errors: Vec<SyntaxError>,
+    pub config_label: String,
+    pub config_hash: String,
+}
+
+/// A directory of [`CachedAnalysis`] entries, one JSON file per key.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    /// The key of an analysis depending on `parts` (content hash, config
+    /// hash, options), under this version of compass.
+    pub fn key(parts: &[&str]) -> String {
+        let mut joined = CURRENT_VERSION.to_string();
+        for part in parts {
+            joined.push('\0');
+            joined.push_str(part);
+        }
+        sha256_hex(joined.as_bytes())
+    }
+
+    fn entry(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The entry stored under `key`; unreadable or outdated entries are
+    /// misses.
+    pub fn get(&self, key: &str) -> Option<CachedAnalysis> {
+        let content = fs::read_to_string(self.entry(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Stores `analysis` under `key`, writing a temporary file first so a
+    /// concurrent run never reads half an entry.
+    pub fn put(
+        &self,
+        key: &str,
+        analysis: &CachedAnalysis,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(&self.dir)?;
+        let temporary = self.dir.join(format!("{}.tmp{}", key, std::process::id()));
+        fs::write(&temporary, serde_json::to_string(analysis)?)?;
+        fs::rename(&temporary, self.entry(key))?;
+        Ok(())
+    }
+
+    /// Removes every entry, returning how many there were.
+    pub fn clear(&self) -> io::Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        let entries = count_entries(&self.dir)?;
+        fs::remove_dir_all(&self.dir)?;
+        Ok(entries)
+    }
+}
+
+fn count_entries(dir: &Path) -> io::Result<usize> {
+    let mut entries = 0;
+    for entry in fs::read_dir(dir)? {
+        if entry?.path().extension().is_some_and(|ext| ext == "json") {
+            entries += 1;
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(all(test, feature = "lang-rust"))]
+mod tests {
+    use super::*;
+    use crate::analyzer::CodeAnalyzer;
+
+    #[test]
+    fn test_cache_roundtrips_entries_and_clears_them() {
+        let dir = std::env::temp_dir().join("compass_cache_unit_test");
+        let cache = Cache::new(&dir);
+        cache.clear().unwrap();
+
+        let language = tree_sitter_rust::LANGUAGE.into();
+        let (results, score) = CodeAnalyzer::new()
+            .analyze_with_score("fn main() {}", &language)
+            .unwrap();
+        let analysis = CachedAnalysis {
+            results,
+            score,
+            line_count: 1,
+            functions: Vec::new(),
+            metrics: None,
+            rules: Vec::new(),
+            rule_severities: BTreeMap::new(),
+            syntax_errors: Vec::new(),
+            config_label: "built-in rust".to_string(),
+            config_hash: String::new(),
+        };
+        let key = Cache::key(&["main.rs", "content", "config"]);
+        assert_ne!(key, Cache::key(&["main.rs", "changed", "config"]));
+        assert!(cache.get(&key).is_none());
+
+        cache.put(&key, &analysis).unwrap();
+        let cached = cache.get(&key).unwrap();
+        assert_eq!(cached.score.overall_score, analysis.score.overall_score);
+        assert_eq!(cached.config_label, "built-in rust");
+
+        assert_eq!(cache.clear().unwrap(), 1);
+        assert!(cache.get(&key).is_none());
+    }
+}
@@ -0,0 +1,87 @@
+//! Cooperative cancellation of analysis, so a pathological file or query
+//! can't hang a run. Parsing and query matching poll the token through
+//! tree-sitter's progress callbacks, and the analyzer checks it between
+//! rules; a cancelled analysis fails with [`Cancelled`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Cancels the analyses holding it, or a clone of it, when
+/// [`cancel`](Self::cancel) is called or its deadline passes. The default
+/// token never cancels.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token cancelled by this one, or once `timeout` from now passes.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let deadline = Instant::now() + timeout;
+        CancellationToken {
+            cancelled: Arc::clone(&self.cancelled),
+            deadline: Some(self.deadline.map_or(deadline, |own| own.min(deadline))),
+        }
+    }
+
+    /// Cancels this token and every token sharing it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// `Err(Cancelled)` once the token is cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The error of an analysis stopped by its [`CancellationToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "analysis was cancelled or timed out")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_and_cancel_reach_derived_tokens() {
+        let run = CancellationToken::new();
+        let file = run.with_timeout(Duration::from_secs(60));
+        let expired = file.with_timeout(Duration::ZERO);
+        assert!(!run.is_cancelled() && !file.is_cancelled());
+        assert_eq!(expired.check(), Err(Cancelled));
+        assert!(
+            !file.is_cancelled(),
+            "a derived deadline stays with its token"
+        );
+
+        run.cancel();
+        assert!(file.is_cancelled() && run.is_cancelled());
+    }
+}
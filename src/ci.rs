@@ -0,0 +1,123 @@
+//! Output defaults taken from the environment: the CI service compass runs
+//! under picks the default `--format`, and `--color auto` colors only
+//! terminals outside CI.
+
+use std::env;
+
+/// A CI service, recognized by the variables it sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    /// `GITHUB_ACTIONS=true`.
+    GithubActions,
+    /// `GITLAB_CI=true`.
+    GitlabCi,
+    /// Any other service setting `CI`.
+    Other,
+}
+
+impl CiProvider {
+    /// The CI service of this process's environment, if any.
+    pub fn detect() -> Option<Self> {
+        Self::detect_with(|name| env::var(name).ok())
+    }
+
+    /// [`detect`](Self::detect) reading variables through `var`.
+    pub fn detect_with(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let set = |name: &str| {
+            var(name).is_some_and(|value| !value.is_empty() && value != "0" && value != "false")
+        };
+        if set("GITHUB_ACTIONS") {
+            Some(CiProvider::GithubActions)
+        } else if set("GITLAB_CI") {
+            Some(CiProvider::GitlabCi)
+        } else if set("CI") {
+            Some(CiProvider::Other)
+        } else {
+            None
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            CiProvider::GithubActions => "GitHub Actions",
+            CiProvider::GitlabCi => "GitLab CI",
+            CiProvider::Other => "CI",
+        }
+    }
+
+    /// The format the service shows findings from: workflow annotations on
+    /// GitHub, the Code Quality report on GitLab. Other services keep the
+    /// usual JSON.
+    pub fn default_format(&self) -> Option<&'static str> {
+        match self {
+            CiProvider::GithubActions => Some("github"),
+            CiProvider::GitlabCi => Some("codeclimate"),
+            CiProvider::Other => None,
+        }
+    }
+}
+
+/// `--color`: whether text output uses ANSI colors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color terminals, unless `NO_COLOR` is set or under CI.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// Whether to color output going to a terminal or not
+    /// (`is_terminal`), under `ci`.
+    pub fn enabled(&self, is_terminal: bool, ci: Option<CiProvider>) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal
+                    && ci.is_none()
+                    && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ci_services_are_detected_from_their_variables() {
+        let detect = |vars: &[(&str, &str)]| {
+            CiProvider::detect_with(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(
+            detect(&[("CI", "true"), ("GITHUB_ACTIONS", "true")]),
+            Some(CiProvider::GithubActions)
+        );
+        assert_eq!(detect(&[("GITLAB_CI", "true")]), Some(CiProvider::GitlabCi));
+        assert_eq!(detect(&[("CI", "1")]), Some(CiProvider::Other));
+        assert_eq!(detect(&[("CI", "false")]), None);
+        assert_eq!(detect(&[]), None);
+        assert_eq!(CiProvider::GitlabCi.default_format(), Some("codeclimate"));
+
+        assert!(!ColorChoice::Auto.enabled(true, Some(CiProvider::Other)));
+        assert!(!ColorChoice::Auto.enabled(false, None));
+        assert!(ColorChoice::Always.enabled(false, Some(CiProvider::Other)));
+        assert!(!ColorChoice::Never.enabled(true, None));
+    }
+}
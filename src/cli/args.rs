@@ -0,0 +1,727 @@
+//! The command line as clap definitions: `compass [options] <source>
+//! [config]` analyzes, and every other job is a subcommand. Values are
+//! checked here, so `--help` lists them and typos get suggestions; the
+//! parent module's `run_*` functions act on the parsed arguments.
+
+use std::time::Duration;
+
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+
+use crate::analyzer::{Confidence, Severity};
+use crate::cache::DEFAULT_CACHE_DIR;
+use crate::ci::ColorChoice;
+use crate::config::{self, resolver::DEFAULT_CONFIG_FILE};
+use crate::exemption;
+use crate::export::{IssueGrouping, Tracker};
+use crate::language::LanguageRegistry;
+use crate::logging::LogFormat;
+use crate::marketplace::DEFAULT_INDEX_URL;
+use crate::output::FormatterRegistry;
+use crate::policy::DEFAULT_POLICY_FILE;
+use crate::position::PositionEncoding;
+use crate::report::OutputGrouping;
+
+#[cfg(feature = "grpc")]
+use super::DEFAULT_GRPC_ADDR;
+#[cfg(feature = "playground")]
+use super::DEFAULT_PLAYGROUND_ADDR;
+use super::{
+    CONFIG_ENV_VAR, DEFAULT_EXPORT_STATE, DEFAULT_FEEDBACK_DIR, DEFAULT_HISTORY,
+    DEFAULT_SIMILARITY_THRESHOLD, DEFAULT_TOP, RULE_INDEX_ENV_VAR,
+};
+
+/// Formats `--format` accepts besides the [`FormatterRegistry`]'s.
+const CLI_FORMATS: &[&str] = &[
+    "json", "jsonl", "template", "bazel", "summary", "junit", "badge", "shields",
+];
+
+/// Subcommands taking [`AnalyzeFlags`], whose `--format` lists the
+/// registered formats.
+const ANALYZE_COMMANDS: &[&str] = &["analyze", "summary", "badge"];
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "compass",
+    version,
+    about = "Scores source code against tree-sitter query rules",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    arg_required_else_help = true
+)]
+pub(super) struct Cli {
+    /// Log config loading, skipped files and stage times to stderr; -vv
+    /// adds each file and rule
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Format of -v logs
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub log_format: LogFormat,
+    /// Load grammar libraries named by a discovered compass.toml, which
+    /// run native code when loaded
+    #[arg(long, global = true, env = "COMPASS_TRUST_PROJECT_GRAMMARS")]
+    pub trust_project_grammars: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub analyze: AnalyzeFlags,
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum Command {
+    /// Analyze a file or directory (what `compass <source>` does)
+    Analyze(AnalyzeFlags),
+    /// Only the lowest-scoring files, top rules, and worst functions
+    Summary(AnalyzeFlags),
+    /// The project score as an SVG badge, or shields.io JSON
+    Badge(AnalyzeFlags),
+    /// Print issue tracker payloads for the findings
+    ExportIssues(ExportIssuesArgs),
+    /// Grade each subdirectory of a directory as one student's submission
+    Grade(GradeArgs),
+    /// Search the index of community rule packs
+    Search(SearchArgs),
+    /// Write a starter config for the languages found in a directory
+    Init(InitArgs),
+    /// Describe a rule: what it flags, how to fix it, and examples
+    Explain(ExplainArgs),
+    /// Show every node a rule's query captures in a snippet
+    TestRule(TestRuleArgs),
+    /// Print a file's syntax tree
+    Ast(AstArgs),
+    /// Run the [[tests]] of a config
+    #[command(visible_alias = "validate")]
+    CheckConfig(CheckConfigArgs),
+    /// Apply the fixes of rules that have one
+    Fix(FixArgs),
+    /// List the rules of a config
+    Rules(RulesArgs),
+    /// Sign a token that lets one commit pass a failing gate
+    Exempt(ExemptArgs),
+    /// Print the version
+    Version(VersionArgs),
+    /// Install the latest release over this binary
+    SelfUpdate(SelfUpdateArgs),
+    /// Print the score trend recorded by --record-history
+    History(HistoryArgs),
+    /// Compare two JSON reports and gate on regressions
+    Compare(CompareArgs),
+    /// Manage the result cache
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Gate commits on the staged content of staged files
+    #[command(subcommand)]
+    Hook(HookCommand),
+    /// Inspect the config layers
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Serve the built-in rules over HTTP for a public playground
+    #[cfg(feature = "playground")]
+    Playground(PlaygroundArgs),
+    /// Serve analysis over gRPC for build farms
+    #[cfg(feature = "grpc")]
+    Grpc(GrpcArgs),
+}
+
+/// Everything an analysis run takes. `--format` and `--output` are kept
+/// as given; the parent module pairs each `--output` with the `--format`
+/// before it.
+#[derive(Args, Debug)]
+pub(super) struct AnalyzeFlags {
+    /// File or directory to analyze, or - for stdin
+    #[arg(required_unless_present_any = ["stdin", "srcs_file"])]
+    pub source: Option<String>,
+    /// Config file (default: the nearest compass.toml, or $COMPASS_CONFIG_TOML)
+    pub config: Option<String>,
+    /// Output format; repeat with --output for more (default: json, github on
+    /// GitHub Actions, codeclimate on GitLab CI)
+    #[arg(long, value_name = "FORMAT", ignore_case = true)]
+    pub format: Vec<String>,
+    /// Write the preceding --format to FILE
+    #[arg(long, value_name = "FILE")]
+    pub output: Vec<String>,
+    /// Handlebars template used by --format template
+    #[arg(long, value_name = "FILE")]
+    pub template: Option<String>,
+    /// Suppress the banner; emit only the selected format
+    #[arg(long)]
+    pub porcelain: bool,
+    /// No progress bar for directories (also off unless stderr is a terminal)
+    #[arg(long)]
+    pub quiet: bool,
+    /// Color text output (auto: terminals outside CI)
+    #[arg(
+        long,
+        value_name = "WHEN",
+        default_value = "auto",
+        ignore_case = true,
+        value_parser = named(["auto", "always", "never"], ColorChoice::from_name)
+    )]
+    pub color: ColorChoice,
+    /// Write a JSON run summary (files, issues, scores)
+    #[arg(long, value_name = "FILE")]
+    pub summary_file: Option<String>,
+    /// Hide findings below this severity
+    #[arg(long, value_name = "SEVERITY", ignore_case = true, value_parser = severity())]
+    pub min_severity: Option<Severity>,
+    /// Also leave findings below this severity out of the score
+    #[arg(long, value_name = "SEVERITY", ignore_case = true, value_parser = severity())]
+    pub score_min_severity: Option<Severity>,
+    /// Skip a file whose analysis takes longer, e.g. 30s
+    #[arg(long, value_name = "DURATION", value_parser = duration)]
+    pub timeout: Option<Duration>,
+    /// Stop analyzing files after this long, e.g. 10m
+    #[arg(long, value_name = "DURATION", value_parser = duration)]
+    pub total_timeout: Option<Duration>,
+    /// Apply [profile.NAME] of the config (e.g. ci, local)
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Use a rule preset: security, minimal, recommended or strict
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+    /// Fail unless a grammar matches, e.g. rust>=0.21
+    #[arg(long, value_name = "REQ")]
+    pub require_grammar: Vec<String>,
+    /// Exit non-zero when any file has a syntax error
+    #[arg(long)]
+    pub fail_on_parse_error: bool,
+    /// Count columns of the preceding --format in these units, or of every
+    /// format when given first (default: byte)
+    #[arg(
+        long,
+        value_name = "ENC",
+        ignore_case = true,
+        value_parser = named(
+            [
+                PossibleValue::new("byte").aliases(["utf-8", "utf8"]),
+                PossibleValue::new("char"),
+                PossibleValue::new("utf-16").alias("utf16"),
+            ],
+            PositionEncoding::from_name
+        )
+    )]
+    pub column_encoding: Vec<PositionEncoding>,
+    /// Report parse and per-rule query times (JSON: "timings")
+    #[arg(long)]
+    pub timings: bool,
+    /// Report Halstead volume, complexity and maintainability index (JSON: "metrics")
+    #[arg(long)]
+    pub metrics: bool,
+    /// Re-analyze every file instead of reusing the cache
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Cache directory and --srcs-file results here
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_CACHE_DIR)]
+    pub cache_dir: String,
+    /// Enforce an org policy file; violations exit non-zero
+    #[arg(long, value_name = "FILE")]
+    pub policy: Option<String>,
+    /// CI mode: enforce the default policy file when present
+    #[arg(long, long_help = format!("CI mode: enforce {} when present", DEFAULT_POLICY_FILE))]
+    pub ci: bool,
+    /// Append a gate decision record to a file or POST it
+    #[arg(long, value_name = "FILE|URL")]
+    pub audit_log: Option<String>,
+    /// Let this commit pass a failing gate (see exempt)
+    #[arg(long, value_name = "TOKEN")]
+    pub allow_failure_token: Option<String>,
+    /// Read source from stdin (requires --language)
+    #[arg(long)]
+    pub stdin: bool,
+    /// Language of stdin input, e.g. rust or go
+    #[arg(long, value_name = "NAME")]
+    pub language: Option<String>,
+    /// Analyze the newline-separated paths listed in FILE
+    #[arg(long, value_name = "FILE")]
+    pub srcs_file: Option<String>,
+    /// Bazel target label for --format bazel
+    #[arg(long, value_name = "LABEL")]
+    pub target: Option<String>,
+    /// Where --format bazel writes its result files
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<String>,
+    /// Append the run's score to the history file
+    #[arg(long, long_help = format!("Append the run's score to {}", DEFAULT_HISTORY))]
+    pub record_history: bool,
+    /// Like --record-history, but to FILE
+    #[arg(long, value_name = "FILE")]
+    pub history_file: Option<String>,
+    /// Collapse identical JSON findings
+    #[arg(
+        long,
+        value_name = "GROUPING",
+        default_value = "none",
+        ignore_case = true,
+        value_parser = named(["rule", "file", "none"], OutputGrouping::from_name)
+    )]
+    pub group_by: OutputGrouping,
+    /// Only the lowest-scoring files, top rules, and worst functions
+    #[arg(long)]
+    pub summary_only: bool,
+    /// Entries per list of --summary-only
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_TOP)]
+    pub top: usize,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct ExportIssuesArgs {
+    /// Files whose findings are exported
+    #[arg(required = true)]
+    pub sources: Vec<String>,
+    /// GitHub repository or Jira project key
+    #[arg(long, value_name = "NAME")]
+    pub repo: String,
+    /// Payload format
+    #[arg(
+        long,
+        default_value = "github",
+        ignore_case = true,
+        value_parser = named(["github", "jira"], Tracker::from_name)
+    )]
+    pub tracker: Tracker,
+    /// Lowest severity to export
+    #[arg(
+        long,
+        value_name = "SEVERITY",
+        default_value = "error",
+        ignore_case = true,
+        value_parser = severity()
+    )]
+    pub min_severity: Severity,
+    /// Lowest rule confidence to export
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        default_value = "low",
+        ignore_case = true,
+        value_parser = named(["high", "medium", "low"], Confidence::from_name)
+    )]
+    pub min_confidence: Confidence,
+    /// One issue per rule or per file
+    #[arg(
+        long,
+        value_name = "GROUPING",
+        default_value = "rule",
+        ignore_case = true,
+        value_parser = named(["rule", "file"], IssueGrouping::from_name)
+    )]
+    pub group_by: IssueGrouping,
+    /// Override the built-in config
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<String>,
+    /// Exported fingerprints
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_EXPORT_STATE)]
+    pub state: String,
+    /// Print payloads without recording fingerprints
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct GradeArgs {
+    /// Directory holding one subdirectory per student
+    #[arg(long, value_name = "DIR")]
+    pub submissions: String,
+    /// Rubric config (default: built-in rules)
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<String>,
+    /// Grade sheet format
+    #[arg(long, value_enum, default_value_t = GradeFormat::Csv)]
+    pub format: GradeFormat,
+    /// Also write a Markdown feedback report per student
+    #[arg(long)]
+    pub per_student: bool,
+    /// Where --per-student writes reports
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_FEEDBACK_DIR)]
+    pub output_dir: String,
+    /// Write pairs of structurally similar submissions
+    #[arg(long, value_name = "FILE")]
+    pub similarity_report: Option<String>,
+    /// Lowest similarity reported, 0 to 1
+    #[arg(long, value_name = "X", default_value_t = DEFAULT_SIMILARITY_THRESHOLD)]
+    pub similarity_threshold: f64,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub(super) enum GradeFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct SearchArgs {
+    pub keyword: String,
+    /// HTTPS URL or local copy of the index
+    #[arg(long, value_name = "URL|FILE", env = RULE_INDEX_ENV_VAR, default_value = DEFAULT_INDEX_URL)]
+    pub index: String,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TextOrJson,
+}
+
+/// `--format` of the subcommands that print for people or for tools.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum TextOrJson {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = "Vendored paths are excluded in the written config.")]
+pub(super) struct InitArgs {
+    /// Directory to scan and write the config to
+    #[arg(default_value = ".")]
+    pub directory: String,
+    /// Overwrite an existing config
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = lookup_note())]
+pub(super) struct ExplainArgs {
+    pub rule: String,
+    pub config: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TextOrJson,
+}
+
+fn lookup_note() -> String {
+    format!(
+        "Rules are looked up in the config (or ${}) first, then in the built-in rules.",
+        CONFIG_ENV_VAR
+    )
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = lookup_note())]
+pub(super) struct TestRuleArgs {
+    pub rule: String,
+    /// Snippet file, or - for stdin
+    #[arg(default_value = "-")]
+    pub snippet: String,
+    pub config: Option<String>,
+    /// Snippet language (default: the rule's, or the file extension)
+    #[arg(long, value_name = "NAME")]
+    pub language: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TextOrJson,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct AstArgs {
+    /// Source file, or - for stdin
+    pub file: String,
+    pub config: Option<String>,
+    /// Mark the nodes the tree-sitter query captures with @name
+    #[arg(long)]
+    pub query: Option<String>,
+    /// Only the smallest named node at this position
+    #[arg(long, value_name = "LINE:COL", value_parser = line_column)]
+    pub at: Option<(usize, usize)>,
+    /// Also print anonymous nodes (keywords, punctuation)
+    #[arg(long)]
+    pub all: bool,
+    /// Language of the input (default: from the file extension)
+    #[arg(long, value_name = "NAME")]
+    pub language: Option<String>,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = "Exits non-zero if a test's findings differ from its `expect`.")]
+pub(super) struct CheckConfigArgs {
+    /// Config file (default: $COMPASS_CONFIG_TOML, or every built-in config)
+    pub config: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TextOrJson,
+}
+
+#[derive(Args, Debug)]
+#[command(
+    after_help = "Without --write, files are left alone and the fixes are only printed. \
+Fixes that overlap an earlier fix, or that their own rule would fix again, are skipped and \
+reported. Each fixed file is parsed and analyzed again; if the fixes introduce a syntax error, \
+all of that file's fixes are rolled back."
+)]
+pub(super) struct FixArgs {
+    /// File or directory to fix
+    pub source: String,
+    pub config: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: FixFormat,
+    /// Write the fixed files in place
+    #[arg(long)]
+    pub write: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) enum FixFormat {
+    /// Unified diff
+    #[default]
+    Diff,
+    /// LSP workspace edit JSON
+    WorkspaceEdit,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct RulesArgs {
+    /// Config file (default: $COMPASS_CONFIG_TOML, or the built-in rules)
+    pub config: Option<String>,
+    /// Only rules that apply to this language
+    #[arg(long, value_name = "NAME")]
+    pub language: Option<String>,
+    /// Apply [profile.NAME] of the config first
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Select a rule preset: security, minimal, recommended or strict
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: TextOrJson,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = format!(
+    "Pass the token to the gated run with --allow-failure-token; the exemption is recorded \
+     in the audit log. Tokens are signed with ${}.",
+    exemption::KEY_ENV_VAR
+))]
+pub(super) struct ExemptArgs {
+    /// Why the gate may be bypassed
+    #[arg(long, value_name = "TEXT")]
+    pub reason: String,
+    /// How long the token is valid, e.g. 7d, 12h, 30m
+    #[arg(long, value_name = "DURATION", value_parser = duration)]
+    pub ttl: Duration,
+    /// Commit to exempt, as a hash, branch or tag (default: HEAD)
+    #[arg(long, value_name = "SHA")]
+    pub commit: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct VersionArgs {
+    /// Also check for a newer release
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Args, Debug)]
+pub(super) struct SelfUpdateArgs {
+    /// Reinstall even when this is the latest release
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = "Runs under a different config than the latest are marked stale.")]
+pub(super) struct HistoryArgs {
+    /// History file
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_HISTORY)]
+    pub file: String,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: HistoryFormat,
+    /// Only the most recent N runs
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+    /// Weight runs by age, halving every DURATION (e.g. 7d)
+    #[arg(long, value_name = "DURATION", value_parser = duration)]
+    pub half_life: Option<Duration>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub(super) enum HistoryFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Args, Debug)]
+#[command(after_help = "Exits non-zero when the new run regresses past the thresholds.")]
+pub(super) struct CompareArgs {
+    #[arg(value_name = "OLD.json")]
+    pub old: String,
+    #[arg(value_name = "NEW.json")]
+    pub new: String,
+    /// New issues allowed
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub max_new_issues: usize,
+    /// Score drop allowed (default: unchecked)
+    #[arg(long, value_name = "X")]
+    pub max_score_drop: Option<f64>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum CacheCommand {
+    /// Delete the results cached by directory and --srcs-file runs
+    Clear {
+        /// Cache directory
+        #[arg(long, value_name = "DIR", default_value = DEFAULT_CACHE_DIR)]
+        cache_dir: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum HookCommand {
+    /// Write a git pre-commit hook that runs `hook run` with the same options
+    Install {
+        #[command(flatten)]
+        gate: HookGate,
+        /// Replace a pre-commit hook compass didn't install
+        #[arg(long)]
+        force: bool,
+    },
+    /// Analyze the staged content of staged files; exit non-zero when they
+    /// score below --min-score or break the policy
+    Run {
+        #[command(flatten)]
+        gate: HookGate,
+    },
+}
+
+/// What `hook run` checks, and what `hook install` passes on to it.
+#[derive(Args, Debug)]
+pub(super) struct HookGate {
+    pub config: Option<String>,
+    /// Lowest passing score of the staged files
+    #[arg(long, value_name = "X")]
+    pub min_score: Option<f64>,
+    /// Policy file (default: the default policy file when present)
+    #[arg(long, value_name = "FILE")]
+    pub policy: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum ConfigCommand {
+    /// List the config layers, lowest precedence first: the built-in rules,
+    /// the user config, and the project config
+    Show {
+        /// Config file (default: $COMPASS_CONFIG_TOML, or the nearest compass.toml)
+        config: Option<String>,
+        /// Print the effective config the layers merge into
+        #[arg(long)]
+        resolved: bool,
+        /// Apply [profile.NAME] to the resolved config
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+        /// Select [presets.NAME] over the layers
+        #[arg(long, value_name = "NAME")]
+        preset: Option<String>,
+    },
+    /// Print the JSON Schema of the config format, for editor validation
+    /// and completion
+    Schema,
+}
+
+#[cfg(feature = "playground")]
+#[derive(Args, Debug)]
+pub(super) struct PlaygroundArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = DEFAULT_PLAYGROUND_ADDR)]
+    pub addr: String,
+    /// Requests per client address per minute (default: 30)
+    #[arg(long, value_name = "N")]
+    pub rate_limit: Option<u32>,
+}
+
+#[cfg(feature = "grpc")]
+#[derive(Args, Debug)]
+pub(super) struct GrpcArgs {
+    /// Config file (default: $COMPASS_CONFIG_TOML, or the built-in rules)
+    pub config: Option<String>,
+    /// Address to listen on
+    #[arg(long, value_name = "HOST:PORT", default_value = DEFAULT_GRPC_ADDR)]
+    pub addr: String,
+}
+
+/// The [`Cli`] command, with `--format` of analysis runs taking the names
+/// in `formatters`.
+pub(super) fn command(formatters: &FormatterRegistry) -> clap::Command {
+    let mut names: Vec<String> = CLI_FORMATS.iter().map(|name| name.to_string()).collect();
+    for name in formatters.names() {
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    let formats = |arg: clap::Arg| arg.value_parser(PossibleValuesParser::new(names.clone()));
+    let after_help = format!(
+        "A directory may be given instead of a file to analyze every supported file in it.\n\
+         Without a config file, the nearest {} or ${} (TOML contents) is used.\n\n\
+         Supported extensions: {}",
+        DEFAULT_CONFIG_FILE,
+        CONFIG_ENV_VAR,
+        LanguageRegistry::builtin().extensions_label()
+    );
+    let mut command = Cli::command()
+        .mut_arg("format", formats)
+        .after_help(after_help.clone());
+    for name in ANALYZE_COMMANDS {
+        command = command.mut_subcommand(name, |subcommand| {
+            subcommand
+                .mut_arg("format", formats)
+                .after_help(after_help.clone())
+        });
+    }
+    command
+}
+
+/// Parses one of `names` with the type's own `from_name`, the way config
+/// files spell it; clap rejects other values with the list and a
+/// suggestion.
+fn named<T>(
+    names: impl IntoIterator<Item = impl Into<PossibleValue>>,
+    from_name: fn(&str) -> Option<T>,
+) -> impl TypedValueParser<Value = T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    PossibleValuesParser::new(names)
+        .try_map(move |name| from_name(&name).ok_or("not a recognized name"))
+}
+
+fn severity() -> impl TypedValueParser<Value = Severity> {
+    named(["error", "warning", "info", "style"], Severity::from_name)
+}
+
+fn duration(value: &str) -> Result<Duration, String> {
+    config::parse_ttl(value)
+        .ok_or_else(|| "expected a duration such as 30s, 5m, 12h or 7d".to_string())
+}
+
+fn line_column(value: &str) -> Result<(usize, usize), String> {
+    value
+        .split_once(':')
+        .and_then(|(line, column)| Some((line.parse().ok()?, column.parse().ok()?)))
+        .ok_or_else(|| "expected <line>:<column>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging;
+    use clap::FromArgMatches;
+
+    #[test]
+    fn test_logging_flags_are_global_and_formats_come_from_the_registry() {
+        let parse = |args: &[&str]| {
+            command(&FormatterRegistry::builtin())
+                .try_get_matches_from(args)
+                .and_then(|matches| Cli::from_arg_matches(&matches))
+        };
+        let cli = parse(&["compass", "-vv", "src/", "--log-format", "json", "-v"]).unwrap();
+        assert_eq!((cli.verbose, cli.log_format), (3, LogFormat::Json));
+        assert_eq!(logging::level(cli.verbose), Some(tracing::Level::DEBUG));
+        assert_eq!(cli.analyze.source.as_deref(), Some("src/"));
+
+        let cli = parse(&["compass", "validate", "-v", "rules.toml"]).unwrap();
+        assert_eq!(cli.verbose, 1);
+        assert!(
+            matches!(cli.command, Some(Command::CheckConfig(args)) if args.config.as_deref() == Some("rules.toml"))
+        );
+
+        let cli = parse(&["compass", "summary", "--format", "SARIF", "src/"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Summary(flags)) if flags.format == ["SARIF"]));
+        assert!(parse(&["compass", "--format", "xml", "src/"]).is_err());
+        assert!(parse(&["compass", "--min-severity", "fatal", "src/"]).is_err());
+    }
+}
@@ -0,0 +1,3117 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::analyzer::{self, Severity, SyntaxError};
+use crate::api;
+use crate::ast;
+use crate::audit::{self, AuditRecord, PolicyFingerprint};
+use crate::badge;
+use crate::blame;
+use crate::cache::{Cache, CachedAnalysis};
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::ci::{CiProvider, ColorChoice};
+use crate::compare::{self, RunSnapshot};
+use crate::config::resolver::{self, Layer, LayerKind};
+use crate::config::AnalyzerConfig;
+use crate::config_test;
+use crate::discovery;
+use crate::exemption::{self, Exemption};
+use crate::export::{build_issue_payloads, ExportOptions, ExportState, FileFindings};
+use crate::fingerprint::sha256_hex;
+use crate::fix::{self, AppliedFix, FileFixes};
+use crate::grade::{self, Submission};
+use crate::history::{self, FileSnapshot, HistoryEntry};
+use crate::hook;
+use crate::hotspots::{self, FunctionMetrics};
+use crate::init;
+use crate::language::{self, LanguageDefinition, LanguageRegistry};
+use crate::logging;
+use crate::marketplace::RuleIndex;
+use crate::metrics::{self, FileMetrics};
+use crate::output::bazel::{self, BazelFile};
+use crate::output::json::format_score_as_json;
+use crate::output::jsonl;
+use crate::output::junit::{self, JunitFile};
+use crate::output::text::TextFormatter;
+use crate::output::{Formatter, FormatterRegistry};
+use crate::pairing;
+use crate::paths::user_config_file;
+use crate::policy::{Policy, DEFAULT_POLICY_FILE};
+use crate::position::{self, Columns, PositionEncoding};
+use crate::progress::{FileCounter, ProgressBar};
+use crate::ratings::Ratings;
+use crate::report::{
+    apply_grouping, project_report_with, FileReport, OutputGrouping, ProjectScore, ProjectTally,
+    ProjectWeighting,
+};
+use crate::similarity::{self, Fingerprints, SimilarPair};
+use crate::source::{self, SourceFile};
+use crate::template::render_template_file;
+use crate::timing::{self, FileTimings};
+use crate::update;
+use crate::workspace;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, FromArgMatches};
+use serde_json::{json, to_string_pretty, Value};
+
+mod args;
+
+#[cfg(feature = "grpc")]
+use args::GrpcArgs;
+#[cfg(feature = "playground")]
+use args::PlaygroundArgs;
+use args::{
+    AnalyzeFlags, AstArgs, CacheCommand, CheckConfigArgs, Cli, Command, CompareArgs, ConfigCommand,
+    ExemptArgs, ExplainArgs, ExportIssuesArgs, FixArgs, FixFormat, GradeArgs, GradeFormat,
+    HistoryArgs, HistoryFormat, HookCommand, HookGate, InitArgs, RulesArgs, SearchArgs,
+    SelfUpdateArgs, TestRuleArgs, TextOrJson, VersionArgs,
+};
+
+const DEFAULT_EXPORT_STATE: &str = ".compass/exported-issues";
+const DEFAULT_HISTORY: &str = ".compass/history.jsonl";
+const DEFAULT_TOP: usize = 10;
+const DEFAULT_FEEDBACK_DIR: &str = "feedback";
+const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.8;
+#[cfg(feature = "playground")]
+const DEFAULT_PLAYGROUND_ADDR: &str = "127.0.0.1:8080";
+#[cfg(feature = "grpc")]
+const DEFAULT_GRPC_ADDR: &str = "127.0.0.1:50051";
+const CONFIG_ENV_VAR: &str = "COMPASS_CONFIG_TOML";
+const RULE_INDEX_ENV_VAR: &str = "COMPASS_RULE_INDEX";
+
+pub fn run() {
+    run_with_formatters(FormatterRegistry::builtin());
+}
+
+/// [`run`] with `formatters` selectable by `--format`, so a wrapper binary
+/// can add its own output formats.
+pub fn run_with_formatters(formatters: FormatterRegistry) {
+    let program = env::args().next().unwrap_or_else(|| "compass".to_string());
+    let mut command = args::command(&formatters);
+    let matches = command.get_matches_mut();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.format(&mut command).exit());
+    logging::init(cli.verbose, cli.log_format);
+    TRUST_PROJECT_GRAMMARS.store(cli.trust_project_grammars, Ordering::Relaxed);
+
+    // Analysis runs, with the format used when no --format is given.
+    let (flags, default_format) = match cli.command {
+        None => (cli.analyze, None),
+        Some(Command::Analyze(flags)) => (flags, None),
+        Some(Command::Summary(flags)) => (flags, Some(OutputFormat::Summary)),
+        // An SVG badge unless --format asks for shields.io JSON.
+        Some(Command::Badge(flags)) => (flags, Some(OutputFormat::Badge)),
+        Some(Command::ExportIssues(args)) => {
+            run_export_issues(args);
+            return;
+        }
+        Some(Command::Version(args)) => {
+            run_version(args);
+            return;
+        }
+        Some(Command::SelfUpdate(args)) => {
+            run_self_update(args);
+            return;
+        }
+        Some(Command::History(args)) => {
+            run_history(args);
+            return;
+        }
+        Some(Command::Compare(args)) => {
+            run_compare(args);
+            return;
+        }
+        Some(Command::Grade(args)) => {
+            run_grade(args);
+            return;
+        }
+        Some(Command::Exempt(args)) => {
+            run_exempt(args);
+            return;
+        }
+        Some(Command::Search(args)) => {
+            run_search(args);
+            return;
+        }
+        Some(Command::Explain(args)) => {
+            run_explain(args);
+            return;
+        }
+        Some(Command::Rules(args)) => {
+            run_rules(args);
+            return;
+        }
+        Some(Command::Init(args)) => {
+            run_init(&program, args);
+            return;
+        }
+        Some(Command::TestRule(args)) => {
+            run_test_rule(args);
+            return;
+        }
+        Some(Command::Ast(args)) => {
+            run_ast(args);
+            return;
+        }
+        Some(Command::CheckConfig(args)) => {
+            run_check_config(args);
+            return;
+        }
+        Some(Command::Fix(args)) => {
+            run_fix(args);
+            return;
+        }
+        Some(Command::Cache(command)) => {
+            run_cache(command);
+            return;
+        }
+        Some(Command::Config(command)) => {
+            run_config(command);
+            return;
+        }
+        Some(Command::Hook(command)) => {
+            run_hook(&program, command);
+            return;
+        }
+        #[cfg(feature = "playground")]
+        Some(Command::Playground(args)) => {
+            run_playground(args);
+            return;
+        }
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc(args)) => {
+            run_grpc(args);
+            return;
+        }
+    };
+    let flag_matches = matches
+        .subcommand()
+        .map_or(&matches, |(_, subcommand)| subcommand);
+    let args = AnalyzeArgs::from_flags(
+        flags,
+        flag_matches,
+        default_format,
+        &formatters,
+        &mut command,
+    );
+    let mut config_source =
+        ConfigSource::resolve_with_preset(args.config_override.as_deref(), args.preset.as_deref());
+    if let Some(profile) = &args.profile {
+        config_source.apply_profile(profile);
+    }
+    let registry = config_source.language_registry();
+    for requirement in args
+        .require_grammar
+        .iter()
+        .chain(config_source.grammar_requirements())
+    {
+        if let Err(e) = registry.check_grammar(requirement) {
+            eprintln!("Error: grammar requirement {}", e);
+            process::exit(1);
+        }
+    }
+
+    // Cached results carry no timings, so --timings analyzes every file.
+    let cache = args
+        .cache_dir
+        .as_ref()
+        .filter(|_| !args.timings)
+        .map(Cache::new);
+    // Files of built-in languages this build has no grammar for, per
+    // language; directory and --srcs-file runs skip them.
+    let mut missing_grammars: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let options = FileOptions {
+        collect: Collect {
+            functions: args.has_format(&OutputFormat::Summary),
+            metrics: args.metrics,
+        },
+        severity_filter: args.severity_filter.clone(),
+        run: args
+            .total_timeout
+            .map(|timeout| CancellationToken::new().with_timeout(timeout))
+            .unwrap_or_default(),
+        timeout: args.timeout,
+        timed_out: RefCell::default(),
+    };
+    let analysis_started = Instant::now();
+    let policy_path = args.policy_file();
+    // Each file's JSON Lines go out as soon as it's analyzed. When nothing
+    // else reads the files, only the project totals outlive that.
+    let mut jsonl = JsonlStream::open(&args.outputs);
+    let keep_files = !args
+        .outputs
+        .iter()
+        .all(|output| output.format == OutputFormat::Jsonl)
+        || args.timings
+        || args.metrics
+        || policy_path.is_some()
+        || args.audit_log.is_some()
+        || args.history_file.is_some();
+    let mut analyzed_files = Vec::new();
+    let mut tally = ProjectTally::new(config_source.project_weighting());
+    let mut parse_failures = Vec::new();
+    let mut finish = |mut analyzed: AnalyzedFile| {
+        if let Some(stream) = &mut jsonl {
+            stream.write(&mut analyzed.report);
+        }
+        tally.add(&analyzed.report);
+        if args.fail_on_parse_error {
+            parse_failures.extend(
+                analyzed
+                    .syntax_errors
+                    .iter()
+                    .map(|error| format!("{}:{}", analyzed.report.path, error)),
+            );
+        }
+        if keep_files {
+            analyzed_files.push(analyzed);
+        }
+    };
+    if let Some(srcs_file) = &args.srcs_file {
+        let listed = fs::read_to_string(srcs_file).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read '{}': {}", srcs_file, e);
+            process::exit(1);
+        });
+        if !args.porcelain {
+            eprintln!("Analyzing source set from {}", srcs_file);
+            eprintln!("Config: {}", config_source.label());
+            eprintln!("----------------------------------------");
+        }
+        listed
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .filter_map(|path| {
+                if let Some(missing) = registry.missing_grammar(path) {
+                    tracing::debug!(
+                        path,
+                        language = missing.name,
+                        "skipped file: grammar not compiled in"
+                    );
+                    *missing_grammars.entry(missing.name).or_default() += 1;
+                    return None;
+                }
+                analyze_source_file(
+                    &registry,
+                    path,
+                    &config_source,
+                    false,
+                    &options,
+                    cache.as_ref(),
+                )
+            })
+            .for_each(&mut finish);
+    } else if args.stdin {
+        let language = args
+            .language
+            .as_deref()
+            .and_then(|name| registry.get(name))
+            .cloned()
+            .unwrap_or_else(|| {
+                let missing = args
+                    .language
+                    .as_deref()
+                    .and_then(language::builtin_language)
+                    .filter(|language| !language.compiled);
+                if let Some(missing) = missing {
+                    eprintln!(
+                        "Error: compass supports {} but this build was compiled without it; {}",
+                        missing.display_name,
+                        missing.install_hint()
+                    );
+                    process::exit(1);
+                }
+                eprintln!(
+                    "Error: reading from stdin requires --language <name> (one of: {})",
+                    registry
+                        .iter()
+                        .map(|language| language.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                process::exit(1);
+            });
+        let mut bytes = Vec::new();
+        if let Err(e) = io::stdin().read_to_end(&mut bytes) {
+            eprintln!("Error: failed to read stdin: {}", e);
+            process::exit(1);
+        }
+        let max_file_size = config_source.max_file_size();
+        if max_file_size > 0 && bytes.len() as u64 > max_file_size {
+            eprintln!(
+                "Error: stdin is {} bytes, over max_file_size ({} bytes)",
+                bytes.len(),
+                max_file_size
+            );
+            process::exit(1);
+        }
+        let source = SourceFile::from_bytes(bytes);
+        if !args.porcelain {
+            eprintln!("Analyzing {} source from stdin", language.display_name);
+            eprintln!("Config: {}", config_source.label());
+            eprintln!("----------------------------------------");
+        }
+        let analyzed = analyze_source("<stdin>", language, &source, &config_source, true, &options)
+            .expect("strict analysis exits instead of skipping");
+        finish(analyzed);
+    } else if Path::new(&args.source_path).is_dir() {
+        let root = Path::new(&args.source_path);
+        let files = discovery::collect_files(root, |path| {
+            (registry.detect(path).is_some() || registry.missing_grammar(path).is_some())
+                && !config_source.excludes(root, path)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Error: failed to scan '{}': {}", args.source_path, e);
+            process::exit(1);
+        });
+        tracing::info!(
+            root = %args.source_path,
+            files = files.len(),
+            elapsed_ms = timing::millis(analysis_started.elapsed()),
+            "discovered files"
+        );
+        if !args.porcelain {
+            eprintln!("Analyzing {} files under {}", files.len(), args.source_path);
+            eprintln!("Config: {}", config_source.label());
+            eprintln!("----------------------------------------");
+        }
+        // Log lines would tear through the bar.
+        let logging = tracing::enabled!(tracing::Level::INFO);
+        let mut bar =
+            (!args.quiet && !logging && io::stderr().is_terminal()).then(ProgressBar::new);
+        let mut counter = FileCounter::new(files.len());
+        files
+            .iter()
+            .filter_map(|path| {
+                if let Some(bar) = &mut bar {
+                    bar.update(&counter.start(&path.to_string_lossy()));
+                }
+                if let Some(missing) = registry.missing_grammar(path) {
+                    tracing::debug!(path = %path.display(), language = missing.name, "skipped file: grammar not compiled in");
+                    *missing_grammars.entry(missing.name).or_default() += 1;
+                    return None;
+                }
+                analyze_source_file(
+                    &registry,
+                    &path.to_string_lossy(),
+                    &config_source,
+                    false,
+                    &options,
+                    cache.as_ref(),
+                )
+            })
+            .for_each(&mut finish);
+        if let Some(bar) = &mut bar {
+            bar.finish();
+        }
+    } else {
+        let analyzed = analyze_source_file(
+            &registry,
+            &args.source_path,
+            &config_source,
+            true,
+            &options,
+            None,
+        )
+        .expect("strict analysis exits instead of skipping");
+        // Only the selected format goes to stdout so `compass ... | jq` works;
+        // the banner is diagnostic chatter and belongs on stderr.
+        if !args.porcelain {
+            eprintln!(
+                "Analyzing {} file with custom preferences: {}",
+                analyzed.language.display_name, args.source_path
+            );
+            eprintln!("Config: {}", analyzed.config_label);
+            eprintln!("----------------------------------------");
+        }
+        finish(analyzed);
+    }
+    if options.run.is_cancelled() {
+        eprintln!(
+            "Warning: --total-timeout reached; files not yet analyzed were skipped and the results are incomplete"
+        );
+    }
+    let timed_out = options.timed_out.into_inner();
+    let project_score = tally.score().with_ratings(&config_source.ratings());
+    tracing::info!(
+        files = project_score.files_analyzed,
+        elapsed_ms = timing::millis(analysis_started.elapsed()),
+        "analyzed files"
+    );
+    for (name, files) in &missing_grammars {
+        let language = language::builtin_language(name).expect("missing grammars are built-in");
+        eprintln!(
+            "Warning: skipped {} {} file(s): compass supports {} but this build was compiled without it; {}",
+            files,
+            language.display_name,
+            language.display_name,
+            language.install_hint()
+        );
+    }
+
+    let weighting = config_source.project_weighting();
+    let is_multi_file = args.srcs_file.is_some() || Path::new(&args.source_path).is_dir();
+    let mut file_reports: Vec<FileReport> = analyzed_files
+        .iter()
+        .map(|analyzed| analyzed.report.clone())
+        .collect();
+    let timings: Vec<(&str, &FileTimings)> = analyzed_files
+        .iter()
+        .map(|analyzed| (analyzed.report.path.as_str(), &analyzed.timings))
+        .collect();
+    let file_metrics: Vec<(&str, &FileMetrics)> = analyzed_files
+        .iter()
+        .filter_map(|analyzed| {
+            let metrics = analyzed.metrics.as_ref()?;
+            Some((analyzed.report.path.as_str(), metrics))
+        })
+        .collect();
+
+    let ci = CiProvider::detect();
+    // `file_reports` count columns in the output's encoding.
+    let render = |format: &OutputFormat, to_stdout: bool, file_reports: &[FileReport]| -> String {
+        match format {
+            OutputFormat::Json => {
+                let formatted = if is_multi_file {
+                    let mut report = project_report_with(file_reports, &project_score);
+                    if !missing_grammars.is_empty() {
+                        report["summary"]["missing_grammars"] =
+                            missing_grammars_json(&missing_grammars);
+                    }
+                    if !timed_out.is_empty() {
+                        report["summary"]["timed_out"] = json!(timed_out);
+                    }
+                    if let Some(packages) = workspace_packages(&args) {
+                        report["packages"] = workspace::package_breakdown(
+                            &packages,
+                            file_reports,
+                            weighting,
+                            &config_source.ratings(),
+                        );
+                    }
+                    apply_grouping(&mut report, file_reports, args.group_by);
+                    if args.timings {
+                        report["timings"] = timing::timings_report(&timings);
+                    }
+                    if args.metrics {
+                        report["metrics"] = metrics::metrics_report(&file_metrics);
+                    }
+                    to_string_pretty(&report)
+                } else {
+                    // Single-file JSON keeps its original shape.
+                    let file = &file_reports[0];
+                    let mut report = format_score_as_json(&file.results, &file.score);
+                    if let Some(grammar) = &file.grammar {
+                        report["grammar"] = json!(grammar);
+                    }
+                    apply_grouping(&mut report, file_reports, args.group_by);
+                    if args.timings {
+                        report["timings"] = timing::timings_report(&timings);
+                    }
+                    if args.metrics {
+                        report["metrics"] = metrics::metrics_report(&file_metrics);
+                    }
+                    to_string_pretty(&report)
+                };
+                match formatted {
+                    Ok(json) => format!("{}\n", json),
+                    Err(e) => {
+                        eprintln!("Error: failed to format analysis result: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            OutputFormat::Bazel => {
+                let target = args.target.as_deref().unwrap_or("//:compass");
+                let output_dir = args.output_dir.as_deref().unwrap_or_else(|| {
+                    eprintln!("Error: --format bazel requires --output-dir <dir>");
+                    process::exit(1);
+                });
+                // Result files hold the path-independent report so identical
+                // content produces identical, shareable outputs.
+                let raw_reports: Vec<Value> = file_reports
+                    .iter()
+                    .map(|report| format_score_as_json(&report.results, &report.score))
+                    .collect();
+                let files: Vec<BazelFile> = analyzed_files
+                    .iter()
+                    .zip(&raw_reports)
+                    .map(|(analyzed, report)| BazelFile {
+                        path: &analyzed.report.path,
+                        source_hash: &analyzed.source_hash,
+                        config_hash: &analyzed.config_hash,
+                        report,
+                    })
+                    .collect();
+                match bazel::write_outputs(target, Path::new(output_dir), &files)
+                    .map_err(|e| e.to_string())
+                    .and_then(|manifest| to_string_pretty(&manifest).map_err(|e| e.to_string()))
+                {
+                    Ok(manifest) => format!("{}\n", manifest),
+                    Err(e) => {
+                        eprintln!("Error: failed to write Bazel outputs: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            OutputFormat::Summary => {
+                let functions: Vec<FunctionMetrics> = analyzed_files
+                    .iter()
+                    .flat_map(|analyzed| analyzed.functions.iter().cloned())
+                    .collect();
+                let report =
+                    hotspots::summary_report(file_reports, &functions, &project_score, args.top);
+                match to_string_pretty(&report) {
+                    Ok(json) => format!("{}\n", json),
+                    Err(e) => {
+                        eprintln!("Error: failed to format summary: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            // The files were streamed as they were analyzed.
+            OutputFormat::Jsonl => jsonl::summary_line(&project_score),
+            OutputFormat::Badge => badge::svg(&project_score, &config_source.ratings()),
+            OutputFormat::Shields => {
+                match to_string_pretty(&badge::shields_endpoint(
+                    &project_score,
+                    &config_source.ratings(),
+                )) {
+                    Ok(json) => format!("{}\n", json),
+                    Err(e) => {
+                        eprintln!("Error: failed to format badge: {}", e);
+                        process::exit(1);
+                    }
+                }
+            }
+            OutputFormat::Junit => {
+                let files: Vec<JunitFile> = analyzed_files
+                    .iter()
+                    .zip(file_reports)
+                    .map(|(analyzed, report)| JunitFile {
+                        report,
+                        rules: &analyzed.rules,
+                    })
+                    .collect();
+                junit::render(&files)
+            }
+            OutputFormat::Registered(name) if name == "text" => TextFormatter {
+                color: args
+                    .color
+                    .enabled(to_stdout && io::stdout().is_terminal(), ci),
+            }
+            .format(file_reports),
+            OutputFormat::Registered(name) => {
+                let formatter = formatters
+                    .get(name)
+                    .expect("format names are checked when parsed");
+                formatter.format(file_reports)
+            }
+            OutputFormat::Template => {
+                let template_path = args.template.as_deref().unwrap_or_else(|| {
+                    eprintln!("Error: --format template requires --template <file>");
+                    process::exit(1);
+                });
+                let mut rendered = String::new();
+                for (analyzed, report) in analyzed_files.iter().zip(file_reports) {
+                    match render_template_file(template_path, &template_data(analyzed, report)) {
+                        Ok(file) => rendered.push_str(&file),
+                        Err(e) => {
+                            eprintln!(
+                                "Error: failed to render template '{}': {}",
+                                template_path, e
+                            );
+                            process::exit(1);
+                        }
+                    }
+                }
+                rendered
+            }
+        }
+    };
+    let output_started = Instant::now();
+    for output in &args.outputs {
+        for report in &mut file_reports {
+            position::reencode(&mut report.results, output.column_encoding);
+        }
+        let rendered = render(&output.format, output.path.is_none(), &file_reports);
+        if let (OutputFormat::Jsonl, Some(stream)) = (&output.format, &mut jsonl) {
+            stream.write_to(output.path.as_deref(), &rendered);
+            continue;
+        }
+        match &output.path {
+            Some(path) => {
+                if let Err(e) = fs::write(path, rendered) {
+                    eprintln!("Error: failed to write '{}': {}", path, e);
+                    process::exit(1);
+                }
+            }
+            None => print!("{}", rendered),
+        }
+    }
+    tracing::info!(
+        outputs = args.outputs.len(),
+        elapsed_ms = timing::millis(output_started.elapsed()),
+        "wrote output"
+    );
+    // Other formats have no place for timings, which go to stderr instead.
+    if args.timings && !args.has_format(&OutputFormat::Json) {
+        eprint!("{}", timing::timings_table(&timings));
+    }
+    if args.metrics && !args.has_format(&OutputFormat::Json) {
+        eprint!("{}", metrics::metrics_table(&file_metrics));
+    }
+
+    let violations = match &policy_path {
+        Some(path) => {
+            let policy = Policy::from_file(path).unwrap_or_else(|e| {
+                eprintln!("Error: invalid policy '{}': {}", path, e);
+                process::exit(1);
+            });
+            let mut enabled_rules: BTreeMap<String, BTreeMap<String, Severity>> = BTreeMap::new();
+            for analyzed in &analyzed_files {
+                enabled_rules
+                    .entry(analyzed.language.name.clone())
+                    .or_default()
+                    .extend(analyzed.rule_severities.clone());
+            }
+            policy.violations(&file_reports, &enabled_rules, weighting)
+        }
+        None => Vec::new(),
+    };
+    let exemption = match &args.allow_failure_token {
+        Some(token) if !violations.is_empty() => verify_exemption(token),
+        _ => None,
+    };
+    // Exemptions waive policy violations, not files that fail to parse.
+    let exit_code = if (violations.is_empty() || exemption.is_some()) && parse_failures.is_empty() {
+        0
+    } else {
+        1
+    };
+
+    if let Some(destination) = &args.audit_log {
+        let record = audit_record(
+            &analyzed_files,
+            &config_source,
+            policy_path.as_deref(),
+            &violations,
+            exemption.clone(),
+            project_score.clone(),
+        );
+        if let Err(e) = audit::write(destination, &record) {
+            eprintln!(
+                "Error: failed to write audit record to '{}': {}",
+                destination, e
+            );
+            process::exit(1);
+        }
+    }
+
+    if let Some(summary_path) = &args.summary_file {
+        let mut summary = exit_summary(&project_score, exit_code);
+        if !missing_grammars.is_empty() {
+            summary["missing_grammars"] = missing_grammars_json(&missing_grammars);
+        }
+        if !timed_out.is_empty() {
+            summary["timed_out"] = json!(timed_out);
+        }
+        let written = to_string_pretty(&summary)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(summary_path, json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("Error: failed to write summary '{}': {}", summary_path, e);
+            process::exit(1);
+        }
+    }
+
+    if let Some(history_path) = &args.history_file {
+        let files = analyzed_files
+            .iter()
+            .map(|analyzed| {
+                let snapshot = FileSnapshot {
+                    sha256: analyzed.source_hash.clone(),
+                    config: analyzed.config_hash.clone(),
+                    score: analyzed.report.score.overall_score,
+                };
+                (analyzed.report.path.clone(), snapshot)
+            })
+            .collect();
+        let entry =
+            HistoryEntry::record(&project_score, config_fingerprint(&analyzed_files), files);
+        if let Err(e) = history::append(history_path, &entry) {
+            eprintln!("Error: failed to record history '{}': {}", history_path, e);
+            process::exit(1);
+        }
+    }
+
+    for failure in &parse_failures {
+        eprintln!("Parse error: {}", failure);
+    }
+    if !violations.is_empty() {
+        for violation in &violations {
+            eprintln!("Policy violation: {}", violation);
+        }
+        if let Some(exemption) = &exemption {
+            eprintln!(
+                "Gate failure exempted until {} UTC: {}",
+                history::format_timestamp(exemption.expires_at),
+                exemption.reason
+            );
+        }
+        process::exit(exit_code);
+    }
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
+
+/// The exemption `token` grants to the current commit, if it is valid.
+/// Rejected tokens are reported and leave the gate failing.
+fn verify_exemption(token: &str) -> Option<Exemption> {
+    let verified = env::var(exemption::KEY_ENV_VAR)
+        .map_err(|_| format!("{} is not set", exemption::KEY_ENV_VAR))
+        .and_then(|key| {
+            let commit = history::current_commit().ok_or("not in a git repository")?;
+            Exemption::verify(token, key.as_bytes(), &commit, unix_now())
+        });
+    match verified {
+        Ok(exemption) => Some(exemption),
+        Err(e) => {
+            eprintln!("Error: exemption token rejected: {}", e);
+            None
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// SHA-256 over every config used. Built-in configs differ per language,
+/// so each is fingerprinted.
+fn config_fingerprint(analyzed_files: &[AnalyzedFile]) -> String {
+    let mut config_hashes: Vec<&str> = analyzed_files
+        .iter()
+        .map(|analyzed| analyzed.config_hash.as_str())
+        .collect();
+    config_hashes.sort();
+    config_hashes.dedup();
+    sha256_hex(config_hashes.join("\n").as_bytes())
+}
+
+/// The audit trail entry for this run's gate decision.
+fn audit_record(
+    analyzed_files: &[AnalyzedFile],
+    config_source: &ConfigSource,
+    policy_path: Option<&str>,
+    violations: &[String],
+    exemption: Option<Exemption>,
+    score: ProjectScore,
+) -> AuditRecord {
+    let inputs = analyzed_files
+        .iter()
+        .map(|analyzed| (analyzed.report.path.clone(), analyzed.source_hash.clone()))
+        .collect();
+    let policy = policy_path.map(|path| PolicyFingerprint {
+        path: path.to_string(),
+        sha256: fs::read(path)
+            .map(|content| sha256_hex(&content))
+            .unwrap_or_default(),
+    });
+    AuditRecord::record(
+        &score,
+        inputs,
+        config_fingerprint(analyzed_files),
+        policy,
+        violations.to_vec(),
+        exemption,
+        config_source.severity_overrides(),
+    )
+}
+
+/// Packages of the monorepo being scanned, when the source is a directory
+/// that declares them.
+fn workspace_packages(args: &AnalyzeArgs) -> Option<Vec<workspace::WorkspacePackage>> {
+    if args.srcs_file.is_some() {
+        return None;
+    }
+    let root = Path::new(&args.source_path);
+    if !root.is_dir() {
+        return None;
+    }
+    workspace::detect_packages(root)
+}
+
+/// Files skipped for a missing grammar, per language, and the feature
+/// that would analyze them.
+fn missing_grammars_json(missing_grammars: &BTreeMap<&str, usize>) -> Value {
+    missing_grammars
+        .iter()
+        .map(|(name, files)| {
+            let language = language::builtin_language(name).expect("missing grammars are built-in");
+            (
+                name.to_string(),
+                json!({ "files": files, "feature": language.feature }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Machine-readable outcome of a run, written by `--summary-file` so
+/// container orchestrators can inspect results without parsing stdout.
+fn exit_summary(project_score: &ProjectScore, exit_code: i32) -> Value {
+    let mut summary = project_score.to_json();
+    summary["exit_code"] = Value::from(exit_code);
+    summary
+}
+
+/// Where the rule configuration comes from: the user config with the
+/// project config (an explicit config file, the `COMPASS_CONFIG_TOML`
+/// environment variable, handy for containers, or the nearest
+/// `compass.toml`) merged over it, or, when there is neither, the built-in
+/// config for each file's language.
+enum ConfigSource {
+    /// One or more config files; the label joins their paths.
+    File(String, AnalyzerConfig),
+    Env(AnalyzerConfig),
+    BuiltIn,
+}
+
+impl ConfigSource {
+    fn resolve(config_override: Option<&str>) -> Self {
+        Self::resolve_with_preset(config_override, None)
+    }
+
+    /// [`resolve`](Self::resolve), selecting `[presets.<name>]` over the
+    /// config layers.
+    fn resolve_with_preset(config_override: Option<&str>, preset: Option<&str>) -> Self {
+        let mut layers = config_layers(config_override);
+        if let Some(name) = preset {
+            let alone = layers.is_empty();
+            layers.push(resolver::preset_layer(name, alone));
+        }
+        for layer in &layers {
+            tracing::debug!(layer = %layer.label, kind = ?layer.kind, "config layer");
+        }
+        let label = layers
+            .iter()
+            .map(|layer| layer.label.as_str())
+            .collect::<Vec<_>>()
+            .join(" + ");
+        let from_env = matches!(
+            &layers[..],
+            [layer] if layer.kind == LayerKind::Project && layer.path.is_none()
+        );
+        let config = resolver::resolve(&layers).unwrap_or_else(|e| {
+            if from_env {
+                eprintln!("Error: failed to parse {}: {}", CONFIG_ENV_VAR, e);
+            } else {
+                eprintln!("Error: failed to load config '{}': {}", label, e);
+            }
+            process::exit(1);
+        });
+        let source = match config {
+            None => ConfigSource::BuiltIn,
+            Some(config) if from_env => ConfigSource::Env(config),
+            Some(config) => ConfigSource::File(label, config),
+        };
+        tracing::info!(config = %source.label(), "loaded config");
+        source
+    }
+
+    /// Applies `[profile.<name>]` of the config, exiting when it has none.
+    fn apply_profile(&mut self, name: &str) {
+        let applied = match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => config.apply_profile(name),
+            ConfigSource::BuiltIn => Err(format!(
+                "--profile needs a config file or ${}",
+                CONFIG_ENV_VAR
+            )),
+        };
+        if let Err(e) = applied {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    /// Built-in languages plus any external grammars the config declares.
+    fn language_registry(&self) -> LanguageRegistry {
+        let mut registry = LanguageRegistry::builtin();
+        let (config, base_dir) = match self {
+            ConfigSource::File(path, config) => (
+                config,
+                Path::new(path)
+                    .parent()
+                    .unwrap_or(Path::new("."))
+                    .to_path_buf(),
+            ),
+            ConfigSource::Env(config) => (config, PathBuf::from(".")),
+            ConfigSource::BuiltIn => return registry,
+        };
+
+        if let Err(e) = registry.register_from_config(config, &base_dir) {
+            eprintln!("Error: failed to load grammar: {}", e);
+            process::exit(1);
+        }
+        registry
+    }
+
+    /// Hash of the effective config; empty for built-in configs, which are
+    /// hashed per language instead.
+    fn content_hash(&self) -> String {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => {
+                let serialized = toml::to_string(config).unwrap_or_default();
+                sha256_hex(serialized.as_bytes())
+            }
+            ConfigSource::BuiltIn => String::new(),
+        }
+    }
+
+    /// `[severity_overrides]` of the config; built-in configs have none.
+    fn severity_overrides(&self) -> BTreeMap<String, String> {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => {
+                config.severity_overrides.clone()
+            }
+            ConfigSource::BuiltIn => BTreeMap::new(),
+        }
+    }
+
+    /// `require_grammar` of the config; built-in configs have none.
+    fn grammar_requirements(&self) -> &[String] {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => &config.require_grammar,
+            ConfigSource::BuiltIn => &[],
+        }
+    }
+
+    /// `max_file_size` of the config, or the default for built-in configs.
+    fn max_file_size(&self) -> u64 {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => config.max_file_size(),
+            ConfigSource::BuiltIn => source::DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+
+    /// `skip_undecodable` of the config; built-in configs decode everything.
+    fn skips_undecodable(&self) -> bool {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => config.skip_undecodable,
+            ConfigSource::BuiltIn => false,
+        }
+    }
+
+    /// Whether a rule dates its findings with `git blame` (`older_than`).
+    fn uses_blame(&self) -> bool {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => config
+                .rules
+                .iter()
+                .any(|rule| rule.enabled && rule.older_than.is_some()),
+            ConfigSource::BuiltIn => false,
+        }
+    }
+
+    /// Whether `path`, found under `root`, matches the config's `exclude`.
+    fn excludes(&self, root: &Path, path: &Path) -> bool {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => {
+                config.excludes(path.strip_prefix(root).unwrap_or(path))
+            }
+            ConfigSource::BuiltIn => false,
+        }
+    }
+
+    /// Project score weighting from `[scoring]`; built-in configs use the
+    /// default.
+    fn project_weighting(&self) -> ProjectWeighting {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => {
+                config.scoring.project_weighting
+            }
+            ConfigSource::BuiltIn => ProjectWeighting::default(),
+        }
+    }
+
+    /// Rating bands and summaries from `[ratings]`; built-in configs use
+    /// the default.
+    fn ratings(&self) -> Ratings {
+        match self {
+            ConfigSource::File(_, config) | ConfigSource::Env(config) => config.ratings.clone(),
+            ConfigSource::BuiltIn => Ratings::default(),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            ConfigSource::File(path, _) => path.clone(),
+            ConfigSource::Env(_) => format!("${}", CONFIG_ENV_VAR),
+            ConfigSource::BuiltIn => "built-in".to_string(),
+        }
+    }
+}
+
+/// The config layers that apply, lowest precedence first: the user config,
+/// then the project config.
+/// Set by `--trust-project-grammars`.
+static TRUST_PROJECT_GRAMMARS: AtomicBool = AtomicBool::new(false);
+
+fn config_layers(config_override: Option<&str>) -> Vec<Layer> {
+    let read = |kind: LayerKind, path: &Path| {
+        Layer::from_file(kind, path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to load config '{}': {}", path.display(), e);
+            process::exit(1);
+        })
+    };
+    let mut layers = Vec::new();
+    if let Some(path) = resolver::user_config() {
+        layers.push(read(LayerKind::User, &path));
+    }
+
+    if let Some(path) = config_override {
+        layers.push(read(LayerKind::Project, Path::new(path)));
+        return layers;
+    }
+    match env::var(CONFIG_ENV_VAR) {
+        Ok(contents) if !contents.trim().is_empty() => {
+            let label = format!("${}", CONFIG_ENV_VAR);
+            let layer = Layer::from_str(LayerKind::Project, &label, &contents, Path::new(""))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: failed to parse {}: {}", CONFIG_ENV_VAR, e);
+                    process::exit(1);
+                });
+            layers.push(layer);
+        }
+        _ => {
+            let project = env::current_dir()
+                .ok()
+                .and_then(|dir| resolver::find_project_config(&dir));
+            if let Some(path) = project {
+                let layer = read(LayerKind::Project, &path);
+                // A compass.toml found by walking up from the working
+                // directory comes with whatever was checked out, so it
+                // doesn't get to run native code unless the user says so.
+                let libraries = layer.grammar_libraries();
+                if !libraries.is_empty() && !TRUST_PROJECT_GRAMMARS.load(Ordering::Relaxed) {
+                    eprintln!(
+                        "Error: '{}' loads grammar libraries, which run native code: {}",
+                        path.display(),
+                        libraries.join(", ")
+                    );
+                    eprintln!(
+                        "Pass --trust-project-grammars if you trust them, or declare the grammars in your user config."
+                    );
+                    process::exit(1);
+                }
+                layers.push(layer);
+            }
+        }
+    }
+    layers
+}
+
+#[derive(Clone, PartialEq)]
+enum OutputFormat {
+    Json,
+    Template,
+    Bazel,
+    Summary,
+    Junit,
+    /// JSON Lines, streamed while files are analyzed.
+    Jsonl,
+    /// The project score as an SVG badge.
+    Badge,
+    /// The project score as shields.io endpoint JSON.
+    Shields,
+    /// A format from the [`FormatterRegistry`].
+    Registered(String),
+}
+
+impl OutputFormat {
+    /// Formats with CLI-specific behavior come first; any other name must
+    /// be in `formatters`.
+    fn from_name(name: &str, formatters: &FormatterRegistry) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(OutputFormat::Json),
+            "template" => Some(OutputFormat::Template),
+            "bazel" => Some(OutputFormat::Bazel),
+            "summary" => Some(OutputFormat::Summary),
+            "junit" => Some(OutputFormat::Junit),
+            "jsonl" => Some(OutputFormat::Jsonl),
+            "badge" => Some(OutputFormat::Badge),
+            "shields" => Some(OutputFormat::Shields),
+            name => formatters
+                .get(name)
+                .map(|_| OutputFormat::Registered(name.to_string())),
+        }
+    }
+}
+
+/// Where `--format jsonl` outputs go, and the columns each counts in, kept
+/// open for the whole run.
+struct JsonlStream(Vec<(Option<String>, PositionEncoding, Box<dyn Write>)>);
+
+impl JsonlStream {
+    fn open(outputs: &[Output]) -> Option<Self> {
+        let writers: Vec<(Option<String>, PositionEncoding, Box<dyn Write>)> = outputs
+            .iter()
+            .filter(|output| output.format == OutputFormat::Jsonl)
+            .map(|output| {
+                let writer: Box<dyn Write> = match &output.path {
+                    Some(path) => {
+                        Box::new(BufWriter::new(fs::File::create(path).unwrap_or_else(|e| {
+                            eprintln!("Error: failed to write '{}': {}", path, e);
+                            process::exit(1);
+                        })))
+                    }
+                    None => Box::new(io::stdout()),
+                };
+                (output.path.clone(), output.column_encoding, writer)
+            })
+            .collect();
+        (!writers.is_empty()).then_some(JsonlStream(writers))
+    }
+
+    /// Appends the lines of `report` to every output, in its column
+    /// encoding, flushed so readers see them now.
+    fn write(&mut self, report: &mut FileReport) {
+        for (path, encoding, writer) in &mut self.0 {
+            position::reencode(&mut report.results, *encoding);
+            Self::write_lines(path.as_deref(), writer, &jsonl::file_lines(report));
+        }
+    }
+
+    /// Appends `lines` to the output at `path`, or to stdout.
+    fn write_to(&mut self, path: Option<&str>, lines: &str) {
+        if let Some((path, _, writer)) = self.0.iter_mut().find(|(p, _, _)| p.as_deref() == path) {
+            Self::write_lines(path.as_deref(), writer, lines);
+        }
+    }
+
+    fn write_lines(path: Option<&str>, writer: &mut Box<dyn Write>, lines: &str) {
+        if let Err(e) = writer
+            .write_all(lines.as_bytes())
+            .and_then(|_| writer.flush())
+        {
+            eprintln!(
+                "Error: failed to write '{}': {}",
+                path.unwrap_or("<stdout>"),
+                e
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// One `--format`, the `--output` file it is written to instead of
+/// stdout, and the `--column-encoding` its columns count in.
+struct Output {
+    format: OutputFormat,
+    path: Option<String>,
+    column_encoding: PositionEncoding,
+}
+
+struct AnalyzeArgs {
+    source_path: String,
+    config_override: Option<String>,
+    /// Every format of the run, rendered from the same analysis.
+    outputs: Vec<Output>,
+    template: Option<String>,
+    porcelain: bool,
+    /// No progress bar for directories.
+    quiet: bool,
+    /// `--timeout`: how long each file may take before it is skipped.
+    timeout: Option<Duration>,
+    /// `--total-timeout`: how long the whole run may take.
+    total_timeout: Option<Duration>,
+    summary_file: Option<String>,
+    srcs_file: Option<String>,
+    target: Option<String>,
+    output_dir: Option<String>,
+    stdin: bool,
+    language: Option<String>,
+    history_file: Option<String>,
+    group_by: OutputGrouping,
+    top: usize,
+    policy: Option<String>,
+    ci: bool,
+    audit_log: Option<String>,
+    allow_failure_token: Option<String>,
+    severity_filter: SeverityFilter,
+    require_grammar: Vec<String>,
+    profile: Option<String>,
+    preset: Option<String>,
+    fail_on_parse_error: bool,
+    timings: bool,
+    metrics: bool,
+    /// Where directory and `--srcs-file` runs cache results; `None` with
+    /// `--no-cache`.
+    cache_dir: Option<String>,
+    color: ColorChoice,
+}
+
+/// Lowest severities kept by `--min-severity` and `--score-min-severity`.
+#[derive(Default, Clone)]
+struct SeverityFilter {
+    /// Findings below this are dropped from the output.
+    shown: Severity,
+    /// Findings below this do not lower the score.
+    scored: Severity,
+}
+
+impl AnalyzeArgs {
+    /// Resolves parsed `flags`. Each `--output` and `--column-encoding`
+    /// goes with the `--format` before it on the command line, as `matches`
+    /// tells, and a `--column-encoding` before any `--format` with every
+    /// format; without any `--format`, `default_format`, the CI service's
+    /// own format, or JSON.
+    fn from_flags(
+        flags: AnalyzeFlags,
+        matches: &ArgMatches,
+        default_format: Option<OutputFormat>,
+        formatters: &FormatterRegistry,
+        command: &mut clap::Command,
+    ) -> AnalyzeArgs {
+        enum Flag {
+            Format(OutputFormat),
+            Output(String),
+            ColumnEncoding(PositionEncoding),
+        }
+
+        let ci_provider = CiProvider::detect();
+        let ci_format = ci_provider
+            .and_then(|ci| ci.default_format())
+            .and_then(|name| OutputFormat::from_name(name, formatters))
+            .filter(|_| default_format.is_none());
+        let fallback = default_format
+            .or_else(|| ci_format.clone())
+            .unwrap_or(OutputFormat::Json);
+        let mut defaulted = false;
+
+        let positions = |id: &str| matches.indices_of(id).into_iter().flatten();
+        let mut in_order: Vec<(usize, Flag)> = positions("format")
+            .zip(&flags.format)
+            .map(|(index, name)| {
+                let format = OutputFormat::from_name(name, formatters)
+                    .expect("format names are checked when parsed");
+                (index, Flag::Format(format))
+            })
+            .chain(
+                positions("output")
+                    .zip(flags.output)
+                    .map(|(index, path)| (index, Flag::Output(path))),
+            )
+            .chain(
+                positions("column_encoding")
+                    .zip(flags.column_encoding)
+                    .map(|(index, encoding)| (index, Flag::ColumnEncoding(encoding))),
+            )
+            .collect();
+        if matches.value_source("summary_only") == Some(ValueSource::CommandLine) {
+            in_order.extend(
+                positions("summary_only").map(|index| (index, Flag::Format(OutputFormat::Summary))),
+            );
+        }
+        in_order.sort_by_key(|(index, _)| *index);
+
+        // Outputs, and whether each has its own --column-encoding.
+        let mut outputs: Vec<(Output, bool)> = Vec::new();
+        let mut column_encoding = PositionEncoding::default();
+        for (_, flag) in in_order {
+            match flag {
+                Flag::Format(format) => outputs.push((
+                    Output {
+                        format,
+                        path: None,
+                        column_encoding,
+                    },
+                    false,
+                )),
+                Flag::ColumnEncoding(encoding) => {
+                    let Some((output, encoded)) = outputs.last_mut() else {
+                        column_encoding = encoding;
+                        continue;
+                    };
+                    if *encoded {
+                        command
+                            .error(
+                                ErrorKind::ArgumentConflict,
+                                "each --column-encoding must follow its own --format",
+                            )
+                            .exit();
+                    }
+                    output.column_encoding = encoding;
+                    *encoded = true;
+                }
+                Flag::Output(path) => {
+                    if outputs.is_empty() {
+                        outputs.push((
+                            Output {
+                                format: fallback.clone(),
+                                path: None,
+                                column_encoding,
+                            },
+                            false,
+                        ));
+                        defaulted = true;
+                    }
+                    let (output, _) = outputs.last_mut().expect("an output was just added");
+                    if output.path.is_some() {
+                        command
+                            .error(
+                                ErrorKind::ArgumentConflict,
+                                "each --output must follow its own --format",
+                            )
+                            .exit();
+                    }
+                    output.path = Some(path);
+                }
+            }
+        }
+        let mut outputs: Vec<Output> = outputs.into_iter().map(|(output, _)| output).collect();
+        if outputs.is_empty() {
+            outputs.push(Output {
+                format: fallback,
+                path: None,
+                column_encoding,
+            });
+            defaulted = true;
+        }
+        if let (Some(provider), Some(_)) = (ci_provider, &ci_format) {
+            if defaulted && !flags.porcelain {
+                eprintln!(
+                    "Detected {}: defaulting to --format {} (pass --format to choose another)",
+                    provider.display_name(),
+                    provider.default_format().unwrap_or_default()
+                );
+            }
+        }
+        if outputs
+            .iter()
+            .filter(|output| output.path.is_none())
+            .count()
+            > 1
+        {
+            command
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "only one --format can go to stdout; give the others --output <file>",
+                )
+                .exit();
+        }
+
+        // "-" reads stdin. With --srcs-file or stdin the sources come from
+        // elsewhere, so a lone positional argument is the config file.
+        let mut stdin = flags.stdin;
+        let (source_path, config_override) = match (flags.source, flags.config) {
+            (Some(source), config) if source == "-" => {
+                stdin = true;
+                (String::new(), config)
+            }
+            (Some(config), None) if stdin || flags.srcs_file.is_some() => {
+                (String::new(), Some(config))
+            }
+            (Some(_), Some(_)) if stdin || flags.srcs_file.is_some() => {
+                command
+                    .error(
+                        ErrorKind::TooManyValues,
+                        "only a config file may follow --stdin or --srcs-file",
+                    )
+                    .exit();
+            }
+            (source, config) => (source.unwrap_or_default(), config),
+        };
+        if !stdin && flags.srcs_file.is_none() && !Path::new(&source_path).exists() {
+            if let Some(name) = similar_subcommand(command, &source_path) {
+                command
+                    .error(
+                        ErrorKind::InvalidSubcommand,
+                        format!(
+                            "'{}' is neither a file nor a subcommand\n\n  tip: a similar subcommand exists: '{}'",
+                            source_path, name
+                        ),
+                    )
+                    .exit();
+            }
+        }
+
+        AnalyzeArgs {
+            source_path,
+            config_override,
+            outputs,
+            template: flags.template,
+            porcelain: flags.porcelain,
+            quiet: flags.quiet,
+            timeout: flags.timeout,
+            total_timeout: flags.total_timeout,
+            summary_file: flags.summary_file,
+            srcs_file: flags.srcs_file,
+            target: flags.target,
+            output_dir: flags.output_dir,
+            stdin,
+            language: flags.language,
+            history_file: flags
+                .history_file
+                .or_else(|| flags.record_history.then(|| DEFAULT_HISTORY.to_string())),
+            group_by: flags.group_by,
+            top: flags.top,
+            policy: flags.policy,
+            ci: flags.ci,
+            audit_log: flags.audit_log,
+            allow_failure_token: flags.allow_failure_token,
+            severity_filter: SeverityFilter {
+                shown: flags.min_severity.unwrap_or_default(),
+                scored: flags.score_min_severity.unwrap_or_default(),
+            },
+            require_grammar: flags.require_grammar,
+            profile: flags.profile,
+            preset: flags.preset,
+            fail_on_parse_error: flags.fail_on_parse_error,
+            timings: flags.timings,
+            metrics: flags.metrics,
+            cache_dir: (!flags.no_cache).then_some(flags.cache_dir),
+            color: flags.color,
+        }
+    }
+
+    fn has_format(&self, format: &OutputFormat) -> bool {
+        self.outputs.iter().any(|output| output.format == *format)
+    }
+
+    /// `--policy`, or in CI mode the default policy file when it exists.
+    fn policy_file(&self) -> Option<String> {
+        if self.policy.is_some() {
+            return self.policy.clone();
+        }
+        (self.ci && Path::new(DEFAULT_POLICY_FILE).exists())
+            .then(|| DEFAULT_POLICY_FILE.to_string())
+    }
+}
+
+/// The subcommand `name` was likely a typo of: a bare word, not a path,
+/// close to a subcommand's name or alias.
+fn similar_subcommand(command: &clap::Command, name: &str) -> Option<String> {
+    if name.is_empty() || name.contains(['/', '\\', '.']) {
+        return None;
+    }
+    command
+        .get_subcommands()
+        .flat_map(|subcommand| {
+            std::iter::once(subcommand.get_name()).chain(subcommand.get_all_aliases())
+        })
+        .map(|candidate| (strsim::jaro(name, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.7)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// The data model exposed to output templates: `report`, the file's report
+/// in the output's column encoding, plus `file`, the language's display
+/// name, and `config`.
+fn template_data(analyzed: &AnalyzedFile, report: &FileReport) -> Value {
+    let mut data = format_score_as_json(&report.results, &report.score);
+    if let Value::Object(map) = &mut data {
+        map.insert("file".to_string(), Value::from(report.path.as_str()));
+        map.insert(
+            "language".to_string(),
+            Value::from(analyzed.language.display_name.as_str()),
+        );
+        map.insert(
+            "config".to_string(),
+            Value::from(analyzed.config_label.as_str()),
+        );
+    }
+    data
+}
+
+/// What analyzing a file gathers besides its findings.
+#[derive(Debug, Clone, Copy, Default)]
+struct Collect {
+    /// Per-function metrics, for the summary format.
+    functions: bool,
+    /// Halstead measures and complexity, for `--metrics`.
+    metrics: bool,
+}
+
+/// How each file of a run is analyzed, besides its config.
+#[derive(Default)]
+struct FileOptions {
+    collect: Collect,
+    severity_filter: SeverityFilter,
+    /// Cancelled by `--total-timeout`; files left are skipped.
+    run: CancellationToken,
+    /// `--timeout`: how long each file may take.
+    timeout: Option<Duration>,
+    /// Files skipped for taking longer than `timeout`.
+    timed_out: RefCell<Vec<String>>,
+}
+
+impl FileOptions {
+    /// The token one file's analysis stops on.
+    fn file_cancellation(&self) -> CancellationToken {
+        match self.timeout {
+            Some(timeout) => self.run.with_timeout(timeout),
+            None => self.run.clone(),
+        }
+    }
+}
+
+struct AnalyzedFile {
+    report: FileReport,
+    source_hash: String,
+    config_hash: String,
+    language: LanguageDefinition,
+    config_label: String,
+    functions: Vec<FunctionMetrics>,
+    /// With `--metrics`, the file's Halstead measures and complexity.
+    metrics: Option<FileMetrics>,
+    /// Names of the rules that ran on the file.
+    rules: Vec<String>,
+    /// Effective severity of each rule in `rules`.
+    rule_severities: BTreeMap<String, Severity>,
+    /// Parse errors, whether or not a rule reports them.
+    syntax_errors: Vec<SyntaxError>,
+    timings: FileTimings,
+}
+
+impl AnalyzedFile {
+    /// `source` at `path` as an earlier run analyzed it.
+    fn from_cache(
+        path: &str,
+        source: &SourceFile,
+        language: LanguageDefinition,
+        cached: CachedAnalysis,
+    ) -> Self {
+        AnalyzedFile {
+            report: FileReport {
+                path: path.to_string(),
+                language: language.name.clone(),
+                line_count: cached.line_count,
+                results: cached.results,
+                score: cached.score,
+                grammar: Some(language.grammar.clone()),
+            },
+            source_hash: sha256_hex(source.text.as_bytes()),
+            config_hash: cached.config_hash,
+            language,
+            config_label: cached.config_label,
+            functions: cached.functions,
+            metrics: cached.metrics,
+            rules: cached.rules,
+            rule_severities: cached.rule_severities,
+            syntax_errors: cached.syntax_errors,
+            timings: FileTimings::default(),
+        }
+    }
+
+    fn to_cache(&self) -> CachedAnalysis {
+        CachedAnalysis {
+            results: self.report.results.clone(),
+            score: self.report.score.clone(),
+            line_count: self.report.line_count,
+            functions: self.functions.clone(),
+            metrics: self.metrics.clone(),
+            rules: self.rules.clone(),
+            rule_severities: self.rule_severities.clone(),
+            syntax_errors: self.syntax_errors.clone(),
+            config_label: self.config_label.clone(),
+            config_hash: self.config_hash.clone(),
+        }
+    }
+}
+
+/// Analyzes one file. When the config has no rules for the file's language,
+/// the file is over `max_file_size`, or its analysis times out, `strict`
+/// makes that a fatal error; otherwise the file is skipped. With a `cache`,
+/// an unchanged file under an unchanged config reuses the results of an
+/// earlier run.
+fn analyze_source_file(
+    registry: &LanguageRegistry,
+    source_path: &str,
+    config_source: &ConfigSource,
+    strict: bool,
+    options: &FileOptions,
+    cache: Option<&Cache>,
+) -> Option<AnalyzedFile> {
+    if options.run.is_cancelled() {
+        return None;
+    }
+    if !Path::new(source_path).exists() {
+        eprintln!("Error: file '{}' does not exist", source_path);
+        process::exit(1);
+    }
+
+    let language = registry.detect(source_path).cloned().unwrap_or_else(|| {
+        if let Some(missing) = registry.missing_grammar(source_path) {
+            eprintln!(
+                "Error: '{}' is a {} file: compass supports {} but this build was compiled without it; {}",
+                source_path,
+                missing.display_name,
+                missing.display_name,
+                missing.install_hint()
+            );
+            process::exit(1);
+        }
+        eprintln!(
+            "Error: unsupported file extension for '{}'. Supported extensions: {}",
+            source_path,
+            registry.extensions_label()
+        );
+        process::exit(1);
+    });
+
+    let max_file_size = config_source.max_file_size();
+    if let Some(size) = source::oversized(source_path, max_file_size) {
+        let reason = format!(
+            "{} bytes, over max_file_size ({} bytes)",
+            size, max_file_size
+        );
+        if strict {
+            eprintln!("Error: '{}' is {}", source_path, reason);
+            process::exit(1);
+        }
+        eprintln!("Warning: skipping '{}': it is {}", source_path, reason);
+        return None;
+    }
+
+    let source = source::read(source_path).unwrap_or_else(|e| {
+        eprintln!("Error: failed to read '{}': {}", source_path, e);
+        process::exit(1);
+    });
+    if !strict && source.is_undecodable() && config_source.skips_undecodable() {
+        tracing::info!(
+            path = source_path,
+            "skipped file: binary or not valid UTF-8"
+        );
+        return None;
+    }
+
+    // Ages from git blame change without the file changing.
+    let Some(cache) = cache.filter(|_| !config_source.uses_blame()) else {
+        return analyze_source(
+            source_path,
+            language,
+            &source,
+            config_source,
+            strict,
+            options,
+        );
+    };
+    // Pair checks also read the file's counterpart, so its content is part
+    // of the key too, looked up the same way `analyze_source` does.
+    let counterpart = pairing::counterpart(Path::new(source_path), &BTreeMap::new())
+        .map(|counterpart| sha256_hex(counterpart.source_code.as_bytes()))
+        .unwrap_or_default();
+    let key = Cache::key(&[
+        source_path,
+        &language.name,
+        &sha256_hex(source.text.as_bytes()),
+        &format!("{:?} {}", source.invalid_at, source.binary),
+        &config_identity(config_source, &language).1,
+        &counterpart,
+        &format!(
+            "{:?} {:?} {:?}",
+            options.collect, options.severity_filter.shown, options.severity_filter.scored
+        ),
+    ]);
+    if let Some(cached) = cache.get(&key) {
+        tracing::debug!(path = source_path, "reused cached results");
+        return Some(AnalyzedFile::from_cache(
+            source_path,
+            &source,
+            language,
+            cached,
+        ));
+    }
+
+    let analyzed = analyze_source(
+        source_path,
+        language,
+        &source,
+        config_source,
+        strict,
+        options,
+    )?;
+    if let Err(e) = cache.put(&key, &analyzed.to_cache()) {
+        eprintln!("Warning: failed to write the analysis cache: {}", e);
+    }
+    Some(analyzed)
+}
+
+/// Label and hash of the config `language` is analyzed under.
+fn config_identity(
+    config_source: &ConfigSource,
+    language: &LanguageDefinition,
+) -> (String, String) {
+    match config_source {
+        ConfigSource::File(..) | ConfigSource::Env(_) => {
+            (config_source.label(), config_source.content_hash())
+        }
+        ConfigSource::BuiltIn => (
+            format!("built-in {}", language.name),
+            sha256_hex(
+                language
+                    .default_config
+                    .as_deref()
+                    .unwrap_or_default()
+                    .as_bytes(),
+            ),
+        ),
+    }
+}
+
+/// Analyzes an already-loaded source; `label` is reported as its path.
+/// `options.collect` says what it gathers besides findings.
+fn analyze_source(
+    label: &str,
+    language: LanguageDefinition,
+    source: &SourceFile,
+    config_source: &ConfigSource,
+    strict: bool,
+    options: &FileOptions,
+) -> Option<AnalyzedFile> {
+    let FileOptions {
+        collect,
+        severity_filter,
+        ..
+    } = options;
+    let source_code = source.text.as_str();
+    let (config_label, config_hash) = config_identity(config_source, &language);
+    let config = match config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => Some(config),
+        ConfigSource::BuiltIn => None,
+    };
+    let _file = tracing::debug_span!("file", path = label).entered();
+    let started = Instant::now();
+    let analyzer = api::analyzer_for(label, &language, config, &BTreeMap::new())
+        .expect("embedded config should parse")
+        .with_score_min_severity(severity_filter.scored.clone());
+
+    if !analyzer.has_rules() {
+        if !strict {
+            tracing::debug!(language = %language.name, "skipped file: no enabled rules for its language");
+            return None;
+        }
+        eprintln!(
+            "Error: config '{}' contains no enabled rules for language '{}'",
+            config_label, language.name
+        );
+        process::exit(1);
+    }
+
+    let analyzer = analyzer.with_cancellation(options.file_cancellation());
+    let (results, score, timings, tree) =
+        match analyzer.analyze_with_tree(source_code, &language.language) {
+            Ok(analyzed) => analyzed,
+            // The run's --total-timeout passed; the caller reports that once.
+            Err(e) if e.is::<Cancelled>() && options.run.is_cancelled() => {
+                tracing::debug!("skipped file: --total-timeout reached");
+                return None;
+            }
+            Err(e) if e.is::<Cancelled>() => {
+                let timeout = options.timeout.unwrap_or_default().as_secs();
+                if strict {
+                    eprintln!(
+                        "Error: analysis of '{}' timed out after {}s",
+                        label, timeout
+                    );
+                    process::exit(1);
+                }
+                eprintln!(
+                    "Warning: skipping '{}': analysis timed out after {}s",
+                    label, timeout
+                );
+                options.timed_out.borrow_mut().push(label.to_string());
+                return None;
+            }
+            Err(e) => {
+                eprintln!("Error: analysis failed: {}", e);
+                process::exit(1);
+            }
+        };
+    let (mut results, score) = blame::date_findings(Path::new(label), &analyzer, results, score);
+
+    results.splice(0..0, source.decoding_warning());
+    // Hidden findings were already scored unless --score-min-severity
+    // excluded them too.
+    results.retain(|result| result.severity >= severity_filter.shown);
+
+    // Measured on the tree the rules ran on rather than parsed again.
+    let root = tree.root_node();
+    let functions = if collect.functions {
+        hotspots::function_metrics_in(root, label, &language.name, source_code, &results)
+    } else {
+        Vec::new()
+    };
+    let metrics = collect.metrics.then(|| metrics::measure(root, source_code));
+    let syntax_errors = analyzer::syntax_errors_in(root, source_code);
+    tracing::debug!(
+        language = %language.name,
+        issues = results.len(),
+        elapsed_ms = timing::millis(started.elapsed()),
+        "analyzed file"
+    );
+
+    Some(AnalyzedFile {
+        report: FileReport {
+            path: label.to_string(),
+            language: language.name.clone(),
+            line_count: source_code.lines().count(),
+            results,
+            score,
+            grammar: Some(language.grammar.clone()),
+        },
+        source_hash: sha256_hex(source_code.as_bytes()),
+        config_hash,
+        language,
+        config_label,
+        functions,
+        metrics,
+        rules: analyzer
+            .rules()
+            .iter()
+            .map(|rule| rule.name.clone())
+            .collect(),
+        rule_severities: analyzer
+            .rules()
+            .iter()
+            .map(|rule| (rule.name.clone(), rule.severity.clone()))
+            .collect(),
+        syntax_errors,
+        timings,
+    })
+}
+
+fn run_export_issues(args: ExportIssuesArgs) {
+    let ExportIssuesArgs {
+        sources,
+        repo,
+        tracker,
+        min_severity,
+        min_confidence,
+        group_by,
+        config: config_override,
+        state: state_path,
+        dry_run,
+    } = args;
+
+    let config_source = ConfigSource::resolve(config_override.as_deref());
+    let registry = config_source.language_registry();
+    let analyzed: Vec<AnalyzedFile> = sources
+        .iter()
+        .filter_map(|path| {
+            analyze_source_file(
+                &registry,
+                path,
+                &config_source,
+                true,
+                &FileOptions::default(),
+                None,
+            )
+        })
+        .collect();
+    let files: Vec<FileFindings> = sources
+        .iter()
+        .zip(&analyzed)
+        .map(|(path, file)| FileFindings {
+            path,
+            results: &file.report.results,
+        })
+        .collect();
+
+    let mut state = ExportState::load(&state_path).unwrap_or_else(|e| {
+        eprintln!("Error: failed to read export state '{}': {}", state_path, e);
+        process::exit(1);
+    });
+    let options = ExportOptions {
+        tracker,
+        repo: &repo,
+        min_severity,
+        min_confidence,
+        group_by,
+    };
+    let payloads = build_issue_payloads(&files, &options, &mut state);
+
+    match to_string_pretty(&payloads) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error: failed to format issue payloads: {}", e);
+            process::exit(1);
+        }
+    }
+
+    if !dry_run {
+        if let Err(e) = state.save(&state_path) {
+            eprintln!(
+                "Error: failed to write export state '{}': {}",
+                state_path, e
+            );
+            process::exit(1);
+        }
+    }
+}
+
+fn run_version(args: VersionArgs) {
+    println!("compass {}", update::CURRENT_VERSION);
+    if !args.check {
+        return;
+    }
+
+    match update::latest_release_tag() {
+        Ok(tag) if update::is_newer(&tag, update::CURRENT_VERSION) => {
+            println!("A newer release is available: {}", tag);
+            println!("Run 'compass self-update' to install it.");
+        }
+        Ok(_) => println!("compass is up to date"),
+        Err(e) => {
+            eprintln!("Error: failed to check for updates: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn run_self_update(args: SelfUpdateArgs) {
+    let force = args.force;
+    let tag = update::latest_release_tag().unwrap_or_else(|e| {
+        eprintln!("Error: failed to check for updates: {}", e);
+        process::exit(1);
+    });
+
+    if !force && !update::is_newer(&tag, update::CURRENT_VERSION) {
+        eprintln!(
+            "compass {} is already the latest release",
+            update::CURRENT_VERSION
+        );
+        return;
+    }
+
+    eprintln!("Updating compass {} -> {}...", update::CURRENT_VERSION, tag);
+    match update::self_update(&tag) {
+        Ok(path) => eprintln!("Installed {} to {}", tag, path.display()),
+        Err(e) => {
+            eprintln!("Error: self-update failed: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn run_history(args: HistoryArgs) {
+    let path = &args.file;
+    let half_life = args.half_life;
+    let mut entries = history::load(path).unwrap_or_else(|e| {
+        eprintln!("Error: failed to read history '{}': {}", path, e);
+        process::exit(1);
+    });
+    if let Some(limit) = args.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+
+    match args.format {
+        HistoryFormat::Text if entries.is_empty() => {
+            eprintln!(
+                "No history recorded in '{}'; run an analysis with --record-history first.",
+                path
+            );
+        }
+        HistoryFormat::Text => print!("{}", history::format_table(&entries, half_life)),
+        HistoryFormat::Csv => print!("{}", history::to_csv(&entries)),
+        HistoryFormat::Json => match to_string_pretty(&history::to_json(&entries, half_life)) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error: failed to format history: {}", e);
+                process::exit(1);
+            }
+        },
+    }
+}
+
+fn run_exempt(args: ExemptArgs) {
+    let ExemptArgs {
+        reason,
+        ttl,
+        commit,
+    } = args;
+    let key = env::var(exemption::KEY_ENV_VAR).unwrap_or_else(|_| {
+        eprintln!(
+            "Error: {} must hold the secret that signs exemptions",
+            exemption::KEY_ENV_VAR
+        );
+        process::exit(1);
+    });
+    // Tokens are checked against the full hash of HEAD, so sign the
+    // full hash of whatever `--commit` names.
+    let commit = match commit {
+        Some(revision) => history::resolve_commit(&revision).unwrap_or_else(|| {
+            eprintln!("Error: '{}' is not a commit in this repository", revision);
+            process::exit(1);
+        }),
+        None => history::current_commit().unwrap_or_else(|| {
+            eprintln!("Error: not in a git repository; pass --commit <sha>");
+            process::exit(1);
+        }),
+    };
+
+    let exemption = Exemption::new(&commit, &reason, ttl, unix_now());
+    eprintln!(
+        "Exemption for {} valid until {} UTC",
+        commit,
+        history::format_timestamp(exemption.expires_at)
+    );
+    println!("{}", exemption.sign(key.as_bytes()));
+}
+
+fn run_search(args: SearchArgs) {
+    let SearchArgs {
+        keyword,
+        index,
+        format,
+    } = args;
+
+    let index = RuleIndex::load(&index).unwrap_or_else(|e| {
+        eprintln!("Error: failed to load rule index '{}': {}", index, e);
+        process::exit(1);
+    });
+    let packs = index.search(&keyword);
+
+    match format {
+        TextOrJson::Text if packs.is_empty() => eprintln!("No rule packs match '{}'", keyword),
+        TextOrJson::Text => {
+            for pack in packs {
+                println!("{}", pack.name);
+                println!("  {}", pack.description);
+                if !pack.languages.is_empty() {
+                    println!("  Languages: {}", pack.languages.join(", "));
+                }
+                println!("  Install: {}", pack.install);
+                println!(
+                    "  Then add \"{}\" to `include` in {}",
+                    pack.file,
+                    resolver::DEFAULT_CONFIG_FILE
+                );
+                if Path::new(&pack.file).exists() {
+                    eprintln!(
+                        "Warning: {} already exists; installing {} overwrites it",
+                        pack.file, pack.name
+                    );
+                }
+            }
+        }
+        TextOrJson::Json => {
+            let packs: Vec<Value> = packs
+                .iter()
+                .map(|pack| {
+                    json!({
+                        "name": pack.name,
+                        "description": pack.description,
+                        "languages": pack.languages,
+                        "keywords": pack.keywords,
+                        "file": pack.file,
+                        "exists": Path::new(&pack.file).exists(),
+                        "install": pack.install,
+                    })
+                })
+                .collect();
+            println!("{}", Value::from(packs));
+        }
+    }
+}
+
+fn run_compare(args: CompareArgs) {
+    let CompareArgs {
+        old: old_path,
+        new: new_path,
+        max_new_issues,
+        max_score_drop,
+    } = args;
+
+    let load = |path: &str| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str::<Value>(&content).map_err(|e| e.to_string()))
+            .and_then(|report| RunSnapshot::from_json(&report).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                eprintln!("Error: failed to read report '{}': {}", path, e);
+                process::exit(1);
+            })
+    };
+    let comparison = compare::compare(&load(&old_path), &load(&new_path));
+
+    let mut regressions = Vec::new();
+    if comparison.new_issues.len() > max_new_issues {
+        regressions.push(format!(
+            "{} new issues (allowed: {})",
+            comparison.new_issues.len(),
+            max_new_issues
+        ));
+    }
+    if let (Some(max_drop), Some(delta)) = (max_score_drop, comparison.score_delta()) {
+        if -delta > max_drop {
+            regressions.push(format!(
+                "score dropped by {:.1} (allowed: {:.1})",
+                -delta, max_drop
+            ));
+        }
+    }
+
+    let mut report = comparison.to_json();
+    report["regressed"] = Value::from(!regressions.is_empty());
+    match to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error: failed to format comparison: {}", e);
+            process::exit(1);
+        }
+    }
+
+    if !regressions.is_empty() {
+        eprintln!("Regression: {}", regressions.join("; "));
+        process::exit(1);
+    }
+}
+
+fn run_grade(args: GradeArgs) {
+    let GradeArgs {
+        submissions: submissions_root,
+        config: config_override,
+        format,
+        per_student,
+        output_dir,
+        similarity_report,
+        similarity_threshold,
+    } = args;
+
+    let config_source = ConfigSource::resolve(config_override.as_deref());
+    let registry = config_source.language_registry();
+    let weighting = config_source.project_weighting();
+    let ratings = config_source.ratings();
+    let dirs = grade::submission_dirs(Path::new(&submissions_root)).unwrap_or_else(|e| {
+        eprintln!(
+            "Error: failed to read submissions '{}': {}",
+            submissions_root, e
+        );
+        process::exit(1);
+    });
+
+    let mut fingerprints = Vec::new();
+    let submissions: Vec<Submission> = dirs
+        .into_iter()
+        .map(|(student, root)| {
+            let paths = discovery::collect_files(&root, |path| registry.detect(path).is_some())
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: failed to scan '{}': {}", root.display(), e);
+                    process::exit(1);
+                });
+            if similarity_report.is_some() {
+                fingerprints.push(submission_fingerprints(&registry, &student, &paths));
+            }
+            let files = paths
+                .iter()
+                .filter_map(|path| {
+                    analyze_source_file(
+                        &registry,
+                        &path.to_string_lossy(),
+                        &config_source,
+                        false,
+                        &FileOptions::default(),
+                        None,
+                    )
+                })
+                .map(|analyzed| analyzed.report)
+                .collect();
+            Submission {
+                student,
+                root,
+                files,
+            }
+        })
+        .collect();
+
+    if per_student {
+        let written = fs::create_dir_all(&output_dir).and_then(|_| {
+            submissions.iter().try_for_each(|submission| {
+                fs::write(
+                    Path::new(&output_dir).join(format!("{}.md", submission.student)),
+                    grade::feedback_report(submission, weighting, &ratings),
+                )
+            })
+        });
+        if let Err(e) = written {
+            eprintln!("Error: failed to write feedback to '{}': {}", output_dir, e);
+            process::exit(1);
+        }
+    }
+
+    if let Some(report_path) = &similarity_report {
+        let pairs = similarity::similar_pairs(&fingerprints, similarity_threshold);
+        let report = json!({
+            "threshold": similarity_threshold,
+            "pairs": pairs.iter().map(SimilarPair::to_json).collect::<Vec<_>>(),
+        });
+        let written = to_string_pretty(&report)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(report_path, json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!(
+                "Error: failed to write similarity report '{}': {}",
+                report_path, e
+            );
+            process::exit(1);
+        }
+    }
+
+    match format {
+        GradeFormat::Json => {
+            match to_string_pretty(&grade::grade_sheet_json(&submissions, weighting, &ratings)) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    eprintln!("Error: failed to format grade sheet: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        GradeFormat::Csv => print!(
+            "{}",
+            grade::grade_sheet_csv(&submissions, weighting, &ratings)
+        ),
+    }
+}
+
+/// Structural fingerprints of every source file of one submission.
+fn submission_fingerprints(
+    registry: &LanguageRegistry,
+    student: &str,
+    paths: &[PathBuf],
+) -> Fingerprints {
+    let mut fingerprints = Fingerprints::new(student);
+    for path in paths {
+        let Some(language) = registry.detect(path) else {
+            continue;
+        };
+        let added = source::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| {
+                fingerprints
+                    .add_source(&source.text, &language.language)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = added {
+            eprintln!("Error: failed to fingerprint '{}': {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+    fingerprints
+}
+
+fn run_init(program: &str, args: InitArgs) {
+    let root = Path::new(&args.directory);
+
+    let target = root.join(resolver::DEFAULT_CONFIG_FILE);
+    if target.exists() && !args.force {
+        eprintln!(
+            "Error: '{}' already exists (use --force to overwrite)",
+            target.display()
+        );
+        process::exit(1);
+    }
+    let languages =
+        init::detect_languages(root, &LanguageRegistry::builtin()).unwrap_or_else(|e| {
+            eprintln!("Error: failed to scan '{}': {}", root.display(), e);
+            process::exit(1);
+        });
+    if languages.is_empty() {
+        eprintln!(
+            "Error: no supported source files under '{}'",
+            root.display()
+        );
+        process::exit(1);
+    }
+    if let Err(e) = fs::write(&target, init::starter_config(&languages)) {
+        eprintln!("Error: failed to write '{}': {}", target.display(), e);
+        process::exit(1);
+    }
+    println!("Wrote {} for {}", target.display(), languages.join(", "));
+    println!("Run: {} {} {}", program, root.display(), target.display());
+}
+
+fn run_explain(args: ExplainArgs) {
+    let rule_name = args.rule.as_str();
+    let config_override = args.config.as_deref();
+
+    let (language, config) = find_rule(rule_name, ConfigSource::resolve(config_override));
+    let (language, rule) = (language.as_deref(), config.rule(rule_name).unwrap());
+    let severity = config.effective_severity(rule);
+
+    match args.format {
+        TextOrJson::Text => {
+            match language {
+                Some(language) => println!("{} ({}, {})", rule.name, severity, language),
+                None => println!("{} ({})", rule.name, severity),
+            }
+            println!("{}", rule.message);
+            if let Some(description) = &rule.description {
+                println!("\n{}", description.trim());
+            }
+            if let Some(suggestion) = &rule.suggestion {
+                println!("\nFix: {}", suggestion);
+            }
+            if let Some(bad) = &rule.example_bad {
+                println!("\nAvoid:\n{}", indent(bad));
+            }
+            if let Some(good) = &rule.example_good {
+                println!("\nPrefer:\n{}", indent(good));
+            }
+            if let Some(docs_url) = &rule.docs_url {
+                println!("\nDocs: {}", docs_url);
+            }
+        }
+        TextOrJson::Json => println!(
+            "{:#}",
+            json!({
+                "name": rule.name,
+                "language": language,
+                "severity": severity,
+                "enabled": rule.enabled,
+                "message": rule.message,
+                "description": rule.description,
+                "suggestion": rule.suggestion,
+                "docs_url": rule.docs_url,
+                "example_bad": rule.example_bad,
+                "example_good": rule.example_good,
+            })
+        ),
+    }
+}
+
+/// Looks a rule up in the loaded config first, then in the built-in rules of
+/// every language, so built-in names always resolve. Returns the config that
+/// defines the rule and the rule's language, when known.
+fn find_rule(rule_name: &str, config_source: ConfigSource) -> (Option<String>, AnalyzerConfig) {
+    let mut configs = Vec::new();
+    match config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => configs.push((None, config)),
+        ConfigSource::BuiltIn => {}
+    }
+    for (language, config) in builtin_configs() {
+        configs.push((Some(language), config));
+    }
+    for (language, config) in configs {
+        if let Some(rule) = config.rule(rule_name) {
+            return (language.or(rule.language.clone()), config);
+        }
+    }
+    eprintln!("Error: no rule named '{}'", rule_name);
+    process::exit(1);
+}
+
+fn run_test_rule(args: TestRuleArgs) {
+    let rule_name = args.rule.as_str();
+    let snippet = args.snippet.as_str();
+    let config_override = args.config.as_deref();
+
+    let config_source = ConfigSource::resolve(config_override);
+    let registry = config_source.language_registry();
+    let (rule_language, config) = find_rule(rule_name, config_source);
+    let rule = config.rule(rule_name).unwrap();
+
+    let mut source_code = String::new();
+    let read = if snippet == "-" {
+        io::stdin().read_to_string(&mut source_code).map(|_| ())
+    } else {
+        fs::read_to_string(snippet).map(|contents| source_code = contents)
+    };
+    if let Err(e) = read {
+        eprintln!("Error: failed to read '{}': {}", snippet, e);
+        process::exit(1);
+    }
+
+    let definition = match args.language.or(rule_language) {
+        Some(name) => registry.get(&name).unwrap_or_else(|| {
+            eprintln!(
+                "Error: unknown language '{}' (one of: {})",
+                name,
+                registry
+                    .iter()
+                    .map(|language| language.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            process::exit(1);
+        }),
+        None => registry.detect(snippet).unwrap_or_else(|| {
+            eprintln!(
+                "Error: rule '{}' applies to any language; pass --language <name>",
+                rule.name
+            );
+            process::exit(1);
+        }),
+    };
+
+    let captures = match config.analysis_rule(rule).captures(
+        &source_code,
+        &definition.language,
+        pairing::counterpart(Path::new(snippet), &BTreeMap::new()).as_ref(),
+    ) {
+        Ok(captures) => captures,
+        Err(e) => {
+            eprintln!(
+                "Error: rule '{}' does not compile for {}: {}",
+                rule.name, definition.name, e
+            );
+            process::exit(1);
+        }
+    };
+
+    match args.format {
+        TextOrJson::Text => {
+            let source = match (&rule.check, &rule.comment) {
+                (Some(check), _) => format!("check '{}'", check),
+                (None, Some(comment)) => format!("comment pattern '{}'", comment),
+                (None, None) if !rule.banned_imports.is_empty() => {
+                    format!("banned imports {}", rule.banned_imports.join(", "))
+                }
+                (None, None) if rule.max_parameters.is_some() || rule.max_returns.is_some() => {
+                    let limits: Vec<_> = [
+                        rule.max_parameters
+                            .map(|max| format!("max {} parameters", max)),
+                        rule.max_returns.map(|max| format!("max {} returns", max)),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    limits.join(", ")
+                }
+                (None, None) => "query".to_string(),
+            };
+            println!(
+                "{} ({}): {} captured {} node(s)",
+                rule.name,
+                definition.name,
+                source,
+                captures.len()
+            );
+            for capture in &captures {
+                let span = format!(
+                    "{}:{}-{}:{}",
+                    capture.start_line, capture.start_column, capture.end_line, capture.end_column
+                );
+                let text = capture.text.lines().next().unwrap_or_default();
+                println!(
+                    "  {:<13} pattern {} @{} {}: {}",
+                    span, capture.pattern, capture.capture, capture.kind, text
+                );
+            }
+        }
+        TextOrJson::Json => println!(
+            "{:#}",
+            json!({
+                "rule": rule.name,
+                "language": definition.name,
+                "check": rule.check,
+                "comment": rule.comment,
+                "banned_imports": rule.banned_imports,
+                "max_parameters": rule.max_parameters,
+                "max_returns": rule.max_returns,
+                "captures": captures,
+            })
+        ),
+    }
+}
+
+fn run_check_config(args: CheckConfigArgs) {
+    let config_override = args.config.as_deref();
+
+    let config_source = ConfigSource::resolve(config_override);
+    let registry = config_source.language_registry();
+    let outcomes = match &config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => {
+            config_test::run_tests(config, &registry, None)
+        }
+        ConfigSource::BuiltIn => builtin_configs()
+            .iter()
+            .flat_map(|(language, config)| {
+                config_test::run_tests(config, &registry, Some(language))
+            })
+            .collect(),
+    };
+    if outcomes.is_empty() {
+        eprintln!("No [[tests]] in {}", config_source.label());
+        return;
+    }
+
+    match args.format {
+        TextOrJson::Text => {
+            print!("{}", config_test::format_outcomes(&outcomes));
+            let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+            println!("\n{} passed, {} failed", outcomes.len() - failed, failed);
+        }
+        TextOrJson::Json => println!("{:#}", json!(outcomes)),
+    }
+    if outcomes.iter().any(|outcome| !outcome.passed()) {
+        process::exit(1);
+    }
+}
+
+fn run_ast(args: AstArgs) {
+    let options = ast::DumpOptions {
+        query: args.query,
+        at: args.at,
+        anonymous: args.all,
+    };
+    let (file, config_override) = (args.file.as_str(), args.config.as_deref());
+
+    let registry = ConfigSource::resolve(config_override).language_registry();
+    let definition = match &args.language {
+        Some(name) => registry.get(name),
+        None => registry.detect(file),
+    };
+    let Some(definition) = definition else {
+        eprintln!(
+            "Error: cannot tell the language of '{}'; pass --language <name> (one of: {})",
+            file,
+            registry
+                .iter()
+                .map(|language| language.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        process::exit(1);
+    };
+
+    let mut source_code = String::new();
+    let read = if file == "-" {
+        io::stdin().read_to_string(&mut source_code).map(|_| ())
+    } else {
+        fs::read_to_string(file).map(|contents| source_code = contents)
+    };
+    if let Err(e) = read {
+        eprintln!("Error: failed to read '{}': {}", file, e);
+        process::exit(1);
+    }
+
+    match ast::dump(&source_code, &definition.language, &options) {
+        Ok(dump) => {
+            print!("{}", dump.text);
+            if options.query.is_some() {
+                eprintln!("{} match(es)", dump.matches);
+            }
+        }
+        Err(e) => {
+            eprintln!(
+                "Error: query does not compile for {}: {}",
+                definition.name, e
+            );
+            process::exit(1);
+        }
+    }
+}
+
+fn run_fix(args: FixArgs) {
+    let (source_path, config_override) = (args.source.as_str(), args.config.as_deref());
+    let (format, write) = (args.format, args.write);
+
+    let config_source = ConfigSource::resolve(config_override);
+    let registry = config_source.language_registry();
+    let config = match &config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => Some(config),
+        ConfigSource::BuiltIn => None,
+    };
+    let root = Path::new(source_path);
+    let paths = if root.is_dir() {
+        discovery::collect_files(root, |path| {
+            registry.detect(path).is_some() && !config_source.excludes(root, path)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Error: failed to scan '{}': {}", source_path, e);
+            process::exit(1);
+        })
+    } else if root.exists() {
+        vec![root.to_path_buf()]
+    } else {
+        eprintln!("Error: file '{}' does not exist", source_path);
+        process::exit(1);
+    };
+
+    let mut files: Vec<FileFixes> = Vec::new();
+    for path in paths {
+        let label = path.to_string_lossy();
+        let Some(language) = registry.detect(&path) else {
+            eprintln!(
+                "Error: unsupported file extension for '{}'. Supported extensions: {}",
+                label,
+                registry.extensions_label()
+            );
+            process::exit(1);
+        };
+        let max_file_size = config_source.max_file_size();
+        if let Some(size) = source::oversized(&path, max_file_size) {
+            eprintln!(
+                "Warning: not fixing '{}': it is {} bytes, over max_file_size ({} bytes)",
+                label, size, max_file_size
+            );
+            continue;
+        }
+        let source = source::read(&path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read '{}': {}", label, e);
+            process::exit(1);
+        });
+        // Writing back lossily decoded text would corrupt the file.
+        if source.is_undecodable() {
+            if !config_source.skips_undecodable() {
+                eprintln!(
+                    "Warning: not fixing '{}': it is not valid UTF-8 text",
+                    label
+                );
+            }
+            continue;
+        }
+        let source_code = source.text;
+        let report =
+            api::analyze_source(&label, &source_code, language, config).unwrap_or_else(|e| {
+                eprintln!("Error: analysis of '{}' failed: {}", label, e);
+                process::exit(1);
+            });
+        if let Some(report) = report {
+            let fixes = fix::fix_and_verify(
+                &label,
+                &source_code,
+                &report.results,
+                &language.language,
+                |fixed| {
+                    api::analyze_source(&label, fixed, language, config)
+                        .map(|report| report.map(|report| report.results).unwrap_or_default())
+                        .map_err(Into::into)
+                },
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("Error: analysis of fixed '{}' failed: {}", label, e);
+                process::exit(1);
+            });
+            if !fixes.is_empty() || !fixes.skipped.is_empty() {
+                files.push(fixes);
+            }
+        }
+    }
+
+    let skipped: usize = files.iter().map(|file| file.skipped.len()).sum();
+    for file in &files {
+        let location = |fix: &AppliedFix| {
+            let start = fix.fix.start_byte;
+            format!(
+                "{}:{}:{}",
+                file.path,
+                file.original[..start].matches('\n').count() + 1,
+                Columns::at(&file.original, start).char
+            )
+        };
+        for skipped in &file.skipped {
+            eprintln!(
+                "Skipped fix of {} at {}: {}",
+                skipped.fix.rule,
+                location(&skipped.fix),
+                skipped.reason
+            );
+        }
+        for unresolved in &file.unresolved {
+            eprintln!(
+                "Warning: fix of {} at {} did not resolve the finding",
+                unresolved.rule,
+                location(unresolved)
+            );
+        }
+    }
+    files.retain(|file| !file.is_empty());
+
+    if write {
+        for file in &files {
+            if let Err(e) = fs::write(&file.path, &file.fixed) {
+                eprintln!("Error: failed to write '{}': {}", file.path, e);
+                process::exit(1);
+            }
+        }
+    }
+    match format {
+        FixFormat::Diff => {
+            for file in &files {
+                print!("{}", fix::unified_diff(file));
+            }
+        }
+        FixFormat::WorkspaceEdit => println!("{:#}", fix::workspace_edit(&files)),
+    }
+
+    let fixes: usize = files.iter().map(|file| file.applied.len()).sum();
+    if write {
+        eprintln!("Applied {} fix(es) to {} file(s)", fixes, files.len());
+    } else if fixes > 0 {
+        eprintln!(
+            "{} fix(es) in {} file(s); run with --write to apply them",
+            fixes,
+            files.len()
+        );
+    } else {
+        eprintln!("Nothing to fix");
+    }
+    if skipped > 0 {
+        eprintln!("Skipped {} conflicting or unstable fix(es)", skipped);
+    }
+}
+
+/// Each built-in language with its built-in rules.
+fn builtin_configs() -> Vec<(String, AnalyzerConfig)> {
+    LanguageRegistry::builtin()
+        .iter()
+        .filter_map(|language| {
+            let default_config = language.default_config.as_deref()?;
+            let config =
+                AnalyzerConfig::from_str(default_config).expect("embedded config should parse");
+            Some((language.name.clone(), config))
+        })
+        .collect()
+}
+
+fn run_rules(args: RulesArgs) {
+    let language = args.language.map(|name| name.to_lowercase());
+
+    let mut config_source =
+        ConfigSource::resolve_with_preset(args.config.as_deref(), args.preset.as_deref());
+    if let Some(profile) = &args.profile {
+        config_source.apply_profile(profile);
+    }
+    // (language, config, rule); rules without a language apply to all.
+    let configs = match config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => vec![(None, config)],
+        ConfigSource::BuiltIn => builtin_configs()
+            .into_iter()
+            .map(|(language, config)| (Some(language), config))
+            .collect(),
+    };
+    let mut rules = Vec::new();
+    for (config_language, config) in &configs {
+        for rule in &config.rules {
+            let rule_language = config_language.as_deref().or(rule.language.as_deref());
+            if let (Some(wanted), Some(scoped)) = (&language, rule_language) {
+                if !scoped.eq_ignore_ascii_case(wanted) {
+                    continue;
+                }
+            }
+            rules.push((rule_language, config, rule));
+        }
+    }
+
+    match args.format {
+        TextOrJson::Text if rules.is_empty() => eprintln!("No rules found"),
+        TextOrJson::Text => {
+            let width = rules
+                .iter()
+                .map(|(_, _, rule)| rule.name.len())
+                .max()
+                .unwrap_or_default();
+            for (language, config, rule) in &rules {
+                let tags: Vec<String> = rule
+                    .labels
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+                let line = format!(
+                    "{:<width$}  {:<10} {:<7} {:<8} {:<4.1} {}",
+                    rule.name,
+                    language.unwrap_or("any"),
+                    config.effective_severity(rule),
+                    if rule.enabled { "enabled" } else { "disabled" },
+                    rule.weight,
+                    tags.join(",")
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+        TextOrJson::Json => {
+            let rules: Vec<Value> = rules
+                .iter()
+                .map(|(language, config, rule)| {
+                    json!({
+                        "name": rule.name,
+                        "language": language,
+                        "severity": config.effective_severity(rule),
+                        "weight": rule.weight,
+                        "enabled": rule.enabled,
+                        "confidence": rule.confidence.as_str(),
+                        "tags": rule.labels,
+                    })
+                })
+                .collect();
+            println!("{:#}", Value::from(rules));
+        }
+    }
+}
+
+fn indent(code: &str) -> String {
+    code.trim_end()
+        .lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn run_cache(command: CacheCommand) {
+    let CacheCommand::Clear { cache_dir: dir } = command;
+    match Cache::new(&dir).clear() {
+        Ok(removed) => println!("Removed {} cached file result(s) from {}", removed, dir),
+        Err(e) => {
+            eprintln!("Error: failed to clear '{}': {}", dir, e);
+            process::exit(1);
+        }
+    }
+}
+
+fn run_hook(program: &str, command: HookCommand) {
+    match command {
+        HookCommand::Install { gate, force } => {
+            // What the installed hook passes on to `hook run`.
+            let mut hook_args = Vec::new();
+            if let Some(min_score) = gate.min_score {
+                hook_args.extend(["--min-score".to_string(), min_score.to_string()]);
+            }
+            if let Some(policy) = gate.policy {
+                hook_args.extend(["--policy".to_string(), policy]);
+            }
+            hook_args.extend(gate.config);
+            // A program run by path keeps working from the hook's directory.
+            let program = if Path::new(program).components().count() > 1 {
+                fs::canonicalize(program)
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| program.to_string())
+            } else {
+                program.to_string()
+            };
+            match hook::install(&program, &hook_args, force) {
+                Ok(path) => println!("Installed the pre-commit hook at {}", path.display()),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        HookCommand::Run { gate } => run_hook_checks(gate),
+    }
+}
+
+/// `compass hook run`: analyzes the staged content of staged files and
+/// exits non-zero when it scores below `min_score` or breaks the policy.
+fn run_hook_checks(gate: HookGate) {
+    let HookGate {
+        config: config_override,
+        min_score,
+        policy,
+    } = gate;
+    let config_source = ConfigSource::resolve(config_override.as_deref());
+    let registry = config_source.language_registry();
+    let staged = hook::staged_files().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+    let max_file_size = config_source.max_file_size();
+    let analyzed_files: Vec<AnalyzedFile> = staged
+        .iter()
+        .filter(|path| !config_source.excludes(Path::new(""), Path::new(path)))
+        .filter_map(|path| {
+            let language = registry.detect(path)?.clone();
+            let bytes = hook::staged_content(path).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            });
+            if max_file_size > 0 && bytes.len() as u64 > max_file_size {
+                eprintln!(
+                    "Warning: skipping '{}': it is {} bytes, over max_file_size ({} bytes)",
+                    path,
+                    bytes.len(),
+                    max_file_size
+                );
+                return None;
+            }
+            let source = SourceFile::from_bytes(bytes);
+            analyze_source(
+                path,
+                language,
+                &source,
+                &config_source,
+                false,
+                &FileOptions::default(),
+            )
+        })
+        .collect();
+    if analyzed_files.is_empty() {
+        eprintln!("compass: no staged files to analyze");
+        return;
+    }
+
+    let weighting = config_source.project_weighting();
+    let file_reports: Vec<FileReport> = analyzed_files
+        .iter()
+        .map(|analyzed| analyzed.report.clone())
+        .collect();
+    let color = ColorChoice::default().enabled(io::stderr().is_terminal(), CiProvider::detect());
+    eprint!("{}", TextFormatter { color }.format(&file_reports));
+    let project_score =
+        ProjectScore::from_files(&file_reports, weighting).with_ratings(&config_source.ratings());
+
+    let mut violations = Vec::new();
+    if let (Some(min_score), Some(overall)) = (min_score, project_score.overall_score) {
+        if overall < min_score {
+            violations.push(format!(
+                "score {:.1} is below the minimum of {:.1}",
+                overall, min_score
+            ));
+        }
+    }
+    let policy_path = policy.or_else(|| {
+        Path::new(DEFAULT_POLICY_FILE)
+            .exists()
+            .then(|| DEFAULT_POLICY_FILE.to_string())
+    });
+    if let Some(path) = &policy_path {
+        let policy = Policy::from_file(path).unwrap_or_else(|e| {
+            eprintln!("Error: invalid policy '{}': {}", path, e);
+            process::exit(1);
+        });
+        let mut enabled_rules: BTreeMap<String, BTreeMap<String, Severity>> = BTreeMap::new();
+        for analyzed in &analyzed_files {
+            enabled_rules
+                .entry(analyzed.language.name.clone())
+                .or_default()
+                .extend(analyzed.rule_severities.clone());
+        }
+        violations.extend(policy.violations(&file_reports, &enabled_rules, weighting));
+    }
+
+    if violations.is_empty() {
+        eprintln!(
+            "compass: {} staged file(s) score {:.1} ({})",
+            project_score.files_analyzed,
+            project_score.overall_score.unwrap_or_default(),
+            project_score.rating
+        );
+        return;
+    }
+    for violation in &violations {
+        eprintln!("compass: commit blocked: {}", violation);
+    }
+    eprintln!("Fix the findings above, or skip this check with git commit --no-verify.");
+    process::exit(1);
+}
+
+fn run_config(command: ConfigCommand) {
+    let (config_override, resolved, profile, preset) = match command {
+        ConfigCommand::Show {
+            config,
+            resolved,
+            profile,
+            preset,
+        } => (config, resolved, profile, preset),
+        ConfigCommand::Schema => {
+            println!("{:#}", AnalyzerConfig::json_schema());
+            return;
+        }
+    };
+    let (config_override, preset) = (config_override.as_deref(), preset.as_deref());
+
+    if !resolved {
+        let layers = config_layers(config_override);
+        let layer = |kind: LayerKind| {
+            layers
+                .iter()
+                .find(|layer| layer.kind == kind)
+                .map(|layer| layer.label.clone())
+        };
+        println!("built-in  rules of each language, through extends or when no config is found");
+        let user = layer(LayerKind::User).unwrap_or_else(|| match user_config_file() {
+            Some(path) => format!("{} (not found)", path.display()),
+            None => "(none)".to_string(),
+        });
+        println!("user      {}", user);
+        println!(
+            "project   {}",
+            layer(LayerKind::Project).unwrap_or_else(|| "(none)".to_string())
+        );
+        if let Some(name) = preset {
+            println!("command   --preset {}", name);
+        }
+        return;
+    }
+
+    let mut config_source = ConfigSource::resolve_with_preset(config_override, preset);
+    if let Some(name) = &profile {
+        config_source.apply_profile(name);
+    }
+    let builtin;
+    let config = match &config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => config,
+        ConfigSource::BuiltIn => {
+            let names: Vec<String> = LanguageRegistry::builtin()
+                .iter()
+                .filter(|language| language.default_config.is_some())
+                .map(|language| format!("{:?}", language.name))
+                .collect();
+            builtin = AnalyzerConfig::from_str(&format!("extends = [{}]\n", names.join(", ")))
+                .expect("embedded configs should parse");
+            &builtin
+        }
+    };
+    match toml::to_string_pretty(config) {
+        Ok(text) => {
+            println!("# Resolved from {}", config_source.label());
+            print!("{}", text);
+        }
+        Err(e) => {
+            eprintln!("Error: failed to serialize the config: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "playground")]
+fn run_playground(args: PlaygroundArgs) {
+    use crate::playground::{serve, PlaygroundOptions};
+
+    let addr = args.addr;
+    let mut options = PlaygroundOptions::default();
+    if let Some(rate_limit) = args.rate_limit {
+        options.requests_per_window = rate_limit;
+    }
+
+    eprintln!("Serving the compass playground on http://{}", addr);
+    if let Err(e) = serve(&addr, options) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "grpc")]
+fn run_grpc(args: GrpcArgs) {
+    use crate::grpc::{serve, AnalysisService};
+
+    let config_source = ConfigSource::resolve_with_preset(args.config.as_deref(), None);
+    let registry = config_source.language_registry();
+    let config = match config_source {
+        ConfigSource::File(_, config) | ConfigSource::Env(config) => Some(config),
+        ConfigSource::BuiltIn => None,
+    };
+    let service = AnalysisService::new(registry, config);
+
+    eprintln!("Serving compass over gRPC on {}", args.addr);
+    if let Err(e) = serve(&args.addr, service) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,219 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// How far (in lines) an issue may move and still match its earlier self
+/// when its source text changed too.
+const DRIFT_WINDOW: usize = 3;
+
+/// A finding read back from compass JSON output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedIssue {
+    pub path: String,
+    pub rule: String,
+    pub severity: String,
+    pub message: String,
+    pub line: usize,
+    pub text: String,
+}
+
+impl ReportedIssue {
+    fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "rule": self.rule,
+            "severity": self.severity,
+            "message": self.message,
+            "line": self.line,
+        })
+    }
+}
+
+/// The issues and score of one run, from either the single-file or the
+/// aggregated JSON shape.
+#[derive(Debug, Clone, Default)]
+pub struct RunSnapshot {
+    pub score: Option<f64>,
+    pub issues: Vec<ReportedIssue>,
+}
+
+impl RunSnapshot {
+    pub fn from_json(report: &Value) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Some(files) = report["files"].as_array() {
+            let mut issues = Vec::new();
+            for file in files {
+                let path = file["path"].as_str().unwrap_or_default();
+                issues.extend(read_issues(path, &file["issues"])?);
+            }
+            return Ok(RunSnapshot {
+                score: report["summary"]["average_score"].as_f64(),
+                issues,
+            });
+        }
+
+        if report["issues"].is_array() {
+            return Ok(RunSnapshot {
+                score: report["score"].as_f64(),
+                issues: read_issues("", &report["issues"])?,
+            });
+        }
+
+        Err("not a compass report: expected `issues` or `files`".into())
+    }
+}
+
+fn read_issues(
+    path: &str,
+    issues: &Value,
+) -> Result<Vec<ReportedIssue>, Box<dyn std::error::Error>> {
+    let issues = issues
+        .as_array()
+        .ok_or_else(|| format!("`issues` of '{}' is not an array", path))?;
+    Ok(issues
+        .iter()
+        .map(|issue| ReportedIssue {
+            path: path.to_string(),
+            rule: issue["rule"].as_str().unwrap_or_default().to_string(),
+            severity: issue["severity"].as_str().unwrap_or_default().to_string(),
+            message: issue["message"].as_str().unwrap_or_default().to_string(),
+            line: issue["line"].as_u64().unwrap_or_default() as usize,
+            text: issue["text"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect())
+}
+
+/// The difference between two runs.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub old_score: Option<f64>,
+    pub new_score: Option<f64>,
+    pub new_issues: Vec<ReportedIssue>,
+    pub fixed_issues: Vec<ReportedIssue>,
+}
+
+impl Comparison {
+    /// `new - old`, rounded to one decimal; `None` if either score is missing.
+    pub fn score_delta(&self) -> Option<f64> {
+        let delta = self.new_score? - self.old_score?;
+        Some((delta * 10.0).round() / 10.0)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "old_score": self.old_score,
+            "new_score": self.new_score,
+            "score_delta": self.score_delta(),
+            "new_issue_count": self.new_issues.len(),
+            "fixed_issue_count": self.fixed_issues.len(),
+            "new_issues": self.new_issues.iter().map(ReportedIssue::to_json).collect::<Vec<_>>(),
+            "fixed_issues": self.fixed_issues.iter().map(ReportedIssue::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Matches issues of `old` against `new`. Line numbers are not part of an
+/// issue's identity: issues match on path, rule, and source text, so code
+/// moving up or down a file is not reported as fixed-then-new. Issues whose
+/// text changed still match a same-rule issue within `DRIFT_WINDOW` lines.
+pub fn compare(old: &RunSnapshot, new: &RunSnapshot) -> Comparison {
+    let mut unmatched_old: Vec<Option<&ReportedIssue>> = old.issues.iter().map(Some).collect();
+    let mut unmatched_new: Vec<Option<&ReportedIssue>> = new.issues.iter().map(Some).collect();
+
+    // Exact text matches, paired in line order within each group.
+    let mut groups: BTreeMap<IssueIdentity, (Vec<usize>, Vec<usize>)> = BTreeMap::new();
+    for (index, issue) in old.issues.iter().enumerate() {
+        groups.entry(identity(issue)).or_default().0.push(index);
+    }
+    for (index, issue) in new.issues.iter().enumerate() {
+        groups.entry(identity(issue)).or_default().1.push(index);
+    }
+    for (old_indices, new_indices) in groups.values_mut() {
+        old_indices.sort_by_key(|&index| old.issues[index].line);
+        new_indices.sort_by_key(|&index| new.issues[index].line);
+        for (&old_index, &new_index) in old_indices.iter().zip(new_indices.iter()) {
+            unmatched_old[old_index] = None;
+            unmatched_new[new_index] = None;
+        }
+    }
+
+    // Edited text: the nearest same-rule issue within the drift window.
+    for old_slot in unmatched_old.iter_mut() {
+        let Some(old_issue) = *old_slot else {
+            continue;
+        };
+        let nearest = unmatched_new
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.map(|issue| (index, issue)))
+            .filter(|(_, issue)| issue.path == old_issue.path && issue.rule == old_issue.rule)
+            .map(|(index, issue)| (index, issue.line.abs_diff(old_issue.line)))
+            .filter(|(_, distance)| *distance <= DRIFT_WINDOW)
+            .min_by_key(|(_, distance)| *distance);
+        if let Some((new_index, _)) = nearest {
+            unmatched_new[new_index] = None;
+            *old_slot = None;
+        }
+    }
+
+    Comparison {
+        old_score: old.score,
+        new_score: new.score,
+        new_issues: unmatched_new.into_iter().flatten().cloned().collect(),
+        fixed_issues: unmatched_old.into_iter().flatten().cloned().collect(),
+    }
+}
+
+/// Path, rule, and whitespace-normalized source text.
+type IssueIdentity<'a> = (&'a str, &'a str, String);
+
+fn identity(issue: &ReportedIssue) -> IssueIdentity<'_> {
+    let text = issue.text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (&issue.path, &issue.rule, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(rule: &str, line: usize, text: &str) -> ReportedIssue {
+        ReportedIssue {
+            path: "main.go".to_string(),
+            rule: rule.to_string(),
+            severity: "warning".to_string(),
+            message: String::new(),
+            line,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare_tolerates_line_drift() {
+        let old = RunSnapshot {
+            score: Some(8.0),
+            issues: vec![
+                issue("panic_usage", 10, "panic(\"a\")"),
+                issue("panic_usage", 20, "panic(\"b\")"),
+                issue("magic_number", 30, "42"),
+                issue("todo", 40, "// TODO"),
+            ],
+        };
+        let new = RunSnapshot {
+            score: Some(7.5),
+            issues: vec![
+                // Shifted by 15 lines, same text.
+                issue("panic_usage", 25, "panic(\"a\")"),
+                issue("panic_usage", 35, "panic(\"b\")"),
+                // Edited in place.
+                issue("magic_number", 31, "43"),
+                issue("panic_usage", 50, "panic(\"c\")"),
+            ],
+        };
+
+        let comparison = compare(&old, &new);
+        assert_eq!(
+            comparison.new_issues,
+            [issue("panic_usage", 50, "panic(\"c\")")]
+        );
+        assert_eq!(comparison.fixed_issues, [issue("todo", 40, "// TODO")]);
+        assert_eq!(comparison.score_delta(), Some(-0.5));
+    }
+}
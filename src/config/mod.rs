@@ -0,0 +1,1310 @@
+use crate::analyzer::{AnalysisRule, CodeAnalyzer, Confidence, RepeatDamping, Severity};
+use crate::glob::glob_match;
+use crate::language::{builtin_language, parse_requirement, LanguageRegistry};
+use crate::ratings::Ratings;
+use crate::report::ProjectWeighting;
+use crate::scope::RuleScope;
+use crate::snippet::SnippetOptions;
+use crate::source::DEFAULT_MAX_FILE_SIZE;
+use crate::workspace::SourceSet;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub mod resolver;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct RuleConfig {
+    pub name: String,
+    /// Tree-sitter query; may be omitted when `check`, `comment`,
+    /// `banned_imports`, or a function limit is set.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+    /// Built-in structural check to run instead of `query`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<String>,
+    /// Regex matched against the text of each comment, run instead of
+    /// `query`. Its first group, or else the whole match, is reported as
+    /// the finding's `marker` label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Modules whose imports the rule flags, run instead of `query`: each
+    /// matches itself and what is inside it, so `"java.sql"` flags
+    /// `import java.sql.Connection`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned_imports: Vec<String>,
+    /// Flags functions declaring more parameters than this, run instead
+    /// of `query`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_parameters: Option<usize>,
+    /// Flags functions with more `return`s than this, run instead of
+    /// `query`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_returns: Option<usize>,
+    /// Reports only findings on lines last changed longer ago than this,
+    /// such as `90d`, according to `git blame`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub older_than: Option<String>,
+    /// `error`, `warning`, `info` or `style`.
+    pub severity: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+    /// Longer explanation of the problem, shown by `compass explain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs_url: Option<String>,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub confidence: Confidence,
+    /// Cap on the total score deduction from this rule within one file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_impact: Option<f64>,
+    /// Only files with at least this many lines are checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_lines: Option<usize>,
+    /// Only files with at most this many lines are checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_lines: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Restricts the rule to `main` or `test` sources; unset applies to both.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_set: Option<SourceSet>,
+    /// Flags only nodes in `function` bodies, in `test` code, or outside
+    /// it (`non_test`); unset flags them anywhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<RuleScope>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Code the rule flags, shown next to its findings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_bad: Option<String>,
+    /// The preferred form of `example_bad`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub example_good: Option<String>,
+    /// Text `compass fix` replaces each flagged node with; empty deletes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl RuleConfig {
+    /// Whether this rule takes the place of `other`: they share a name and
+    /// a language, or one of them applies to every language.
+    fn replaces(&self, other: &RuleConfig) -> bool {
+        self.name == other.name
+            && match (&self.language, &other.language) {
+                (Some(own), Some(theirs)) => own.eq_ignore_ascii_case(theirs),
+                _ => true,
+            }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+/// Parses a time-to-live such as `7d`, `12h`, `30m`, or `90s`.
+pub(crate) fn parse_ttl(ttl: &str) -> Option<Duration> {
+    let split = ttl.len().checked_sub(1)?;
+    let (amount, unit) = ttl.split_at(split);
+    let amount: u64 = amount.parse().ok()?;
+    let seconds = match unit {
+        "d" => amount.checked_mul(86_400)?,
+        "h" => amount.checked_mul(3_600)?,
+        "m" => amount.checked_mul(60)?,
+        "s" => amount,
+        _ => return None,
+    };
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// `query` with each `{{name}}` replaced by fragment `name`, itself
+/// expanded. `expanding` holds the fragments being expanded, to catch
+/// cycles.
+fn expand_fragments(
+    query: &str,
+    fragments: &BTreeMap<String, String>,
+    expanding: &mut Vec<String>,
+) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = query;
+    while let Some(open) = rest.find("{{") {
+        let Some(length) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[open + 2..open + 2 + length].trim();
+        let fragment = fragments
+            .get(name)
+            .ok_or_else(|| format!("unknown fragment '{}'", name))?;
+        if expanding.iter().any(|outer| outer == name) {
+            return Err(format!("fragment '{}' refers to itself", name));
+        }
+        expanding.push(name.to_string());
+        expanded.push_str(&rest[..open]);
+        expanded.push_str(&expand_fragments(fragment, fragments, expanding)?);
+        expanding.pop();
+        rest = &rest[open + 2 + length + 2..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ScoringConfig {
+    #[serde(default)]
+    pub repeat_damping: RepeatDamping,
+    #[serde(default)]
+    pub weight_by_confidence: bool,
+    /// How file scores combine into the project score of multi-file runs.
+    #[serde(default)]
+    pub project_weighting: ProjectWeighting,
+}
+
+/// A named set of adjustments, e.g. `[profile.ci]`, applied on top of the
+/// config with `--profile`.
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Rule names to turn on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enable: Vec<String>,
+    /// Rule names to turn off.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// Merged over the config's `[severity_overrides]`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity_overrides: BTreeMap<String, String>,
+    #[serde(default)]
+    pub scoring: ProfileScoring,
+}
+
+/// `[scoring]` settings a profile replaces; unset ones are kept.
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileScoring {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_damping: Option<RepeatDamping>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight_by_confidence: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_weighting: Option<ProjectWeighting>,
+}
+
+/// A `[presets.<name>]` section: a curated rule list, selected with
+/// `preset = "<name>"` or `--preset`. Rules outside the list are turned off.
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Preset whose rules and severities this one builds on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// Rule names turned on, on top of those of `extends`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    /// Rule name to severity, over those of `extends`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity_overrides: BTreeMap<String, String>,
+}
+
+/// An `[[overrides]]` section: rule changes for the files matching
+/// `paths`. Later sections win over earlier ones.
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PathOverride {
+    /// Globs (`*`, `?`, `**`) matched against each file's path and every
+    /// trailing part of it, so `tests/**` also matches `app/tests/a.rs`.
+    pub paths: Vec<String>,
+    /// Rule names to turn on.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enable: Vec<String>,
+    /// Rule names to turn off.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disable: Vec<String>,
+    /// Rule name to severity, over the config's `[severity_overrides]`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity_overrides: BTreeMap<String, String>,
+    /// Rule name to weight, replacing the rule's `weight`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weights: BTreeMap<String, f64>,
+}
+
+impl PathOverride {
+    /// Whether `path` (`/`-separated) or a trailing part of it matches one
+    /// of the section's globs.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./");
+        let mut tails =
+            std::iter::once(path).chain(path.match_indices('/').map(|(at, _)| &path[at + 1..]));
+        tails.any(|tail| self.paths.iter().any(|pattern| glob_match(pattern, tail)))
+    }
+
+    /// Every rule name the section refers to.
+    fn rule_names(&self) -> impl Iterator<Item = &String> {
+        self.enable
+            .iter()
+            .chain(&self.disable)
+            .chain(self.severity_overrides.keys())
+            .chain(self.weights.keys())
+    }
+}
+
+/// An executable example run by `compass check-config`: `code` analyzed as
+/// `language` must produce exactly the `expect`ed findings.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigTest {
+    pub name: String,
+    /// Defaults to the language of the built-in config being checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub code: String,
+    /// Only findings of these rules are compared; empty compares all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    #[serde(default)]
+    pub expect: Vec<ExpectedHit>,
+}
+
+/// A finding a [`ConfigTest`] expects, one per rule and line.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedHit {
+    pub rule: String,
+    /// 1-based; unset matches a finding of the rule on any line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+/// A `[languages.<name>]` section: rules for one language, and for a
+/// language compass has no grammar for, the shared library to load it from
+/// at runtime.
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageConfig {
+    /// Path to the compiled grammar, relative to the config file; unset
+    /// for built-in languages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// Exported constructor; defaults to `tree_sitter_<name>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// `[[languages.<name>.rules]]`, the same as top-level `[[rules]]`
+    /// with `language = "<name>"`. Moved into
+    /// [`AnalyzerConfig::rules`] when the config is loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// A compass config file, usually `compass.toml`.
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct AnalyzerConfig {
+    /// Built-in rule sets to include by language, e.g. `["rust", "go"]`.
+    /// Rules defined here replace built-in rules of the same name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
+    /// `[presets.<name>]` to select from this config and the built-in
+    /// configs of `extends`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Config files, relative to this one, whose rules, fragments,
+    /// excludes, severity overrides, path overrides, profiles, and tests
+    /// are merged in.
+    /// This config's own definitions win.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Named query snippets that rule queries splice in as `{{name}}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fragments: BTreeMap<String, String>,
+    /// Globs, relative to the analyzed directory, of files to skip.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Skip binary files and files that aren't valid UTF-8 when scanning a
+    /// directory, instead of decoding them lossily with a warning.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skip_undecodable: bool,
+    /// Files larger than this many bytes are skipped when scanning a
+    /// directory, and refused when named directly; 0 disables the limit.
+    /// Defaults to [`DEFAULT_MAX_FILE_SIZE`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_size: Option<u64>,
+    /// Grammar versions the config was written against, e.g.
+    /// `["rust>=0.21"]`; analysis refuses to run otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub require_grammar: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub languages: BTreeMap<String, LanguageConfig>,
+    #[serde(default)]
+    pub scoring: ScoringConfig,
+    #[serde(default)]
+    pub snippets: SnippetOptions,
+    /// Rating bands and summary wording.
+    #[serde(default, skip_serializing_if = "Ratings::is_default")]
+    pub ratings: Ratings,
+    /// Rule name to severity, replacing the severity the rule declares.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity_overrides: BTreeMap<String, String>,
+    /// Per-path rule changes, applied in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<PathOverride>,
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+    #[serde(
+        default,
+        rename = "profile",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, Preset>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<ConfigTest>,
+}
+
+impl AnalyzerConfig {
+    /// JSON Schema of the config file format, derived from these types, for
+    /// editors to validate and complete configs with.
+    pub fn json_schema() -> serde_json::Value {
+        let mut schema = schemars::schema_for!(AnalyzerConfig);
+        schema.insert("title".to_string(), "compass.toml".into());
+        schema.to_value()
+    }
+
+    /// `max_file_size`, or its default.
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Self::load(&content, base_dir, vec![fs::canonicalize(path)?])
+    }
+
+    /// Parses a config whose `include`s are relative to the working
+    /// directory.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::load(content, Path::new(""), Vec::new())
+    }
+
+    fn load(
+        content: &str,
+        base_dir: &Path,
+        mut including: Vec<PathBuf>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self::parse_with_includes(content, base_dir, &mut including)?;
+        config.expand_fragments()?;
+        for requirement in &config.require_grammar {
+            parse_requirement(requirement).map_err(|e| format!("require_grammar: {}", e))?;
+        }
+        config.include_extended_rules()?;
+        config.check_overrides()?;
+        config.check_comment_rules()?;
+        config.ratings.check()?;
+        Ok(config)
+    }
+
+    /// `content` with the configs its `include` lists, and theirs, merged
+    /// in. `including` holds the files being read, to catch cycles.
+    fn parse_with_includes(
+        content: &str,
+        base_dir: &Path,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config: AnalyzerConfig = toml::from_str(content)?;
+        config.flatten_language_rules()?;
+        let mut rules: Vec<RuleConfig> = Vec::new();
+        let mut overrides: Vec<PathOverride> = Vec::new();
+        for include in config.include.clone() {
+            let path = fs::canonicalize(base_dir.join(&include))
+                .map_err(|e| format!("include '{}': {}", include, e))?;
+            if including.contains(&path) {
+                return Err(format!("include '{}' includes itself", include).into());
+            }
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("include '{}': {}", include, e))?;
+            including.push(path.clone());
+            let included =
+                Self::parse_with_includes(&content, path.parent().unwrap_or(base_dir), including)
+                    .map_err(|e| format!("include '{}': {}", include, e))?;
+            including.pop();
+
+            for rule in included.rules {
+                if !config
+                    .rules
+                    .iter()
+                    .chain(&rules)
+                    .any(|existing| existing.replaces(&rule))
+                {
+                    rules.push(rule);
+                }
+            }
+            for (name, fragment) in included.fragments {
+                config.fragments.entry(name).or_insert(fragment);
+            }
+            for (rule, severity) in included.severity_overrides {
+                config.severity_overrides.entry(rule).or_insert(severity);
+            }
+            for (name, profile) in included.profiles {
+                config.profiles.entry(name).or_insert(profile);
+            }
+            for (name, preset) in included.presets {
+                config.presets.entry(name).or_insert(preset);
+            }
+            if config.preset.is_none() {
+                config.preset = included.preset;
+            }
+            for name in included.extends {
+                if !config.extends.contains(&name) {
+                    config.extends.push(name);
+                }
+            }
+            config.exclude.extend(included.exclude);
+            config.tests.extend(included.tests);
+            overrides.extend(included.overrides);
+        }
+        rules.append(&mut config.rules);
+        config.rules = rules;
+        overrides.append(&mut config.overrides);
+        config.overrides = overrides;
+        Ok(config)
+    }
+
+    /// Moves each `[languages.<name>]` section's rules after the top-level
+    /// ones, scoped to that language.
+    fn flatten_language_rules(&mut self) -> Result<(), String> {
+        for (name, language) in &mut self.languages {
+            if language.library.is_none() {
+                if !language.extensions.is_empty()
+                    || language.symbol.is_some()
+                    || language.display_name.is_some()
+                {
+                    return Err(format!(
+                        "languages.{}: extensions, symbol, and display_name need a library",
+                        name
+                    ));
+                }
+                if builtin_language(name).is_none() {
+                    return Err(format!(
+                        "languages.{} is not a built-in language; external grammars need a library",
+                        name
+                    ));
+                }
+            }
+            for mut rule in language.rules.drain(..) {
+                match &rule.language {
+                    Some(scoped) if !scoped.eq_ignore_ascii_case(name) => {
+                        return Err(format!(
+                            "rule '{}' in languages.{} has language = \"{}\"",
+                            rule.name, name, scoped
+                        ));
+                    }
+                    _ => rule.language = Some(name.clone()),
+                }
+                self.rules.push(rule);
+            }
+        }
+        Ok(())
+    }
+
+    /// Splices `[fragments]` into the rule queries that name them.
+    fn expand_fragments(&mut self) -> Result<(), String> {
+        for rule in &mut self.rules {
+            rule.query = expand_fragments(&rule.query, &self.fragments, &mut Vec::new())
+                .map_err(|e| format!("rule '{}': {}", rule.name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Adds the built-in rules named by `extends`, scoped to their
+    /// language, ahead of the config's own rules. The selected `preset`
+    /// applies to the config's own rules and to each built-in config that
+    /// defines it; at least one must.
+    fn include_extended_rules(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let registry = LanguageRegistry::builtin();
+        let mut rules = Vec::new();
+        let mut presets: Vec<String> = self.presets.keys().cloned().collect();
+        let mut preset_found = false;
+        if let Some(preset) = self.preset.clone() {
+            if self.presets.contains_key(&preset) {
+                self.apply_preset(&preset)?;
+                preset_found = true;
+            }
+        }
+        for name in &self.extends {
+            let builtin = registry
+                .get(name)
+                .and_then(|language| language.default_config.as_deref())
+                .ok_or_else(|| {
+                    if let Some(missing) = builtin_language(name) {
+                        return format!(
+                            "built-in config '{}' in extends needs the {} grammar, which this build was compiled without; {}",
+                            name,
+                            missing.display_name,
+                            missing.install_hint()
+                        );
+                    }
+                    let names: Vec<&str> = registry
+                        .iter()
+                        .map(|language| language.name.as_str())
+                        .collect();
+                    format!(
+                        "unknown built-in config '{}' in extends (expected one of: {})",
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            let mut builtin: AnalyzerConfig = toml::from_str(builtin)?;
+            if let Some(preset) = &self.preset {
+                if builtin.presets.contains_key(preset) {
+                    builtin
+                        .apply_preset(preset)
+                        .map_err(|e| format!("built-in config '{}': {}", name, e))?;
+                    preset_found = true;
+                }
+            }
+            for preset in builtin.presets.keys() {
+                if !presets.contains(preset) {
+                    presets.push(preset.clone());
+                }
+            }
+            for mut rule in builtin.rules {
+                rule.language.get_or_insert_with(|| name.to_lowercase());
+                if !self.rules.iter().any(|own| own.replaces(&rule)) {
+                    rules.push(rule);
+                }
+            }
+        }
+        if let Some(preset) = self.preset.as_ref().filter(|_| !preset_found) {
+            return Err(format!(
+                "unknown preset '{}' (defined: {}; the built-in presets come with extends)",
+                preset,
+                if presets.is_empty() {
+                    "none".to_string()
+                } else {
+                    presets.join(", ")
+                }
+            )
+            .into());
+        }
+        rules.append(&mut self.rules);
+        self.rules = rules;
+        Ok(())
+    }
+
+    /// Turns on exactly the rules of `[presets.<name>]` and the presets it
+    /// extends, at their preset severities.
+    fn apply_preset(&mut self, name: &str) -> Result<(), String> {
+        let mut chain = Vec::new();
+        let mut next = Some(name);
+        while let Some(current) = next {
+            if chain.contains(&current) {
+                return Err(format!("preset '{}' extends itself", current));
+            }
+            let preset = self.presets.get(current).ok_or_else(|| {
+                format!(
+                    "preset '{}' extends unknown preset '{}'",
+                    chain.last().unwrap_or(&name),
+                    current
+                )
+            })?;
+            chain.push(current);
+            next = preset.extends.as_deref();
+        }
+
+        let mut enabled = Vec::new();
+        let mut severities = BTreeMap::new();
+        for preset_name in chain.iter().rev() {
+            let preset = &self.presets[*preset_name];
+            for rule_name in preset.rules.iter().chain(preset.severity_overrides.keys()) {
+                if self.rule(rule_name).is_none() {
+                    return Err(format!(
+                        "preset '{}' refers to unknown rule '{}'",
+                        preset_name, rule_name
+                    ));
+                }
+            }
+            if let Some(severity) = preset
+                .severity_overrides
+                .values()
+                .find(|severity| Severity::from_name(severity).is_none())
+            {
+                return Err(format!(
+                    "preset '{}' has unknown severity '{}' (expected error, warning, info, or style)",
+                    preset_name, severity
+                ));
+            }
+            enabled.extend(preset.rules.iter().cloned());
+            severities.extend(preset.severity_overrides.clone());
+        }
+        for rule in &mut self.rules {
+            rule.enabled = enabled.contains(&rule.name);
+            if let Some(severity) = severities.get(&rule.name) {
+                rule.severity = severity.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails on `[[overrides]]` that name unknown rules or severities.
+    fn check_overrides(&self) -> Result<(), String> {
+        for (index, section) in self.overrides.iter().enumerate() {
+            if let Some(name) = section.rule_names().find(|name| self.rule(name).is_none()) {
+                return Err(format!(
+                    "overrides[{}] refers to unknown rule '{}'",
+                    index, name
+                ));
+            }
+            if let Some(severity) = section
+                .severity_overrides
+                .values()
+                .find(|severity| Severity::from_name(severity).is_none())
+            {
+                return Err(format!(
+                    "overrides[{}] has unknown severity '{}' (expected error, warning, info, or style)",
+                    index, severity
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails on rules with an invalid `comment` regex or `older_than`.
+    fn check_comment_rules(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Some(pattern) = &rule.comment {
+                Regex::new(pattern)
+                    .map_err(|e| format!("rule '{}': invalid comment pattern: {}", rule.name, e))?;
+            }
+            if let Some(age) = rule
+                .older_than
+                .as_deref()
+                .filter(|age| parse_ttl(age).is_none())
+            {
+                return Err(format!(
+                    "rule '{}': invalid older_than '{}' (expected a duration such as 90d)",
+                    rule.name, age
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the `[profile.<name>]` section: rules it enables or disables,
+    /// its severity overrides, and its scoring settings.
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            format!(
+                "unknown profile '{}' (defined: {})",
+                name,
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })?;
+
+        for (rules, enabled) in [(&profile.enable, true), (&profile.disable, false)] {
+            for rule_name in rules {
+                let mut found = false;
+                for rule in self.rules.iter_mut().filter(|rule| &rule.name == rule_name) {
+                    rule.enabled = enabled;
+                    found = true;
+                }
+                if !found {
+                    return Err(format!(
+                        "profile '{}' refers to unknown rule '{}'",
+                        name, rule_name
+                    ));
+                }
+            }
+        }
+        self.severity_overrides.extend(
+            profile
+                .severity_overrides
+                .iter()
+                .map(|(rule, severity)| (rule.clone(), severity.clone())),
+        );
+        let scoring = &profile.scoring;
+        if let Some(repeat_damping) = scoring.repeat_damping {
+            self.scoring.repeat_damping = repeat_damping;
+        }
+        if let Some(weight_by_confidence) = scoring.weight_by_confidence {
+            self.scoring.weight_by_confidence = weight_by_confidence;
+        }
+        if let Some(project_weighting) = scoring.project_weighting {
+            self.scoring.project_weighting = project_weighting;
+        }
+        Ok(())
+    }
+
+    /// Whether `relative`, a path inside the analyzed directory, matches
+    /// an `exclude` glob.
+    pub fn excludes(&self, relative: &Path) -> bool {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        self.exclude
+            .iter()
+            .any(|pattern| glob_match(pattern, &relative))
+    }
+
+    pub fn from_language(language: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_name = format!("config/{}.toml", language);
+        Self::from_file(&config_name)
+    }
+
+    /// The rule called `name`, enabled or not.
+    pub fn rule(&self, name: &str) -> Option<&RuleConfig> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Severity `rule` runs at once `[severity_overrides]` are applied.
+    pub fn effective_severity<'a>(&'a self, rule: &'a RuleConfig) -> &'a str {
+        self.severity_overrides
+            .get(&rule.name)
+            .unwrap_or(&rule.severity)
+    }
+
+    pub fn to_analyzer(&self) -> CodeAnalyzer {
+        self.build_analyzer(None, None, None)
+    }
+
+    /// Builds an analyzer with only the rules that apply to `language`:
+    /// rules scoped to that language plus rules with no `language` field.
+    pub fn to_analyzer_for_language(&self, language: &str) -> CodeAnalyzer {
+        self.build_analyzer(Some(language), None, None)
+    }
+
+    /// Like [`to_analyzer_for_language`](Self::to_analyzer_for_language),
+    /// additionally dropping rules whose `source_set` excludes `source_set`.
+    /// `language: None` keeps rules for every language.
+    pub fn to_analyzer_for_source_set(
+        &self,
+        language: Option<&str>,
+        source_set: SourceSet,
+    ) -> CodeAnalyzer {
+        self.build_analyzer(language, Some(source_set), None)
+    }
+
+    /// The analyzer for the file at `path`: rules for `language` and the
+    /// file's source set, with the `[[overrides]]` matching `path` applied.
+    pub fn to_analyzer_for_path(&self, language: Option<&str>, path: &str) -> CodeAnalyzer {
+        self.build_analyzer(language, Some(SourceSet::of(path)), Some(path))
+    }
+
+    fn build_analyzer(
+        &self,
+        language: Option<&str>,
+        source_set: Option<SourceSet>,
+        path: Option<&str>,
+    ) -> CodeAnalyzer {
+        let mut analyzer = CodeAnalyzer::new()
+            .with_repeat_damping(self.scoring.repeat_damping)
+            .with_confidence_weighting(self.scoring.weight_by_confidence)
+            .with_snippets(self.snippets)
+            .with_ratings(self.ratings.clone());
+        let path = path.map(|path| path.replace('\\', "/"));
+        let overrides: Vec<&PathOverride> = match &path {
+            Some(path) => self
+                .overrides
+                .iter()
+                .filter(|section| section.matches(path))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        for rule_config in &self.rules {
+            let enabled = overrides
+                .iter()
+                .fold(rule_config.enabled, |enabled, section| {
+                    if section.disable.contains(&rule_config.name) {
+                        false
+                    } else {
+                        enabled || section.enable.contains(&rule_config.name)
+                    }
+                });
+            if !enabled {
+                continue;
+            }
+
+            if let (Some(wanted), Some(scoped)) = (language, rule_config.language.as_deref()) {
+                if !scoped.eq_ignore_ascii_case(wanted) {
+                    continue;
+                }
+            }
+
+            if let (Some(wanted), Some(scoped)) = (source_set, rule_config.source_set) {
+                if wanted != scoped {
+                    continue;
+                }
+            }
+
+            let mut rule = self.analysis_rule(rule_config);
+            for section in &overrides {
+                if let Some(severity) = section
+                    .severity_overrides
+                    .get(&rule.name)
+                    .and_then(|severity| Severity::from_name(severity))
+                {
+                    rule.severity = severity;
+                }
+                if let Some(&weight) = section.weights.get(&rule.name) {
+                    rule.weight_multiplier = weight;
+                }
+            }
+            analyzer.add_rule(rule);
+        }
+
+        analyzer
+    }
+
+    /// The rule as the analyzer runs it, with this config's severity
+    /// overrides applied.
+    pub fn analysis_rule(&self, rule: &RuleConfig) -> AnalysisRule {
+        let severity = Severity::from_name(self.effective_severity(rule)).unwrap_or(Severity::Info);
+
+        AnalysisRule::new(
+            rule.name.clone(),
+            rule.query.clone(),
+            severity,
+            rule.message.clone(),
+            rule.suggestion.clone(),
+        )
+        .with_weight(rule.weight)
+        .with_confidence(rule.confidence)
+        .with_labels(rule.labels.clone())
+        .with_check(rule.check.clone())
+        .with_comment(rule.comment.as_deref().map(|pattern| {
+            Regex::new(pattern).expect("comment patterns are checked when the config loads")
+        }))
+        .with_banned_imports(rule.banned_imports.clone())
+        .with_function_limits(rule.max_parameters, rule.max_returns)
+        .with_older_than(rule.older_than.as_deref().and_then(parse_ttl))
+        .with_max_total_impact(rule.max_total_impact)
+        .with_line_limits(rule.min_lines, rule.max_lines)
+        .with_examples(rule.example_bad.clone(), rule.example_good.clone())
+        .with_fix(rule.fix.clone())
+        .with_scope(rule.scope)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let content = toml::to_string_pretty(self)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parsing() {
+        let toml_str = r#"
+[[rules]]
+name = "test_rule"
+query = "(ERROR) @error"
+severity = "error"
+message = "Test error"
+enabled = true
+weight = 2.0
+        "#;
+
+        let config: AnalyzerConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.rules[0].name, "test_rule");
+        assert_eq!(config.rules[0].weight, 2.0);
+        assert!(config.rules[0].labels.is_empty());
+        assert_eq!(config.scoring.repeat_damping, RepeatDamping::None);
+    }
+
+    #[test]
+    fn test_scoring_repeat_damping_parsing() {
+        let config =
+            AnalyzerConfig::from_str("[scoring]\nrepeat_damping = \"logarithmic\"\n").unwrap();
+        assert_eq!(config.scoring.repeat_damping, RepeatDamping::Logarithmic);
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let toml_str = r#"
+[severity_overrides]
+unwrap_usage = "error"
+
+[[rules]]
+name = "unwrap_usage"
+query = "(ERROR) @error"
+severity = "warning"
+message = "unwrap"
+enabled = true
+
+[[rules]]
+name = "todo"
+query = "(ERROR) @error"
+severity = "info"
+message = "todo"
+enabled = true
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        let analyzer = config.to_analyzer();
+        let severities: Vec<&Severity> =
+            analyzer.rules().iter().map(|rule| &rule.severity).collect();
+        assert_eq!(severities, [&Severity::Error, &Severity::Info]);
+        assert_eq!(config.rules[0].severity, "warning");
+    }
+
+    #[test]
+    fn test_language_scoped_rules() {
+        let toml_str = r#"
+[[rules]]
+name = "rust_only"
+query = "(ERROR) @error"
+severity = "error"
+message = "Rust"
+enabled = true
+language = "rust"
+
+[[rules]]
+name = "everywhere"
+query = "(ERROR) @error"
+severity = "error"
+message = "Any"
+enabled = true
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        let names = |analyzer: CodeAnalyzer| {
+            analyzer
+                .rules()
+                .iter()
+                .map(|rule| rule.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(config.to_analyzer_for_language("go")), ["everywhere"]);
+        assert_eq!(
+            names(config.to_analyzer_for_language("rust")),
+            ["rust_only", "everywhere"]
+        );
+        assert_eq!(config.to_analyzer().rules().len(), 2);
+    }
+
+    #[test]
+    fn test_external_language_parsing() {
+        let toml_str = r#"
+[languages.elixir]
+library = "grammars/libtree-sitter-elixir.so"
+extensions = [".ex", ".exs"]
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        let elixir = &config.languages["elixir"];
+        assert_eq!(
+            elixir.library.as_deref(),
+            Some("grammars/libtree-sitter-elixir.so")
+        );
+        assert_eq!(elixir.extensions, [".ex", ".exs"]);
+        assert!(elixir.symbol.is_none());
+    }
+
+    #[cfg(all(feature = "lang-rust", feature = "lang-go"))]
+    #[test]
+    fn test_language_sections_hold_rules_for_one_language() {
+        let toml_str = r#"
+extends = ["rust", "go"]
+
+[scoring]
+repeat_damping = "logarithmic"
+
+[[rules]]
+name = "everywhere"
+query = "(ERROR) @error"
+severity = "info"
+message = "Everywhere"
+enabled = true
+
+[[languages.rust.rules]]
+name = "syntax_error"
+query = "(ERROR) @error"
+severity = "warning"
+message = "Rust syntax"
+enabled = true
+
+[[languages.go.rules]]
+name = "go_only"
+query = "(ERROR) @error"
+severity = "info"
+message = "Go"
+enabled = true
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        assert!(config
+            .languages
+            .values()
+            .all(|language| language.rules.is_empty()));
+        assert_eq!(config.scoring.repeat_damping, RepeatDamping::Logarithmic);
+        let rust = config.to_analyzer_for_language("rust");
+        let syntax: Vec<_> = rust
+            .rules()
+            .iter()
+            .filter(|rule| rule.name == "syntax_error")
+            .collect();
+        assert_eq!(syntax.len(), 1);
+        assert_eq!(syntax[0].message_template, "Rust syntax");
+        assert!(rust.rules().iter().any(|rule| rule.name == "everywhere"));
+        assert!(!rust.rules().iter().any(|rule| rule.name == "go_only"));
+
+        // The rust section's syntax_error leaves Go's built-in one alone.
+        let go = config.to_analyzer_for_language("go");
+        assert!(go.rules().iter().any(|rule| rule.name == "go_only"));
+        assert!(go
+            .rules()
+            .iter()
+            .any(|rule| rule.name == "syntax_error" && rule.message_template != "Rust syntax"));
+
+        for (invalid, error) in [
+            ("[[languages.rsut.rules]]\nname = \"r\"\nquery = \"(x) @x\"\nseverity = \"info\"\nmessage = \"m\"\n", "not a built-in language"),
+            ("[languages.rust]\nextensions = [\".rsx\"]\n", "need a library"),
+            ("[[languages.go.rules]]\nname = \"r\"\nlanguage = \"rust\"\nquery = \"(x) @x\"\nseverity = \"info\"\nmessage = \"m\"\n", "has language = \"rust\""),
+        ] {
+            let message = AnalyzerConfig::from_str(invalid).unwrap_err().to_string();
+            assert!(message.contains(error), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_rule_confidence_parsing() {
+        let toml_str = r#"
+[[rules]]
+name = "heuristic_rule"
+query = "(ERROR) @error"
+severity = "info"
+message = "Heuristic"
+enabled = true
+confidence = "low"
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        assert_eq!(config.rules[0].confidence, Confidence::Low);
+    }
+
+    #[test]
+    fn test_rule_labels_parsing() {
+        let toml_str = r#"
+[[rules]]
+name = "labelled_rule"
+query = "(ERROR) @error"
+severity = "warning"
+message = "Labelled"
+enabled = true
+labels = { team = "platform", epic = "cleanup-q3" }
+        "#;
+
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        let labels = &config.rules[0].labels;
+        assert_eq!(labels.get("team").map(String::as_str), Some("platform"));
+        assert_eq!(labels.get("epic").map(String::as_str), Some("cleanup-q3"));
+    }
+
+    #[test]
+    fn test_profiles_toggle_rules_and_scoring() {
+        let toml_str = r#"
+[scoring]
+repeat_damping = "logarithmic"
+
+[[rules]]
+name = "unwrap_usage"
+query = "(ERROR) @error"
+severity = "warning"
+message = "unwrap"
+enabled = true
+
+[[rules]]
+name = "todo"
+query = "(ERROR) @error"
+severity = "info"
+message = "todo"
+
+[profile.ci]
+enable = ["todo"]
+severity_overrides = { unwrap_usage = "error" }
+scoring = { weight_by_confidence = true }
+
+[profile.local]
+disable = ["unwrap_usage"]
+        "#;
+
+        let mut ci = AnalyzerConfig::from_str(toml_str).unwrap();
+        ci.apply_profile("ci").unwrap();
+        let analyzer = ci.to_analyzer();
+        assert_eq!(analyzer.rules().len(), 2);
+        assert_eq!(analyzer.rules()[0].severity, Severity::Error);
+        assert!(ci.scoring.weight_by_confidence);
+        assert_eq!(ci.scoring.repeat_damping, RepeatDamping::Logarithmic);
+
+        let mut local = AnalyzerConfig::from_str(toml_str).unwrap();
+        local.apply_profile("local").unwrap();
+        assert!(!local.to_analyzer().has_rules());
+
+        let error = local.apply_profile("strict").unwrap_err();
+        assert!(error.contains("unknown profile 'strict' (defined: ci, local)"));
+        assert!(
+            AnalyzerConfig::from_str("[profile.ci]\nenable = [\"nope\"]\n")
+                .unwrap()
+                .apply_profile("ci")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_presets_layer_and_select_rules() {
+        let registry = LanguageRegistry::builtin();
+        for language in registry.iter().filter(|l| l.default_config.is_some()) {
+            let enabled = |preset: &str| {
+                let config = AnalyzerConfig::from_str(&format!(
+                    "extends = [\"{}\"]\npreset = \"{}\"\n",
+                    language.name, preset
+                ))
+                .unwrap();
+                let names: Vec<String> = config
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.enabled)
+                    .map(|rule| rule.name.clone())
+                    .collect();
+                (names, config.rules.len())
+            };
+            let (minimal, _) = enabled("minimal");
+            let (recommended, _) = enabled("recommended");
+            let (strict, total) = enabled("strict");
+            assert!(minimal.iter().all(|rule| recommended.contains(rule)));
+            assert!(recommended.len() > minimal.len(), "{}", language.name);
+            assert_eq!(strict.len(), total, "{}", language.name);
+        }
+
+        let toml_str = r#"
+preset = "team"
+
+[[rules]]
+name = "call"
+query = "(call_expression) @call"
+severity = "info"
+message = "call"
+enabled = true
+
+[[rules]]
+name = "literal"
+query = "(integer_literal) @literal"
+severity = "info"
+message = "literal"
+enabled = true
+
+[presets.base]
+rules = ["literal"]
+
+[presets.team]
+extends = "base"
+severity_overrides = { literal = "error" }
+"#;
+        let config = AnalyzerConfig::from_str(toml_str).unwrap();
+        assert!(!config.rule("call").unwrap().enabled);
+        assert_eq!(config.rule("literal").unwrap().severity, "error");
+
+        let unknown = toml_str.replace("preset = \"team\"", "preset = \"strict\"");
+        let error = AnalyzerConfig::from_str(&unknown).unwrap_err().to_string();
+        assert!(error.contains("unknown preset 'strict' (defined: base, team;"));
+        let cycle = toml_str.replace("rules = [\"literal\"]", "extends = \"team\"");
+        let error = AnalyzerConfig::from_str(&cycle).unwrap_err().to_string();
+        assert!(error.contains("preset 'team' extends itself"));
+    }
+
+    #[test]
+    fn test_includes_merge_rules_and_fragments_expand() {
+        let dir = std::env::temp_dir().join("compass_config_include_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::write(
+            dir.join("shared/common.toml"),
+            r#"
+exclude = ["vendor/**"]
+
+[fragments]
+console = '(member_expression object: (identifier) @obj (#eq? @obj "console"))'
+console_call = "(call_expression function: {{console}})"
+
+[[rules]]
+name = "console_call"
+query = "{{console_call}} @call"
+severity = "info"
+message = "Shared"
+
+[[rules]]
+name = "todo"
+query = "(comment) @comment"
+severity = "info"
+message = "Shared"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("compass.toml"),
+            r#"
+include = ["shared/common.toml"]
+
+[[rules]]
+name = "todo"
+query = "(comment) @comment"
+severity = "warning"
+message = "Own"
+"#,
+        )
+        .unwrap();
+
+        let config = AnalyzerConfig::from_file(dir.join("compass.toml")).unwrap();
+        let names: Vec<&str> = config.rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["console_call", "todo"]);
+        assert_eq!(config.rule("todo").unwrap().message, "Own");
+        assert_eq!(
+            config.rule("console_call").unwrap().query,
+            r#"(call_expression function: (member_expression object: (identifier) @obj (#eq? @obj "console"))) @call"#
+        );
+        assert_eq!(config.exclude, ["vendor/**"]);
+
+        fs::write(
+            dir.join("shared/loop.toml"),
+            "include = [\"../compass.toml\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("compass.toml"),
+            "include = [\"shared/loop.toml\"]\n",
+        )
+        .unwrap();
+        let error = AnalyzerConfig::from_file(dir.join("compass.toml")).unwrap_err();
+        assert!(error.to_string().contains("includes itself"), "{}", error);
+
+        let unknown = "[[rules]]\nname = \"r\"\nquery = \"{{missing}} @x\"\nseverity = \"info\"\nmessage = \"m\"\n";
+        let error = AnalyzerConfig::from_str(unknown).unwrap_err();
+        assert_eq!(error.to_string(), "rule 'r': unknown fragment 'missing'");
+        let cyclic = "[fragments]\na = \"{{b}}\"\nb = \"{{a}}\"\n\n[[rules]]\nname = \"r\"\nquery = \"{{a}}\"\nseverity = \"info\"\nmessage = \"m\"\n";
+        let error = AnalyzerConfig::from_str(cyclic).unwrap_err();
+        assert_eq!(error.to_string(), "rule 'r': fragment 'a' refers to itself");
+    }
+}
@@ -0,0 +1,355 @@
+//! Config layering: the built-in rules, then the user config
+//! (`~/.config/compass/config.toml`), then the project config (the file
+//! passed to compass, `$COMPASS_CONFIG_TOML`, or the nearest
+//! `compass.toml`), then command-line settings such as `--preset`.
+//! `--profile` is applied on top of the result by the caller.
+//!
+//! Layers merge as TOML: tables key by key, rules by name (and language),
+//! other arrays by appending what the lower layer lacks, and any other
+//! value of a higher layer replaces the lower one.
+
+use super::AnalyzerConfig;
+use crate::language::LanguageRegistry;
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// The project config compass looks for and `compass init` writes.
+pub const DEFAULT_CONFIG_FILE: &str = "compass.toml";
+
+/// Where a layer comes from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerKind {
+    User,
+    Project,
+    /// Settings given as flags, such as `--preset`.
+    CommandLine,
+}
+
+impl LayerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LayerKind::User => "user",
+            LayerKind::Project => "project",
+            LayerKind::CommandLine => "command line",
+        }
+    }
+}
+
+/// One config file or string, parsed but not yet loaded, with its relative
+/// `include` and grammar `library` paths made absolute.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub kind: LayerKind,
+    /// The file's path, or `$VAR` for a config read from the environment.
+    pub label: String,
+    /// The file, for configs loaded from disk.
+    pub path: Option<PathBuf>,
+    table: Table,
+}
+
+impl Layer {
+    /// Reads the config file at `path`.
+    pub fn from_file(kind: LayerKind, path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut layer = Self::from_str(kind, &path.display().to_string(), &content, base_dir)?;
+        layer.path = Some(path.to_path_buf());
+        Ok(layer)
+    }
+
+    /// Parses `content`, whose relative paths are relative to `base_dir`.
+    /// A value of the wrong type fails here, at its line in `content`,
+    /// rather than once the layers are merged into text of their own.
+    pub fn from_str(
+        kind: LayerKind,
+        label: &str,
+        content: &str,
+        base_dir: &Path,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        toml::from_str::<AnalyzerConfig>(content)?;
+        let mut table: Table = toml::from_str(content)?;
+        let base_dir = if base_dir.as_os_str().is_empty() {
+            std::path::absolute(".")?
+        } else {
+            std::path::absolute(base_dir)?
+        };
+        if let Some(Value::Array(includes)) = table.get_mut("include") {
+            for include in includes {
+                absolutize(include, &base_dir);
+            }
+        }
+        let mut language_rules = Vec::new();
+        if let Some(Value::Table(languages)) = table.get_mut("languages") {
+            for (name, language) in languages.iter_mut() {
+                let Value::Table(language) = language else {
+                    continue;
+                };
+                if let Some(library) = language.get_mut("library") {
+                    absolutize(library, &base_dir);
+                }
+                language_rules.extend(scope_rules(name, language));
+            }
+        }
+        if !language_rules.is_empty() {
+            let rules = table
+                .entry("rules")
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(rules) = rules {
+                rules.extend(language_rules);
+            }
+        }
+        Ok(Layer {
+            kind,
+            label: label.to_string(),
+            path: None,
+            table,
+        })
+    }
+
+    /// The grammar `library` of each `[languages.<name>]` section.
+    pub fn grammar_libraries(&self) -> Vec<&str> {
+        let Some(Value::Table(languages)) = self.table.get("languages") else {
+            return Vec::new();
+        };
+        languages
+            .values()
+            .filter_map(|language| language.get("library")?.as_str())
+            .collect()
+    }
+
+    /// Loads the layer on its own, the way a single config file loads.
+    fn load(&self) -> Result<AnalyzerConfig, Box<dyn std::error::Error>> {
+        match &self.path {
+            Some(path) => AnalyzerConfig::from_file(path),
+            None => AnalyzerConfig::from_str(&toml::to_string(&self.table)?),
+        }
+    }
+}
+
+fn absolutize(value: &mut Value, base_dir: &Path) {
+    if let Value::String(path) = value {
+        if Path::new(path.as_str()).is_relative() {
+            *path = base_dir.join(path.as_str()).display().to_string();
+        }
+    }
+}
+
+/// Takes the rules out of the `[languages.<name>]` section `language` as
+/// top-level rules with `language = "<name>"`, so they merge with rules
+/// of other layers written either way. Rules naming another language stay
+/// for loading to reject.
+fn scope_rules(name: &str, language: &mut Table) -> Vec<Value> {
+    let Some(Value::Array(rules)) = language.remove("rules") else {
+        return Vec::new();
+    };
+    let (mut scoped, mut kept) = (Vec::new(), Vec::new());
+    for mut rule in rules {
+        match rule.get("language").and_then(Value::as_str) {
+            Some(other) if !other.eq_ignore_ascii_case(name) => kept.push(rule),
+            _ => {
+                if let Value::Table(fields) = &mut rule {
+                    fields.insert("language".to_string(), Value::String(name.to_string()));
+                }
+                scoped.push(rule);
+            }
+        }
+    }
+    if !kept.is_empty() {
+        language.insert("rules".to_string(), Value::Array(kept));
+    }
+    scoped
+}
+
+/// A layer selecting `[presets.<name>]`. On its own (`alone`) it extends
+/// every built-in config, so the preset picks among all built-in rules.
+pub fn preset_layer(name: &str, alone: bool) -> Layer {
+    let mut table = Table::new();
+    if alone {
+        let names = LanguageRegistry::builtin()
+            .iter()
+            .filter(|language| language.default_config.is_some())
+            .map(|language| Value::String(language.name.clone()))
+            .collect();
+        table.insert("extends".to_string(), Value::Array(names));
+    }
+    table.insert("preset".to_string(), Value::String(name.to_string()));
+    Layer {
+        kind: LayerKind::CommandLine,
+        label: if alone {
+            format!("built-in --preset {}", name)
+        } else {
+            format!("--preset {}", name)
+        },
+        path: None,
+        table,
+    }
+}
+
+/// The user-level config file, when it exists.
+pub fn user_config() -> Option<PathBuf> {
+    paths::user_config_file().filter(|path| path.is_file())
+}
+
+/// The nearest `compass.toml` in `start` or a directory above it, up to
+/// the root of the repository `start` is in.
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let candidate = dir.join(DEFAULT_CONFIG_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// `layers`, lowest precedence first, merged into one config. `None`
+/// when there are no layers, meaning each language uses its built-in
+/// rules.
+pub fn resolve(layers: &[Layer]) -> Result<Option<AnalyzerConfig>, Box<dyn std::error::Error>> {
+    match layers {
+        [] => Ok(None),
+        [layer] => layer.load().map(Some),
+        [lowest, rest @ ..] => {
+            let mut table = lowest.table.clone();
+            for layer in rest {
+                merge(&mut table, layer.table.clone());
+            }
+            AnalyzerConfig::from_str(&toml::to_string(&table)?).map(Some)
+        }
+    }
+}
+
+/// Merges `upper` over `lower`.
+pub fn merge(lower: &mut Table, upper: Table) {
+    for (key, value) in upper {
+        match (lower.get_mut(&key), value) {
+            (Some(Value::Table(lower)), Value::Table(upper)) => merge(lower, upper),
+            (Some(Value::Array(lower)), Value::Array(upper)) if key == "rules" => {
+                for rule in upper {
+                    lower.retain(|existing| !replaces(&rule, existing));
+                    lower.push(rule);
+                }
+            }
+            (Some(Value::Array(lower)), Value::Array(upper)) => {
+                for item in upper {
+                    if !lower.contains(&item) {
+                        lower.push(item);
+                    }
+                }
+            }
+            (_, value) => {
+                lower.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Whether rule `upper` takes the place of rule `lower`: they share a
+/// name, and a language unless one of them applies to every language.
+fn replaces(upper: &Value, lower: &Value) -> bool {
+    let field = |rule: &Value, name: &str| {
+        rule.get(name)
+            .and_then(Value::as_str)
+            .map(str::to_lowercase)
+    };
+    field(upper, "name") == field(lower, "name")
+        && match (field(upper, "language"), field(lower, "language")) {
+            (Some(upper), Some(lower)) => upper == lower,
+            _ => true,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(kind: LayerKind, content: &str) -> Layer {
+        Layer::from_str(kind, kind.as_str(), content, Path::new("/configs")).unwrap()
+    }
+
+    #[test]
+    fn test_project_layer_wins_over_user_layer() {
+        let user = layer(
+            LayerKind::User,
+            "exclude = [\"vendor/**\"]\ninclude = [\"shared.toml\"]\n\n[scoring]\nrepeat_damping = \"logarithmic\"\nweight_by_confidence = true\n\n[severity_overrides]\ncall = \"error\"\nliteral = \"info\"\n\n[[rules]]\nname = \"call\"\nquery = \"(call_expression) @call\"\nseverity = \"warning\"\nmessage = \"user\"\nenabled = true\n\n[[rules]]\nname = \"literal\"\nlanguage = \"go\"\nquery = \"(int_literal) @literal\"\nseverity = \"info\"\nmessage = \"user\"\nenabled = true\n",
+        );
+        let project = layer(
+            LayerKind::Project,
+            "exclude = [\"gen/**\", \"vendor/**\"]\n\n[scoring]\nrepeat_damping = \"none\"\n\n[severity_overrides]\ncall = \"style\"\n\n[[languages.rust.rules]]\nname = \"literal\"\nquery = \"(integer_literal) @literal\"\nseverity = \"info\"\nmessage = \"project\"\nenabled = true\n\n[[rules]]\nname = \"call\"\nquery = \"(call_expression) @call\"\nseverity = \"warning\"\nmessage = \"project\"\nenabled = true\n",
+        );
+
+        let mut table = user.table.clone();
+        merge(&mut table, project.table.clone());
+        assert_eq!(
+            table["exclude"],
+            Value::Array(vec!["vendor/**".into(), "gen/**".into()])
+        );
+        assert_eq!(table["include"][0].as_str(), Some("/configs/shared.toml"));
+        assert_eq!(table["scoring"]["repeat_damping"].as_str(), Some("none"));
+        assert_eq!(
+            table["scoring"]["weight_by_confidence"].as_bool(),
+            Some(true)
+        );
+        assert_eq!(table["severity_overrides"]["call"].as_str(), Some("style"));
+        assert_eq!(
+            table["severity_overrides"]["literal"].as_str(),
+            Some("info")
+        );
+
+        // Both `literal` rules survive: they are for different languages.
+        let rules = table["rules"].as_array().unwrap();
+        let messages: Vec<_> = rules
+            .iter()
+            .map(|rule| {
+                (
+                    rule["name"].as_str().unwrap(),
+                    rule["message"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("literal", "user"),
+                ("call", "project"),
+                ("literal", "project")
+            ]
+        );
+        assert_eq!(rules[2]["language"].as_str(), Some("rust"));
+        assert!(resolve(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_layer_fails_at_its_own_line() {
+        let error = Layer::from_str(
+            LayerKind::Project,
+            "compass.toml",
+            "exclude = [\"gen/**\"]\n\n[[rules]]\nname = \"call\"\nenabled = \"yes\"\n",
+            Path::new("/configs"),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("line 5"), "{}", error);
+    }
+
+    #[test]
+    fn test_grammar_libraries_are_listed_absolute() {
+        let project = layer(
+            LayerKind::Project,
+            "[languages.elixir]\nlibrary = \"grammars/elixir.so\"\nextensions = [\".ex\"]\n\n[languages.rust]\n",
+        );
+        assert_eq!(
+            project.grammar_libraries(),
+            [Path::new("/configs/grammars/elixir.so")
+                .display()
+                .to_string()]
+        );
+        assert!(layer(LayerKind::Project, "exclude = []\n")
+            .grammar_libraries()
+            .is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! Runs the `[[tests]]` embedded in a config, so rule sets carry their own
+//! regression tests. From `cargo test`:
+//!
+//! ```no_run
+//! #[test]
+//! fn compass_config() {
+//!     compass::config_test::assert_config_passes("compass.toml");
+//! }
+//! ```
+
+use crate::config::{AnalyzerConfig, ConfigTest, ExpectedHit};
+use crate::language::LanguageRegistry;
+use serde::Serialize;
+use std::path::Path;
+
+/// The result of one config test.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestOutcome {
+    pub name: String,
+    pub language: Option<String>,
+    /// Expected findings the rules did not produce.
+    pub missing: Vec<ExpectedHit>,
+    /// Findings the test did not expect.
+    pub unexpected: Vec<ExpectedHit>,
+    /// Why the test could not run, such as an unknown language.
+    pub error: Option<String>,
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// Runs every test of `config`. Tests without a `language` use
+/// `default_language`, the language of a built-in config.
+pub fn run_tests(
+    config: &AnalyzerConfig,
+    registry: &LanguageRegistry,
+    default_language: Option<&str>,
+) -> Vec<TestOutcome> {
+    config
+        .tests
+        .iter()
+        .map(|test| run_test(config, registry, test, default_language))
+        .collect()
+}
+
+fn run_test(
+    config: &AnalyzerConfig,
+    registry: &LanguageRegistry,
+    test: &ConfigTest,
+    default_language: Option<&str>,
+) -> TestOutcome {
+    let language = test.language.as_deref().or(default_language);
+    let mut outcome = TestOutcome {
+        name: test.name.clone(),
+        language: language.map(str::to_string),
+        missing: Vec::new(),
+        unexpected: Vec::new(),
+        error: None,
+    };
+    let Some(language) = language else {
+        outcome.error = Some("no language; set `language` on the test".to_string());
+        return outcome;
+    };
+    let Some(definition) = registry.get(language) else {
+        outcome.error = Some(format!("unknown language '{}'", language));
+        return outcome;
+    };
+
+    let analyzer = config.to_analyzer_for_language(&definition.name);
+    let results = match analyzer.analyze(&test.code, &definition.language) {
+        Ok(results) => results,
+        Err(e) => {
+            outcome.error = Some(e.to_string());
+            return outcome;
+        }
+    };
+
+    // One finding per rule and line, as reports show them.
+    let mut found: Vec<ExpectedHit> = results
+        .iter()
+        .filter(|result| test.rules.is_empty() || test.rules.contains(&result.rule_name))
+        .map(|result| ExpectedHit {
+            rule: result.rule_name.clone(),
+            line: Some(result.line),
+        })
+        .collect();
+    found.sort();
+    found.dedup();
+
+    // Exact lines first, so an expectation without a line can't take a
+    // finding another expectation pins down.
+    let mut expected: Vec<&ExpectedHit> = test.expect.iter().collect();
+    expected.sort_by_key(|hit| hit.line.is_none());
+    for hit in expected {
+        let position = found.iter().position(|finding| {
+            finding.rule == hit.rule && (hit.line.is_none() || finding.line == hit.line)
+        });
+        match position {
+            Some(index) => {
+                found.remove(index);
+            }
+            None => outcome.missing.push(hit.clone()),
+        }
+    }
+    outcome.unexpected = found;
+    outcome
+}
+
+/// Loads the config at `path`, with the grammars it declares, and runs its
+/// tests.
+pub fn check_file<P: AsRef<Path>>(path: P) -> Result<Vec<TestOutcome>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let config = AnalyzerConfig::from_file(path)?;
+    let mut registry = LanguageRegistry::builtin();
+    registry.register_from_config(&config, path.parent().unwrap_or(Path::new(".")))?;
+    Ok(run_tests(&config, &registry, None))
+}
+
+/// Panics with a readable report unless every test of the config at `path`
+/// passes.
+pub fn assert_config_passes<P: AsRef<Path>>(path: P) {
+    let path = path.as_ref();
+    let outcomes = check_file(path)
+        .unwrap_or_else(|e| panic!("failed to load config '{}': {}", path.display(), e));
+    if outcomes.iter().any(|outcome| !outcome.passed()) {
+        panic!(
+            "config tests of '{}' failed:\n{}",
+            path.display(),
+            format_outcomes(&outcomes)
+        );
+    }
+}
+
+/// One line per test, with the differences of failing ones.
+pub fn format_outcomes(outcomes: &[TestOutcome]) -> String {
+    let mut out = String::new();
+    for outcome in outcomes {
+        let status = if outcome.passed() { "ok" } else { "FAIL" };
+        match &outcome.language {
+            Some(language) => {
+                out.push_str(&format!("{:<4}  {} ({})\n", status, outcome.name, language))
+            }
+            None => out.push_str(&format!("{:<4}  {}\n", status, outcome.name)),
+        }
+        if let Some(error) = &outcome.error {
+            out.push_str(&format!("      error: {}\n", error));
+        }
+        for hit in &outcome.missing {
+            out.push_str(&format!("      missing: {}\n", describe(hit)));
+        }
+        for hit in &outcome.unexpected {
+            out.push_str(&format!("      unexpected: {}\n", describe(hit)));
+        }
+    }
+    out
+}
+
+fn describe(hit: &ExpectedHit) -> String {
+    match hit.line {
+        Some(line) => format!("{} at line {}", hit.rule, line),
+        None => hit.rule.clone(),
+    }
+}
+
+#[cfg(all(test, feature = "lang-go"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_report_missing_and_unexpected_findings() {
+        let config = AnalyzerConfig::from_str(
+            r#"
+[[rules]]
+name = "panic"
+query = '(call_expression function: (identifier) @fn (#eq? @fn "panic"))'
+severity = "warning"
+message = "panic"
+enabled = true
+
+[[tests]]
+name = "flags panic"
+language = "go"
+code = "package main\n\nfunc main() {\n\tpanic(1)\n}\n"
+expect = [{ rule = "panic", line = 4 }]
+
+[[tests]]
+name = "wrong line"
+language = "go"
+code = "package main\n\nfunc main() {\n\tpanic(1)\n}\n"
+expect = [{ rule = "panic", line = 3 }]
+
+[[tests]]
+name = "no language"
+code = ""
+"#,
+        )
+        .unwrap();
+
+        let outcomes = run_tests(&config, &LanguageRegistry::builtin(), None);
+        assert!(outcomes[0].passed());
+        assert!(!outcomes[1].passed());
+        assert_eq!(outcomes[1].missing[0].line, Some(3));
+        assert_eq!(outcomes[1].unexpected[0].line, Some(4));
+        assert!(outcomes[2].error.is_some());
+        assert_eq!(
+            format_outcomes(&outcomes[1..2]),
+            "FAIL  wrong line (go)\n      missing: panic at line 3\n      unexpected: panic at line 4\n"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Build-output and dependency directories that never contain sources worth
+/// analyzing.
+pub const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Build output directories keyed by the manifest that produces them. They
+/// are only skipped when they sit next to that manifest, since elsewhere a
+/// directory called `build` may well hold sources.
+pub const BUILD_OUTPUT_DIRS: &[(&str, &[&str])] = &[
+    ("package.json", &["dist", "build", "out", "coverage"]),
+    ("build.gradle", &["build"]),
+    ("build.gradle.kts", &["build"]),
+];
+
+/// Recursively collects files under `root` for which `accept` returns true.
+///
+/// Hidden entries (names starting with `.`), [`SKIPPED_DIRS`], and
+/// [`BUILD_OUTPUT_DIRS`] are skipped, and results are sorted so output is
+/// stable across runs. The tree is only read, never written, so read-only
+/// mounts are fine.
+pub fn collect_files<F>(root: &Path, accept: F) -> io::Result<Vec<PathBuf>>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut files = Vec::new();
+    visit(root, &accept, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn visit<F>(dir: &Path, accept: &F, files: &mut Vec<PathBuf>) -> io::Result<()>
+where
+    F: Fn(&Path) -> bool,
+{
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if is_hidden(&path) {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if is_skipped_dir(&path) {
+                continue;
+            }
+            visit(&path, accept, files)?;
+        } else if file_type.is_file() && accept(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_skipped_dir(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    if SKIPPED_DIRS.contains(&name) {
+        return true;
+    }
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    BUILD_OUTPUT_DIRS
+        .iter()
+        .any(|(manifest, dirs)| dirs.contains(&name) && parent.join(manifest).is_file())
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
@@ -0,0 +1,89 @@
+//! The error of the embedding API: [`Compass`](crate::Compass),
+//! [`RuleSet`](crate::RuleSet), and the functions of [`api`](crate::api).
+
+use crate::cancel::Cancelled;
+use std::fmt;
+use std::io;
+use tree_sitter::QueryError;
+
+/// Why analysis, or loading the rules for it, failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The config is invalid, or a grammar it requires or declares can't
+    /// be loaded.
+    Config(String),
+    /// No language is registered for the file's extension.
+    UnsupportedFile(String),
+    /// A rule's query doesn't compile for the language it runs on.
+    Query(QueryError),
+    /// A file or directory couldn't be read.
+    Io(io::Error),
+    /// The run was cancelled, or a file ran past its timeout.
+    Cancelled,
+    /// The file can't be analyzed for another reason, such as being over
+    /// `max_file_size` or having no enabled rules.
+    Other(String),
+}
+
+impl Error {
+    /// A failure to load a config: `Io` when its file couldn't be read.
+    pub(crate) fn config(error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast::<io::Error>() {
+            Ok(error) => Error::Io(*error),
+            Err(error) => Error::Config(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(message) | Error::UnsupportedFile(message) | Error::Other(message) => {
+                write!(f, "{}", message)
+            }
+            Error::Query(error) => write!(f, "{}", error),
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Cancelled => write!(f, "{}", Cancelled),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Query(error) => Some(error),
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<Cancelled> for Error {
+    fn from(_: Cancelled) -> Self {
+        Error::Cancelled
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+/// Sorts the errors of the analyzer and the modules under it.
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(error: Box<dyn std::error::Error>) -> Self {
+        if error.is::<Cancelled>() {
+            return Error::Cancelled;
+        }
+        let error = match error.downcast::<QueryError>() {
+            Ok(error) => return Error::Query(*error),
+            Err(error) => error,
+        };
+        match error.downcast::<io::Error>() {
+            Ok(error) => Error::Io(*error),
+            Err(error) => Error::Other(error.to_string()),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+/// Environment variable holding the secret that signs and verifies
+/// exemption tokens. Keep it in CI secrets, readable only by gate owners.
+pub const KEY_ENV_VAR: &str = "COMPASS_EXEMPTION_KEY";
+
+const TOKEN_PREFIX: &str = "compass-exempt-v1";
+
+/// Permission for one commit to pass the gate despite violations, until
+/// `expires_at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exemption {
+    pub commit: String,
+    pub reason: String,
+    /// Seconds since the Unix epoch.
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+impl Exemption {
+    pub fn new(commit: &str, reason: &str, ttl: Duration, now: u64) -> Self {
+        Exemption {
+            commit: commit.to_string(),
+            reason: reason.to_string(),
+            issued_at: now,
+            expires_at: now.saturating_add(ttl.as_secs()),
+        }
+    }
+
+    /// `compass-exempt-v1.<payload>.<signature>`, both hex encoded; the
+    /// signature is HMAC-SHA256 of the payload under `key`.
+    pub fn sign(&self, key: &[u8]) -> String {
+        let payload = serde_json::to_vec(self).expect("exemptions always serialize");
+        format!(
+            "{}.{}.{}",
+            TOKEN_PREFIX,
+            hex(&payload),
+            hex(&hmac_sha256(key, &payload))
+        )
+    }
+
+    /// Checks the signature, expiry, and that the token was issued for
+    /// `commit`.
+    pub fn verify(token: &str, key: &[u8], commit: &str, now: u64) -> Result<Self, String> {
+        let mut parts = token.trim().split('.');
+        let (Some(TOKEN_PREFIX), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err("malformed exemption token".to_string());
+        };
+        let payload = unhex(payload).ok_or("malformed exemption token")?;
+        let signature = unhex(signature).ok_or("malformed exemption token")?;
+        if !constant_time_eq(&hmac_sha256(key, &payload), &signature) {
+            return Err("exemption token signature is invalid".to_string());
+        }
+
+        let exemption: Exemption =
+            serde_json::from_slice(&payload).map_err(|_| "malformed exemption token")?;
+        if exemption.expires_at <= now {
+            return Err(format!(
+                "exemption token expired {}s ago",
+                now - exemption.expires_at
+            ));
+        }
+        if exemption.commit != commit {
+            return Err(format!(
+                "exemption token is for commit {}, not {}",
+                exemption.commit, commit
+            ));
+        }
+        Ok(exemption)
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_pad: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block_key.iter().map(|byte| byte ^ 0x5c).collect();
+    let inner = Sha256::new()
+        .chain_update(&inner_pad)
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(&outer_pad)
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_ttl;
+
+    #[test]
+    fn test_hmac_matches_rfc_4231() {
+        // Test case 2 of RFC 4231.
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_tokens_are_bound_to_key_commit_and_time() {
+        let exemption = Exemption::new("abc123", "hotfix", parse_ttl("7d").unwrap(), 1_000);
+        let token = exemption.sign(b"secret");
+
+        assert_eq!(
+            Exemption::verify(&token, b"secret", "abc123", 2_000),
+            Ok(exemption)
+        );
+        assert!(Exemption::verify(&token, b"other", "abc123", 2_000).is_err());
+        assert!(Exemption::verify(&token, b"secret", "def456", 2_000).is_err());
+        assert!(Exemption::verify(&token, b"secret", "abc123", 1_000 + 7 * 86_400).is_err());
+
+        let tampered = token.replacen("686f74666978", "686f74666979", 1);
+        assert!(Exemption::verify(&tampered, b"secret", "abc123", 2_000).is_err());
+        assert_eq!(parse_ttl("12h"), Some(Duration::from_secs(43_200)));
+        assert_eq!(parse_ttl("7"), None);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::analyzer::{AnalysisResult, Confidence, Severity};
+use crate::fingerprint::fingerprint;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tracker {
+    GitHub,
+    Jira,
+}
+
+impl Tracker {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "github" => Some(Tracker::GitHub),
+            "jira" => Some(Tracker::Jira),
+            _ => None,
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Tracker::GitHub => "github",
+            Tracker::Jira => "jira",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueGrouping {
+    Rule,
+    File,
+}
+
+impl IssueGrouping {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "rule" => Some(IssueGrouping::Rule),
+            "file" => Some(IssueGrouping::File),
+            _ => None,
+        }
+    }
+}
+
+/// Findings for a single analyzed file, tagged with the path they came from.
+pub struct FileFindings<'a> {
+    pub path: &'a str,
+    pub results: &'a [AnalysisResult],
+}
+
+pub struct ExportOptions<'a> {
+    pub tracker: Tracker,
+    /// `org/name` for GitHub, the project key for Jira.
+    pub repo: &'a str,
+    pub min_severity: Severity,
+    pub min_confidence: Confidence,
+    pub group_by: IssueGrouping,
+}
+
+/// Fingerprints of issues exported by earlier runs, one per line on disk.
+#[derive(Debug, Default)]
+pub struct ExportState {
+    fingerprints: BTreeSet<String>,
+}
+
+impl ExportState {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)?;
+        let fingerprints = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(ExportState { fingerprints })
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+
+    pub fn record(&mut self, fingerprint: &str) {
+        self.fingerprints.insert(fingerprint.to_string());
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let mut content = String::new();
+        for fingerprint in &self.fingerprints {
+            content.push_str(fingerprint);
+            content.push('\n');
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Builds tracker-ready issue payloads, skipping groups already present in
+/// `state`. Newly exported fingerprints are recorded in `state`.
+pub fn build_issue_payloads(
+    files: &[FileFindings],
+    options: &ExportOptions,
+    state: &mut ExportState,
+) -> Vec<Value> {
+    let mut groups: BTreeMap<String, Vec<(&str, &AnalysisResult)>> = BTreeMap::new();
+
+    for file in files {
+        for result in file.results {
+            if result.severity < options.min_severity || result.confidence < options.min_confidence
+            {
+                continue;
+            }
+
+            let key = match options.group_by {
+                IssueGrouping::Rule => result.rule_name.clone(),
+                IssueGrouping::File => file.path.to_string(),
+            };
+            groups.entry(key).or_default().push((file.path, result));
+        }
+    }
+
+    let mut payloads = Vec::new();
+    for (key, findings) in groups {
+        let group_kind = match options.group_by {
+            IssueGrouping::Rule => "rule",
+            IssueGrouping::File => "file",
+        };
+        let id = fingerprint(&[options.tracker.key(), options.repo, group_kind, &key]);
+        if state.contains(&id) {
+            continue;
+        }
+        state.record(&id);
+
+        let title = match options.group_by {
+            IssueGrouping::Rule => format!(
+                "compass: {} ({} findings)",
+                findings[0].1.message,
+                findings.len()
+            ),
+            IssueGrouping::File => format!("compass: {} findings in {}", findings.len(), key),
+        };
+        let body = issue_body(&findings, &id);
+        let labels = issue_labels(&findings);
+
+        let payload = match options.tracker {
+            Tracker::GitHub => json!({
+                "repo": options.repo,
+                "title": title,
+                "body": body,
+                "labels": labels,
+                "fingerprint": id,
+            }),
+            Tracker::Jira => json!({
+                "fields": {
+                    "project": { "key": options.repo },
+                    "summary": title,
+                    "description": body,
+                    "issuetype": { "name": "Task" },
+                    "labels": labels,
+                },
+                "fingerprint": id,
+            }),
+        };
+        payloads.push(payload);
+    }
+
+    payloads
+}
+
+fn issue_body(findings: &[(&str, &AnalysisResult)], id: &str) -> String {
+    let mut body = String::new();
+    for (path, result) in findings {
+        body.push_str(&format!(
+            "- `{}:{}:{}` [{:?}] {}: {}\n",
+            path, result.line, result.column, result.severity, result.rule_name, result.message
+        ));
+    }
+
+    let suggestions: BTreeSet<&str> = findings
+        .iter()
+        .filter_map(|(_, result)| result.suggestion.as_deref())
+        .collect();
+    if !suggestions.is_empty() {
+        body.push_str("\nSuggested fix:\n");
+        for suggestion in suggestions {
+            body.push_str(&format!("- {}\n", suggestion));
+        }
+    }
+
+    let mut explained = BTreeSet::new();
+    for (_, result) in findings {
+        if result.example_bad.is_none() && result.example_good.is_none() {
+            continue;
+        }
+        if !explained.insert(result.rule_name.as_str()) {
+            continue;
+        }
+        body.push_str(&format!("\nExample for {}:\n", result.rule_name));
+        if let Some(example) = &result.example_bad {
+            body.push_str(&format!("\nAvoid:\n```\n{}\n```\n", example.trim_end()));
+        }
+        if let Some(example) = &result.example_good {
+            body.push_str(&format!("\nPrefer:\n```\n{}\n```\n", example.trim_end()));
+        }
+    }
+
+    body.push_str(&format!("\n<!-- compass-fingerprint: {} -->\n", id));
+    body
+}
+
+fn issue_labels(findings: &[(&str, &AnalysisResult)]) -> Vec<String> {
+    let mut labels = BTreeSet::new();
+    labels.insert("compass".to_string());
+    for (_, result) in findings {
+        labels.insert(result.severity.to_string());
+        for (key, value) in &result.labels {
+            labels.insert(format!("{}:{}", key, value));
+        }
+    }
+    labels.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Columns;
+
+    fn finding(rule: &str, severity: Severity) -> AnalysisResult {
+        AnalysisResult {
+            rule_name: rule.to_string(),
+            severity,
+            message: format!("{} found", rule),
+            line: 3,
+            column: 5,
+            columns: Columns::default(),
+            end_line: 3,
+            end_columns: Columns::default(),
+            start_byte: 0,
+            end_byte: 0,
+            text: String::new(),
+            context: None,
+            suggestion: Some("Fix it.".to_string()),
+            score_impact: -1.0,
+            confidence: Confidence::High,
+            labels: BTreeMap::new(),
+            example_bad: None,
+            example_good: None,
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn test_payloads_filter_severity_and_dedup() {
+        let results = vec![
+            finding("panic_usage", Severity::Error),
+            finding("panic_usage", Severity::Error),
+            finding("console_log", Severity::Info),
+        ];
+        let files = [FileFindings {
+            path: "main.go",
+            results: &results,
+        }];
+        let options = ExportOptions {
+            tracker: Tracker::GitHub,
+            repo: "org/name",
+            min_severity: Severity::Warning,
+            min_confidence: Confidence::Low,
+            group_by: IssueGrouping::Rule,
+        };
+        let mut state = ExportState::default();
+
+        let payloads = build_issue_payloads(&files, &options, &mut state);
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(
+            payloads[0]["title"],
+            "compass: panic_usage found (2 findings)"
+        );
+        assert!(payloads[0]["body"]
+            .as_str()
+            .unwrap()
+            .contains("main.go:3:5"));
+
+        let repeated = build_issue_payloads(&files, &options, &mut state);
+        assert!(repeated.is_empty(), "Already exported groups are skipped");
+    }
+}
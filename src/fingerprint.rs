@@ -0,0 +1,73 @@
+use crate::analyzer::AnalysisResult;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Stable 64-bit FNV-1a fingerprint over a sequence of string parts.
+///
+/// Parts are separated by a NUL byte so `["ab", "c"]` and `["a", "bc"]`
+/// produce different fingerprints. The output is identical across platforms
+/// and compass versions, which makes it safe to persist.
+pub fn fingerprint(parts: &[&str]) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            hash ^= 0;
+            hash = hash.wrapping_mul(PRIME);
+        }
+        for byte in part.as_bytes() {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// One [`fingerprint`] per finding of a file, in the order of `results`.
+///
+/// Line numbers are left out so findings keep their fingerprint when code
+/// above them changes; identical findings (same rule and whitespace-
+/// normalized text) are told apart by their order in the file.
+pub fn finding_fingerprints(path: &str, results: &[AnalysisResult]) -> Vec<String> {
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by_key(|&index| (results[index].line, results[index].column));
+
+    let mut seen: HashMap<(&str, String), usize> = HashMap::new();
+    let mut fingerprints = vec![String::new(); results.len()];
+    for index in order {
+        let result = &results[index];
+        let text = result.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let occurrence = seen.entry((&result.rule_name, text.clone())).or_default();
+        *occurrence += 1;
+        fingerprints[index] =
+            fingerprint(&[path, &result.rule_name, &text, &occurrence.to_string()]);
+    }
+    fingerprints
+}
+
+/// Lowercase hex SHA-256 digest, used for content-addressed keys and
+/// download verification.
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_stable_and_separates_parts() {
+        assert_eq!(fingerprint(&["a"]), "af63dc4c8601ec8c");
+        assert_eq!(
+            fingerprint(&["rule", "file"]),
+            fingerprint(&["rule", "file"])
+        );
+        assert_ne!(fingerprint(&["ab", "c"]), fingerprint(&["a", "bc"]));
+    }
+}
@@ -0,0 +1,642 @@
+//! `compass fix`: applies the `fix` of every finding that has one, and
+//! describes the result as a unified diff or as an LSP `WorkspaceEdit` that
+//! editors and bots can apply edit by edit.
+
+use crate::analyzer::{self, AnalysisResult, Fix};
+use crate::position::Columns;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::path::Path;
+use tree_sitter::Language;
+
+/// Unchanged lines shown around each change of a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+/// A fix applied to a file, and the finding it resolves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedFix {
+    pub rule: String,
+    pub message: String,
+    pub fix: Fix,
+}
+
+/// Why a fix was not applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// It overlaps a fix of this rule that comes first in source order.
+    Conflict(String),
+    /// Its replacement is flagged, and fixed, again by the same rule, so
+    /// fixing twice would change the file twice.
+    NotIdempotent,
+    /// The file's fixes together introduce this syntax error, so all of
+    /// them were rolled back.
+    BrokeSyntax(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Conflict(rule) => write!(f, "overlaps a fix of {}", rule),
+            SkipReason::NotIdempotent => write!(f, "the rule flags its own replacement"),
+            SkipReason::BrokeSyntax(error) => {
+                write!(
+                    f,
+                    "rolled back, the fixed file has a syntax error at {}",
+                    error
+                )
+            }
+        }
+    }
+}
+
+/// A fix left out of a file, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedFix {
+    pub fix: AppliedFix,
+    pub reason: SkipReason,
+}
+
+/// A file before and after its fixes.
+#[derive(Debug, Clone)]
+pub struct FileFixes {
+    pub path: String,
+    pub original: String,
+    pub fixed: String,
+    /// In source order, without overlaps.
+    pub applied: Vec<AppliedFix>,
+    /// In source order.
+    pub skipped: Vec<SkippedFix>,
+    /// Applied fixes whose finding the fixed file still has.
+    pub unresolved: Vec<AppliedFix>,
+}
+
+impl FileFixes {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+/// Applies the fixes of `results` to `source` in source order. Fixes that
+/// would not change the text are dropped, and a fix overlapping one before
+/// it, or inserting at the same place, is skipped as a conflict. Which fix
+/// wins depends only on the fixes, never on the order of `results`.
+pub fn fix_source(path: &str, source: &str, results: &[AnalysisResult]) -> FileFixes {
+    let candidates = candidates(source, results);
+    resolve(path, source, candidates, Vec::new())
+}
+
+/// [`fix_source`], checked against the fixed text as re-parsed as
+/// `language` and re-analyzed with `reanalyze`:
+///
+/// - a fix whose replacement its own rule would fix again is skipped, so
+///   fixing the result once more changes nothing unless a skipped conflict
+///   still applies;
+/// - if the fixed text has a syntax error the source doesn't, every fix of
+///   the file is rolled back;
+/// - a fix whose rule still flags its replacement is listed as unresolved.
+pub fn fix_and_verify(
+    path: &str,
+    source: &str,
+    results: &[AnalysisResult],
+    language: &Language,
+    mut reanalyze: impl FnMut(&str) -> Result<Vec<AnalysisResult>, Box<dyn std::error::Error>>,
+) -> Result<FileFixes, Box<dyn std::error::Error>> {
+    let mut pending = candidates(source, results);
+    let mut unstable = Vec::new();
+    loop {
+        let mut file = resolve(path, source, pending.clone(), unstable.clone());
+        if file.is_empty() {
+            return Ok(file);
+        }
+        let reanalyzed = reanalyze(&file.fixed)?;
+        let refixes = candidates(&file.fixed, &reanalyzed);
+        let again: Vec<AppliedFix> = replaced_ranges(&file.applied)
+            .filter(|(applied, start, end)| {
+                refixes
+                    .iter()
+                    .any(|refix| refix.rule == applied.rule && overlaps(&refix.fix, *start, *end))
+            })
+            .map(|(applied, _, _)| applied.clone())
+            .collect();
+        if again.is_empty() {
+            if let Some(error) = new_syntax_error(source, &file.fixed, language)? {
+                file.skipped
+                    .extend(file.applied.drain(..).map(|fix| SkippedFix {
+                        fix,
+                        reason: SkipReason::BrokeSyntax(error.clone()),
+                    }));
+                file.skipped.sort_by(|a, b| a.fix.fix.cmp(&b.fix.fix));
+                file.fixed = file.original.clone();
+                return Ok(file);
+            }
+            file.unresolved = replaced_ranges(&file.applied)
+                .filter(|(applied, start, end)| {
+                    reanalyzed.iter().any(|result| {
+                        result.rule_name == applied.rule
+                            && overlaps_range(result.start_byte, result.end_byte, *start, *end)
+                    })
+                })
+                .map(|(applied, _, _)| applied.clone())
+                .collect();
+            return Ok(file);
+        }
+        // Each round drops at least one candidate, so this ends.
+        pending.retain(|candidate| !again.contains(candidate));
+        unstable.extend(again.into_iter().map(|fix| SkippedFix {
+            fix,
+            reason: SkipReason::NotIdempotent,
+        }));
+    }
+}
+
+/// The fixes of `results` that change `source`, sorted by position, then
+/// replacement, then rule.
+fn candidates(source: &str, results: &[AnalysisResult]) -> Vec<AppliedFix> {
+    let mut candidates: Vec<AppliedFix> = results
+        .iter()
+        .filter_map(|result| {
+            result.fix.clone().map(|fix| AppliedFix {
+                rule: result.rule_name.clone(),
+                message: result.message.clone(),
+                fix,
+            })
+        })
+        .filter(|candidate| {
+            source.get(candidate.fix.start_byte..candidate.fix.end_byte)
+                != Some(candidate.fix.replacement.as_str())
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.fix.cmp(&b.fix).then_with(|| a.rule.cmp(&b.rule)));
+    candidates.dedup();
+    candidates
+}
+
+fn resolve(
+    path: &str,
+    source: &str,
+    candidates: Vec<AppliedFix>,
+    mut skipped: Vec<SkippedFix>,
+) -> FileFixes {
+    let mut applied: Vec<AppliedFix> = Vec::new();
+    for candidate in candidates {
+        if let Some(last) = applied.last() {
+            // Captures of one match share a node, and so a fix.
+            if candidate.fix == last.fix {
+                continue;
+            }
+            if candidate.fix.start_byte < last.fix.end_byte
+                || candidate.fix.start_byte == last.fix.start_byte
+            {
+                skipped.push(SkippedFix {
+                    reason: SkipReason::Conflict(last.rule.clone()),
+                    fix: candidate,
+                });
+                continue;
+            }
+        }
+        applied.push(candidate);
+    }
+    skipped.sort_by(|a, b| a.fix.fix.cmp(&b.fix.fix));
+
+    FileFixes {
+        path: path.to_string(),
+        original: source.to_string(),
+        fixed: apply(source, 0, applied.iter().map(|applied| &applied.fix)),
+        applied,
+        skipped,
+        unresolved: Vec::new(),
+    }
+}
+
+/// Each applied fix with the byte range its replacement takes up in the
+/// fixed text.
+fn replaced_ranges(applied: &[AppliedFix]) -> impl Iterator<Item = (&AppliedFix, usize, usize)> {
+    let mut shift: isize = 0;
+    applied.iter().map(move |applied| {
+        let fix = &applied.fix;
+        let start = (fix.start_byte as isize + shift) as usize;
+        shift += fix.replacement.len() as isize - (fix.end_byte - fix.start_byte) as isize;
+        (applied, start, start + fix.replacement.len())
+    })
+}
+
+/// Whether `fix` touches bytes `start..end`.
+fn overlaps(fix: &Fix, start: usize, end: usize) -> bool {
+    overlaps_range(fix.start_byte, fix.end_byte, start, end)
+}
+
+/// Whether byte ranges `a_start..a_end` and `b_start..b_end` touch. An
+/// empty range touches the ranges it is inside of or next to.
+fn overlaps_range(a_start: usize, a_end: usize, b_start: usize, b_end: usize) -> bool {
+    if a_start == a_end || b_start == b_end {
+        a_start <= b_end && b_start <= a_end
+    } else {
+        a_start < b_end && b_start < a_end
+    }
+}
+
+/// The first syntax error of `fixed`, as `line:column: message`, that
+/// `source` doesn't also have. Errors are told apart by message, since
+/// fixes move their positions.
+fn new_syntax_error(
+    source: &str,
+    fixed: &str,
+    language: &Language,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let mut existing: HashMap<String, usize> = HashMap::new();
+    for error in analyzer::syntax_errors(source, language)? {
+        *existing.entry(error.message).or_default() += 1;
+    }
+    for error in analyzer::syntax_errors(fixed, language)? {
+        match existing.get_mut(&error.message) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => return Ok(Some(error.to_string())),
+        }
+    }
+    Ok(None)
+}
+
+/// `source`, which starts at byte `offset` of its file, with `fixes`
+/// applied. The fixes must be sorted and not overlap.
+fn apply<'a>(source: &str, offset: usize, fixes: impl Iterator<Item = &'a Fix>) -> String {
+    let mut fixed = String::with_capacity(source.len());
+    let mut copied = 0;
+    for fix in fixes {
+        fixed.push_str(&source[copied..fix.start_byte - offset]);
+        fixed.push_str(&fix.replacement);
+        copied = fix.end_byte - offset;
+    }
+    fixed.push_str(&source[copied..]);
+    fixed
+}
+
+/// Consecutive original lines `first..=last` touched by `fixes`.
+struct Change<'a> {
+    first: usize,
+    last: usize,
+    fixes: Vec<&'a Fix>,
+}
+
+/// The fixes of `file` as a unified diff with `a/` and `b/` path prefixes,
+/// which `git apply` and `patch -p1` accept. Empty when nothing changed.
+pub fn unified_diff(file: &FileFixes) -> String {
+    if file.is_empty() {
+        return String::new();
+    }
+    let mut lines: Vec<&str> = file.original.split_inclusive('\n').collect();
+    if lines.is_empty() {
+        lines.push("");
+    }
+    let mut starts = Vec::with_capacity(lines.len() + 1);
+    let mut offset = 0;
+    for line in &lines {
+        starts.push(offset);
+        offset += line.len();
+    }
+    starts.push(offset);
+    let line_of = |byte: usize| {
+        starts
+            .partition_point(|&start| start <= byte)
+            .saturating_sub(1)
+            .min(lines.len().saturating_sub(1))
+    };
+
+    let mut changes: Vec<Change> = Vec::new();
+    for applied in &file.applied {
+        let fix = &applied.fix;
+        let first = line_of(fix.start_byte);
+        let last = line_of(fix.end_byte.saturating_sub(1).max(fix.start_byte));
+        match changes.last_mut() {
+            Some(change) if first <= change.last => {
+                change.last = change.last.max(last);
+                change.fixes.push(fix);
+            }
+            _ => changes.push(Change {
+                first,
+                last,
+                fixes: vec![fix],
+            }),
+        }
+    }
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", file.path, file.path);
+    // Lines added minus lines removed by earlier hunks.
+    let mut shift: isize = 0;
+    let mut hunk_start = 0;
+    while hunk_start < changes.len() {
+        let mut hunk_end = hunk_start + 1;
+        while hunk_end < changes.len()
+            && changes[hunk_end].first - changes[hunk_end - 1].last <= 2 * CONTEXT_LINES + 1
+        {
+            hunk_end += 1;
+        }
+        let hunk = &changes[hunk_start..hunk_end];
+        let from = hunk[0].first.saturating_sub(CONTEXT_LINES);
+        let to = (hunk[hunk.len() - 1].last + CONTEXT_LINES + 1).min(lines.len());
+
+        let mut body = String::new();
+        let mut old_count = 0;
+        let mut new_count = 0;
+        let mut line = from;
+        for change in hunk {
+            for context in &lines[line..change.first] {
+                push_line(&mut body, ' ', context);
+            }
+            let old = &lines[change.first..(change.last + 1).min(lines.len())];
+            let old_text = &file.original[starts[change.first]..starts[change.last + 1]];
+            let new_text = apply(old_text, starts[change.first], change.fixes.iter().copied());
+            let new: Vec<&str> = new_text.split_inclusive('\n').collect();
+            for removed in old {
+                push_line(&mut body, '-', removed);
+            }
+            for added in &new {
+                push_line(&mut body, '+', added);
+            }
+            old_count += change.first - line + old.len();
+            new_count += change.first - line + new.len();
+            line = change.last + 1;
+        }
+        for context in &lines[line.min(to)..to] {
+            push_line(&mut body, ' ', context);
+        }
+        old_count += to.saturating_sub(line);
+        new_count += to.saturating_sub(line);
+
+        let new_from = (from as isize + shift) as usize;
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(from, old_count),
+            hunk_range(new_from, new_count)
+        ));
+        diff.push_str(&body);
+        shift += new_count as isize - old_count as isize;
+        hunk_start = hunk_end;
+    }
+    diff
+}
+
+/// `start,count` of a hunk starting at 0-based line `from`; an empty range
+/// names the line before it.
+fn hunk_range(from: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", from)
+    } else {
+        format!("{},{}", from + 1, count)
+    }
+}
+
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line);
+    if !line.ends_with('\n') {
+        diff.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// An LSP `WorkspaceEdit` of every file's fixes. Each edit is annotated with
+/// the rule it comes from, so editors can show and accept them one by one.
+pub fn workspace_edit(files: &[FileFixes]) -> Value {
+    let mut document_changes = Vec::new();
+    let mut annotations = Map::new();
+    for file in files.iter().filter(|file| !file.is_empty()) {
+        let edits: Vec<Value> = file
+            .applied
+            .iter()
+            .map(|applied| {
+                annotations.entry(applied.rule.clone()).or_insert_with(|| {
+                    json!({
+                        "label": applied.message,
+                        "description": format!("compass rule {}", applied.rule),
+                        "needsConfirmation": false,
+                    })
+                });
+                json!({
+                    "range": {
+                        "start": position(&file.original, applied.fix.start_byte),
+                        "end": position(&file.original, applied.fix.end_byte),
+                    },
+                    "newText": applied.fix.replacement,
+                    "annotationId": applied.rule,
+                })
+            })
+            .collect();
+        document_changes.push(json!({
+            "textDocument": { "uri": file_uri(&file.path), "version": null },
+            "edits": edits,
+        }));
+    }
+    json!({
+        "documentChanges": document_changes,
+        "changeAnnotations": annotations,
+    })
+}
+
+/// LSP position of `byte`: 0-based line, and UTF-16 code units into it.
+fn position(source: &str, byte: usize) -> Value {
+    json!({
+        "line": source[..byte].matches('\n').count(),
+        "character": Columns::at(source, byte).utf16 - 1,
+    })
+}
+
+/// `file://` URI of `path`, made absolute against the working directory.
+fn file_uri(path: &str) -> String {
+    let path = Path::new(path);
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+    let mut uri = String::from("file://");
+    for byte in absolute.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(all(test, feature = "lang-javascript"))]
+mod tests {
+    use super::*;
+    use crate::analyzer::{AnalysisRule, CodeAnalyzer, Severity};
+
+    fn fixes(source: &str) -> FileFixes {
+        let mut analyzer = CodeAnalyzer::new();
+        analyzer.add_rule(
+            AnalysisRule::new(
+                "no_var".to_string(),
+                r#"(variable_declaration "var" @keyword)"#.to_string(),
+                Severity::Warning,
+                "Use of 'var' keyword".to_string(),
+                None,
+            )
+            .with_fix(Some("let".to_string())),
+        );
+        let language = tree_sitter_javascript::LANGUAGE.into();
+        let results = analyzer.analyze(source, &language).unwrap();
+        fix_source("src/app.js", source, &results)
+    }
+
+    #[test]
+    fn test_fixes_render_as_unified_diff_hunks() {
+        let mut source = String::from("var a = 1;\n");
+        for line in 2..=10 {
+            source.push_str(&format!("// line {}\n", line));
+        }
+        source.push_str("var b = 2; var c = 3;");
+
+        let file = fixes(&source);
+        assert_eq!(file.applied.len(), 3);
+        assert!(file.fixed.starts_with("let a = 1;\n"));
+        assert!(file.fixed.ends_with("let b = 2; let c = 3;"));
+        assert_eq!(
+            unified_diff(&file),
+            "--- a/src/app.js\n+++ b/src/app.js\n\
+             @@ -1,4 +1,4 @@\n-var a = 1;\n+let a = 1;\n // line 2\n // line 3\n // line 4\n\
+             @@ -8,4 +8,4 @@\n // line 8\n // line 9\n // line 10\n\
+             -var b = 2; var c = 3;\n\\ No newline at end of file\n\
+             +let b = 2; let c = 3;\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified_diff(&fixes("let a = 1;\n")), "");
+    }
+
+    #[test]
+    fn test_conflicts_are_skipped_deterministically_and_fixing_is_idempotent() {
+        let mut analyzer = CodeAnalyzer::new();
+        for (name, query, fix) in [
+            ("to_let", r#"(variable_declaration "var" @keyword)"#, "let"),
+            (
+                "to_const",
+                r#"(variable_declaration "var" @keyword)"#,
+                "const",
+            ),
+            ("wrap", "(number) @number", "(1)"),
+            ("same", "(identifier) @id", "a"),
+        ] {
+            analyzer.add_rule(
+                AnalysisRule::new(
+                    name.to_string(),
+                    query.to_string(),
+                    Severity::Warning,
+                    name.to_string(),
+                    None,
+                )
+                .with_fix(Some(fix.to_string())),
+            );
+        }
+        let language = tree_sitter_javascript::LANGUAGE.into();
+        let source = "var a = 1;\n";
+        let mut results = analyzer.analyze(source, &language).unwrap();
+        let reanalyze = |fixed: &str| analyzer.analyze(fixed, &language);
+
+        let file = fix_and_verify("app.js", source, &results, &language, reanalyze).unwrap();
+        assert_eq!(file.fixed, "const a = 1;\n");
+        let skipped: Vec<(&str, &SkipReason)> = file
+            .skipped
+            .iter()
+            .map(|skipped| (skipped.fix.rule.as_str(), &skipped.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("to_let", &SkipReason::Conflict("to_const".to_string())),
+                ("wrap", &SkipReason::NotIdempotent),
+            ]
+        );
+
+        results.reverse();
+        let reversed = fix_and_verify("app.js", source, &results, &language, reanalyze).unwrap();
+        assert_eq!(reversed.fixed, file.fixed);
+
+        let results = analyzer.analyze(&file.fixed, &language).unwrap();
+        let again = fix_and_verify("app.js", &file.fixed, &results, &language, reanalyze).unwrap();
+        assert!(again.is_empty());
+        assert_eq!(again.fixed, file.fixed);
+    }
+
+    #[test]
+    fn test_fixes_that_break_syntax_are_rolled_back_and_unresolved_ones_listed() {
+        let language = tree_sitter_javascript::LANGUAGE.into();
+        let analyzer_with = |fix: &str| {
+            let mut analyzer = CodeAnalyzer::new();
+            analyzer.add_rule(
+                AnalysisRule::new(
+                    "no_var".to_string(),
+                    r#"(variable_declaration "var" @keyword)"#.to_string(),
+                    Severity::Warning,
+                    "Use of 'var' keyword".to_string(),
+                    None,
+                )
+                .with_fix(Some(fix.to_string())),
+            );
+            analyzer
+        };
+        let source = "var a = 1;\nvar b = 2;\n";
+
+        let breaking = analyzer_with("let (");
+        let results = breaking.analyze(source, &language).unwrap();
+        let file = fix_and_verify("app.js", source, &results, &language, |fixed| {
+            breaking.analyze(fixed, &language)
+        })
+        .unwrap();
+        assert!(file.is_empty());
+        assert_eq!(file.fixed, source);
+        assert_eq!(file.skipped.len(), 2);
+        assert!(
+            matches!(&file.skipped[0].reason, SkipReason::BrokeSyntax(error) if error.starts_with("1:"))
+        );
+
+        // The fix leaves a number, which the rule flags again but can't fix.
+        let mut zeroing = CodeAnalyzer::new();
+        zeroing.add_rule(
+            AnalysisRule::new(
+                "magic_number".to_string(),
+                "(number) @number".to_string(),
+                Severity::Info,
+                "Magic number".to_string(),
+                None,
+            )
+            .with_fix(Some("0".to_string())),
+        );
+        let results = zeroing.analyze(source, &language).unwrap();
+        let file = fix_and_verify("app.js", source, &results, &language, |fixed| {
+            zeroing.analyze(fixed, &language)
+        })
+        .unwrap();
+        assert_eq!(file.fixed, "var a = 0;\nvar b = 0;\n");
+        assert_eq!(file.unresolved.len(), 2);
+    }
+
+    #[test]
+    fn test_workspace_edit_uses_utf16_positions_and_rule_annotations() {
+        let file = fixes("const s = \"😀\"; var a = 1;\n");
+        let edit = workspace_edit(&[file]);
+
+        let change = &edit["documentChanges"][0];
+        assert!(change["textDocument"]["uri"]
+            .as_str()
+            .unwrap()
+            .ends_with("/src/app.js"));
+        assert_eq!(
+            change["edits"][0],
+            json!({
+                "range": {
+                    "start": { "line": 0, "character": 16 },
+                    "end": { "line": 0, "character": 19 },
+                },
+                "newText": "let",
+                "annotationId": "no_var",
+            })
+        );
+        assert_eq!(
+            edit["changeAnnotations"]["no_var"]["label"],
+            "Use of 'var' keyword"
+        );
+    }
+}
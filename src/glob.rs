@@ -0,0 +1,58 @@
+/// Matches a `/`-separated path against a glob pattern.
+///
+/// Supports `*` (any characters within one segment), `?` (one character),
+/// and `**` (any number of whole segments, including none). Matching is
+/// case-sensitive and both inputs should use `/` separators.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let path: Vec<&str> = path
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((first, path_rest)) => {
+                match_segment(segment.as_bytes(), first.as_bytes())
+                    && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| match_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && match_segment(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && match_segment(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("packages/*", "packages/web"));
+        assert!(!glob_match("packages/*", "packages/web/src"));
+        assert!(glob_match("tests/**", "tests/unit/a.rs"));
+        assert!(glob_match("**/*.rs", "main.rs"));
+        assert!(glob_match("**/*_test.go", "pkg/a/b_test.go"));
+        assert!(glob_match("src/?.js", "src/a.js"));
+        assert!(!glob_match("src/*.js", "lib/a.js"));
+        assert!(glob_match("./apps/*", "apps/site"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::ratings::Ratings;
+use crate::report::{FileReport, ProjectScore, ProjectWeighting};
+use serde_json::{json, Value};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One student's submission: a subdirectory of the submissions root.
+#[derive(Debug, Clone)]
+pub struct Submission {
+    /// Name of the subdirectory.
+    pub student: String,
+    pub root: PathBuf,
+    pub files: Vec<FileReport>,
+}
+
+impl Submission {
+    pub fn score(&self, weighting: ProjectWeighting, ratings: &Ratings) -> ProjectScore {
+        ProjectScore::from_files(&self.files, weighting).with_ratings(ratings)
+    }
+}
+
+/// Non-hidden subdirectories of `root` as `(student, path)`, sorted by name.
+pub fn submission_dirs(root: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        dirs.push((name, entry.path()));
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// One row per submission with its score and finding counts.
+pub fn grade_sheet_json(
+    submissions: &[Submission],
+    weighting: ProjectWeighting,
+    ratings: &Ratings,
+) -> Value {
+    Value::from(
+        submissions
+            .iter()
+            .map(|submission| {
+                let score = submission.score(weighting, ratings);
+                json!({
+                    "student": submission.student,
+                    "score": score.overall_score,
+                    "rating": score.rating,
+                    "files_analyzed": score.files_analyzed,
+                    "total_lines": score.total_lines,
+                    "total_issues": score.total_issues,
+                    "errors": score.breakdown.errors,
+                    "warnings": score.breakdown.warnings,
+                    "info_issues": score.breakdown.info_issues,
+                    "style_issues": score.breakdown.style_issues,
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// [`grade_sheet_json`] as CSV with a header row, for gradebook imports.
+pub fn grade_sheet_csv(
+    submissions: &[Submission],
+    weighting: ProjectWeighting,
+    ratings: &Ratings,
+) -> String {
+    let mut csv = String::from(
+        "student,score,rating,files_analyzed,total_lines,total_issues,errors,warnings,info_issues,style_issues\n",
+    );
+    for submission in submissions {
+        let score = submission.score(weighting, ratings);
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{}\n",
+            csv_field(&submission.student),
+            score
+                .overall_score
+                .map(|score| score.to_string())
+                .unwrap_or_default(),
+            score.rating,
+            score.files_analyzed,
+            score.total_lines,
+            score.total_issues,
+            score.breakdown.errors,
+            score.breakdown.warnings,
+            score.breakdown.info_issues,
+            score.breakdown.style_issues
+        ));
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Markdown feedback for one student: the overall grade, then every finding
+/// per file with the rubric's suggestion.
+pub fn feedback_report(
+    submission: &Submission,
+    weighting: ProjectWeighting,
+    ratings: &Ratings,
+) -> String {
+    let score = submission.score(weighting, ratings);
+    let mut report = format!("# Feedback for {}\n\n", submission.student);
+    match score.overall_score {
+        Some(overall) => report.push_str(&format!(
+            "Score: {:.1}/10 ({})\n\n{}\n",
+            overall, score.rating, score.summary
+        )),
+        None => {
+            report.push_str("No source files in a supported language were found.\n");
+            return report;
+        }
+    }
+
+    for file in &submission.files {
+        let path = Path::new(&file.path);
+        let path = path.strip_prefix(&submission.root).unwrap_or(path);
+        report.push_str(&format!(
+            "\n## {} ({:.1}/10)\n\n",
+            path.display(),
+            file.score.overall_score
+        ));
+        if file.results.is_empty() {
+            report.push_str("No issues found.\n");
+            continue;
+        }
+        let mut results: Vec<_> = file.results.iter().collect();
+        results.sort_by_key(|result| (result.line, &result.rule_name));
+        for result in results {
+            report.push_str(&format!(
+                "- Line {}: {} ({:?})\n",
+                result.line, result.message, result.severity
+            ));
+            if let Some(suggestion) = &result.suggestion {
+                report.push_str(&format!("  Suggestion: {}\n", suggestion));
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quotes_separators() {
+        assert_eq!(csv_field("alice"), "alice");
+        assert_eq!(csv_field("doe, jane"), "\"doe, jane\"");
+        assert_eq!(csv_field("o\"neil"), "\"o\"\"neil\"");
+    }
+}
@@ -0,0 +1,390 @@
+//! gRPC analysis service for build farms, defined in `proto/compass.proto`.
+//!
+//! Unlike the [playground](crate::playground), clients are trusted: they
+//! send their own sources and may send their own config. Analysis runs on
+//! blocking threads, and [`AnalyzeStream`](proto::analysis_server::Analysis::analyze_stream)
+//! analyzes several of a client's files at once but reads its next batch
+//! only once its results so far fit in the response buffer, so a slow
+//! reader slows its sender down.
+//!
+//! A file the client sent that can't be analyzed, for its extension or a
+//! rule that doesn't compile, is `InvalidArgument`, or an `error` result
+//! within a stream; a failure inside compass is `Internal`.
+
+use crate::analyzer::AnalysisResult;
+use crate::config::{AnalyzerConfig, RuleConfig};
+use crate::error::Error;
+use crate::language::LanguageRegistry;
+use futures_util::{stream, StreamExt};
+use proto::analysis_server::{Analysis, AnalysisServer};
+use proto::{
+    AnalyzeRequest, FileBatch, FileResult, Finding, ListRulesRequest, ListRulesResponse, Rule,
+    SourceFile,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+/// Messages and client and server stubs generated from the proto file.
+pub mod proto {
+    tonic::include_proto!("compass.v1");
+}
+
+/// Results of a stream buffered before the server stops reading batches.
+const STREAM_BUFFER: usize = 32;
+
+/// Files of a stream analyzed at once.
+const CONCURRENT_FILES: usize = 8;
+
+/// The service: languages and the config files are analyzed with.
+#[derive(Clone)]
+pub struct AnalysisService {
+    registry: Arc<LanguageRegistry>,
+    config: Option<Arc<AnalyzerConfig>>,
+}
+
+impl AnalysisService {
+    /// Analyzes with `config`, or each language's built-in rules.
+    pub fn new(registry: LanguageRegistry, config: Option<AnalyzerConfig>) -> Self {
+        AnalysisService {
+            registry: Arc::new(registry),
+            config: config.map(Arc::new),
+        }
+    }
+
+    /// The service ready to add to a tonic server.
+    pub fn into_server(self) -> AnalysisServer<Self> {
+        AnalysisServer::new(self)
+    }
+
+    async fn analyze_file(
+        &self,
+        file: SourceFile,
+        config: Option<Arc<AnalyzerConfig>>,
+    ) -> Result<FileResult, Status> {
+        let registry = self.registry.clone();
+        let path = file.path.clone();
+        tokio::task::spawn_blocking(move || analyze(&registry, config.as_deref(), file))
+            .await
+            .map_err(|e| Status::internal(format!("analysis of '{}' failed: {}", path, e)))?
+    }
+}
+
+#[tonic::async_trait]
+impl Analysis for AnalysisService {
+    async fn analyze(
+        &self,
+        request: Request<AnalyzeRequest>,
+    ) -> Result<Response<FileResult>, Status> {
+        let request = request.into_inner();
+        let file = request
+            .file
+            .ok_or_else(|| Status::invalid_argument("expected a file"))?;
+        let config = match request.config_toml {
+            Some(toml) => {
+                Some(Arc::new(AnalyzerConfig::from_str(&toml).map_err(|e| {
+                    Status::invalid_argument(format!("invalid config: {}", e))
+                })?))
+            }
+            None => self.config.clone(),
+        };
+        let result = self.analyze_file(file, config).await?;
+        match result.error {
+            Some(error) => Err(Status::invalid_argument(error)),
+            None => Ok(Response::new(result)),
+        }
+    }
+
+    type AnalyzeStreamStream = ReceiverStream<Result<FileResult, Status>>;
+
+    async fn analyze_stream(
+        &self,
+        request: Request<Streaming<FileBatch>>,
+    ) -> Result<Response<Self::AnalyzeStreamStream>, Status> {
+        let files = request
+            .into_inner()
+            .map(|batch| {
+                stream::iter(match batch {
+                    Ok(batch) => batch.files.into_iter().map(Ok).collect(),
+                    Err(status) => vec![Err(status)],
+                })
+            })
+            .flatten();
+        let service = self.clone();
+        let mut results = files
+            .map(move |file| {
+                let service = service.clone();
+                async move {
+                    let config = service.config.clone();
+                    service.analyze_file(file?, config).await
+                }
+            })
+            .buffered(CONCURRENT_FILES);
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            while let Some(result) = results.next().await {
+                let failed = result.is_err();
+                // The client hung up, or the stream ends with the error.
+                if sender.send(result).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn list_rules(
+        &self,
+        request: Request<ListRulesRequest>,
+    ) -> Result<Response<ListRulesResponse>, Status> {
+        let language = request.into_inner().language;
+        let mut rules = Vec::new();
+        match &self.config {
+            Some(config) => rules.extend(
+                config
+                    .rules
+                    .iter()
+                    .filter(|rule| {
+                        language.is_empty()
+                            || rule
+                                .language
+                                .as_deref()
+                                .is_none_or(|scoped| scoped.eq_ignore_ascii_case(&language))
+                    })
+                    .map(|rule| rule_message(rule.language.as_deref().unwrap_or_default(), rule)),
+            ),
+            None => {
+                for definition in self.registry.iter() {
+                    if !language.is_empty() && !definition.name.eq_ignore_ascii_case(&language) {
+                        continue;
+                    }
+                    let Some(source) = definition.default_config.as_deref() else {
+                        continue;
+                    };
+                    let config = AnalyzerConfig::from_str(source)
+                        .map_err(|e| Status::internal(e.to_string()))?;
+                    rules.extend(
+                        config
+                            .rules
+                            .iter()
+                            .map(|rule| rule_message(&definition.name, rule)),
+                    );
+                }
+            }
+        }
+        Ok(Response::new(ListRulesResponse { rules }))
+    }
+}
+
+/// Serves `service` on `addr` until the process is stopped.
+pub fn serve(addr: &str, service: AnalysisService) -> Result<(), Box<dyn std::error::Error>> {
+    let addr: SocketAddr = addr
+        .parse()
+        .map_err(|e| format!("invalid address '{}': {}", addr, e))?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(
+        tonic::transport::Server::builder()
+            .add_service(service.into_server())
+            .serve(addr),
+    )?;
+    Ok(())
+}
+
+/// The result for `file`, with `error` set when the client sent something
+/// compass can't analyze.
+fn analyze(
+    registry: &LanguageRegistry,
+    config: Option<&AnalyzerConfig>,
+    file: SourceFile,
+) -> Result<FileResult, Status> {
+    let Some(language) = registry.detect(&file.path) else {
+        let error = format!(
+            "unsupported file extension for '{}'. Supported extensions: {}",
+            file.path,
+            registry.extensions_label()
+        );
+        return Ok(failed(file.path, error));
+    };
+    Ok(
+        match crate::api::analyze_source(&file.path, &file.source, language, config) {
+            Ok(Some(report)) => FileResult {
+                findings: report.results.iter().map(finding).collect(),
+                score: report.score.overall_score,
+                max_score: report.score.max_score,
+                rating: report.score.rating,
+                language: report.language,
+                path: report.path,
+                ..FileResult::default()
+            },
+            Ok(None) => FileResult {
+                language: language.name.clone(),
+                path: file.path,
+                skipped: true,
+                ..FileResult::default()
+            },
+            Err(e @ Error::Query(_)) => failed(file.path, e.to_string()),
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "analysis of '{}' failed: {}",
+                    file.path, e
+                )))
+            }
+        },
+    )
+}
+
+fn failed(path: String, error: String) -> FileResult {
+    FileResult {
+        path,
+        error: Some(error),
+        ..FileResult::default()
+    }
+}
+
+fn finding(result: &AnalysisResult) -> Finding {
+    Finding {
+        rule: result.rule_name.clone(),
+        severity: result.severity.as_str().to_string(),
+        message: result.message.clone(),
+        line: result.line as u32,
+        column: result.column as u32,
+        end_line: result.end_line as u32,
+        suggestion: result.suggestion.clone(),
+        score_impact: result.score_impact,
+        confidence: result.confidence.as_str().to_string(),
+    }
+}
+
+fn rule_message(language: &str, rule: &RuleConfig) -> Rule {
+    Rule {
+        name: rule.name.clone(),
+        language: language.to_string(),
+        severity: rule.severity.clone(),
+        message: rule.message.clone(),
+        suggestion: rule.suggestion.clone(),
+        enabled: rule.enabled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::analysis_client::AnalysisClient;
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    #[test]
+    fn test_analyze_stream_and_list_rules_over_a_connection() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let service = AnalysisService::new(LanguageRegistry::builtin(), None);
+            tokio::spawn(
+                tonic::transport::Server::builder()
+                    .add_service(service.into_server())
+                    .serve_with_incoming(TcpListenerStream::new(listener)),
+            );
+            let mut client = AnalysisClient::connect(format!("http://{}", addr))
+                .await
+                .unwrap();
+
+            let unwrap = SourceFile {
+                path: "src/main.rs".into(),
+                source: "fn main() {\n    let x = Some(1).unwrap();\n}\n".into(),
+            };
+            let result = client
+                .analyze(AnalyzeRequest {
+                    file: Some(unwrap.clone()),
+                    config_toml: None,
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(result.language, "rust");
+            assert!(result
+                .findings
+                .iter()
+                .any(|f| f.rule == "no_unwrap_prefer_error_handling" && f.line == 2));
+
+            let notes = SourceFile {
+                path: "notes.txt".into(),
+                source: String::new(),
+            };
+            let status = client
+                .analyze(AnalyzeRequest {
+                    file: Some(notes.clone()),
+                    config_toml: None,
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+            let status = client
+                .analyze(AnalyzeRequest {
+                    file: Some(unwrap.clone()),
+                    config_toml: Some("[[rules]]\nname = 1".into()),
+                })
+                .await
+                .unwrap_err();
+            assert!(
+                status.message().starts_with("invalid config"),
+                "{}",
+                status.message()
+            );
+            let status = client
+                .analyze(AnalyzeRequest {
+                    file: Some(unwrap.clone()),
+                    config_toml: Some("[[rules]]\nname = \"broken\"\nquery = \"(nope\"\nseverity = \"warning\"\nmessage = \"m\"\nenabled = true\n".into()),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+            let batches = vec![
+                FileBatch {
+                    files: vec![unwrap.clone(), notes],
+                },
+                FileBatch {
+                    files: vec![SourceFile {
+                        path: "lib.go".into(),
+                        source: "package lib\n".into(),
+                    }],
+                },
+            ];
+            let mut results = client
+                .analyze_stream(tokio_stream::iter(batches))
+                .await
+                .unwrap()
+                .into_inner();
+            let mut paths = Vec::new();
+            while let Some(result) = results.message().await.unwrap() {
+                paths.push((result.path, result.error.is_some()));
+            }
+            assert_eq!(
+                paths,
+                [
+                    ("src/main.rs".to_string(), false),
+                    ("notes.txt".to_string(), true),
+                    ("lib.go".to_string(), false)
+                ],
+                "one result per file, in order; a bad file doesn't end the stream"
+            );
+
+            let rules = client
+                .list_rules(ListRulesRequest {
+                    language: "rust".into(),
+                })
+                .await
+                .unwrap()
+                .into_inner()
+                .rules;
+            assert!(rules
+                .iter()
+                .any(|rule| rule.name == "no_unwrap_prefer_error_handling"
+                    && rule.language == "rust"));
+            assert!(rules.iter().all(|rule| rule.language == "rust"));
+        });
+    }
+}
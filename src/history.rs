@@ -0,0 +1,458 @@
+use crate::report::ProjectScore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One recorded run, stored as a line of JSON in the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub recorded_at: u64,
+    /// `HEAD` of the enclosing git repository, if any.
+    pub commit: Option<String>,
+    pub score: Option<f64>,
+    pub rating: String,
+    pub files_analyzed: usize,
+    pub total_lines: usize,
+    pub total_issues: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub info_issues: usize,
+    pub style_issues: usize,
+    /// SHA-256 over the effective rule configs; runs under different
+    /// configs are not comparable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_fingerprint: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, FileSnapshot>,
+}
+
+/// What one file looked like in a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    /// SHA-256 of the file's contents.
+    pub sha256: String,
+    /// SHA-256 of the config the file was analyzed with.
+    pub config: String,
+    pub score: f64,
+}
+
+impl HistoryEntry {
+    /// An entry for `score`, stamped with the current time and git commit.
+    pub fn record(
+        score: &ProjectScore,
+        config_fingerprint: String,
+        files: BTreeMap<String, FileSnapshot>,
+    ) -> Self {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+
+        HistoryEntry {
+            recorded_at,
+            commit: current_commit(),
+            score: score.overall_score,
+            rating: score.rating.clone(),
+            files_analyzed: score.files_analyzed,
+            total_lines: score.total_lines,
+            total_issues: score.total_issues,
+            errors: score.breakdown.errors,
+            warnings: score.breakdown.warnings,
+            info_issues: score.breakdown.info_issues,
+            style_issues: score.breakdown.style_issues,
+            config_fingerprint: Some(config_fingerprint),
+            files,
+        }
+    }
+
+    /// Whether this run used a different config than `latest`. Entries
+    /// recorded before configs were fingerprinted are never stale.
+    pub fn is_stale(&self, latest: &HistoryEntry) -> bool {
+        matches!(
+            (&self.config_fingerprint, &latest.config_fingerprint),
+            (Some(config), Some(latest)) if config != latest
+        )
+    }
+
+    /// Files whose contents match `latest` but which were scored under a
+    /// different config: their score changes come from the config alone.
+    pub fn stale_files<'a>(&'a self, latest: &HistoryEntry) -> Vec<&'a str> {
+        self.files
+            .iter()
+            .filter(|(path, file)| {
+                latest.files.get(*path).is_some_and(|current| {
+                    current.sha256 == file.sha256 && current.config != file.config
+                })
+            })
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+}
+
+/// Weight of a run `age` old when weights halve every `half_life`.
+pub fn decay_weight(age: Duration, half_life: Duration) -> f64 {
+    0.5_f64.powf(age.as_secs_f64() / half_life.as_secs_f64().max(1.0))
+}
+
+/// Mean score of the runs comparable with the latest one, each weighted by
+/// [`decay_weight`] of its age relative to the latest run.
+pub fn weighted_score(entries: &[HistoryEntry], half_life: Duration) -> Option<f64> {
+    let latest = entries.last()?;
+    let (mut total, mut weights) = (0.0, 0.0);
+    for entry in entries.iter().filter(|entry| !entry.is_stale(latest)) {
+        let Some(score) = entry.score else {
+            continue;
+        };
+        let age = Duration::from_secs(latest.recorded_at.saturating_sub(entry.recorded_at));
+        let weight = decay_weight(age, half_life);
+        total += score * weight;
+        weights += weight;
+    }
+    (weights > 0.0).then(|| (total / weights * 10.0).round() / 10.0)
+}
+
+/// The entries as JSON, each with `stale` and `stale_files` relative to the
+/// latest run and, given a half-life, its decay `weight`.
+pub fn to_json(entries: &[HistoryEntry], half_life: Option<Duration>) -> Value {
+    let Some(latest) = entries.last() else {
+        return Value::Array(Vec::new());
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let mut value = serde_json::to_value(entry).expect("history entries serialize");
+            value["stale"] = Value::from(entry.is_stale(latest));
+            value["stale_files"] = Value::from(entry.stale_files(latest));
+            if let Some(half_life) = half_life {
+                let age = latest.recorded_at.saturating_sub(entry.recorded_at);
+                let weight = if entry.is_stale(latest) {
+                    0.0
+                } else {
+                    decay_weight(Duration::from_secs(age), half_life)
+                };
+                value["weight"] = Value::from(weight);
+            }
+            value
+        })
+        .collect()
+}
+
+/// Reads every entry, oldest first. A missing file is an empty history.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(line)
+            .map_err(|e| format!("{}:{}: {}", path.display(), index + 1, e))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Appends `entry`, creating the file and its directory as needed.
+pub fn append<P: AsRef<Path>>(
+    path: P,
+    entry: &HistoryEntry,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Human-readable trend: one row per run with the score change since the
+/// previous run, followed by the overall change.
+/// Runs under a config other than the latest one are marked `*` and left
+/// out of the trend; with `half_life`, a decay-weighted score follows.
+pub fn format_table(entries: &[HistoryEntry], half_life: Option<Duration>) -> String {
+    let mut table = format!(
+        "{:<20} {:<8} {:>6} {:>6} {:>7} {:>7} {:>9}\n",
+        "DATE", "COMMIT", "SCORE", "DELTA", "ISSUES", "ERRORS", "WARNINGS"
+    );
+
+    let Some(latest) = entries.last() else {
+        return table;
+    };
+    let mut previous: Option<&HistoryEntry> = None;
+    for entry in entries {
+        let commit = entry
+            .commit
+            .as_deref()
+            .map(|commit| &commit[..commit.len().min(7)])
+            .unwrap_or("-");
+        table.push_str(&format!(
+            "{:<20} {:<8} {:>6} {:>6} {:>7} {:>7} {:>9}\n",
+            format_timestamp(entry.recorded_at),
+            commit,
+            format!(
+                "{}{}",
+                format_score(entry.score),
+                if entry.is_stale(latest) { "*" } else { "" }
+            ),
+            // Scores under different configs are not comparable.
+            match previous {
+                Some(previous) if !previous.is_stale(entry) =>
+                    format_delta(previous.score, entry.score),
+                _ => "-".to_string(),
+            },
+            entry.total_issues,
+            entry.errors,
+            entry.warnings
+        ));
+        if entry.score.is_some() {
+            previous = Some(entry);
+        }
+    }
+
+    let current: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|entry| !entry.is_stale(latest))
+        .collect();
+    let scores: Vec<f64> = current.iter().filter_map(|entry| entry.score).collect();
+    if let (Some(first), Some(last)) = (scores.first(), scores.last()) {
+        table.push_str(&format!(
+            "\nTrend over {} runs: {:.1} -> {:.1} ({})\n",
+            current.len(),
+            first,
+            last,
+            format_delta(Some(*first), Some(*last))
+        ));
+    }
+    if let Some(weighted) = half_life.and_then(|half_life| weighted_score(entries, half_life)) {
+        table.push_str(&format!("Decay-weighted score: {:.1}\n", weighted));
+    }
+    if current.len() < entries.len() {
+        table.push_str(&format!(
+            "* {} run{} used a different config and {} left out of the trend\n",
+            entries.len() - current.len(),
+            if entries.len() - current.len() == 1 {
+                ""
+            } else {
+                "s"
+            },
+            if entries.len() - current.len() == 1 {
+                "is"
+            } else {
+                "are"
+            }
+        ));
+    }
+    table
+}
+
+/// CSV export with a header row, for spreadsheets and dashboards.
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let mut csv = String::from(
+        "recorded_at,date,commit,score,rating,files_analyzed,total_lines,total_issues,errors,warnings,info_issues,style_issues,stale\n",
+    );
+    for entry in entries {
+        let stale = entries.last().is_some_and(|latest| entry.is_stale(latest));
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            entry.recorded_at,
+            format_timestamp(entry.recorded_at),
+            entry.commit.as_deref().unwrap_or_default(),
+            entry
+                .score
+                .map(|score| score.to_string())
+                .unwrap_or_default(),
+            entry.rating,
+            entry.files_analyzed,
+            entry.total_lines,
+            entry.total_issues,
+            entry.errors,
+            entry.warnings,
+            entry.info_issues,
+            entry.style_issues,
+            stale
+        ));
+    }
+    csv
+}
+
+/// `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+
+    // Civil-from-days (Howard Hinnant), valid for any date after 1970.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+fn format_score(score: Option<f64>) -> String {
+    score
+        .map(|score| format!("{:.1}", score))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_delta(previous: Option<f64>, current: Option<f64>) -> String {
+    match (previous, current) {
+        (Some(previous), Some(current)) => format!("{:+.1}", current - previous),
+        _ => "-".to_string(),
+    }
+}
+
+/// `HEAD` of the git repository enclosing the working directory.
+pub(crate) fn current_commit() -> Option<String> {
+    resolve_commit("HEAD")
+}
+
+/// The full hash of the commit `revision` names in the git repository
+/// enclosing the working directory, such as a short hash, branch or tag.
+pub(crate) fn resolve_commit(revision: &str) -> Option<String> {
+    if revision.starts_with('-') {
+        return None;
+    }
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", revision))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(recorded_at: u64, score: f64) -> HistoryEntry {
+        HistoryEntry {
+            recorded_at,
+            commit: Some("0123456789abcdef".to_string()),
+            score: Some(score),
+            rating: "Good".to_string(),
+            files_analyzed: 3,
+            total_lines: 120,
+            total_issues: 4,
+            errors: 0,
+            warnings: 1,
+            info_issues: 3,
+            style_issues: 0,
+            config_fingerprint: None,
+            files: BTreeMap::new(),
+        }
+    }
+
+    fn snapshot(sha256: &str, config: &str, score: f64) -> FileSnapshot {
+        FileSnapshot {
+            sha256: sha256.to_string(),
+            config: config.to_string(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn test_history_round_trip_and_trend() {
+        let path = std::env::temp_dir().join("compass_history_test.jsonl");
+        let _ = fs::remove_file(&path);
+
+        append(&path, &entry(1_700_000_000, 7.5)).unwrap();
+        append(&path, &entry(1_700_086_400, 8.1)).unwrap();
+        let entries = load(&path).unwrap();
+        assert_eq!(
+            entries,
+            [entry(1_700_000_000, 7.5), entry(1_700_086_400, 8.1)]
+        );
+
+        let table = format_table(&entries, None);
+        assert!(table.contains("0123456"));
+        assert!(table.contains("+0.6"));
+        assert!(table.contains("Trend over 2 runs: 7.5 -> 8.1 (+0.6)"));
+        assert_eq!(to_csv(&entries).lines().count(), 3);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_config_changes_mark_runs_stale_and_decay_weights_recent_runs() {
+        let mut old = entry(1_700_000_000, 5.0);
+        old.config_fingerprint = Some("old".to_string());
+        old.files
+            .insert("a.rs".to_string(), snapshot("a1", "c1", 5.0));
+        old.files
+            .insert("b.rs".to_string(), snapshot("b1", "c1", 5.0));
+        let mut previous = entry(1_700_000_000, 6.0);
+        previous.config_fingerprint = Some("new".to_string());
+        let mut latest = entry(1_700_086_400, 9.0);
+        latest.config_fingerprint = Some("new".to_string());
+        latest
+            .files
+            .insert("a.rs".to_string(), snapshot("a1", "c2", 9.0));
+        latest
+            .files
+            .insert("b.rs".to_string(), snapshot("b2", "c2", 9.0));
+
+        assert!(old.is_stale(&latest));
+        assert!(!previous.is_stale(&latest));
+        assert!(!entry(0, 1.0).is_stale(&latest));
+        assert_eq!(old.stale_files(&latest), ["a.rs"]);
+
+        let entries = [old, previous, latest];
+        let day = Duration::from_secs(86_400);
+        assert_eq!(decay_weight(day, day), 0.5);
+        // (6.0 * 0.5 + 9.0 * 1.0) / 1.5; the stale 5.0 is ignored.
+        assert_eq!(weighted_score(&entries, day), Some(8.0));
+
+        let table = format_table(&entries, Some(day));
+        assert!(table.contains("5.0*"));
+        assert!(table.contains("Trend over 2 runs: 6.0 -> 9.0 (+3.0)"));
+        assert!(table.contains("Decay-weighted score: 8.0"));
+        assert!(table.contains("* 1 run used a different config and is left out"));
+
+        let json = to_json(&entries, Some(day));
+        assert_eq!(json[0]["stale"], true);
+        assert_eq!(json[0]["stale_files"][0], "a.rs");
+        assert_eq!(json[0]["weight"], 0.0);
+        assert_eq!(json[1]["weight"], 0.5);
+        assert!(to_csv(&entries).lines().nth(1).unwrap().ends_with(",true"));
+    }
+}
@@ -0,0 +1,124 @@
+//! `compass hook`: a git pre-commit hook that analyzes what is about to be
+//! committed. Staged files are read from the index rather than the working
+//! tree, so unstaged edits neither hide nor add findings.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// First comment of hooks `compass hook install` writes, so reinstalling
+/// replaces them without `--force`.
+const MARKER: &str = "# Installed by compass hook install.";
+
+/// Output of `git args`, or its error message.
+fn git(args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Files the next commit adds, copies, modifies, or renames, relative to
+/// the repository root. Deleted files have nothing to analyze.
+pub fn staged_files() -> Result<Vec<String>, String> {
+    let output = git(&[
+        "diff",
+        "--cached",
+        "--name-only",
+        "--diff-filter=ACMR",
+        "-z",
+    ])?;
+    Ok(output
+        .split(|byte| *byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .collect())
+}
+
+/// Staged content of `path`, relative to the repository root.
+pub fn staged_content(path: &str) -> Result<Vec<u8>, String> {
+    git(&["cat-file", "blob", &format!(":{}", path)])
+}
+
+/// Where git looks for hooks, honoring `core.hooksPath` and worktrees.
+fn hooks_dir() -> Result<PathBuf, String> {
+    let output = git(&["rev-parse", "--git-path", "hooks"])?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output).trim()))
+}
+
+/// The pre-commit hook running `program hook run` with `args`.
+pub fn hook_script(program: &str, args: &[String]) -> String {
+    let command: Vec<String> = [program.to_string(), "hook".to_string(), "run".to_string()]
+        .into_iter()
+        .chain(args.iter().cloned())
+        .map(|arg| shell_quote(&arg))
+        .collect();
+    format!("#!/bin/sh\n{}\nexec {}\n", MARKER, command.join(" "))
+}
+
+/// Writes [`hook_script`] as the repository's pre-commit hook and returns
+/// its path. A hook compass didn't write is only replaced with `force`.
+pub fn install(program: &str, args: &[String], force: bool) -> Result<PathBuf, String> {
+    let dir = hooks_dir()?;
+    let path = dir.join("pre-commit");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !force && !existing.contains(MARKER) {
+            return Err(format!(
+                "'{}' exists and was not installed by compass; pass --force to replace it",
+                path.display()
+            ));
+        }
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create '{}': {}", dir.display(), e))?;
+    fs::write(&path, hook_script(program, args))
+        .map_err(|e| format!("failed to write '{}': {}", path.display(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("failed to make '{}' executable: {}", path.display(), e))?;
+    }
+    Ok(path)
+}
+
+/// `arg` as one word of a POSIX shell command.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_./:=+@".contains(&b));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_script_quotes_its_arguments() {
+        let script = hook_script(
+            "/usr/local/bin/compass",
+            &[
+                "--min-score".to_string(),
+                "7.5".to_string(),
+                "my config's.toml".to_string(),
+            ],
+        );
+        assert_eq!(
+            script,
+            "#!/bin/sh\n# Installed by compass hook install.\nexec /usr/local/bin/compass hook run --min-score 7.5 'my config'\\''s.toml'\n"
+        );
+    }
+}
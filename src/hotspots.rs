@@ -0,0 +1,404 @@
+use tree_sitter::Node;
+
+// The function metrics and hotspot report serve the CLI; the function
+// helpers also back the analyzer's structural checks.
+#[cfg(feature = "cli")]
+use {
+    crate::analyzer::{AnalysisResult, Severity},
+    crate::report::{FileReport, ProjectScore},
+    serde::{Deserialize, Serialize},
+    serde_json::{json, Value},
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+/// Node kinds that define a named function, per language config key.
+pub(crate) const FUNCTION_KINDS: &[(&str, &[&str])] = &[
+    ("rust", &["function_item"]),
+    ("go", &["function_declaration", "method_declaration"]),
+    (
+        "javascript",
+        &[
+            "function_declaration",
+            "generator_function_declaration",
+            "method_definition",
+        ],
+    ),
+    ("java", &["method_declaration", "constructor_declaration"]),
+    ("cpp", &["function_definition"]),
+    ("swift", &["function_declaration"]),
+    ("zig", &["function_declaration"]),
+];
+
+/// Closures and other anonymous functions, whose parameters and returns
+/// are their own rather than the enclosing function's.
+const CLOSURE_KINDS: &[&str] = &[
+    "closure_expression",
+    "func_literal",
+    "arrow_function",
+    "function_expression",
+    "generator_function",
+    "lambda_expression",
+    "lambda_literal",
+];
+
+#[cfg(feature = "cli")]
+/// Size, shape, and findings of one function.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FunctionMetrics {
+    pub path: String,
+    pub name: String,
+    pub line: usize,
+    pub lines: usize,
+    /// Declared parameters, not counting `self` or a Go receiver.
+    pub parameters: usize,
+    /// `return` statements, not counting those of nested closures.
+    pub returns: usize,
+    pub issues: usize,
+    /// Score deduction of the findings inside the function.
+    pub deduction: f64,
+}
+
+#[cfg(feature = "cli")]
+/// Metrics for every named function in the tree under `root`, parsed from
+/// `source`. Each finding counts towards the innermost function containing
+/// its line. Languages without an entry in the function table have no
+/// functions.
+pub fn function_metrics_in(
+    root: Node<'_>,
+    path: &str,
+    language_name: &str,
+    source: &str,
+    results: &[AnalysisResult],
+) -> Vec<FunctionMetrics> {
+    let Some((_, kinds)) = FUNCTION_KINDS
+        .iter()
+        .find(|(name, _)| *name == language_name)
+    else {
+        return Vec::new();
+    };
+
+    let mut functions = Vec::new();
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if kinds.contains(&node.kind()) {
+            functions.push(FunctionMetrics {
+                path: path.to_string(),
+                name: function_name(node, source.as_bytes())
+                    .unwrap_or("<anonymous>")
+                    .to_string(),
+                line: node.start_position().row + 1,
+                lines: node.end_position().row - node.start_position().row + 1,
+                parameters: parameter_count(node, source.as_bytes()),
+                returns: return_count(node),
+                issues: 0,
+                deduction: 0.0,
+            });
+        }
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        let mut done = true;
+        while cursor.goto_parent() {
+            if cursor.goto_next_sibling() {
+                done = false;
+                break;
+            }
+        }
+        if done {
+            break;
+        }
+    }
+
+    for result in results {
+        let innermost = functions
+            .iter_mut()
+            .filter(|function| {
+                (function.line..function.line + function.lines).contains(&result.line)
+            })
+            .min_by_key(|function| function.lines);
+        if let Some(function) = innermost {
+            function.issues += 1;
+            function.deduction += result.score_impact.abs();
+        }
+    }
+    functions
+}
+
+#[cfg(feature = "cli")]
+fn function_name<'a>(node: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return name.utf8_text(source).ok();
+    }
+
+    // C++ nests the name in declarators: `int *Widget::size(...)`.
+    let mut declarator = node.child_by_field_name("declarator")?;
+    while let Some(inner) = declarator.child_by_field_name("declarator") {
+        declarator = inner;
+    }
+    declarator.utf8_text(source).ok()
+}
+
+/// Named functions under `root`, of the kinds any language in the
+/// function table uses, outermost first.
+pub(crate) fn functions(root: Node<'_>) -> Vec<Node<'_>> {
+    let mut functions = Vec::new();
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        if FUNCTION_KINDS
+            .iter()
+            .any(|(_, kinds)| kinds.contains(&node.kind()))
+        {
+            functions.push(node);
+        }
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.children(&mut cursor).collect();
+        pending.extend(children.into_iter().rev());
+    }
+    functions
+}
+
+/// The parameter list of `function`: its `parameters` field, the one of
+/// a C++ function declarator, Zig's `parameters` child, or the function
+/// itself for Swift, which lists `parameter`s directly.
+pub(crate) fn parameter_list(function: Node<'_>) -> Node<'_> {
+    if let Some(parameters) = function.child_by_field_name("parameters") {
+        return parameters;
+    }
+    let mut declarator = function.child_by_field_name("declarator");
+    while let Some(node) = declarator {
+        if let Some(parameters) = node.child_by_field_name("parameters") {
+            return parameters;
+        }
+        declarator = node.child_by_field_name("declarator");
+    }
+    let mut cursor = function.walk();
+    let parameters = function
+        .named_children(&mut cursor)
+        .find(|child| child.kind() == "parameters");
+    parameters.unwrap_or(function)
+}
+
+/// Parameters `function` declares. Go's `a, b int` counts as two; Rust's
+/// `self`, Java's receiver, and C's `(void)` don't count.
+pub fn parameter_count(function: Node<'_>, source: &[u8]) -> usize {
+    let list = parameter_list(function);
+    let mut cursor = list.walk();
+    let parameters: Vec<_> = list
+        .named_children(&mut cursor)
+        .filter(|child| match child.kind() {
+            kind if list == function => kind == "parameter",
+            "self_parameter" | "receiver_parameter" | "attribute_item" => false,
+            kind => !kind.contains("comment"),
+        })
+        .collect();
+    if let [only] = parameters.as_slice() {
+        if only.utf8_text(source) == Ok("void") {
+            return 0;
+        }
+    }
+    parameters
+        .iter()
+        .map(|parameter| {
+            let mut cursor = parameter.walk();
+            let names = parameter
+                .children_by_field_name("name", &mut cursor)
+                .count();
+            // Go lists the names sharing a type in one declaration.
+            if parameter.kind() == "parameter_declaration" && names > 1 {
+                names
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+/// `return`s in `function`'s body, leaving out nested functions and
+/// closures. Rust's and Zig's `return` expressions count; tail expressions
+/// don't.
+pub fn return_count(function: Node<'_>) -> usize {
+    let mut count = 0;
+    let mut cursor = function.walk();
+    let mut pending: Vec<_> = function.children(&mut cursor).collect();
+    while let Some(node) = pending.pop() {
+        let kind = node.kind();
+        if CLOSURE_KINDS.contains(&kind)
+            || FUNCTION_KINDS
+                .iter()
+                .any(|(_, kinds)| kinds.contains(&kind))
+        {
+            continue;
+        }
+        let swift_return = kind == "control_transfer_statement"
+            && node
+                .child(0)
+                .is_some_and(|keyword| keyword.kind() == "return");
+        if matches!(kind, "return_statement" | "return_expression") || swift_return {
+            count += 1;
+        }
+        let mut cursor = node.walk();
+        pending.extend(node.children(&mut cursor));
+    }
+    count
+}
+
+#[cfg(feature = "cli")]
+/// The hotspot view of a run: the lowest-scoring files, the rules with the
+/// most findings, and the functions carrying the largest deductions, at
+/// most `top` of each, after the project `score`.
+pub fn summary_report(
+    files: &[FileReport],
+    functions: &[FunctionMetrics],
+    score: &ProjectScore,
+    top: usize,
+) -> Value {
+    let mut lowest: Vec<&FileReport> = files.iter().collect();
+    lowest.sort_by(|a, b| {
+        a.score
+            .overall_score
+            .total_cmp(&b.score.overall_score)
+            .then(b.score.total_issues.cmp(&a.score.total_issues))
+    });
+
+    let mut rules: BTreeMap<&str, (usize, BTreeSet<&str>, f64, Severity)> = BTreeMap::new();
+    for file in files {
+        for result in &file.results {
+            let entry = rules
+                .entry(&result.rule_name)
+                .or_insert_with(|| (0, BTreeSet::new(), 0.0, result.severity.clone()));
+            entry.0 += 1;
+            entry.1.insert(&file.path);
+            entry.2 += result.score_impact.abs();
+        }
+    }
+    let mut rules: Vec<_> = rules.into_iter().collect();
+    rules.sort_by_key(|(_, (count, ..))| std::cmp::Reverse(*count));
+
+    let mut worst: Vec<&FunctionMetrics> = functions.iter().collect();
+    worst.sort_by(|a, b| {
+        b.deduction
+            .total_cmp(&a.deduction)
+            .then(b.lines.cmp(&a.lines))
+    });
+
+    json!({
+        "summary": score.to_json(),
+        "lowest_scoring_files": lowest.iter().take(top).map(|file| json!({
+            "path": file.path,
+            "score": file.score.overall_score,
+            "rating": file.score.rating,
+            "total_issues": file.score.total_issues,
+            "line_count": file.line_count,
+        })).collect::<Vec<_>>(),
+        "top_rules": rules.iter().take(top).map(|(rule, (count, paths, deduction, severity))| json!({
+            "rule": rule,
+            "severity": severity,
+            "count": count,
+            "files": paths.len(),
+            "deduction": deduction,
+        })).collect::<Vec<_>>(),
+        "worst_functions": worst.iter().take(top).map(|function| json!({
+            "path": function.path,
+            "name": function.name,
+            "line": function.line,
+            "lines": function.lines,
+            "parameters": function.parameters,
+            "returns": function.returns,
+            "issues": function.issues,
+            "deduction": function.deduction,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(all(test, feature = "cli", feature = "lang-rust"))]
+mod tests {
+    use super::*;
+    use tree_sitter::{Language, Parser};
+
+    /// Parses `source` for [`function_metrics_in`].
+    fn function_metrics(
+        path: &str,
+        language_name: &str,
+        source: &str,
+        language: &Language,
+        results: &[AnalysisResult],
+    ) -> Result<Vec<FunctionMetrics>, Box<dyn std::error::Error>> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        let tree = parser
+            .parse(source, None)
+            .ok_or_else(|| format!("failed to parse '{}'", path))?;
+        Ok(function_metrics_in(
+            tree.root_node(),
+            path,
+            language_name,
+            source,
+            results,
+        ))
+    }
+
+    #[test]
+    fn test_findings_count_towards_innermost_function() {
+        let source = "fn outer() {\n    fn inner() {\n        x.unwrap();\n    }\n    y.unwrap();\n}\n\nimpl S {\n    fn method(&self) {}\n}\n";
+        let analyzer = crate::config::AnalyzerConfig::from_str(
+            "[[rules]]\nname = \"unwrap\"\nquery = '(field_identifier) @f (#eq? @f \"unwrap\")'\nseverity = \"warning\"\nmessage = \"unwrap\"\nenabled = true\n",
+        )
+        .unwrap()
+        .to_analyzer();
+        let language = tree_sitter_rust::LANGUAGE.into();
+        let results = analyzer.analyze(source, &language).unwrap();
+
+        let functions = function_metrics("lib.rs", "rust", source, &language, &results).unwrap();
+        let summary: Vec<(&str, usize, usize, usize)> = functions
+            .iter()
+            .map(|function| {
+                (
+                    function.name.as_str(),
+                    function.line,
+                    function.lines,
+                    function.issues,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [("outer", 1, 6, 1), ("inner", 2, 3, 1), ("method", 9, 1, 0)]
+        );
+        assert!(function_metrics("x", "cobol", source, &language, &results)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(all(feature = "lang-go", feature = "lang-cpp"))]
+    #[test]
+    fn test_parameters_and_returns_are_counted_per_function() {
+        let counts = |source: &str, language: tree_sitter::Language| {
+            let mut parser = tree_sitter::Parser::new();
+            parser.set_language(&language).unwrap();
+            let tree = parser.parse(source, None).unwrap();
+            functions(tree.root_node())
+                .into_iter()
+                .map(|function| {
+                    (
+                        parameter_count(function, source.as_bytes()),
+                        return_count(function),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let go = "package main\n\nfunc (s *S) f(a, b int, c string) int {\n\tg := func() int { return 1 }\n\tif a > b { return 2 }\n\treturn g()\n}\n";
+        assert_eq!(counts(go, tree_sitter_go::LANGUAGE.into()), [(3, 2)]);
+        let cpp = "int f(void) { return 0; }\nint g(int a, int b) { if (a) return a; return b; }\n";
+        assert_eq!(
+            counts(cpp, tree_sitter_cpp::LANGUAGE.into()),
+            [(0, 1), (2, 2)]
+        );
+        let rust = "impl S {\n    fn f(&self, a: u8) -> u8 {\n        fn g() -> u8 { return 1; }\n        a\n    }\n}\n";
+        assert_eq!(
+            counts(rust, tree_sitter_rust::LANGUAGE.into()),
+            [(1, 0), (0, 1)]
+        );
+    }
+}
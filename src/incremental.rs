@@ -0,0 +1,372 @@
+//! Incremental analysis for long-lived sessions such as editor integrations
+//! and file watchers. A [`Document`] keeps the syntax tree and each rule's
+//! matches between edits: an edit re-parses from the previous tree and
+//! re-runs only the rules whose matches could touch what changed, so
+//! findings can follow keystrokes.
+
+use crate::analyzer::{self, AnalysisResult, CodeAnalyzer, CodeScore, Span};
+use crate::cancel::CancellationToken;
+use crate::metrics;
+use crate::pairing::Counterpart;
+use crate::scope::Scopes;
+use crate::timing::RuleTiming;
+use std::ops::Range;
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, Tree};
+
+/// One file's source, syntax tree, and findings, kept up to date edit by
+/// edit.
+pub struct Document<'a> {
+    analyzer: &'a CodeAnalyzer,
+    language: Language,
+    parser: Parser,
+    source: String,
+    tree: Tree,
+    counterpart: Option<(Tree, &'a Counterpart)>,
+    /// Functions and test code of `tree`, when a rule is scoped.
+    scopes: Option<Scopes>,
+    /// Each rule's compiled query; `None` for structural checks.
+    queries: Vec<Option<Query>>,
+    /// Each rule's flagged spans in match order; `None` while the file's
+    /// line count is outside the rule's limits.
+    spans: Vec<Option<Vec<Span>>>,
+    results: Vec<AnalysisResult>,
+}
+
+impl<'a> Document<'a> {
+    /// Parses `source` and runs every rule of `analyzer` on it. Fails if a
+    /// query does not compile for `language`.
+    pub fn open(
+        analyzer: &'a CodeAnalyzer,
+        source: impl Into<String>,
+        language: &Language,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        let source = source.into();
+        let tree = analyzer::parse(&mut parser, &source, analyzer.cancellation())?;
+        let counterpart = analyzer::parse_counterpart(&mut parser, analyzer.counterpart());
+        let scopes = analyzer.scopes(tree.root_node(), &source);
+        let mut queries = Vec::new();
+        for rule in analyzer.rules() {
+            queries.push(if rule.runs_query() {
+                Some(Query::new(language, &rule.query)?)
+            } else {
+                None
+            });
+        }
+
+        let mut document = Document {
+            analyzer,
+            language: language.clone(),
+            parser,
+            source,
+            tree,
+            counterpart,
+            scopes,
+            queries,
+            spans: vec![None; analyzer.rules().len()],
+            results: Vec::new(),
+        };
+        let line_count = document.source.lines().count();
+        for (index, rule) in analyzer.rules().iter().enumerate() {
+            if rule.applies_to_lines(line_count) {
+                document.rematch(index)?;
+            }
+        }
+        document.collect_results();
+        Ok(document)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// The findings, in the order [`CodeAnalyzer::analyze`] reports them.
+    pub fn results(&self) -> &[AnalysisResult] {
+        &self.results
+    }
+
+    pub fn score(&self) -> CodeScore {
+        let lines = metrics::line_counts(self.tree.root_node(), &self.source);
+        self.analyzer.calculate_score(&self.results, lines)
+    }
+
+    /// Replaces the source bytes `range` with `text`, re-parses from the
+    /// previous tree, and brings the findings up to date. Returns how many
+    /// rules were re-run; the others kept their matches, moved past the
+    /// edit.
+    pub fn edit(
+        &mut self,
+        range: Range<usize>,
+        text: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        if range.start > range.end
+            || range.end > self.source.len()
+            || !self.source.is_char_boundary(range.start)
+            || !self.source.is_char_boundary(range.end)
+        {
+            return Err(format!(
+                "edit range {}..{} is not within the source",
+                range.start, range.end
+            )
+            .into());
+        }
+
+        let old_source = self.source.clone();
+        let old_tree = self.tree.clone();
+        self.source.replace_range(range.clone(), text);
+        let edit = InputEdit {
+            start_byte: range.start,
+            old_end_byte: range.end,
+            new_end_byte: range.start + text.len(),
+            start_position: point(&old_source, range.start),
+            old_end_position: point(&old_source, range.end),
+            new_end_position: point(&self.source, range.start + text.len()),
+        };
+        let mut edited = old_tree.clone();
+        edited.edit(&edit);
+        self.tree = self
+            .parser
+            .parse(&self.source, Some(&edited))
+            .ok_or("tree-sitter could not parse the source")?;
+        self.scopes = self.analyzer.scopes(self.tree.root_node(), &self.source);
+
+        // Where the syntax changed, in the new source and in the old one.
+        let mut changed: Vec<Range<usize>> = edited
+            .changed_ranges(&self.tree)
+            .map(|range| range.start_byte..range.end_byte)
+            .collect();
+        changed.push(edit.start_byte..edit.new_end_byte);
+        let old_changed: Vec<Range<usize>> =
+            changed.iter().map(|range| to_old(range, &edit)).collect();
+
+        let line_count = self.source.lines().count();
+        let mut rerun = 0;
+        let cancel = self.analyzer.cancellation();
+        for (index, rule) in self.analyzer.rules().iter().enumerate() {
+            if !rule.applies_to_lines(line_count) {
+                self.spans[index] = None;
+                continue;
+            }
+            let touched = match (&self.queries[index], &self.spans[index]) {
+                (Some(query), Some(spans)) if rule.scope.is_none() => {
+                    spans.iter().any(|span| overlaps_edit(span, &edit))
+                        || touches(query, &old_tree, &old_source, &old_changed, cancel)
+                        || touches(query, &self.tree, &self.source, &changed, cancel)
+                }
+                // Structural checks look at the whole tree, an edit anywhere
+                // in a function can move a scoped rule's matches in or out
+                // of scope, and a rule the file just grew or shrank into has
+                // no matches yet.
+                _ => true,
+            };
+            if touched {
+                self.rematch(index)?;
+                cancel.check()?;
+                rerun += 1;
+            } else if let Some(spans) = &mut self.spans[index] {
+                for span in spans {
+                    shift(span, &edit);
+                }
+            }
+        }
+        self.collect_results();
+        Ok(rerun)
+    }
+
+    /// Runs rule `index` over the whole tree.
+    fn rematch(&mut self, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let root = self.tree.root_node();
+        let rule = &self.analyzer.rules()[index];
+        let nodes = match &self.queries[index] {
+            Some(query) => analyzer::query_nodes(
+                query,
+                root,
+                &self.source,
+                None,
+                self.analyzer.cancellation(),
+            ),
+            None => rule.captured_nodes(
+                root,
+                &self.source,
+                &self.language,
+                analyzer::pair(self.counterpart.as_ref()),
+                &mut RuleTiming::default(),
+                self.analyzer.cancellation(),
+            )?,
+        };
+        self.spans[index] = Some(
+            nodes
+                .into_iter()
+                .filter(|(_, _, node)| analyzer::in_scope(rule, self.scopes.as_ref(), *node))
+                .map(|(_, _, node)| Span::of(node))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn collect_results(&mut self) {
+        let lines: Vec<&str> = self.source.lines().collect();
+        let mut results = Vec::new();
+        for (rule, spans) in self.analyzer.rules().iter().zip(&self.spans) {
+            for (occurrence, span) in spans.iter().flatten().enumerate() {
+                results.push(self.analyzer.result(
+                    rule,
+                    &self.source,
+                    &lines,
+                    *span,
+                    occurrence + 1,
+                ));
+            }
+        }
+        self.results = results;
+    }
+}
+
+/// Row and byte column of `byte` in `source`.
+fn point(source: &str, byte: usize) -> Point {
+    let before = &source.as_bytes()[..byte];
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |newline| newline + 1);
+    Point {
+        row: before.iter().filter(|&&b| b == b'\n').count(),
+        column: byte - line_start,
+    }
+}
+
+/// `range` of the edited source as bytes of the source before `edit`.
+fn to_old(range: &Range<usize>, edit: &InputEdit) -> Range<usize> {
+    let map = |byte: usize, inside: usize| {
+        if byte >= edit.new_end_byte {
+            byte - edit.new_end_byte + edit.old_end_byte
+        } else if byte > edit.start_byte {
+            inside
+        } else {
+            byte
+        }
+    };
+    map(range.start, edit.start_byte)..map(range.end, edit.old_end_byte)
+}
+
+/// Whether `query` matches anywhere near `ranges` of `tree`. Ranges are
+/// widened by a byte on each side so nodes that merely end or start at an
+/// insertion or deletion count.
+fn touches(
+    query: &Query,
+    tree: &Tree,
+    source: &str,
+    ranges: &[Range<usize>],
+    cancel: &CancellationToken,
+) -> bool {
+    ranges.iter().any(|range| {
+        let widened = range.start.saturating_sub(1)..(range.end + 1).min(source.len());
+        !analyzer::query_nodes(query, tree.root_node(), source, Some(widened), cancel).is_empty()
+    })
+}
+
+fn overlaps_edit(span: &Span, edit: &InputEdit) -> bool {
+    span.start_byte < edit.old_end_byte.max(edit.start_byte + 1) && span.end_byte >= edit.start_byte
+}
+
+/// Moves a span that lies past `edit` by the bytes and lines it added or
+/// removed.
+fn shift(span: &mut Span, edit: &InputEdit) {
+    if span.start_byte < edit.old_end_byte {
+        return;
+    }
+    span.start_byte = span.start_byte - edit.old_end_byte + edit.new_end_byte;
+    span.end_byte = span.end_byte - edit.old_end_byte + edit.new_end_byte;
+    span.start_row = span.start_row - edit.old_end_position.row + edit.new_end_position.row;
+    span.end_row = span.end_row - edit.old_end_position.row + edit.new_end_position.row;
+}
+
+#[cfg(all(test, feature = "lang-rust"))]
+mod tests {
+    use super::*;
+    use crate::config::AnalyzerConfig;
+    use crate::language::LanguageRegistry;
+
+    /// Rule, position, and text of each finding, to compare runs.
+    fn summary(results: &[AnalysisResult]) -> Vec<(String, usize, usize, usize, String)> {
+        results
+            .iter()
+            .map(|result| {
+                (
+                    result.rule_name.clone(),
+                    result.line,
+                    result.column,
+                    result.start_byte,
+                    result.text.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edits_match_a_fresh_analysis_and_rerun_only_touched_rules() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.get("rust").unwrap();
+        let config = AnalyzerConfig::from_str(rust.default_config.as_deref().unwrap()).unwrap();
+        let analyzer = config.to_analyzer_for_language("rust");
+        let source = "fn main() {\n    let x = foo().unwrap();\n    println!(\"{}\", x);\n}\n\nfn helper() -> i32 {\n    42\n}\n";
+        let mut document = Document::open(&analyzer, source, &rust.language).unwrap();
+
+        // Each edit replaces the first occurrence of a snippet.
+        let edits = [
+            // A blank line after every finding moves none of them.
+            ("fn helper", "\nfn helper"),
+            ("42", "7"),
+            ("foo()", "bar()?"),
+            ("    println!", "    let y = baz().unwrap();\n    println!"),
+            // Breaking the syntax, and restoring it.
+            ("}\n\n\nfn", "\n\n\nfn"),
+            ("x);\n", "x);\n}"),
+        ];
+        for (snippet, replacement) in edits {
+            let start = document.source().find(snippet).unwrap();
+            document
+                .edit(start..start + snippet.len(), replacement)
+                .unwrap();
+
+            let (results, score) = analyzer
+                .analyze_with_score(document.source(), &rust.language)
+                .unwrap();
+            assert_eq!(
+                summary(document.results()),
+                summary(&results),
+                "after replacing {:?}",
+                snippet
+            );
+            assert_eq!(document.score().overall_score, score.overall_score);
+        }
+
+        let start = document.source().find('7').unwrap();
+        let rerun = document.edit(start..start + 1, "8").unwrap();
+        assert!(rerun < analyzer.rules().len());
+        assert!(document.edit(0..document.source().len() + 1, "").is_err());
+    }
+
+    #[test]
+    fn test_scoped_rules_follow_edits_into_and_out_of_test_code() {
+        let rust = LanguageRegistry::builtin()
+            .get("rust")
+            .unwrap()
+            .language
+            .clone();
+        let config = AnalyzerConfig::from_str("[[rules]]\nname = \"call\"\nquery = \"(call_expression) @call\"\nseverity = \"info\"\nmessage = \"m\"\nenabled = true\nscope = \"non_test\"\n").unwrap();
+        let analyzer = config.to_analyzer();
+        let mut document = Document::open(&analyzer, "fn a() {\n    b();\n}\n", &rust).unwrap();
+        assert_eq!(document.results().len(), 1);
+
+        document.edit(0..0, "#[test]\n").unwrap();
+        assert!(document.results().is_empty());
+        document.edit(0..8, "").unwrap();
+        assert_eq!(document.results()[0].line, 2);
+    }
+}
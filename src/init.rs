@@ -0,0 +1,90 @@
+use crate::config::resolver::DEFAULT_CONFIG_FILE;
+use crate::discovery;
+use crate::language::LanguageRegistry;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+/// Vendored, generated, or bundled paths per language that are rarely
+/// worth scoring.
+const EXCLUDES: &[(&str, &[&str])] = &[
+    ("go", &["vendor/**", "**/testdata/**"]),
+    ("javascript", &["**/*.min.js", "**/*.bundle.js"]),
+    ("cpp", &["third_party/**", "external/**"]),
+    ("swift", &["Pods/**", "Carthage/**"]),
+    ("zig", &["zig-out/**"]),
+];
+
+/// Languages with at least one source file under `root`, most files first.
+pub fn detect_languages(root: &Path, registry: &LanguageRegistry) -> io::Result<Vec<String>> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for file in discovery::collect_files(root, |path| registry.detect(path).is_some())? {
+        if let Some(language) = registry.detect(&file) {
+            *counts.entry(language.name.clone()).or_default() += 1;
+        }
+    }
+    let mut languages: Vec<(String, usize)> = counts.into_iter().collect();
+    languages.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    Ok(languages.into_iter().map(|(name, _)| name).collect())
+}
+
+/// A starter config extending the built-in rules of `languages`, with
+/// their usual vendored and generated paths excluded.
+pub fn starter_config(languages: &[String]) -> String {
+    let quoted: Vec<String> = languages
+        .iter()
+        .map(|language| format!("\"{}\"", language))
+        .collect();
+    let mut excludes = vec!["third_party/**"];
+    for (language, patterns) in EXCLUDES {
+        if languages.iter().any(|name| name == language) {
+            for pattern in *patterns {
+                if !excludes.contains(pattern) {
+                    excludes.push(pattern);
+                }
+            }
+        }
+    }
+
+    let mut config = format!(
+        "# Generated by `compass init`. Analyze with: compass <dir> {}\n\n",
+        DEFAULT_CONFIG_FILE
+    );
+    config.push_str("# Built-in rules for the languages found in this repository. Rules added\n");
+    config.push_str("# below replace built-in rules of the same name.\n");
+    config.push_str(&format!("extends = [{}]\n\n", quoted.join(", ")));
+    config.push_str("# Paths skipped when analyzing a directory, relative to it.\n");
+    config.push_str("exclude = [\n");
+    for pattern in excludes {
+        config.push_str(&format!("    \"{}\",\n", pattern));
+    }
+    config.push_str("]\n\n");
+    config.push_str("[scoring]\n");
+    config.push_str("project_weighting = \"lines\"\n\n");
+    config.push_str("# [severity_overrides]\n");
+    config.push_str("# rule_name = \"error\"\n");
+    config
+}
+
+#[cfg(all(test, feature = "lang-go", feature = "lang-rust"))]
+mod tests {
+    use super::*;
+    use crate::config::AnalyzerConfig;
+
+    #[test]
+    fn test_starter_config_extends_builtins_and_excludes_vendored_code() {
+        let config = starter_config(&["go".to_string(), "rust".to_string()]);
+        let config = AnalyzerConfig::from_str(&config).unwrap();
+
+        assert_eq!(config.extends, ["go", "rust"]);
+        let panic = config.rule("panic_usage").unwrap();
+        assert_eq!(panic.language.as_deref(), Some("go"));
+        assert!(config
+            .rules
+            .iter()
+            .any(|rule| rule.language.as_deref() == Some("rust")));
+        assert!(config.excludes(Path::new("vendor/github.com/lib/pq/conn.go")));
+        assert!(config.excludes(Path::new("pkg/parser/testdata/bad.go")));
+        assert!(!config.excludes(Path::new("pkg/parser/parser.go")));
+    }
+}
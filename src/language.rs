@@ -0,0 +1,559 @@
+use crate::config::AnalyzerConfig;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::path::Path;
+use tree_sitter::{Language, LANGUAGE_VERSION, MIN_COMPATIBLE_LANGUAGE_VERSION};
+use tree_sitter_language::LanguageFn;
+
+#[cfg(feature = "lang-rust")]
+const RUST_CONFIG: &str = include_str!("../config/rust.toml");
+#[cfg(feature = "lang-go")]
+const GO_CONFIG: &str = include_str!("../config/go.toml");
+#[cfg(feature = "lang-javascript")]
+const JAVASCRIPT_CONFIG: &str = include_str!("../config/javascript.toml");
+#[cfg(feature = "lang-java")]
+const JAVA_CONFIG: &str = include_str!("../config/java.toml");
+#[cfg(feature = "lang-zig")]
+const ZIG_CONFIG: &str = include_str!("../config/zig.toml");
+#[cfg(feature = "lang-cpp")]
+const CPP_CONFIG: &str = include_str!("../config/cpp.toml");
+#[cfg(feature = "lang-swift")]
+const SWIFT_CONFIG: &str = include_str!("../config/swift.toml");
+
+/// A language compass ships a grammar and rules for, and the cargo feature
+/// that compiles the grammar in.
+#[derive(Debug)]
+pub struct BuiltinLanguage {
+    pub name: &'static str,
+    pub display_name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub feature: &'static str,
+    /// Whether this build has the grammar.
+    pub compiled: bool,
+}
+
+impl BuiltinLanguage {
+    /// How to get a compass that analyzes this language.
+    pub fn install_hint(&self) -> String {
+        format!(
+            "reinstall with `cargo install --git https://github.com/lyledean1/compass --features {}`",
+            self.feature
+        )
+    }
+}
+
+/// Every built-in language, whether or not this build has its grammar.
+pub const BUILTIN_LANGUAGES: &[BuiltinLanguage] = &[
+    BuiltinLanguage {
+        name: "rust",
+        display_name: "Rust",
+        extensions: &["rs"],
+        feature: "lang-rust",
+        compiled: cfg!(feature = "lang-rust"),
+    },
+    BuiltinLanguage {
+        name: "go",
+        display_name: "Go",
+        extensions: &["go"],
+        feature: "lang-go",
+        compiled: cfg!(feature = "lang-go"),
+    },
+    BuiltinLanguage {
+        name: "javascript",
+        display_name: "JavaScript",
+        extensions: &["js", "jsx"],
+        feature: "lang-javascript",
+        compiled: cfg!(feature = "lang-javascript"),
+    },
+    BuiltinLanguage {
+        name: "zig",
+        display_name: "Zig",
+        extensions: &["zig"],
+        feature: "lang-zig",
+        compiled: cfg!(feature = "lang-zig"),
+    },
+    BuiltinLanguage {
+        name: "java",
+        display_name: "Java",
+        extensions: &["java"],
+        feature: "lang-java",
+        compiled: cfg!(feature = "lang-java"),
+    },
+    BuiltinLanguage {
+        name: "cpp",
+        display_name: "C++",
+        extensions: &["cpp", "cc", "cxx", "h", "hh", "hpp", "hxx"],
+        feature: "lang-cpp",
+        compiled: cfg!(feature = "lang-cpp"),
+    },
+    BuiltinLanguage {
+        name: "swift",
+        display_name: "Swift",
+        extensions: &["swift"],
+        feature: "lang-swift",
+        compiled: cfg!(feature = "lang-swift"),
+    },
+];
+
+/// The built-in language called `name`.
+pub fn builtin_language(name: &str) -> Option<&'static BuiltinLanguage> {
+    BUILTIN_LANGUAGES
+        .iter()
+        .find(|language| language.name.eq_ignore_ascii_case(name))
+}
+
+/// The definition of built-in language `name`, named and with extensions
+/// as in [`BUILTIN_LANGUAGES`].
+#[cfg(any(
+    feature = "lang-rust",
+    feature = "lang-go",
+    feature = "lang-javascript",
+    feature = "lang-zig",
+    feature = "lang-java",
+    feature = "lang-cpp",
+    feature = "lang-swift"
+))]
+fn builtin_definition(
+    name: &str,
+    language: Language,
+    config: &str,
+    grammar_version: &str,
+) -> LanguageDefinition {
+    let builtin = builtin_language(name).expect("built-in languages are listed");
+    LanguageDefinition::new(builtin.name, builtin.display_name, language)
+        .with_extensions(builtin.extensions)
+        .with_default_config(config)
+        .with_grammar_version(grammar_version)
+}
+
+/// A language compass can analyze: its grammar, the file extensions that
+/// select it, and the config used when no override is given.
+#[derive(Clone)]
+pub struct LanguageDefinition {
+    /// Config key, matched against a rule's `language` field (e.g. `rust`).
+    pub name: String,
+    pub display_name: String,
+    /// Lowercase extensions without the leading dot.
+    pub extensions: Vec<String>,
+    pub language: Language,
+    pub default_config: Option<String>,
+    pub grammar: Grammar,
+}
+
+/// The tree-sitter grammar behind a language. Reports carry it so score
+/// shifts caused by grammar upgrades can be told apart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Grammar {
+    /// Crate or library name, e.g. `tree-sitter-rust`.
+    pub name: String,
+    /// `None` when the grammar does not say.
+    pub version: Option<String>,
+}
+
+impl LanguageDefinition {
+    pub fn new(name: &str, display_name: &str, language: Language) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            extensions: Vec::new(),
+            language,
+            default_config: None,
+            grammar: Grammar {
+                name: format!("tree-sitter-{}", name),
+                version: None,
+            },
+        }
+    }
+
+    pub fn with_grammar_version(mut self, version: &str) -> Self {
+        self.grammar.version = Some(version.to_string());
+        self
+    }
+
+    pub fn with_extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn with_default_config(mut self, config: &str) -> Self {
+        self.default_config = Some(config.to_string());
+        self
+    }
+
+    /// Loads a grammar compiled as a shared library by calling its exported
+    /// constructor (`tree_sitter_<name>` unless `symbol` is given).
+    ///
+    /// The library stays loaded for the rest of the process, since the
+    /// returned `Language` points into it.
+    pub fn from_library<P: AsRef<Path>>(
+        name: &str,
+        library: P,
+        symbol: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let library = library.as_ref();
+        let symbol = symbol
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("tree_sitter_{}", name.replace('-', "_")));
+
+        let language = load_library(library, &symbol)?;
+
+        let abi = language.abi_version();
+        if !(MIN_COMPATIBLE_LANGUAGE_VERSION..=LANGUAGE_VERSION).contains(&abi) {
+            return Err(format!(
+                "grammar '{}' uses ABI {}, but compass supports {}..={}",
+                name, abi, MIN_COMPATIBLE_LANGUAGE_VERSION, LANGUAGE_VERSION
+            )
+            .into());
+        }
+
+        // Grammars generated from a tree-sitter.json carry their version.
+        let version = language.metadata().map(|metadata| {
+            format!(
+                "{}.{}.{}",
+                metadata.major_version, metadata.minor_version, metadata.patch_version
+            )
+        });
+        let mut definition = Self::new(name, name, language);
+        definition.grammar.version = version;
+        Ok(definition)
+    }
+}
+
+/// Calls `symbol` in the shared library at `library`, which stays loaded
+/// for the rest of the process.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_library(library: &Path, symbol: &str) -> Result<Language, Box<dyn std::error::Error>> {
+    // SAFETY: loading a library runs its initialisers with the rights of
+    // this process, so `library` must be as trusted as compass itself; the
+    // CLI only loads libraries from configs the user chose or opted into.
+    // The symbol is taken to be the parameterless constructor generated by
+    // the tree-sitter CLI. The library is leaked so the grammar tables it
+    // owns outlive every `Language` that refers to them.
+    unsafe {
+        let lib = libloading::Library::new(library)
+            .map_err(|e| format!("failed to load '{}': {}", library.display(), e))?;
+        let constructor: libloading::Symbol<unsafe extern "C" fn() -> *const ()> = lib
+            .get(symbol.as_bytes())
+            .map_err(|e| format!("'{}' has no symbol '{}': {}", library.display(), symbol, e))?;
+        let language = Language::new(LanguageFn::from_raw(*constructor));
+        std::mem::forget(lib);
+        Ok(language)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load_library(library: &Path, _symbol: &str) -> Result<Language, Box<dyn std::error::Error>> {
+    Err(format!(
+        "failed to load '{}': wasm32 builds cannot load grammar libraries",
+        library.display()
+    )
+    .into())
+}
+
+/// The set of languages available for detection and analysis.
+///
+/// [`LanguageRegistry::builtin`] holds every grammar compass ships with;
+/// library users can [`register`](LanguageRegistry::register) more.
+#[derive(Clone, Default)]
+pub struct LanguageRegistry {
+    languages: Vec<LanguageDefinition>,
+}
+
+impl LanguageRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in languages whose grammars this build has, see
+    /// [`BUILTIN_LANGUAGES`].
+    pub fn builtin() -> Self {
+        let languages = vec![
+            #[cfg(feature = "lang-rust")]
+            builtin_definition(
+                "rust",
+                tree_sitter_rust::LANGUAGE.into(),
+                RUST_CONFIG,
+                "0.24.0",
+            ),
+            #[cfg(feature = "lang-go")]
+            builtin_definition("go", tree_sitter_go::LANGUAGE.into(), GO_CONFIG, "0.23.4"),
+            #[cfg(feature = "lang-javascript")]
+            builtin_definition(
+                "javascript",
+                tree_sitter_javascript::LANGUAGE.into(),
+                JAVASCRIPT_CONFIG,
+                "0.23.1",
+            ),
+            #[cfg(feature = "lang-zig")]
+            builtin_definition("zig", tree_sitter_zig::LANGUAGE.into(), ZIG_CONFIG, "1.1.2"),
+            #[cfg(feature = "lang-java")]
+            builtin_definition(
+                "java",
+                tree_sitter_java::LANGUAGE.into(),
+                JAVA_CONFIG,
+                "0.23.5",
+            ),
+            #[cfg(feature = "lang-cpp")]
+            builtin_definition(
+                "cpp",
+                tree_sitter_cpp::LANGUAGE.into(),
+                CPP_CONFIG,
+                "0.23.4",
+            ),
+            #[cfg(feature = "lang-swift")]
+            builtin_definition(
+                "swift",
+                tree_sitter_swift::LANGUAGE.into(),
+                SWIFT_CONFIG,
+                "0.7.1",
+            ),
+        ];
+        LanguageRegistry { languages }
+    }
+
+    /// Adds a language, replacing any existing definition with the same name.
+    /// Extensions claimed by a later registration win over earlier ones.
+    pub fn register(&mut self, definition: LanguageDefinition) {
+        self.languages
+            .retain(|existing| existing.name != definition.name);
+        self.languages.push(definition);
+    }
+
+    /// Registers every `[languages.<name>]` grammar declared with a
+    /// `library` in `config`.
+    /// Relative library paths are resolved against `base_dir`.
+    pub fn register_from_config(
+        &mut self,
+        config: &AnalyzerConfig,
+        base_dir: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for (name, language) in &config.languages {
+            let Some(library) = &language.library else {
+                continue;
+            };
+            let library = base_dir.join(library);
+            let extensions: Vec<&str> = language.extensions.iter().map(String::as_str).collect();
+            let mut definition =
+                LanguageDefinition::from_library(name, &library, language.symbol.as_deref())?
+                    .with_extensions(&extensions);
+            if let Some(display_name) = &language.display_name {
+                definition.display_name = display_name.clone();
+            }
+            self.register(definition);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LanguageDefinition> {
+        self.languages
+            .iter()
+            .find(|definition| definition.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn detect<P: AsRef<Path>>(&self, path: P) -> Option<&LanguageDefinition> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())?
+            .to_ascii_lowercase();
+
+        self.languages
+            .iter()
+            .rev()
+            .find(|definition| definition.extensions.contains(&extension))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LanguageDefinition> {
+        self.languages.iter()
+    }
+
+    /// Checks a requirement such as `rust>=0.21` against the registered
+    /// grammar versions. Operators are `>=`, `<=`, `>`, `<`, and `=`.
+    pub fn check_grammar(&self, requirement: &str) -> Result<(), String> {
+        let (name, operator, wanted) = parse_requirement(requirement)?;
+        let language = self
+            .get(name)
+            .ok_or_else(|| format!("'{}': unknown language '{}'", requirement, name))?;
+        let grammar = &language.grammar;
+        let version = grammar.version.as_deref().ok_or_else(|| {
+            format!(
+                "'{}': the version of {} is unknown",
+                requirement, grammar.name
+            )
+        })?;
+        let ordering = compare_versions(version, wanted);
+        let satisfied = match operator {
+            ">=" => ordering != Ordering::Less,
+            "<=" => ordering != Ordering::Greater,
+            ">" => ordering == Ordering::Greater,
+            "<" => ordering == Ordering::Less,
+            _ => ordering == Ordering::Equal,
+        };
+        if satisfied {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{}' not met: {} is {}",
+                requirement, grammar.name, version
+            ))
+        }
+    }
+
+    /// The built-in language that would analyze `path` if this build had
+    /// its grammar. `None` when a registered language already does.
+    pub fn missing_grammar<P: AsRef<Path>>(&self, path: P) -> Option<&'static BuiltinLanguage> {
+        if self.detect(&path).is_some() {
+            return None;
+        }
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())?
+            .to_ascii_lowercase();
+        BUILTIN_LANGUAGES.iter().find(|language| {
+            !language.compiled && language.extensions.contains(&extension.as_str())
+        })
+    }
+
+    /// Every registered extension as `.ext`, comma separated, for messages.
+    pub fn extensions_label(&self) -> String {
+        self.languages
+            .iter()
+            .flat_map(|definition| definition.extensions.iter())
+            .map(|ext| format!(".{}", ext))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Splits `rust>=0.21` into the language, operator, and version.
+pub fn parse_requirement(requirement: &str) -> Result<(&str, &str, &str), String> {
+    let split = requirement
+        .find(['<', '>', '='])
+        .ok_or_else(|| format!("'{}': expected <language><op><version>", requirement))?;
+    let (name, rest) = requirement.split_at(split);
+    let operator_len = if rest[1..].starts_with('=') { 2 } else { 1 };
+    let (operator, version) = rest.split_at(operator_len);
+    let valid_operator = [">=", "<=", ">", "<", "=", "=="].contains(&operator);
+    let valid_version = !version.is_empty()
+        && version
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if name.trim().is_empty() || !valid_operator || !valid_version {
+        return Err(format!(
+            "'{}': expected <language><op><version>, e.g. rust>=0.21",
+            requirement
+        ));
+    }
+    Ok((name.trim(), operator, version))
+}
+
+/// Compares dotted numeric versions; missing components count as zero.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .split(['.', '-', '+'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    let (a, b) = (parts(a), parts(b));
+    for index in 0..a.len().max(b.len()) {
+        let ordering = a.get(index).unwrap_or(&0).cmp(b.get(index).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_registry_matches_compiled_builtin_languages() {
+        let registry = LanguageRegistry::builtin();
+        for builtin in BUILTIN_LANGUAGES {
+            let registered = registry.get(builtin.name);
+            assert_eq!(registered.is_some(), builtin.compiled, "{}", builtin.name);
+            if let Some(registered) = registered {
+                assert_eq!(registered.display_name, builtin.display_name);
+                assert_eq!(registered.extensions, builtin.extensions);
+            }
+            let path = format!("main.{}", builtin.extensions[0]);
+            assert_eq!(
+                registry.missing_grammar(&path).map(|missing| missing.name),
+                (!builtin.compiled).then_some(builtin.name)
+            );
+        }
+        assert!(registry.missing_grammar("main.py").is_none());
+    }
+
+    #[cfg(feature = "lang-cpp")]
+    #[test]
+    fn test_cpp_extensions_detected() {
+        let registry = LanguageRegistry::builtin();
+        for path in [
+            "a.cpp", "a.cc", "a.cxx", "a.h", "a.hh", "a.hpp", "a.hxx", "A.CPP",
+        ] {
+            let language = registry
+                .detect(path)
+                .expect("C++ extension should be detected");
+            assert_eq!(language.name, "cpp");
+        }
+    }
+
+    #[cfg(feature = "lang-cpp")]
+    #[test]
+    fn test_registered_language_overrides_extension() {
+        let mut registry = LanguageRegistry::builtin();
+        assert!(registry.detect("main.c").is_none());
+
+        registry.register(
+            LanguageDefinition::new("c", "C", tree_sitter_cpp::LANGUAGE.into())
+                .with_extensions(&[".c", "h"]),
+        );
+        assert_eq!(registry.detect("main.c").unwrap().name, "c");
+        assert_eq!(registry.detect("main.h").unwrap().name, "c");
+        assert_eq!(registry.detect("main.hpp").unwrap().name, "cpp");
+    }
+
+    #[test]
+    fn test_missing_grammar_library_is_an_error() {
+        let error = LanguageDefinition::from_library("elixir", "does/not/exist.so", None)
+            .err()
+            .expect("Loading a missing library should fail");
+        assert!(error.to_string().contains("does/not/exist.so"));
+    }
+
+    #[test]
+    fn test_builtin_grammar_versions_match_cargo_lock() {
+        let lock = include_str!("../Cargo.lock");
+        for language in LanguageRegistry::builtin().iter() {
+            let grammar = &language.grammar;
+            let entry = format!(
+                "name = \"{}\"\nversion = \"{}\"",
+                grammar.name,
+                grammar.version.as_deref().unwrap()
+            );
+            assert!(lock.contains(&entry), "{} is out of date", grammar.name);
+        }
+    }
+
+    #[cfg(all(feature = "lang-rust", feature = "lang-go"))]
+    #[test]
+    fn test_grammar_requirements() {
+        let registry = LanguageRegistry::builtin();
+        assert!(registry.check_grammar("rust>=0.21").is_ok());
+        assert!(registry.check_grammar("rust=0.24.0").is_ok());
+        assert!(registry.check_grammar("go<1").is_ok());
+        let error = registry.check_grammar("rust>=0.25").unwrap_err();
+        assert!(error.contains("tree-sitter-rust is 0.24.0"), "{}", error);
+        assert!(registry.check_grammar("cobol>=1").is_err());
+        assert!(parse_requirement("rust~0.2").is_err());
+        assert!(parse_requirement("rust>=").is_err());
+    }
+}
@@ -1,3 +1,111 @@
+//! Compass scores source code against tree-sitter query rules.
+//!
+//! Embedders start from [`Compass`], or `use compass::prelude::*` for it
+//! and the types its reports are made of. The re-exports below are the
+//! stable API. Modules hidden from these docs serve the `compass` and
+//! `cargo-compass` binaries and may change in any release.
+//!
+//! [`config_test`] runs the tests embedded in a config from `cargo test`,
+//! and [`run_with_formatters`] runs the `compass` command line with output
+//! formats of your own.
+
 pub mod analyzer;
+pub mod api;
+#[cfg(feature = "cli")]
+pub(crate) mod ast;
+#[cfg(feature = "cli")]
+pub(crate) mod audit;
+#[cfg(feature = "cli")]
+pub(crate) mod badge;
+// Dates findings with `git blame`; browsers have no git or clock.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod blame;
+#[cfg(feature = "cli")]
+pub(crate) mod cache;
+pub mod cancel;
+#[cfg(feature = "cli")]
+pub(crate) mod ci;
+#[cfg(feature = "cli")]
+#[doc(hidden)]
 pub mod cli;
+#[cfg(feature = "cli")]
+pub(crate) mod compare;
 pub mod config;
+pub mod config_test;
+#[doc(hidden)]
+pub mod discovery;
+pub mod error;
+#[cfg(feature = "cli")]
+pub(crate) mod exemption;
+#[cfg(feature = "cli")]
+pub(crate) mod export;
+pub(crate) mod fingerprint;
+pub mod fix;
+pub mod glob;
+#[cfg(feature = "cli")]
+pub(crate) mod grade;
+#[cfg(feature = "grpc")]
+#[doc(hidden)]
+pub mod grpc;
+#[cfg(feature = "cli")]
+pub(crate) mod history;
+#[cfg(feature = "cli")]
+pub(crate) mod hook;
+pub(crate) mod hotspots;
+pub mod incremental;
+#[cfg(feature = "cli")]
+pub(crate) mod init;
+pub mod language;
+#[cfg(feature = "cli")]
+pub(crate) mod logging;
+#[cfg(feature = "cli")]
+pub(crate) mod marketplace;
+pub mod metrics;
+pub mod output;
+pub(crate) mod pairing;
+pub(crate) mod paths;
+#[cfg(feature = "playground")]
+#[doc(hidden)]
+pub mod playground;
+#[cfg(feature = "cli")]
+pub(crate) mod policy;
+pub mod position;
+pub(crate) mod progress;
+pub mod ratings;
+pub mod report;
+pub mod scope;
+#[cfg(feature = "cli")]
+pub(crate) mod similarity;
+pub mod snippet;
+pub mod source;
+pub mod structural;
+pub(crate) mod template;
+pub mod timing;
+#[cfg(feature = "cli")]
+pub(crate) mod update;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod workspace;
+
+pub use analyzer::{AnalysisResult, CodeAnalyzer, CodeScore, Confidence, Severity};
+pub use api::{analyze_path, analyze_source, AnalyzeOptions, Compass, Report, RuleSet};
+pub use cancel::{CancellationToken, Cancelled};
+/// Runs the `compass` command line with `formatters` selectable by
+/// `--format`, so a wrapper binary can add its own output formats.
+#[cfg(feature = "cli")]
+pub use cli::run_with_formatters;
+pub use config::AnalyzerConfig;
+pub use error::Error;
+pub use incremental::Document;
+pub use language::{Grammar, LanguageDefinition, LanguageRegistry};
+pub use output::{Formatter, FormatterRegistry};
+pub use progress::Progress;
+pub use report::{FileReport, ProjectScore, ProjectWeighting};
+
+/// The types most embedders need.
+pub mod prelude {
+    pub use crate::{
+        AnalysisResult, Compass, Confidence, Error, FileReport, LanguageDefinition,
+        LanguageRegistry, ProjectScore, Report, RuleSet, Severity,
+    };
+}
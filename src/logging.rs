@@ -0,0 +1,44 @@
+//! `-v`/`-vv` and `--log-format`: the CLI's debug log. The library reports
+//! which config was loaded, which rules ran, which files were skipped, and
+//! how long each stage took as `tracing` events; this installs the
+//! subscriber that writes them to stderr.
+
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use tracing::Level;
+
+/// How log events are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per event.
+    Json,
+}
+
+/// Level shown at `verbosity` `-v` flags: none without any, info with
+/// `-v`, debug with `-vv` or more.
+pub fn level(verbosity: u8) -> Option<Level> {
+    match verbosity {
+        0 => None,
+        1 => Some(Level::INFO),
+        _ => Some(Level::DEBUG),
+    }
+}
+
+/// Writes events at `verbosity`'s [`level`] and above to stderr. Without
+/// `-v` no subscriber is installed and events cost next to nothing.
+pub fn init(verbosity: u8, format: LogFormat) {
+    let Some(level) = level(verbosity) else {
+        return;
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_target(false);
+    // Only a second install fails, and the first one stays.
+    let _ = match format {
+        LogFormat::Text => builder.with_ansi(io::stderr().is_terminal()).try_init(),
+        LogFormat::Json => builder.json().with_ansi(false).try_init(),
+    };
+}
@@ -0,0 +1,127 @@
+use crate::config::resolver::DEFAULT_CONFIG_FILE;
+use crate::update::download;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Index of community rule packs searched by `compass search`.
+pub const DEFAULT_INDEX_URL: &str =
+    "https://raw.githubusercontent.com/lyledean1/compass/main/rule-packs/index.json";
+
+/// One installable rule pack listed in the index.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct RulePack {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// File the pack installs to, for the project config to `include`.
+    pub file: String,
+    /// Shell command that downloads the pack to `file`.
+    pub install: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct RuleIndex {
+    pub packs: Vec<RulePack>,
+}
+
+impl RuleIndex {
+    /// Loads the index from an `https://` URL or a local file (mirrors and
+    /// offline copies). Plain `http://` is refused since install commands
+    /// come from the index.
+    pub fn load(location: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let body = if location.starts_with("https://") {
+            download(location)?
+        } else if location.starts_with("http://") {
+            return Err(
+                format!("refusing to fetch rule index over plain HTTP: {}", location).into(),
+            );
+        } else {
+            fs::read(location)?
+        };
+        let index: RuleIndex = serde_json::from_slice(&body)?;
+        // Packs install next to the project config, never over it.
+        for pack in &index.packs {
+            let plain = Path::new(&pack.file).file_name() == Some(pack.file.as_ref());
+            if !plain || pack.file == DEFAULT_CONFIG_FILE {
+                return Err(format!(
+                    "rule pack '{}' installs to '{}'; packs need a file name of their own",
+                    pack.name, pack.file
+                )
+                .into());
+            }
+        }
+        Ok(index)
+    }
+
+    /// Packs whose name, keywords, languages, or description contain
+    /// `keyword` (case-insensitive); name and keyword matches come first.
+    pub fn search(&self, keyword: &str) -> Vec<&RulePack> {
+        let keyword = keyword.to_lowercase();
+        let contains = |text: &str| text.to_lowercase().contains(&keyword);
+        let mut matches: Vec<(bool, &RulePack)> = self
+            .packs
+            .iter()
+            .filter_map(|pack| {
+                let strong = contains(&pack.name)
+                    || pack.keywords.iter().any(|word| contains(word))
+                    || pack.languages.iter().any(|language| contains(language));
+                (strong || contains(&pack.description)).then_some((strong, pack))
+            })
+            .collect();
+        matches.sort_by_key(|(strong, pack)| (!strong, pack.name.as_str()));
+        matches.into_iter().map(|(_, pack)| pack).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_ranks_name_matches_first() {
+        let index: RuleIndex = serde_json::from_str(
+            r#"{"packs": [
+                {"name": "web-basics", "description": "Catches leftover logging", "file": "a.toml", "install": "a"},
+                {"name": "logging", "description": "Structured logs", "languages": ["go"], "file": "b.toml", "install": "b"},
+                {"name": "security", "description": "Injection checks", "file": "c.toml", "install": "c"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let names: Vec<_> = index
+            .search("LOGGING")
+            .iter()
+            .map(|pack| pack.name.as_str())
+            .collect();
+        assert_eq!(names, ["logging", "web-basics"]);
+        assert_eq!(index.search("go").len(), 1);
+        assert!(index.search("zig").is_empty());
+        assert!(RuleIndex::load("http://example.com/index.json").is_err());
+    }
+
+    #[test]
+    fn test_packs_must_not_install_over_the_project_config() {
+        let path = std::env::temp_dir().join("compass_rule_index_test.json");
+        for file in ["compass.toml", "../pack.toml", "packs/pack.toml"] {
+            fs::write(
+                &path,
+                format!(
+                    r#"{{"packs": [{{"name": "p", "description": "d", "file": "{}", "install": "i"}}]}}"#,
+                    file
+                ),
+            )
+            .unwrap();
+            let error = RuleIndex::load(path.to_str().unwrap()).unwrap_err();
+            assert!(
+                error.to_string().contains("a file name of their own"),
+                "{}",
+                error
+            );
+        }
+        let _ = fs::remove_file(&path);
+    }
+}
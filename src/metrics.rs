@@ -0,0 +1,385 @@
+//! Size and complexity measures: each file's code, comment, and blank
+//! lines, which every report carries, and for `--metrics`, Halstead
+//! measures, cyclomatic complexity, and the maintainability index, for
+//! teams tracking them alongside the rule-based score.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::ops::AddAssign;
+use tree_sitter::{Language, Node, Parser};
+
+/// Nodes that are one operand however the grammar splits them up, such as
+/// a string literal with its quotes and escapes.
+const OPERAND_KINDS: &[&str] = &[
+    "string",
+    "string_literal",
+    "raw_string_literal",
+    "char_literal",
+    "character_literal",
+    "interpreted_string_literal",
+    "line_string_literal",
+    "multi_line_string_literal",
+    "template_string",
+];
+
+/// Nodes that add a path through the code: branches, loops, match arms,
+/// catch clauses, and conditional expressions.
+const DECISION_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "guard_statement",
+    "for_statement",
+    "for_expression",
+    "for_in_statement",
+    "enhanced_for_statement",
+    "for_range_loop",
+    "while_statement",
+    "while_expression",
+    "loop_expression",
+    "do_statement",
+    "repeat_while_statement",
+    "match_arm",
+    "switch_case",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "case_statement",
+    "switch_block_statement_group",
+    "switch_rule",
+    "switch_entry",
+    "switch_prong",
+    "catch_clause",
+    "conditional_expression",
+    "ternary_expression",
+];
+
+/// Short-circuit operators, each another path through a condition.
+const DECISION_OPERATORS: &[&str] = &["&&", "||", "and", "or", "orelse", "??"];
+
+/// Closing brackets, counted with their opening one as a single operator.
+const CLOSING_TOKENS: &[&str] = &[")", "]", "}"];
+
+/// A file's lines by what they hold. A line with code and a comment is a
+/// code line, and a blank line inside a block comment a comment line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LineCounts {
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+}
+
+impl LineCounts {
+    pub fn total(&self) -> usize {
+        self.code + self.comment + self.blank
+    }
+}
+
+impl AddAssign for LineCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// Counts the lines of `source_code` using its tree's comment nodes, so
+/// `//` inside a string is code and every language's comments are found.
+pub fn line_counts(root: Node<'_>, source_code: &str) -> LineCounts {
+    let lines: Vec<&str> = source_code.lines().collect();
+    let mut code = vec![false; lines.len()];
+    let mut comment = vec![false; lines.len()];
+    let mark = |marks: &mut [bool], node: Node<'_>| {
+        let start = node.start_position();
+        let end = node.end_position();
+        // A node ending with its line's newline doesn't reach the next.
+        let last = if end.column == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+        for row in start.row..=last.min(marks.len().saturating_sub(1)) {
+            marks[row] = true;
+        }
+    };
+
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        if node.kind().contains("comment") {
+            mark(&mut comment, node);
+            continue;
+        }
+        if node.child_count() == 0 {
+            if node.start_byte() < node.end_byte() {
+                mark(&mut code, node);
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        pending.extend(node.children(&mut cursor));
+    }
+
+    let mut counts = LineCounts::default();
+    for (row, line) in lines.iter().enumerate() {
+        if code[row] {
+            counts.code += 1;
+        } else if comment[row] {
+            counts.comment += 1;
+        } else if line.trim().is_empty() {
+            counts.blank += 1;
+        } else {
+            // Text the grammar keeps in no leaf, such as some recovered
+            // syntax errors.
+            counts.code += 1;
+        }
+    }
+    counts
+}
+
+/// Halstead's counts of a file's operators (keywords and punctuation) and
+/// operands (identifiers and literals), and the measures derived from them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Halstead {
+    pub distinct_operators: usize,
+    pub distinct_operands: usize,
+    pub operators: usize,
+    pub operands: usize,
+}
+
+impl Halstead {
+    pub fn vocabulary(&self) -> usize {
+        self.distinct_operators + self.distinct_operands
+    }
+
+    pub fn length(&self) -> usize {
+        self.operators + self.operands
+    }
+
+    /// Bits needed to write the program down: length × log2(vocabulary).
+    pub fn volume(&self) -> f64 {
+        match self.vocabulary() {
+            0 | 1 => 0.0,
+            vocabulary => self.length() as f64 * (vocabulary as f64).log2(),
+        }
+    }
+
+    /// How error-prone the program is to write or read.
+    pub fn difficulty(&self) -> f64 {
+        if self.distinct_operands == 0 {
+            return 0.0;
+        }
+        self.distinct_operators as f64 / 2.0 * self.operands as f64 / self.distinct_operands as f64
+    }
+
+    pub fn effort(&self) -> f64 {
+        self.difficulty() * self.volume()
+    }
+}
+
+/// The measures of one file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FileMetrics {
+    /// Lines with something other than whitespace and comments.
+    pub code_lines: usize,
+    /// Cyclomatic complexity of the whole file: one, plus one per branch,
+    /// loop, case, catch, and short-circuit operator.
+    pub complexity: usize,
+    pub halstead: Halstead,
+}
+
+impl FileMetrics {
+    /// The maintainability index, scaled to 0–100 as Visual Studio does:
+    /// `(171 - 5.2 ln(volume) - 0.23 complexity - 16.2 ln(code lines)) ×
+    /// 100 / 171`, floored at zero. Above 20 is maintainable, 10 to 20
+    /// needs attention, and below 10 is hard to maintain.
+    pub fn maintainability_index(&self) -> f64 {
+        let volume = self.halstead.volume().max(1.0);
+        let lines = self.code_lines.max(1) as f64;
+        let index = 171.0 - 5.2 * volume.ln() - 0.23 * self.complexity as f64 - 16.2 * lines.ln();
+        (index * 100.0 / 171.0).clamp(0.0, 100.0)
+    }
+}
+
+/// Parses `source_code` and measures it.
+pub fn file_metrics(
+    source_code: &str,
+    language: &Language,
+) -> Result<FileMetrics, Box<dyn std::error::Error>> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+    let tree = parser
+        .parse(source_code, None)
+        .ok_or("failed to parse source code")?;
+    Ok(measure(tree.root_node(), source_code))
+}
+
+/// Measures the tree under `root`.
+pub fn measure(root: Node<'_>, source_code: &str) -> FileMetrics {
+    let source = source_code.as_bytes();
+    let mut halstead = Halstead::default();
+    let mut operators = HashSet::new();
+    let mut operands = HashSet::new();
+    let mut complexity = 1;
+
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        let kind = node.kind();
+        if kind.contains("comment") {
+            continue;
+        }
+        if DECISION_KINDS.contains(&kind)
+            || (!node.is_named() && DECISION_OPERATORS.contains(&kind))
+        {
+            complexity += 1;
+        }
+        let operand = node.is_named() && (OPERAND_KINDS.contains(&kind) || node.child_count() == 0);
+        if operand {
+            halstead.operands += 1;
+            operands.insert(node.utf8_text(source).unwrap_or_default());
+            continue;
+        }
+        if node.child_count() == 0 && !node.is_missing() && !CLOSING_TOKENS.contains(&kind) {
+            halstead.operators += 1;
+            operators.insert(kind);
+        }
+        let mut cursor = node.walk();
+        pending.extend(node.children(&mut cursor));
+    }
+
+    halstead.distinct_operators = operators.len();
+    halstead.distinct_operands = operands.len();
+    FileMetrics {
+        code_lines: line_counts(root, source_code).code,
+        complexity,
+        halstead,
+    }
+}
+
+/// Rounds to two decimals, which is all the JSON output needs.
+fn rounded(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn file_json(path: &str, metrics: &FileMetrics) -> Value {
+    let halstead = &metrics.halstead;
+    json!({
+        "path": path,
+        "code_lines": metrics.code_lines,
+        "complexity": metrics.complexity,
+        "halstead": {
+            "distinct_operators": halstead.distinct_operators,
+            "distinct_operands": halstead.distinct_operands,
+            "operators": halstead.operators,
+            "operands": halstead.operands,
+            "vocabulary": halstead.vocabulary(),
+            "length": halstead.length(),
+            "volume": rounded(halstead.volume()),
+            "difficulty": rounded(halstead.difficulty()),
+            "effort": rounded(halstead.effort()),
+        },
+        "maintainability_index": rounded(metrics.maintainability_index()),
+    })
+}
+
+/// The `metrics` section of the JSON output: the project's maintainability
+/// index, averaged over files by code lines, and each file's measures,
+/// least maintainable first.
+pub fn metrics_report(files: &[(&str, &FileMetrics)]) -> Value {
+    let lines: usize = files.iter().map(|(_, metrics)| metrics.code_lines).sum();
+    let index = if lines == 0 {
+        100.0
+    } else {
+        files
+            .iter()
+            .map(|(_, metrics)| metrics.maintainability_index() * metrics.code_lines as f64)
+            .sum::<f64>()
+            / lines as f64
+    };
+    json!({
+        "maintainability_index": rounded(index),
+        "code_lines": lines,
+        "complexity": files.iter().map(|(_, metrics)| metrics.complexity).sum::<usize>(),
+        "halstead_volume": rounded(files.iter().map(|(_, metrics)| metrics.halstead.volume()).sum()),
+        "files": least_maintainable(files).iter().map(|(path, metrics)| file_json(path, metrics)).collect::<Vec<_>>(),
+    })
+}
+
+/// Files, lowest maintainability index first.
+fn least_maintainable<'a>(files: &[(&'a str, &'a FileMetrics)]) -> Vec<(&'a str, &'a FileMetrics)> {
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| {
+        a.1.maintainability_index()
+            .total_cmp(&b.1.maintainability_index())
+            .then_with(|| a.0.cmp(b.0))
+    });
+    files
+}
+
+/// Files' maintainability index, complexity, and volume as a plain-text
+/// table, least maintainable first, for stderr.
+pub fn metrics_table(files: &[(&str, &FileMetrics)]) -> String {
+    let mut table = format!(
+        "Maintainability:\n  {:>6}  {:>10}  {:>10}  {:>10}  path\n",
+        "index", "complexity", "volume", "code lines"
+    );
+    for (path, metrics) in least_maintainable(files) {
+        table.push_str(&format!(
+            "  {:>6.1}  {:>10}  {:>10.0}  {:>10}  {}\n",
+            metrics.maintainability_index(),
+            metrics.complexity,
+            metrics.halstead.volume(),
+            metrics.code_lines,
+            path
+        ));
+    }
+    table
+}
+
+#[cfg(all(test, feature = "lang-rust"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_code_comment_or_blank() {
+        let source = "/* Header\n\n   more */\nfn f() { // trailing\n    let s = \"//\n\";\n\n    // note\n}\n";
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_rust::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let counts = line_counts(tree.root_node(), source);
+
+        assert_eq!(
+            counts,
+            LineCounts {
+                code: 4,
+                comment: 4,
+                blank: 1
+            }
+        );
+        assert_eq!(counts.total(), source.lines().count());
+    }
+
+    #[test]
+    fn test_halstead_counts_and_complexity_of_a_function() {
+        let source = "// Picks the larger.\nfn max(a: i32, b: i32) -> i32 {\n\n    if a > b && a != 0 { a } else { b }\n}\n";
+        let metrics = file_metrics(source, &tree_sitter_rust::LANGUAGE.into()).unwrap();
+
+        assert_eq!(metrics.code_lines, 3);
+        assert_eq!(metrics.complexity, 3);
+        // Operands: max a i32 b i32 i32 a b a 0 a b.
+        assert_eq!(metrics.halstead.operands, 12);
+        assert_eq!(metrics.halstead.distinct_operands, 5);
+        assert!(metrics.halstead.volume() > 0.0);
+        let index = metrics.maintainability_index();
+        assert!(index > 50.0 && index < 100.0, "{}", index);
+
+        let report = metrics_report(&[("max.rs", &metrics), ("empty.rs", &FileMetrics::default())]);
+        assert_eq!(report["code_lines"], 3);
+        assert_eq!(report["files"][0]["path"], "max.rs");
+        assert_eq!(
+            report["maintainability_index"],
+            report["files"][0]["maintainability_index"]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::analyzer::Severity;
+use crate::output::{escape_xml, in_line_order, Formatter};
+use crate::report::FileReport;
+
+/// Lint category used when a rule has no `category` label.
+const DEFAULT_CATEGORY: &str = "Correctness";
+
+/// `--format android-lint`, see [`render`].
+pub struct AndroidLintFormatter;
+
+impl Formatter for AndroidLintFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        render(files)
+    }
+}
+
+/// Android Lint XML (`lint-results.xml`, format 6), which Android Studio
+/// opens in its inspection view and Gradle plugins and CI dashboards that
+/// aggregate lint results import. A rule's `category` label sets the lint
+/// category.
+pub fn render(files: &[FileReport]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<issues format=\"6\" by=\"compass\">\n");
+    for file in files {
+        for result in in_line_order(&file.results) {
+            let category = result
+                .labels
+                .get("category")
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_CATEGORY);
+            xml.push_str("    <issue\n");
+            xml.push_str(&format!(
+                "        id=\"{}\"\n",
+                attribute(&result.rule_name)
+            ));
+            xml.push_str(&format!(
+                "        severity=\"{}\"\n",
+                severity(&result.severity)
+            ));
+            xml.push_str(&format!(
+                "        message=\"{}\"\n",
+                attribute(&result.message)
+            ));
+            xml.push_str(&format!("        category=\"{}\"\n", attribute(category)));
+            xml.push_str(&format!(
+                "        priority=\"{}\"\n",
+                priority(&result.severity)
+            ));
+            xml.push_str(&format!(
+                "        summary=\"{}\"\n",
+                attribute(&result.message)
+            ));
+            if let Some(suggestion) = &result.suggestion {
+                xml.push_str(&format!(
+                    "        explanation=\"{}\"\n",
+                    attribute(suggestion)
+                ));
+            }
+            xml.push_str("        >\n");
+            xml.push_str(&format!(
+                "        <location\n            file=\"{}\"\n            line=\"{}\"\n            column=\"{}\"/>\n",
+                attribute(&file.path),
+                result.line,
+                result.column
+            ));
+            xml.push_str("    </issue>\n");
+        }
+    }
+    xml.push_str("</issues>\n");
+    xml
+}
+
+/// Attribute values keep line breaks only as character references.
+fn attribute(text: &str) -> String {
+    escape_xml(text).replace('\n', "&#10;")
+}
+
+fn severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "Error",
+        Severity::Warning => "Warning",
+        Severity::Info | Severity::Style => "Information",
+    }
+}
+
+/// Lint priorities run from 1 (lowest) to 10.
+fn priority(severity: &Severity) -> u8 {
+    match severity {
+        Severity::Error => 8,
+        Severity::Warning => 5,
+        Severity::Info => 3,
+        Severity::Style => 1,
+    }
+}
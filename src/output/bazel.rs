@@ -0,0 +1,130 @@
+use crate::fingerprint::sha256_hex;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+pub const PROTOCOL_VERSION: &str = "compass-bazel/1";
+
+/// One analyzed source in a Bazel source set.
+pub struct BazelFile<'a> {
+    pub path: &'a str,
+    /// SHA-256 of the source contents.
+    pub source_hash: &'a str,
+    /// SHA-256 of the effective rule config.
+    pub config_hash: &'a str,
+    pub report: &'a Value,
+}
+
+/// Cache key for a file's result: identical source, config, and compass
+/// version always map to the same output name, so Bazel (or any other
+/// content-addressed cache) can reuse it.
+pub fn result_key(file: &BazelFile) -> String {
+    let material = format!(
+        "{}\0{}\0{}",
+        env!("CARGO_PKG_VERSION"),
+        file.config_hash,
+        file.source_hash
+    );
+    sha256_hex(material.as_bytes())
+}
+
+/// Writes one `<key>.json` result per file plus a `<target>.compass.json`
+/// manifest into `output_dir`, returning the manifest.
+///
+/// Outputs contain no timestamps or absolute output paths, so repeated
+/// invocations over the same inputs are byte-for-byte identical.
+pub fn write_outputs(
+    target: &str,
+    output_dir: &Path,
+    files: &[BazelFile],
+) -> Result<Value, Box<dyn std::error::Error>> {
+    fs::create_dir_all(output_dir)?;
+
+    let mut entries = Vec::new();
+    let mut total_issues = 0;
+    let mut min_score: Option<f64> = None;
+
+    for file in files {
+        let key = result_key(file);
+        let result_name = format!("{}.json", key);
+        fs::write(
+            output_dir.join(&result_name),
+            serde_json::to_string_pretty(file.report)?,
+        )?;
+
+        let score = file.report["score"].as_f64().unwrap_or(0.0);
+        let issues = file.report["total_issues"].as_u64().unwrap_or(0);
+        total_issues += issues;
+        min_score = Some(min_score.map_or(score, |min| min.min(score)));
+
+        entries.push(json!({
+            "path": file.path,
+            "content_hash": file.source_hash,
+            "result": result_name,
+            "score": score,
+            "total_issues": issues,
+        }));
+    }
+
+    let manifest = json!({
+        "protocol": PROTOCOL_VERSION,
+        "target": target,
+        "files": entries,
+        "total_issues": total_issues,
+        "min_score": min_score,
+    });
+    fs::write(
+        output_dir.join(manifest_name(target)),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+/// `//pkg/sub:lib` becomes `pkg_sub_lib.compass.json`.
+pub fn manifest_name(target: &str) -> String {
+    let sanitized: String = target
+        .trim_start_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.compass.json", sanitized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outputs_are_keyed_by_content() {
+        let dir = std::env::temp_dir().join(format!("compass_bazel_test_{}", std::process::id()));
+        let report = json!({ "score": 8.5, "total_issues": 2 });
+        let file = BazelFile {
+            path: "pkg/lib.rs",
+            source_hash: "abc",
+            config_hash: "def",
+            report: &report,
+        };
+
+        let manifest = write_outputs("//pkg:lib", &dir, &[file]).unwrap();
+        let result_name = manifest["files"][0]["result"].as_str().unwrap();
+        assert!(dir.join(result_name).exists());
+        assert!(dir.join("pkg_lib.compass.json").exists());
+        assert_eq!(manifest["total_issues"], 2);
+
+        let same = BazelFile {
+            path: "elsewhere/lib.rs",
+            source_hash: "abc",
+            config_hash: "def",
+            report: &report,
+        };
+        assert_eq!(format!("{}.json", result_key(&same)), result_name);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
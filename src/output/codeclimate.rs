@@ -0,0 +1,59 @@
+use crate::analyzer::Severity;
+use crate::fingerprint::finding_fingerprints;
+use crate::output::Formatter;
+use crate::report::FileReport;
+use serde_json::{json, Value};
+
+/// `--format codeclimate`, see [`render`].
+pub struct CodeClimateFormatter;
+
+impl Formatter for CodeClimateFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        format!("{:#}\n", render(files))
+    }
+}
+
+/// Code Climate issues, the format GitLab reads for its Code Quality
+/// merge request widget.
+pub fn render(files: &[FileReport]) -> Value {
+    let mut issues = Vec::new();
+    for file in files {
+        let fingerprints = finding_fingerprints(&file.path, &file.results);
+        for (result, fingerprint) in file.results.iter().zip(fingerprints) {
+            let mut issue = json!({
+                "type": "issue",
+                "check_name": result.rule_name,
+                "description": result.message,
+                "categories": [category(&result.severity)],
+                "severity": severity(&result.severity),
+                "fingerprint": fingerprint,
+                "location": {
+                    "path": file.path,
+                    "lines": { "begin": result.line, "end": result.line }
+                }
+            });
+            if let Some(suggestion) = &result.suggestion {
+                issue["content"] = json!({ "body": suggestion });
+            }
+            issues.push(issue);
+        }
+    }
+    Value::from(issues)
+}
+
+fn severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "critical",
+        Severity::Warning => "major",
+        Severity::Info => "minor",
+        Severity::Style => "info",
+    }
+}
+
+fn category(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error | Severity::Warning => "Bug Risk",
+        Severity::Info => "Clarity",
+        Severity::Style => "Style",
+    }
+}
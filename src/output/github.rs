@@ -0,0 +1,51 @@
+use crate::analyzer::Severity;
+use crate::output::{in_line_order, Formatter};
+use crate::report::FileReport;
+
+/// `--format github`: one `::warning file=...,line=...::message` workflow
+/// command per finding. Printed in a GitHub Actions step, the findings show
+/// up as annotations on the run and on the pull request diff.
+pub struct GithubFormatter;
+
+impl Formatter for GithubFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        let mut text = String::new();
+        for file in files {
+            for result in in_line_order(&file.results) {
+                text.push_str(&format!(
+                    "::{} file={},line={},endLine={},col={},title={}::{}\n",
+                    command(&result.severity),
+                    escape_property(&file.path),
+                    result.line,
+                    result.end_line,
+                    result.column,
+                    escape_property(&result.rule_name),
+                    escape_data(&result.message)
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Annotations are errors, warnings, or notices.
+fn command(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Style => "notice",
+    }
+}
+
+/// Escapes a workflow command's message.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a workflow command's property value, which also ends at `,`
+/// and `:`.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
@@ -0,0 +1,81 @@
+use crate::analyzer::{AnalysisResult, CodeScore};
+use crate::output::Formatter;
+use crate::report::{project_report, FileReport, ProjectWeighting};
+use serde_json::{json, Value};
+
+/// The JSON output: the single-file report for one file, the aggregated
+/// project report otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormatter {
+    pub weighting: ProjectWeighting,
+}
+
+impl Formatter for JsonFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        let report = match files {
+            [file] => format_score_as_json(&file.results, &file.score),
+            _ => project_report(files, self.weighting),
+        };
+        format!("{:#}\n", report)
+    }
+}
+
+/// The JSON report for one analyzed source: score, breakdown, and issues.
+pub fn format_score_as_json(results: &[AnalysisResult], score: &CodeScore) -> Value {
+    json!({
+        "score": score.overall_score,
+        "max_score": score.max_score,
+        "rating": score.rating,
+        "summary": score.summary,
+        "total_issues": score.total_issues,
+        "lines": score.lines,
+        "breakdown": {
+            "errors": score.breakdown.errors,
+            "warnings": score.breakdown.warnings,
+            "info_issues": score.breakdown.info_issues,
+            "style_issues": score.breakdown.style_issues,
+            "deductions": {
+                "from_errors": score.breakdown.error_deduction,
+                "from_warnings": score.breakdown.warning_deduction,
+                "from_info": score.breakdown.info_deduction,
+                "from_style": score.breakdown.style_deduction
+            },
+            "size_bonus": score.breakdown.size_bonus,
+            "capped_rules": score.breakdown.capped_rules.iter().map(|(rule, capped)| {
+                (rule.clone(), json!({
+                    "uncapped_impact": capped.uncapped_impact,
+                    "max_total_impact": capped.max_total_impact
+                }))
+            }).collect::<serde_json::Map<_, _>>()
+        },
+        "issues": results.iter().map(issue_json).collect::<Vec<_>>()
+    })
+}
+
+/// One entry of a report's `issues`.
+pub fn issue_json(r: &AnalysisResult) -> Value {
+    let mut issue = json!({
+        "rule": r.rule_name,
+        "severity": r.severity,
+        "message": r.message,
+        "line": r.line,
+        "column": r.column,
+        "text": r.text,
+        "start_byte": r.start_byte,
+        "end_byte": r.end_byte,
+        "suggestion": r.suggestion,
+        "score_impact": r.score_impact,
+        "confidence": r.confidence.as_str(),
+        "labels": r.labels
+    });
+    if let Some(context) = &r.context {
+        issue["context"] = json!(context);
+    }
+    if let Some(example) = &r.example_bad {
+        issue["example_bad"] = Value::from(example.as_str());
+    }
+    if let Some(example) = &r.example_good {
+        issue["example_good"] = Value::from(example.as_str());
+    }
+    issue
+}
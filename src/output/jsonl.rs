@@ -0,0 +1,63 @@
+//! `--format jsonl`: JSON Lines for log pipelines. Each finding is a
+//! `finding` line and each file a `file` line after its findings, so the
+//! CLI writes a file's lines as soon as it is analyzed; a `summary` line
+//! with the project score ends the output.
+
+use crate::output::json::issue_json;
+use crate::output::Formatter;
+use crate::report::{FileReport, ProjectScore, ProjectWeighting};
+use serde_json::{json, Value};
+
+/// All lines of a run at once, for callers that have every report already.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonlFormatter {
+    pub weighting: ProjectWeighting,
+}
+
+impl Formatter for JsonlFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        let mut lines: String = files.iter().map(file_lines).collect();
+        lines.push_str(&summary_line(&ProjectScore::from_files(
+            files,
+            self.weighting,
+        )));
+        lines
+    }
+}
+
+/// The `finding` lines of `file`, then its `file` line: the file's report
+/// without `issues`.
+pub fn file_lines(file: &FileReport) -> String {
+    let mut lines = String::new();
+    for result in &file.results {
+        let mut finding = json!({
+            "type": "finding",
+            "path": file.path,
+            "language": file.language,
+        });
+        merge(&mut finding, issue_json(result));
+        lines.push_str(&format!("{}\n", finding));
+    }
+    let mut report = file.to_json();
+    if let Value::Object(map) = &mut report {
+        map.remove("issues");
+    }
+    let mut line = json!({ "type": "file" });
+    merge(&mut line, report);
+    lines.push_str(&format!("{}\n", line));
+    lines
+}
+
+/// The closing `summary` line: the project summary of the JSON report.
+pub fn summary_line(score: &ProjectScore) -> String {
+    let mut line = json!({ "type": "summary" });
+    merge(&mut line, score.to_json());
+    format!("{}\n", line)
+}
+
+/// Adds the fields of `object` to `into`.
+fn merge(into: &mut Value, object: Value) {
+    if let (Value::Object(into), Value::Object(object)) = (into, object) {
+        into.extend(object);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::output::escape_xml;
+use crate::report::FileReport;
+
+/// One analyzed file and the names of the rules that ran on it.
+pub struct JunitFile<'a> {
+    pub report: &'a FileReport,
+    pub rules: &'a [String],
+}
+
+/// JUnit XML with one `<testsuite>` per file and one `<testcase>` per rule,
+/// failed when the rule has findings in that file.
+pub fn render(files: &[JunitFile]) -> String {
+    let total_tests: usize = files.iter().map(|file| file.rules.len()).sum();
+    let total_failures: usize = files.iter().map(failed_rules).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"compass\" tests=\"{}\" failures=\"{}\">\n",
+        total_tests, total_failures
+    ));
+    for file in files {
+        let path = escape_xml(&file.report.path);
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            path,
+            file.rules.len(),
+            failed_rules(file)
+        ));
+        for rule in file.rules {
+            let mut findings: Vec<_> = file
+                .report
+                .results
+                .iter()
+                .filter(|result| &result.rule_name == rule)
+                .collect();
+            findings.sort_by_key(|result| (result.line, result.column));
+            let name = escape_xml(rule);
+            let Some(first) = findings.first() else {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                    name, path
+                ));
+                continue;
+            };
+
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\">\n",
+                name, path
+            ));
+            xml.push_str(&format!(
+                "      <failure message=\"{}\" type=\"{:?}\">",
+                escape_xml(&format!(
+                    "{} ({} finding{})",
+                    first.message,
+                    findings.len(),
+                    if findings.len() == 1 { "" } else { "s" }
+                )),
+                first.severity
+            ));
+            for finding in &findings {
+                xml.push_str(&escape_xml(&format!(
+                    "{}:{}: {}\n",
+                    file.report.path, finding.line, finding.message
+                )));
+            }
+            xml.push_str("</failure>\n    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn failed_rules(file: &JunitFile) -> usize {
+    file.rules
+        .iter()
+        .filter(|rule| {
+            file.report
+                .results
+                .iter()
+                .any(|result| &&result.rule_name == rule)
+        })
+        .count()
+}
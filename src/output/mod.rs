@@ -0,0 +1,102 @@
+//! Output formats. Formats that need nothing beyond the file reports
+//! implement [`Formatter`] and are looked up by name in a
+//! [`FormatterRegistry`].
+
+use crate::analyzer::AnalysisResult;
+use crate::position::PositionEncoding;
+use crate::report::FileReport;
+
+pub mod android_lint;
+pub mod bazel;
+pub mod codeclimate;
+pub mod github;
+pub mod json;
+pub mod jsonl;
+pub mod junit;
+pub mod rdjson;
+pub mod sarif;
+pub mod text;
+pub mod xcode;
+
+/// Renders the reports of one run.
+pub trait Formatter {
+    fn format(&self, files: &[FileReport]) -> String;
+}
+
+/// Output formats selectable by name with `--format`.
+///
+/// [`FormatterRegistry::builtin`] holds the formats compass ships with;
+/// library users can [`register`](FormatterRegistry::register) more and
+/// pass the registry to [`run_with_formatters`](crate::run_with_formatters).
+#[derive(Default)]
+pub struct FormatterRegistry {
+    formatters: Vec<(String, Box<dyn Formatter>)>,
+}
+
+impl FormatterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("json", Box::new(json::JsonFormatter::default()));
+        registry.register("jsonl", Box::new(jsonl::JsonlFormatter::default()));
+        registry.register("text", Box::new(text::TextFormatter::default()));
+        registry.register("sarif", Box::new(sarif::SarifFormatter));
+        registry.register("codeclimate", Box::new(codeclimate::CodeClimateFormatter));
+        registry.register("rdjson", Box::new(rdjson::RdjsonFormatter));
+        registry.register("xcode", Box::new(xcode::XcodeFormatter));
+        registry.register("android-lint", Box::new(android_lint::AndroidLintFormatter));
+        registry.register("github", Box::new(github::GithubFormatter));
+        registry
+    }
+
+    /// Adds a format, replacing any existing one with the same name.
+    pub fn register(&mut self, name: &str, formatter: Box<dyn Formatter>) {
+        let name = name.to_lowercase();
+        self.formatters.retain(|(existing, _)| *existing != name);
+        self.formatters.push((name, formatter));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        self.formatters
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, formatter)| formatter.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formatters.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+/// Every result, in line order.
+pub(crate) fn in_line_order(results: &[AnalysisResult]) -> Vec<&AnalysisResult> {
+    let mut ordered: Vec<_> = results.iter().collect();
+    ordered.sort_by_key(|result| (result.line, result.column, &result.rule_name));
+    ordered
+}
+
+/// Line and column just past the matched text, counted in `encoding`.
+pub(crate) fn end_position(result: &AnalysisResult, encoding: PositionEncoding) -> (usize, usize) {
+    (result.end_line, result.end_columns.get(encoding))
+}
+
+/// Escapes text for XML element content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_markup_characters() {
+        assert_eq!(escape_xml("a<b> & \"c\""), "a&lt;b&gt; &amp; &quot;c&quot;");
+    }
+}
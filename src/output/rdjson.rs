@@ -0,0 +1,56 @@
+use crate::analyzer::Severity;
+use crate::output::{end_position, in_line_order, Formatter};
+use crate::position::PositionEncoding;
+use crate::report::FileReport;
+use serde_json::{json, Value};
+
+/// The Reviewdog Diagnostic Format counts columns in UTF-8 bytes.
+const COLUMN_ENCODING: PositionEncoding = PositionEncoding::Byte;
+
+/// `--format rdjson`, see [`render`].
+pub struct RdjsonFormatter;
+
+impl Formatter for RdjsonFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        format!("{:#}\n", render(files))
+    }
+}
+
+/// Reviewdog Diagnostic Format (rdjson), which reviewdog turns into pull
+/// request review comments.
+pub fn render(files: &[FileReport]) -> Value {
+    let mut diagnostics = Vec::new();
+    for file in files {
+        for result in in_line_order(&file.results) {
+            let message = match &result.suggestion {
+                Some(suggestion) => format!("{}\n\n{}", result.message, suggestion),
+                None => result.message.clone(),
+            };
+            let (end_line, end_column) = end_position(result, COLUMN_ENCODING);
+            diagnostics.push(json!({
+                "message": message,
+                "location": {
+                    "path": file.path,
+                    "range": {
+                        "start": { "line": result.line, "column": result.columns.get(COLUMN_ENCODING) },
+                        "end": { "line": end_line, "column": end_column }
+                    }
+                },
+                "severity": severity(&result.severity),
+                "code": { "value": result.rule_name }
+            }));
+        }
+    }
+    json!({
+        "source": { "name": "compass" },
+        "diagnostics": diagnostics
+    })
+}
+
+fn severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "ERROR",
+        Severity::Warning => "WARNING",
+        Severity::Info | Severity::Style => "INFO",
+    }
+}
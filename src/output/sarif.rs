@@ -0,0 +1,176 @@
+use crate::analyzer::{AnalysisResult, Severity};
+use crate::fingerprint::finding_fingerprints;
+use crate::output::{end_position, Formatter};
+use crate::position::PositionEncoding;
+use crate::report::{grammars, FileReport};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Declared as the run's `columnKind`; what GitHub code scanning and VS
+/// Code's SARIF viewer count.
+const COLUMN_ENCODING: PositionEncoding = PositionEncoding::Utf16;
+
+/// `--format sarif`, see [`render`].
+pub struct SarifFormatter;
+
+impl Formatter for SarifFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        format!("{:#}\n", render(files))
+    }
+}
+
+/// A SARIF 2.1.0 log with one run, for GitHub code scanning and other
+/// SARIF viewers. Each rule with findings is described once, with its
+/// suggestion and examples as help text, and rules with a `cwe` label are
+/// tagged as security rules.
+pub fn render(files: &[FileReport]) -> Value {
+    let mut rules: BTreeMap<&str, (&AnalysisResult, &str)> = BTreeMap::new();
+    for file in files {
+        for result in &file.results {
+            rules
+                .entry(&result.rule_name)
+                .or_insert((result, &file.language));
+        }
+    }
+    let rule_index: BTreeMap<&str, usize> = rules
+        .keys()
+        .enumerate()
+        .map(|(index, rule)| (*rule, index))
+        .collect();
+
+    let mut results = Vec::new();
+    for file in files {
+        let fingerprints = finding_fingerprints(&file.path, &file.results);
+        for (result, fingerprint) in file.results.iter().zip(fingerprints) {
+            let (end_line, end_column) = end_position(result, COLUMN_ENCODING);
+            results.push(json!({
+                "ruleId": result.rule_name,
+                "ruleIndex": rule_index[result.rule_name.as_str()],
+                "level": level(&result.severity),
+                "message": { "text": result.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file.path.replace('\\', "/") },
+                        "region": {
+                            "startLine": result.line,
+                            "startColumn": result.columns.get(COLUMN_ENCODING),
+                            "endLine": end_line,
+                            "endColumn": end_column
+                        }
+                    }
+                }],
+                "partialFingerprints": { "compass/v1": fingerprint }
+            }));
+        }
+    }
+
+    let rules: Vec<Value> = rules
+        .values()
+        .map(|(result, language)| rule_descriptor(result, language))
+        .collect();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "compass",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/lyledean1/compass",
+                    "rules": rules
+                }
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+            "properties": { "grammars": grammars(files) }
+        }]
+    })
+}
+
+fn rule_descriptor(result: &AnalysisResult, language: &str) -> Value {
+    let mut text = result.suggestion.clone().unwrap_or_default();
+    let mut markdown = text.clone();
+    if let Some(bad) = &result.example_bad {
+        text.push_str(&format!("\n\nAvoid:\n{}", bad));
+        markdown.push_str(&format!(
+            "\n\nAvoid:\n```{}\n{}\n```",
+            language,
+            bad.trim_end()
+        ));
+    }
+    if let Some(good) = &result.example_good {
+        text.push_str(&format!("\n\nPrefer:\n{}", good));
+        markdown.push_str(&format!(
+            "\n\nPrefer:\n```{}\n{}\n```",
+            language,
+            good.trim_end()
+        ));
+    }
+
+    let mut tags = vec![result.severity.as_str().to_string()];
+    let cwes = cwe_ids(result);
+    if !cwes.is_empty() {
+        tags.push("security".to_string());
+        tags.extend(cwes.iter().map(|id| format!("external/cwe/cwe-{}", id)));
+    }
+    let mut descriptor = json!({
+        "id": result.rule_name,
+        "shortDescription": { "text": result.message },
+        "defaultConfiguration": { "level": level(&result.severity) },
+        "properties": { "tags": tags }
+    });
+    if !cwes.is_empty() {
+        let properties = &mut descriptor["properties"];
+        properties["cwe"] = json!(cwes
+            .iter()
+            .map(|id| format!("CWE-{}", id))
+            .collect::<Vec<_>>());
+        properties["security-severity"] = json!(security_severity(&result.severity));
+    }
+    if !text.trim().is_empty() {
+        descriptor["help"] = json!({
+            "text": text.trim(),
+            "markdown": markdown.trim()
+        });
+    }
+    descriptor
+}
+
+/// The numbers of the rule's `cwe` label, e.g. `cwe = "CWE-78, CWE-88"`.
+fn cwe_ids(result: &AnalysisResult) -> Vec<String> {
+    let Some(label) = result.labels.get("cwe") else {
+        return Vec::new();
+    };
+    label
+        .split([',', ' '])
+        .map(|id| {
+            let id = id.trim();
+            id.get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("cwe-"))
+                .map_or(id, |_| &id[4..])
+        })
+        .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// GitHub code scanning's 0-10 score, which ranks security alerts as
+/// critical (9+), high (7+), medium (4+), or low.
+fn security_severity(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "8.0",
+        Severity::Warning => "5.5",
+        Severity::Info => "3.0",
+        Severity::Style => "1.0",
+    }
+}
+
+fn level(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Style => "note",
+    }
+}
@@ -0,0 +1,89 @@
+use crate::analyzer::Severity;
+use crate::metrics::LineCounts;
+use crate::output::{in_line_order, Formatter};
+use crate::report::{comment_markers, FileReport};
+
+/// Plain text for terminals: one `path:line:column` line per finding, in
+/// the form editors and terminals make clickable, with the lines around it
+/// when `context_lines` is set, then each file's score.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextFormatter {
+    /// Color severities with ANSI escapes.
+    pub color: bool,
+}
+
+impl TextFormatter {
+    fn severity(&self, severity: &Severity) -> String {
+        if !self.color {
+            return severity.as_str().to_string();
+        }
+        let code = match severity {
+            Severity::Error => "1;31",
+            Severity::Warning => "33",
+            Severity::Info => "36",
+            Severity::Style => "2",
+        };
+        format!("\x1b[{}m{}\x1b[0m", code, severity.as_str())
+    }
+}
+
+impl Formatter for TextFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        let mut text = String::new();
+        let mut total_issues = 0;
+        for file in files {
+            let results = in_line_order(&file.results);
+            for result in &results {
+                text.push_str(&format!(
+                    "{}:{}:{}: {} [{}] {}\n",
+                    file.path,
+                    result.line,
+                    result.column,
+                    self.severity(&result.severity),
+                    result.rule_name,
+                    result.message
+                ));
+                if let Some(suggestion) = &result.suggestion {
+                    text.push_str(&format!("    {}\n", suggestion));
+                }
+                if let Some(context) = &result.context {
+                    let width = (context.start_line + context.lines.len()).to_string().len();
+                    for (offset, line) in context.lines.iter().enumerate() {
+                        let number = context.start_line + offset;
+                        let marker = if number == result.line { '>' } else { ' ' };
+                        text.push_str(&format!("  {} {:>width$} | {}\n", marker, number, line));
+                    }
+                }
+            }
+            text.push_str(&format!(
+                "{}: {:.1}/10 ({}), {} issue{}\n",
+                file.path,
+                file.score.overall_score,
+                file.score.rating,
+                results.len(),
+                if results.len() == 1 { "" } else { "s" }
+            ));
+            total_issues += results.len();
+        }
+        if files.len() > 1 {
+            text.push_str(&format!("{} files, {} issues\n", files.len(), total_issues));
+            let mut lines = LineCounts::default();
+            for file in files {
+                lines += file.score.lines;
+            }
+            text.push_str(&format!(
+                "Lines: {} code, {} comment, {} blank\n",
+                lines.code, lines.comment, lines.blank
+            ));
+        }
+        let markers = comment_markers(files);
+        if !markers.is_empty() {
+            let counts: Vec<String> = markers
+                .iter()
+                .map(|(marker, count)| format!("{} {}", marker, count))
+                .collect();
+            text.push_str(&format!("Comment markers: {}\n", counts.join(", ")));
+        }
+        text
+    }
+}
@@ -0,0 +1,38 @@
+use crate::analyzer::Severity;
+use crate::output::{in_line_order, Formatter};
+use crate::report::FileReport;
+
+/// `--format xcode`: one `path:line:column: warning: message` line per
+/// finding, the diagnostic form xcodebuild prints. Run as a build phase
+/// script, Xcode and Xcode Cloud show the findings inline in the editor
+/// and issue navigator.
+pub struct XcodeFormatter;
+
+impl Formatter for XcodeFormatter {
+    fn format(&self, files: &[FileReport]) -> String {
+        let mut text = String::new();
+        for file in files {
+            for result in in_line_order(&file.results) {
+                text.push_str(&format!(
+                    "{}:{}:{}: {}: {} [{}]\n",
+                    file.path,
+                    result.line,
+                    result.column,
+                    kind(&result.severity),
+                    result.message,
+                    result.rule_name
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Xcode only knows errors, warnings, and notes.
+fn kind(severity: &Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info | Severity::Style => "note",
+    }
+}
@@ -0,0 +1,151 @@
+//! C/C++ header/source pairs: `widget.h` and `widget.cpp` share a stem, so
+//! pair checks can correlate what one declares with what the other
+//! defines.
+
+use crate::paths;
+use crate::source;
+use crate::structural::PairSide;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx"];
+pub const SOURCE_EXTENSIONS: &[&str] = &["cpp", "cc", "cxx"];
+
+/// Directory names that hold the other half of a split `include/` and
+/// `src/` layout.
+const SPLIT_LAYOUT: &[(&str, &str)] = &[("include", "src")];
+
+/// The other file of the pair a file belongs to.
+#[derive(Debug, Clone)]
+pub struct Counterpart {
+    pub path: PathBuf,
+    pub source_code: String,
+    /// The side of the pair the analyzed file (not the counterpart) is on.
+    pub side: PairSide,
+}
+
+/// The side of a pair `path` is on, from its extension.
+pub fn side_of(path: &Path) -> Option<PairSide> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if HEADER_EXTENSIONS.contains(&extension.as_str()) {
+        Some(PairSide::Header)
+    } else if SOURCE_EXTENSIONS.contains(&extension.as_str()) {
+        Some(PairSide::Source)
+    } else {
+        None
+    }
+}
+
+/// The existing file with the same stem and the opposite side, looked up
+/// next to `path` first and then in the sibling `src/` or `include/`
+/// directory. Files in `overlays`, keyed by absolute path, exist whether
+/// or not they are on disk.
+pub fn counterpart_path(path: &Path, overlays: &BTreeMap<PathBuf, String>) -> Option<PathBuf> {
+    let side = side_of(path)?;
+    let stem = path.file_stem()?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let extensions = match side {
+        PairSide::Header => SOURCE_EXTENSIONS,
+        PairSide::Source => HEADER_EXTENSIONS,
+    };
+
+    let mut dirs = vec![dir.to_path_buf()];
+    let parent = dir.parent().unwrap_or(Path::new(""));
+    for (include, src) in SPLIT_LAYOUT {
+        let (from, to) = match side {
+            PairSide::Header => (include, src),
+            PairSide::Source => (src, include),
+        };
+        if dir.file_name().is_some_and(|name| name == *from) {
+            dirs.push(parent.join(to));
+        }
+    }
+
+    dirs.iter()
+        .flat_map(|dir| {
+            extensions.iter().map(move |extension| {
+                let mut name = stem.to_os_string();
+                name.push(".");
+                name.push(extension);
+                dir.join(name)
+            })
+        })
+        .find(|candidate| overlays.contains_key(&paths::absolute(candidate)) || candidate.is_file())
+}
+
+/// Loads the counterpart of `path`, if it has one, from `overlays` before
+/// the file on disk.
+pub fn counterpart(path: &Path, overlays: &BTreeMap<PathBuf, String>) -> Option<Counterpart> {
+    let side = side_of(path)?;
+    let counterpart = counterpart_path(path, overlays)?;
+    let source_code = match overlays.get(&paths::absolute(&counterpart)) {
+        Some(contents) => contents.clone(),
+        None => source::read(&counterpart).ok()?.text,
+    };
+    Some(Counterpart {
+        path: counterpart,
+        source_code,
+        side,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_counterparts_share_a_stem_next_to_each_other_or_across_include_and_src() {
+        let root = std::env::temp_dir().join("compass_pairing_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("include")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        for file in [
+            "widget.h",
+            "widget.cpp",
+            "include/gauge.hpp",
+            "src/gauge.cc",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join("src/main.cpp"), "").unwrap();
+
+        assert_eq!(
+            counterpart_path(&root.join("widget.h"), &BTreeMap::new()),
+            Some(root.join("widget.cpp"))
+        );
+        assert_eq!(
+            counterpart_path(&root.join("widget.cpp"), &BTreeMap::new()),
+            Some(root.join("widget.h"))
+        );
+        assert_eq!(
+            counterpart_path(&root.join("src/gauge.cc"), &BTreeMap::new()),
+            Some(root.join("include/gauge.hpp"))
+        );
+        assert_eq!(
+            counterpart_path(&root.join("include/gauge.hpp"), &BTreeMap::new()),
+            Some(root.join("src/gauge.cc"))
+        );
+        assert_eq!(
+            counterpart_path(&root.join("src/main.cpp"), &BTreeMap::new()),
+            None
+        );
+        assert_eq!(
+            counterpart_path(Path::new("main.go"), &BTreeMap::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_counterpart_that_is_not_utf8_is_read_lossily() {
+        let root = std::env::temp_dir().join("compass_pairing_latin1_test");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("widget.h"), "").unwrap();
+        fs::write(root.join("widget.cpp"), b"// caf\xe9\nint widget();\n").unwrap();
+
+        let counterpart = counterpart(&root.join("widget.h"), &BTreeMap::new()).unwrap();
+        assert_eq!(counterpart.path, root.join("widget.cpp"));
+        assert_eq!(counterpart.source_code, "// caf\u{fffd}\nint widget();\n");
+    }
+}
@@ -0,0 +1,53 @@
+use std::env;
+use std::path::{self, Path, PathBuf};
+
+/// `path` made absolute against the working directory, without touching
+/// the file system, so overlays match however the path was spelled.
+pub fn absolute(path: &Path) -> PathBuf {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.components().collect()
+}
+
+/// Directory for compass's scratch and cache files.
+///
+/// Resolution order: `COMPASS_CACHE_DIR`, `$XDG_CACHE_HOME/compass`,
+/// `$HOME/.cache/compass`, then the system temp directory. The last fallback
+/// keeps containers running as an arbitrary non-root UID (often with no
+/// writable home) working.
+#[cfg(feature = "cli")]
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = non_empty_var("COMPASS_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = non_empty_var("XDG_CACHE_HOME") {
+        return PathBuf::from(dir).join("compass");
+    }
+    if let Some(home) = non_empty_var("HOME") {
+        return PathBuf::from(home).join(".cache").join("compass");
+    }
+    env::temp_dir().join("compass")
+}
+
+/// The user-level config file, whether or not it exists.
+///
+/// Resolution order: `COMPASS_USER_CONFIG` (set but empty disables the
+/// user config), `$XDG_CONFIG_HOME/compass/config.toml`, then
+/// `$HOME/.config/compass/config.toml`.
+pub fn user_config_file() -> Option<PathBuf> {
+    if let Ok(path) = env::var("COMPASS_USER_CONFIG") {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+    if let Some(dir) = non_empty_var("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("compass").join("config.toml"));
+    }
+    non_empty_var("HOME").map(|home| {
+        PathBuf::from(home)
+            .join(".config")
+            .join("compass")
+            .join("config.toml")
+    })
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
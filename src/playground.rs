@@ -0,0 +1,483 @@
+//! HTTP backend for an anonymous, public compass playground.
+//!
+//! Hardened for untrusted traffic: only the built-in rules and grammars are
+//! available (requests cannot supply queries, configs, or paths), request
+//! sizes are bounded, and each client address is rate limited. Up to
+//! [`MAX_CONNECTIONS`] connections are handled at once, each closed after
+//! one response; a request must arrive within [`REQUEST_TIMEOUT`] and its
+//! analysis finish within [`ANALYSIS_TIMEOUT`].
+//!
+//! | Endpoint | Response |
+//! |----------|----------|
+//! | `POST /analyze` with `{"language": "rust", "source": "..."}` | the single-file JSON report |
+//! | `GET /rules?language=rust` | the built-in rules of a language |
+//! | `GET /rules/<name>` | one rule, with its query and examples |
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::config::{AnalyzerConfig, RuleConfig};
+use crate::language::LanguageRegistry;
+use crate::output::json::format_score_as_json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Largest accepted source snippet, which also bounds analysis time.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+const SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time a client has to send its whole request, however slowly it trickles.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time one analysis may run before it is cancelled.
+pub const ANALYSIS_TIMEOUT: Duration = Duration::from_secs(2);
+/// Connections handled at once; more are turned away until one finishes.
+pub const MAX_CONNECTIONS: usize = 32;
+
+/// Limits applied to a running playground.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaygroundOptions {
+    /// Requests allowed per client address in each `window`.
+    pub requests_per_window: u32,
+    pub window: Duration,
+}
+
+impl Default for PlaygroundOptions {
+    fn default() -> Self {
+        PlaygroundOptions {
+            requests_per_window: 30,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Fixed-window request counter per client address.
+#[derive(Debug)]
+pub struct RateLimiter {
+    options: PlaygroundOptions,
+    clients: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    pub fn new(options: PlaygroundOptions) -> Self {
+        RateLimiter {
+            options,
+            clients: HashMap::new(),
+        }
+    }
+
+    /// Counts a request from `client`, returning whether it is allowed.
+    pub fn allow(&mut self, client: IpAddr, now: Instant) -> bool {
+        let window = self.options.window;
+        self.clients
+            .retain(|_, (started, _)| now.duration_since(*started) < window);
+        let (_, count) = self.clients.entry(client).or_insert((now, 0));
+        *count += 1;
+        *count <= self.options.requests_per_window
+    }
+}
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+/// Status code and JSON body of a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+/// Serves the playground on `addr` until the process is stopped.
+pub fn serve(addr: &str, options: PlaygroundOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(addr)?;
+    let mut limiter = RateLimiter::new(options);
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let Ok(peer) = stream.peer_addr() else {
+            continue;
+        };
+        let refused = if !limiter.allow(peer.ip(), Instant::now()) {
+            Response::error(429, "rate limit exceeded, try again later")
+        } else if active.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+            Response::error(503, "too many requests in progress, try again later")
+        } else {
+            let slot = Slot::take(&active);
+            thread::spawn(move || {
+                let _slot = slot;
+                respond(&mut stream);
+            });
+            continue;
+        };
+        let _ = stream.set_write_timeout(Some(SOCKET_TIMEOUT));
+        let _ = write_response(&mut stream, &refused);
+    }
+    Ok(())
+}
+
+/// A connection counted as in progress until dropped.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Self {
+        active.fetch_add(1, Ordering::SeqCst);
+        Slot(active.clone())
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn respond(stream: &mut TcpStream) {
+    let response = match read_request(stream, Instant::now() + REQUEST_TIMEOUT) {
+        Ok(request) => handle(&request),
+        Err(response) => response,
+    };
+    // The client may already be gone; nothing useful to do about it.
+    let _ = write_response(stream, &response);
+}
+
+/// Reads a request that must have fully arrived by `deadline`.
+fn read_request(stream: &mut TcpStream, deadline: Instant) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error(400, message);
+    stream
+        .set_write_timeout(Some(SOCKET_TIMEOUT))
+        .map_err(|_| bad_request("socket error"))?;
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(end) = find_header_end(&buffer) {
+            break end;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(Response::error(431, "request headers too large"));
+        }
+        let read = read_before(stream, &mut chunk, deadline)?;
+        if read == 0 {
+            return Err(bad_request("incomplete request"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..header_end])
+        .map_err(|_| bad_request("request headers are not UTF-8"))?;
+    let mut request = parse_head(head).ok_or_else(|| bad_request("malformed request"))?;
+    let content_length = head
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()
+        .map_err(|_| bad_request("invalid Content-Length"))?
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return Err(Response::error(413, "request body too large"));
+    }
+
+    let mut body = buffer[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = read_before(stream, &mut chunk, deadline)?;
+        if read == 0 {
+            return Err(bad_request("incomplete body"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+/// Reads what has arrived into `chunk`, giving up at `deadline` so a
+/// client trickling bytes can't hold its connection open.
+fn read_before(
+    stream: &mut TcpStream,
+    chunk: &mut [u8],
+    deadline: Instant,
+) -> Result<usize, Response> {
+    let timed_out = || Response::error(408, "timed out reading request");
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(timed_out());
+    }
+    stream
+        .set_read_timeout(Some(remaining.min(SOCKET_TIMEOUT)))
+        .map_err(|_| Response::error(400, "socket error"))?;
+    stream.read(chunk).map_err(|_| timed_out())
+}
+
+fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+/// Parses the request line; the body is filled in separately.
+fn parse_head(head: &str) -> Option<Request> {
+    let mut parts = head.lines().next()?.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        body: Vec::new(),
+    })
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let body = response.body.to_string();
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Routes one request. Pure, so the endpoints can be exercised without a
+/// socket.
+pub fn handle(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/analyze") => analyze(
+            &request.body,
+            &CancellationToken::new().with_timeout(ANALYSIS_TIMEOUT),
+        ),
+        ("GET", "/rules") => match request.query.get("language") {
+            Some(language) => list_rules(language),
+            None => Response::error(400, "missing ?language="),
+        },
+        ("GET", path) if path.starts_with("/rules/") => explain_rule(&path["/rules/".len()..]),
+        (_, "/analyze" | "/rules") => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+/// Analyzes the body's source, stopping when `cancel` is.
+fn analyze(body: &[u8], cancel: &CancellationToken) -> Response {
+    let Ok(request) = serde_json::from_slice::<Value>(body) else {
+        return Response::error(400, "expected a JSON body");
+    };
+    let (Some(language), Some(source)) = (request["language"].as_str(), request["source"].as_str())
+    else {
+        return Response::error(400, "expected `language` and `source` strings");
+    };
+
+    let registry = LanguageRegistry::builtin();
+    let Some(definition) = registry.get(language) else {
+        return Response::error(422, "unsupported language");
+    };
+    let Some(config) = builtin_config(language) else {
+        return Response::error(422, "no rules for this language");
+    };
+    match config
+        .to_analyzer()
+        .with_cancellation(cancel.clone())
+        .analyze_with_score(source, &definition.language)
+    {
+        Ok((results, score)) => Response::ok(format_score_as_json(&results, &score)),
+        Err(e) if e.is::<Cancelled>() => Response::error(422, "analysis timed out"),
+        Err(_) => Response::error(422, "analysis failed"),
+    }
+}
+
+fn list_rules(language: &str) -> Response {
+    let Some(config) = builtin_config(language) else {
+        return Response::error(404, "unknown language");
+    };
+    Response::ok(Value::from(
+        config
+            .rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| rule_summary(language, rule))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+fn explain_rule(name: &str) -> Response {
+    let registry = LanguageRegistry::builtin();
+    for language in registry.iter() {
+        let Some(config) = builtin_config(&language.name) else {
+            continue;
+        };
+        if let Some(rule) = config.rules.iter().find(|rule| rule.name == name) {
+            let mut explained = rule_summary(&language.name, rule);
+            explained["query"] = Value::from(rule.query.as_str());
+            explained["check"] = json!(rule.check);
+            explained["comment"] = json!(rule.comment);
+            explained["weight"] = json!(rule.weight);
+            explained["example_bad"] = json!(rule.example_bad);
+            explained["example_good"] = json!(rule.example_good);
+            return Response::ok(explained);
+        }
+    }
+    Response::error(404, "unknown rule")
+}
+
+fn rule_summary(language: &str, rule: &RuleConfig) -> Value {
+    json!({
+        "name": rule.name,
+        "language": language,
+        "severity": rule.severity,
+        "message": rule.message,
+        "suggestion": rule.suggestion,
+    })
+}
+
+fn builtin_config(language: &str) -> Option<AnalyzerConfig> {
+    let registry = LanguageRegistry::builtin();
+    let source = registry.get(language)?.default_config.as_deref()?;
+    AnalyzerConfig::from_str(source).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, target: &str, body: &str) -> Request {
+        Request {
+            body: body.as_bytes().to_vec(),
+            ..parse_head(&format!("{} {} HTTP/1.1", method, target)).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let analyzed = handle(&request(
+            "POST",
+            "/analyze",
+            r#"{"language": "go", "source": "package main\nfunc main() { panic(1) }\n"}"#,
+        ));
+        assert_eq!(analyzed.status, 200);
+        assert!(analyzed.body["score"].as_f64().unwrap() < 10.0);
+
+        let rules = handle(&request("GET", "/rules?language=rust", ""));
+        assert!(rules
+            .body
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|rule| rule["name"] == "no_unwrap_prefer_error_handling"));
+
+        let explained = handle(&request(
+            "GET",
+            "/rules/no_unwrap_prefer_error_handling",
+            "",
+        ));
+        assert_eq!(explained.body["language"], "rust");
+        assert!(explained.body["example_good"].is_string());
+
+        assert_eq!(handle(&request("POST", "/analyze", "{}")).status, 400);
+        assert_eq!(
+            handle(&request(
+                "POST",
+                "/analyze",
+                r#"{"language": "cobol", "source": ""}"#
+            ))
+            .status,
+            422
+        );
+        assert_eq!(handle(&request("GET", "/analyze", "")).status, 405);
+        assert_eq!(handle(&request("GET", "/etc/passwd", "")).status, 404);
+    }
+
+    #[test]
+    fn test_analysis_stops_when_cancelled() {
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        let response = analyze(
+            br#"{"language": "go", "source": "package main\nfunc main() { panic(1) }\n"}"#,
+            &cancelled,
+        );
+        assert_eq!(response.status, 422);
+        assert_eq!(response.body["error"], "analysis timed out");
+    }
+
+    #[test]
+    fn test_slow_request_times_out_at_its_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        let started = Instant::now();
+        let trickle = thread::spawn(move || {
+            for byte in b"POST /analyze HTTP/1.1\r\n" {
+                if client.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        let response = read_request(&mut server, started + Duration::from_millis(300)).unwrap_err();
+        assert_eq!(response.status, 408);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        drop(server);
+        trickle.join().unwrap();
+    }
+
+    #[test]
+    fn test_rate_limiter_resets_each_window() {
+        let mut limiter = RateLimiter::new(PlaygroundOptions {
+            requests_per_window: 2,
+            window: Duration::from_secs(60),
+        });
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.allow(client, start));
+        assert!(limiter.allow(client, start));
+        assert!(!limiter.allow(client, start));
+        assert!(limiter.allow(other, start));
+        assert!(limiter.allow(client, start + Duration::from_secs(61)));
+    }
+}
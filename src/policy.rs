@@ -0,0 +1,207 @@
+use crate::analyzer::Severity;
+use crate::glob::glob_match;
+use crate::report::{FileReport, ProjectScore, ProjectWeighting};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Policy file enforced by `--ci` when present in the working directory.
+pub const DEFAULT_POLICY_FILE: &str = "compass-policy.toml";
+
+/// Organization policy, kept apart from rule configs so compliance owners
+/// can own the gates while teams tune rules.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default)]
+    pub gates: Gates,
+    #[serde(default)]
+    pub required_packs: Vec<RequiredPack>,
+    /// Rule name to the lowest severity it may run at.
+    #[serde(default)]
+    pub minimum_severities: BTreeMap<String, Severity>,
+    #[serde(default)]
+    pub protected_paths: ProtectedPaths,
+}
+
+/// Thresholds over the whole run; unset gates are not checked.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Gates {
+    pub min_score: Option<f64>,
+    pub max_errors: Option<usize>,
+    pub max_warnings: Option<usize>,
+    pub max_issues: Option<usize>,
+}
+
+/// Rules that must be enabled whenever `language` (or, without one, any
+/// language) is analyzed.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredPack {
+    pub name: String,
+    pub language: Option<String>,
+    pub rules: Vec<String>,
+}
+
+/// Paths where any finding at or above `min_severity` fails the run.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProtectedPaths {
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default = "default_protected_severity")]
+    pub min_severity: Severity,
+}
+
+impl Default for ProtectedPaths {
+    fn default() -> Self {
+        ProtectedPaths {
+            paths: Vec::new(),
+            min_severity: default_protected_severity(),
+        }
+    }
+}
+
+fn default_protected_severity() -> Severity {
+    Severity::Warning
+}
+
+impl Policy {
+    /// Loads and validates a policy file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)?;
+        let policy: Policy = toml::from_str(&content)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(min_score) = self.gates.min_score {
+            if !(0.0..=10.0).contains(&min_score) {
+                return Err(format!(
+                    "gates.min_score must be between 0 and 10, got {}",
+                    min_score
+                ));
+            }
+        }
+        for pack in &self.required_packs {
+            if pack.rules.is_empty() {
+                return Err(format!("required pack '{}' lists no rules", pack.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every way the run breaks the policy, as human-readable lines.
+    /// `enabled_rules` maps each analyzed language to the rules that ran
+    /// on it and their effective severities.
+    pub fn violations(
+        &self,
+        files: &[FileReport],
+        enabled_rules: &BTreeMap<String, BTreeMap<String, Severity>>,
+        weighting: ProjectWeighting,
+    ) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let score = ProjectScore::from_files(files, weighting);
+        let gates = &self.gates;
+        if let (Some(min_score), Some(overall)) = (gates.min_score, score.overall_score) {
+            if overall < min_score {
+                violations.push(format!(
+                    "score {:.1} is below the minimum of {:.1}",
+                    overall, min_score
+                ));
+            }
+        }
+        let counts = [
+            ("errors", gates.max_errors, score.breakdown.errors),
+            ("warnings", gates.max_warnings, score.breakdown.warnings),
+            ("issues", gates.max_issues, score.total_issues),
+        ];
+        for (label, max, actual) in counts {
+            if let Some(max) = max {
+                if actual > max {
+                    violations.push(format!("{} {} found (allowed: {})", actual, label, max));
+                }
+            }
+        }
+
+        for pack in &self.required_packs {
+            for (language, rules) in enabled_rules {
+                if pack
+                    .language
+                    .as_deref()
+                    .is_some_and(|wanted| !wanted.eq_ignore_ascii_case(language))
+                {
+                    continue;
+                }
+                for rule in pack.rules.iter().filter(|rule| !rules.contains_key(*rule)) {
+                    violations.push(format!(
+                        "required pack '{}': rule '{}' is not enabled for {}",
+                        pack.name, rule, language
+                    ));
+                }
+            }
+        }
+
+        for (rule, minimum) in &self.minimum_severities {
+            for (language, rules) in enabled_rules {
+                if let Some(severity) = rules.get(rule) {
+                    if severity < minimum {
+                        violations.push(format!(
+                            "rule '{}' runs at {:?} for {}, policy requires at least {:?}",
+                            rule, severity, language, minimum
+                        ));
+                    }
+                }
+            }
+        }
+
+        let protected = &self.protected_paths;
+        for file in files {
+            let path = file.path.replace('\\', "/");
+            if !protected
+                .paths
+                .iter()
+                .any(|pattern| glob_match(pattern, &path))
+            {
+                continue;
+            }
+            let mut findings: Vec<_> = file
+                .results
+                .iter()
+                .filter(|result| result.severity >= protected.min_severity)
+                .collect();
+            findings.sort_by_key(|result| (result.line, &result.rule_name));
+            for result in findings {
+                violations.push(format!(
+                    "{}:{}: {} in a protected path",
+                    file.path, result.line, result.rule_name
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_rejects_bad_policies() {
+        let parse = |content: &str| {
+            toml::from_str::<Policy>(content)
+                .map_err(|e| e.to_string())
+                .and_then(|policy| policy.validate())
+        };
+        assert!(parse("[gates]\nmin_score = 7.5\n").is_ok());
+        assert!(parse("[gates]\nmin_score = 11\n").is_err());
+        assert!(parse("[gates]\nmin_scroe = 7\n").is_err());
+        assert!(parse("[minimum_severities]\nno_unwrap = \"fatal\"\n").is_err());
+        assert!(parse("[[required_packs]]\nname = \"security\"\nrules = []\n").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! Column numbers in the units different consumers count: tree-sitter and
+//! Vim count UTF-8 bytes, people count characters, and LSP clients, SARIF
+//! viewers, and most editors built on JavaScript count UTF-16 code units.
+
+use crate::analyzer::AnalysisResult;
+use serde::{Deserialize, Serialize};
+
+/// The unit a column counts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PositionEncoding {
+    #[default]
+    #[serde(rename = "byte")]
+    Byte,
+    #[serde(rename = "char")]
+    Char,
+    #[serde(rename = "utf-16")]
+    Utf16,
+}
+
+impl PositionEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "byte" | "utf-8" | "utf8" => Some(PositionEncoding::Byte),
+            "char" => Some(PositionEncoding::Char),
+            "utf-16" | "utf16" => Some(PositionEncoding::Utf16),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PositionEncoding::Byte => "byte",
+            PositionEncoding::Char => "char",
+            PositionEncoding::Utf16 => "utf-16",
+        }
+    }
+
+    /// Length of `text` in this unit.
+    pub fn len(&self, text: &str) -> usize {
+        match self {
+            PositionEncoding::Byte => text.len(),
+            PositionEncoding::Char => text.chars().count(),
+            PositionEncoding::Utf16 => text.encode_utf16().count(),
+        }
+    }
+}
+
+/// One 1-based column in every [`PositionEncoding`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Columns {
+    pub byte: usize,
+    pub char: usize,
+    pub utf16: usize,
+}
+
+impl Columns {
+    /// Columns of byte offset `byte` of `source`.
+    pub fn at(source: &str, byte: usize) -> Self {
+        let before = &source[..byte];
+        let line = &before[before.rfind('\n').map_or(0, |newline| newline + 1)..];
+        Columns {
+            byte: line.len() + 1,
+            char: line.chars().count() + 1,
+            utf16: line.encode_utf16().count() + 1,
+        }
+    }
+
+    pub fn get(&self, encoding: PositionEncoding) -> usize {
+        match encoding {
+            PositionEncoding::Byte => self.byte,
+            PositionEncoding::Char => self.char,
+            PositionEncoding::Utf16 => self.utf16,
+        }
+    }
+}
+
+/// Recounts each finding's `column` in `encoding`, for formats that print
+/// `column` as is.
+pub fn reencode(results: &mut [AnalysisResult], encoding: PositionEncoding) {
+    for result in results {
+        result.column = result.columns.get(encoding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_count_bytes_chars_and_utf16_units() {
+        // `é` is 2 bytes and 1 UTF-16 unit; `😀` is 4 bytes and 2 units.
+        let source = "x\nlet s = \"é😀\"; var y;\n";
+        let var = source.find("var").unwrap();
+        assert_eq!(
+            Columns::at(source, var),
+            Columns {
+                byte: 19,
+                char: 15,
+                utf16: 16
+            }
+        );
+        assert_eq!(
+            Columns::at(source, 0),
+            Columns {
+                byte: 1,
+                char: 1,
+                utf16: 1
+            }
+        );
+        assert_eq!(PositionEncoding::Utf16.len("é😀"), 3);
+        assert_eq!(
+            PositionEncoding::from_name("UTF-16"),
+            Some(PositionEncoding::Utf16)
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Progress of a directory run: how many of its files are done and which
+//! one is being analyzed, for the CLI's progress bar and for library users
+//! subscribing with [`AnalyzeOptions::with_progress`](crate::AnalyzeOptions::with_progress).
+
+#[cfg(feature = "cli")]
+use std::io::Write;
+use std::time::Duration;
+use web_time::Instant;
+
+#[cfg(feature = "cli")]
+/// Least time between two redraws of a [`ProgressBar`].
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+#[cfg(feature = "cli")]
+/// Width of the bar itself, in characters.
+const BAR_WIDTH: usize = 24;
+#[cfg(feature = "cli")]
+/// Longest current-file path shown; longer ones keep their end.
+const MAX_PATH_WIDTH: usize = 40;
+
+/// Subscriber to a run's [`Progress`].
+pub type Listener = Box<dyn Fn(&Progress)>;
+
+/// Reported as each file of a run starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress<'a> {
+    /// Files finished before this one.
+    pub done: usize,
+    pub total: usize,
+    /// The file starting.
+    pub path: &'a str,
+    /// Time since the run's first file started.
+    pub elapsed: Duration,
+}
+
+impl Progress<'_> {
+    /// Time left, assuming the remaining files take as long as the done
+    /// ones did on average; `None` before any file is done.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.done) as u32;
+        Some(self.elapsed / self.done as u32 * remaining)
+    }
+}
+
+/// Counts the files of a run and times it, to report [`Progress`].
+#[derive(Debug)]
+pub struct FileCounter {
+    total: usize,
+    done: usize,
+    started: Instant,
+}
+
+impl FileCounter {
+    pub fn new(total: usize) -> Self {
+        FileCounter {
+            total,
+            done: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// The progress of starting `path`; each call counts the file before
+    /// as done.
+    pub fn start<'a>(&mut self, path: &'a str) -> Progress<'a> {
+        let progress = Progress {
+            done: self.done,
+            total: self.total,
+            path,
+            elapsed: self.started.elapsed(),
+        };
+        self.done += 1;
+        progress
+    }
+}
+
+#[cfg(feature = "cli")]
+/// A one-line progress bar on stderr, redrawn in place:
+/// `[=========>              ] 120/340  src/lib.rs  ETA 0:42`.
+#[derive(Debug)]
+pub struct ProgressBar {
+    last_draw: Option<Instant>,
+}
+
+#[cfg(feature = "cli")]
+impl ProgressBar {
+    pub fn new() -> Self {
+        ProgressBar { last_draw: None }
+    }
+
+    /// Redraws the bar, at most every 100ms.
+    pub fn update(&mut self, progress: &Progress) {
+        if self
+            .last_draw
+            .is_some_and(|last| last.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_draw = Some(Instant::now());
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r\x1b[K{}", render(progress));
+        let _ = stderr.flush();
+    }
+
+    /// Clears the bar, leaving the line to whatever comes next.
+    pub fn finish(&mut self) {
+        if self.last_draw.take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "cli")]
+/// The text of the bar for `progress`.
+fn render(progress: &Progress) -> String {
+    let filled = (progress.done * BAR_WIDTH)
+        .checked_div(progress.total)
+        .unwrap_or(0);
+    let bar = if filled < BAR_WIDTH {
+        format!(
+            "{}>{}",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled - 1)
+        )
+    } else {
+        "=".repeat(BAR_WIDTH)
+    };
+    let chars = progress.path.chars().count();
+    let path = match progress
+        .path
+        .char_indices()
+        .nth(chars.saturating_sub(MAX_PATH_WIDTH) + 1)
+    {
+        Some((start, _)) if chars > MAX_PATH_WIDTH => format!("…{}", &progress.path[start..]),
+        _ => progress.path.to_string(),
+    };
+    let eta = progress
+        .eta()
+        .map(|eta| format!("  ETA {}:{:02}", eta.as_secs() / 60, eta.as_secs() % 60))
+        .unwrap_or_default();
+    format!(
+        "[{}] {}/{}  {}{}",
+        bar, progress.done, progress.total, path, eta
+    )
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_estimates_time_left_and_renders_a_bar() {
+        let progress = Progress {
+            done: 3,
+            total: 12,
+            path: "src/very/deeply/nested/module/with/a/long/path/lib.rs",
+            elapsed: Duration::from_secs(30),
+        };
+        assert_eq!(progress.eta(), Some(Duration::from_secs(90)));
+        assert_eq!(
+            render(&progress),
+            "[======>                 ] 3/12  …y/nested/module/with/a/long/path/lib.rs  ETA 1:30"
+        );
+        assert_eq!(
+            Progress {
+                done: 0,
+                ..progress
+            }
+            .eta(),
+            None
+        );
+
+        let mut counter = FileCounter::new(2);
+        assert_eq!(counter.start("a.rs").done, 0);
+        assert_eq!(counter.start("b.rs").done, 1);
+    }
+}
@@ -0,0 +1,230 @@
+//! Rating labels and summary sentences for scores, from the `[ratings]`
+//! config table. Summaries are Handlebars templates over the score, its
+//! rating, and its finding counts, so they can be reworded or translated.
+
+use crate::analyzer::ScoreBreakdown;
+use crate::template::render_template;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Findings above which a summary points at warnings, or at minor issues,
+/// rather than at the rating.
+const MANY_WARNINGS: usize = 5;
+const MANY_INFO_ISSUES: usize = 10;
+
+/// Score bands and the summaries written for them.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ratings {
+    /// Rating labels by the lowest score that earns them. A score below
+    /// every band gets the lowest one.
+    pub bands: BTreeMap<String, f64>,
+    /// Summary of a score by its rating, when no finding count calls for
+    /// one of the summaries below.
+    pub summaries: BTreeMap<String, String>,
+    /// Summary of ratings missing from `summaries`.
+    pub summary: String,
+    /// Summary when there are errors.
+    pub errors: String,
+    /// Summary when there are more than five warnings.
+    pub warnings: String,
+    /// Summary when there are more than ten info findings.
+    pub info: String,
+    /// Summary of a project run that analyzed no files.
+    pub no_files: String,
+}
+
+impl Default for Ratings {
+    fn default() -> Self {
+        let bands = [
+            ("Excellent", 9.0),
+            ("Good", 7.5),
+            ("Fair", 6.0),
+            ("Poor", 4.0),
+            ("Critical", 0.0),
+        ];
+        let summaries = [
+            ("Excellent", "Excellent code quality with minimal issues"),
+            ("Good", "Good code quality with room for minor improvements"),
+        ];
+        Ratings {
+            bands: bands
+                .into_iter()
+                .map(|(label, min)| (label.to_string(), min))
+                .collect(),
+            summaries: summaries
+                .into_iter()
+                .map(|(label, summary)| (label.to_string(), summary.to_string()))
+                .collect(),
+            summary: "Code needs improvement in several areas".to_string(),
+            errors: "Code has {{errors}} critical errors that need immediate attention".to_string(),
+            warnings: "Multiple warnings detected - consider addressing them".to_string(),
+            info: "Many minor issues found - good opportunity for cleanup".to_string(),
+            no_files: "No files were analyzed".to_string(),
+        }
+    }
+}
+
+impl Ratings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Errors in the table: no bands, summaries of unknown ratings, or
+    /// templates that don't compile.
+    pub fn check(&self) -> Result<(), String> {
+        if self.bands.is_empty() {
+            return Err("ratings: bands must name at least one rating".to_string());
+        }
+        if let Some(label) = self
+            .summaries
+            .keys()
+            .find(|label| !self.bands.contains_key(*label))
+        {
+            return Err(format!(
+                "ratings: summary for '{}', which is not in bands",
+                label
+            ));
+        }
+        let templates = [
+            ("summary", &self.summary),
+            ("errors", &self.errors),
+            ("warnings", &self.warnings),
+            ("info", &self.info),
+            ("no_files", &self.no_files),
+        ];
+        for (name, template) in templates
+            .into_iter()
+            .chain(self.summaries.iter().map(|(label, t)| (label.as_str(), t)))
+        {
+            render_template(template, &json!({}))
+                .map_err(|e| format!("ratings: invalid template for '{}': {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Bands from the highest to the lowest.
+    pub fn ranked(&self) -> Vec<(&String, f64)> {
+        let mut bands: Vec<(&String, f64)> = self
+            .bands
+            .iter()
+            .map(|(label, min)| (label, *min))
+            .collect();
+        bands.sort_by(|a, b| b.1.total_cmp(&a.1));
+        bands
+    }
+
+    /// The label of the highest band `score` reaches.
+    pub fn label(&self, score: f64) -> String {
+        let bands = self.ranked();
+        bands
+            .iter()
+            .find(|(_, min)| score >= *min)
+            .or(bands.last())
+            .map(|(label, _)| label.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Rating label and one-line summary for a score and its breakdown.
+    /// Templates see `score`, `max_score`, `rating`, `errors`, `warnings`,
+    /// `info_issues`, `style_issues`, and `total_issues`.
+    pub fn rate(&self, score: f64, breakdown: &ScoreBreakdown) -> (String, String) {
+        let rating = self.label(score);
+        let template = if breakdown.errors > 0 {
+            &self.errors
+        } else if breakdown.warnings > MANY_WARNINGS {
+            &self.warnings
+        } else if breakdown.info_issues > MANY_INFO_ISSUES {
+            &self.info
+        } else {
+            self.summaries.get(&rating).unwrap_or(&self.summary)
+        };
+        let data = json!({
+            "score": score,
+            "max_score": 10.0,
+            "rating": rating,
+            "errors": breakdown.errors,
+            "warnings": breakdown.warnings,
+            "info_issues": breakdown.info_issues,
+            "style_issues": breakdown.style_issues,
+            "total_issues": breakdown.errors
+                + breakdown.warnings
+                + breakdown.info_issues
+                + breakdown.style_issues,
+        });
+        let summary = render(template, &data);
+        (rating, summary)
+    }
+
+    /// Summary of a run without files.
+    pub fn no_files_summary(&self) -> String {
+        render(&self.no_files, &json!({}))
+    }
+}
+
+/// `template` over `data`; checked templates render, and one that didn't
+/// is shown as written.
+fn render(template: &str, data: &serde_json::Value) -> String {
+    if !template.contains("{{") {
+        return template.to_string();
+    }
+    render_template(template, data).unwrap_or_else(|_| template.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_ratings_keep_the_built_in_wording() {
+        let ratings = Ratings::default();
+        let clean = ScoreBreakdown::default();
+        let errors = ScoreBreakdown {
+            errors: 2,
+            ..ScoreBreakdown::default()
+        };
+
+        assert_eq!(
+            ratings.rate(9.5, &clean),
+            (
+                "Excellent".to_string(),
+                "Excellent code quality with minimal issues".to_string()
+            )
+        );
+        assert_eq!(ratings.rate(6.0, &clean).0, "Fair");
+        assert_eq!(ratings.rate(-1.0, &clean).0, "Critical");
+        assert_eq!(
+            ratings.rate(3.0, &errors).1,
+            "Code has 2 critical errors that need immediate attention"
+        );
+        assert!(ratings.check().is_ok());
+    }
+
+    #[test]
+    fn test_custom_bands_and_templates() {
+        let ratings: Ratings = toml::from_str(
+            "summary = \"{{total_issues}} Befunde, Note {{rating}}\"\n\n[bands]\nA = 8.0\nB = 5.0\n\n[summaries]\nA = \"Sehr gut ({{score}}/{{max_score}})\"\n",
+        )
+        .unwrap();
+        let breakdown = ScoreBreakdown {
+            warnings: 1,
+            style_issues: 2,
+            ..ScoreBreakdown::default()
+        };
+
+        assert_eq!(ratings.rate(8.5, &breakdown).1, "Sehr gut (8.5/10.0)");
+        assert_eq!(
+            ratings.rate(2.0, &breakdown),
+            ("B".to_string(), "3 Befunde, Note B".to_string())
+        );
+        // Unset summaries keep their default wording.
+        assert_eq!(ratings.info, Ratings::default().info);
+
+        let unknown: Ratings = toml::from_str("[summaries]\nTop = \"x\"\n").unwrap();
+        assert!(unknown.check().unwrap_err().contains("'Top'"));
+        let broken: Ratings = toml::from_str("errors = \"{{#if errors}\"\n").unwrap();
+        assert!(broken.check().unwrap_err().contains("'errors'"));
+    }
+}
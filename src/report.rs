@@ -0,0 +1,422 @@
+use crate::analyzer::{AnalysisResult, CodeScore, ScoreBreakdown, Severity};
+use crate::language::Grammar;
+use crate::metrics::LineCounts;
+use crate::output::json::format_score_as_json;
+use crate::ratings::Ratings;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Locations listed per group of identical findings; `count` has the rest.
+const REPRESENTATIVE_LOCATIONS: usize = 5;
+
+/// Version of the aggregated multi-file JSON schema. Bump when a field is
+/// removed or changes meaning; adding fields is backwards compatible.
+/// Version 2 writes `severity` in lowercase, as `"warning"` rather than
+/// version 1's `"Warning"`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The analysis outcome for a single source file.
+#[derive(Debug, Clone)]
+pub struct FileReport {
+    pub path: String,
+    /// Config key of the language, e.g. `rust`.
+    pub language: String,
+    pub line_count: usize,
+    pub results: Vec<AnalysisResult>,
+    pub score: CodeScore,
+    /// Grammar the file was parsed with.
+    pub grammar: Option<Grammar>,
+}
+
+impl FileReport {
+    /// Per-file entry of the aggregated schema: `path` and `language`
+    /// followed by the same fields as single-file JSON output.
+    pub fn to_json(&self) -> Value {
+        let mut value = format_score_as_json(&self.results, &self.score);
+        if let Value::Object(map) = &mut value {
+            map.insert("path".to_string(), Value::from(self.path.as_str()));
+            map.insert("language".to_string(), Value::from(self.language.as_str()));
+            map.insert("line_count".to_string(), Value::from(self.line_count));
+            if let Some(grammar) = &self.grammar {
+                map.insert("grammar".to_string(), json!(grammar));
+            }
+        }
+        value
+    }
+}
+
+/// How per-file scores are combined into a project score.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectWeighting {
+    /// Each file counts in proportion to its line count, so a handful of
+    /// tiny files can't mask one large, problematic file.
+    #[default]
+    Lines,
+    /// Every file counts the same.
+    Equal,
+    /// Files count in proportion to the severity of their findings
+    /// (error 3, warning 2, info 1, plus 1 per file), pulling the project
+    /// score toward its worst files.
+    Severity,
+}
+
+impl ProjectWeighting {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lines" => Some(ProjectWeighting::Lines),
+            "equal" => Some(ProjectWeighting::Equal),
+            "severity" => Some(ProjectWeighting::Severity),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectWeighting::Lines => "lines",
+            ProjectWeighting::Equal => "equal",
+            ProjectWeighting::Severity => "severity",
+        }
+    }
+
+    fn weight(&self, file: &FileReport) -> f64 {
+        match self {
+            ProjectWeighting::Lines => file.line_count.max(1) as f64,
+            ProjectWeighting::Equal => 1.0,
+            ProjectWeighting::Severity => {
+                1.0 + file
+                    .results
+                    .iter()
+                    .map(|result| match result.severity {
+                        Severity::Error => 3.0,
+                        Severity::Warning => 2.0,
+                        Severity::Info => 1.0,
+                        Severity::Style => 0.0,
+                    })
+                    .sum::<f64>()
+            }
+        }
+    }
+}
+
+/// Per-file [`CodeScore`]s combined into one score for a whole project.
+#[derive(Debug, Clone)]
+pub struct ProjectScore {
+    /// Weighted mean of file scores, rounded to one decimal; `None` when no
+    /// files were analyzed.
+    pub overall_score: Option<f64>,
+    pub lowest_score: Option<f64>,
+    pub files_analyzed: usize,
+    pub total_lines: usize,
+    /// Code, comment, and blank lines summed over every file.
+    pub lines: LineCounts,
+    pub total_issues: usize,
+    /// Finding counts and deductions summed over every file.
+    pub breakdown: ScoreBreakdown,
+    pub weighting: ProjectWeighting,
+    pub rating: String,
+    pub summary: String,
+    /// Findings of comment rules by their `marker` label, such as `TODO`.
+    pub comment_markers: BTreeMap<String, usize>,
+}
+
+impl ProjectScore {
+    pub fn from_files(files: &[FileReport], weighting: ProjectWeighting) -> Self {
+        Self::from_file_refs(&files.iter().collect::<Vec<_>>(), weighting)
+    }
+
+    pub fn from_file_refs(files: &[&FileReport], weighting: ProjectWeighting) -> Self {
+        let mut tally = ProjectTally::new(weighting);
+        for file in files {
+            tally.add(file);
+        }
+        tally.score()
+    }
+
+    /// Rates the score with `ratings`, such as a config's `[ratings]`.
+    pub fn with_ratings(mut self, ratings: &Ratings) -> Self {
+        (self.rating, self.summary) = match self.overall_score {
+            Some(score) => ratings.rate(score, &self.breakdown),
+            None => ("N/A".to_string(), ratings.no_files_summary()),
+        };
+        self
+    }
+
+    /// The `summary` object of the aggregated schema.
+    pub fn to_json(&self) -> Value {
+        let mut summary = json!({
+            "files_analyzed": self.files_analyzed,
+            "total_lines": self.total_lines,
+            "lines": self.lines,
+            "average_score": self.overall_score,
+            "lowest_score": self.lowest_score,
+            "rating": self.rating,
+            "summary": self.summary,
+            "weighting": self.weighting.as_str(),
+            "total_issues": self.total_issues,
+            "errors": self.breakdown.errors,
+            "warnings": self.breakdown.warnings,
+            "info_issues": self.breakdown.info_issues,
+            "style_issues": self.breakdown.style_issues,
+        });
+        if !self.comment_markers.is_empty() {
+            summary["comment_markers"] = json!(self.comment_markers);
+        }
+        summary
+    }
+}
+
+/// A [`ProjectScore`] built up one file at a time, so runs that stream
+/// their files don't need to keep them.
+#[derive(Debug, Clone)]
+pub struct ProjectTally {
+    score: ProjectScore,
+    weighted_score: f64,
+    total_weight: f64,
+}
+
+impl ProjectTally {
+    pub fn new(weighting: ProjectWeighting) -> Self {
+        ProjectTally {
+            score: ProjectScore {
+                overall_score: None,
+                lowest_score: None,
+                files_analyzed: 0,
+                total_lines: 0,
+                lines: LineCounts::default(),
+                total_issues: 0,
+                breakdown: ScoreBreakdown::default(),
+                weighting,
+                rating: String::new(),
+                summary: String::new(),
+                comment_markers: BTreeMap::new(),
+            },
+            weighted_score: 0.0,
+            total_weight: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, file: &FileReport) {
+        let score = &mut self.score;
+        score.lines += file.score.lines;
+        let breakdown = &mut score.breakdown;
+        let file_breakdown = &file.score.breakdown;
+        breakdown.errors += file_breakdown.errors;
+        breakdown.warnings += file_breakdown.warnings;
+        breakdown.info_issues += file_breakdown.info_issues;
+        breakdown.style_issues += file_breakdown.style_issues;
+        breakdown.error_deduction += file_breakdown.error_deduction;
+        breakdown.warning_deduction += file_breakdown.warning_deduction;
+        breakdown.info_deduction += file_breakdown.info_deduction;
+        breakdown.style_deduction += file_breakdown.style_deduction;
+        breakdown.size_bonus += file_breakdown.size_bonus;
+        for (rule, capped) in &file_breakdown.capped_rules {
+            let total = breakdown.capped_rules.entry(rule.clone()).or_default();
+            total.uncapped_impact += capped.uncapped_impact;
+            total.max_total_impact += capped.max_total_impact;
+        }
+        let weight = score.weighting.weight(file);
+        self.weighted_score += file.score.overall_score * weight;
+        self.total_weight += weight;
+        score.lowest_score = Some(match score.lowest_score {
+            Some(lowest) => lowest.min(file.score.overall_score),
+            None => file.score.overall_score,
+        });
+        score.files_analyzed += 1;
+        score.total_lines += file.line_count;
+        score.total_issues += file.score.total_issues;
+        for (marker, count) in comment_markers([file]) {
+            *score.comment_markers.entry(marker).or_default() += count;
+        }
+    }
+
+    /// The score of the files added so far.
+    pub fn score(&self) -> ProjectScore {
+        ProjectScore {
+            overall_score: (self.score.files_analyzed > 0)
+                .then(|| (self.weighted_score / self.total_weight * 10.0).round() / 10.0),
+            ..self.score.clone()
+        }
+        .with_ratings(&Ratings::default())
+    }
+}
+
+/// How many findings carry each `marker` label, uppercased.
+pub fn comment_markers<'a>(
+    files: impl IntoIterator<Item = &'a FileReport>,
+) -> BTreeMap<String, usize> {
+    let mut markers = BTreeMap::new();
+    for result in files.into_iter().flat_map(|file| &file.results) {
+        if let Some(marker) = result.labels.get("marker") {
+            *markers.entry(marker.to_uppercase()).or_default() += 1;
+        }
+    }
+    markers
+}
+
+/// Aggregated report for a multi-file run:
+///
+/// ```json
+/// { "schema_version": 2, "files": [ ... ], "summary": { ... } }
+/// ```
+pub fn project_report(files: &[FileReport], weighting: ProjectWeighting) -> Value {
+    project_report_with(files, &ProjectScore::from_files(files, weighting))
+}
+
+/// [`project_report`] with the project `score` already worked out, for
+/// callers that rate it with a config's `[ratings]`.
+pub fn project_report_with(files: &[FileReport], score: &ProjectScore) -> Value {
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "files": files.iter().map(FileReport::to_json).collect::<Vec<_>>(),
+        "grammars": grammars(files),
+        "summary": score.to_json(),
+    })
+}
+
+/// Language name to the grammar its files were parsed with.
+pub fn grammars(files: &[FileReport]) -> BTreeMap<&str, &Grammar> {
+    files
+        .iter()
+        .filter_map(|file| Some((file.language.as_str(), file.grammar.as_ref()?)))
+        .collect()
+}
+
+pub fn project_summary(files: &[FileReport], weighting: ProjectWeighting) -> Value {
+    ProjectScore::from_files(files, weighting).to_json()
+}
+
+/// How identical findings are collapsed in JSON output.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputGrouping {
+    /// Every finding is listed.
+    #[default]
+    None,
+    /// Identical findings are grouped across every analyzed file.
+    Rule,
+    /// Identical findings are grouped within each file.
+    File,
+}
+
+impl OutputGrouping {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(OutputGrouping::None),
+            "rule" => Some(OutputGrouping::Rule),
+            "file" => Some(OutputGrouping::File),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputGrouping::None => "none",
+            OutputGrouping::Rule => "rule",
+            OutputGrouping::File => "file",
+        }
+    }
+}
+
+/// Collapses findings with the same rule and (whitespace-normalized) source
+/// text into one entry with a `count` and up to five locations, most
+/// frequent first.
+pub fn group_issues<'a>(
+    issues: impl IntoIterator<Item = (&'a str, &'a AnalysisResult)>,
+    include_path: bool,
+) -> Vec<Value> {
+    let mut groups: Vec<Vec<(&str, &AnalysisResult)>> = Vec::new();
+    let mut index_of: HashMap<(&str, String), usize> = HashMap::new();
+    for (path, result) in issues {
+        let text = result.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let index = *index_of
+            .entry((result.rule_name.as_str(), text))
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[index].push((path, result));
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+
+    groups
+        .iter()
+        .map(|group| {
+            let (_, first) = group[0];
+            let locations: Vec<Value> = group
+                .iter()
+                .take(REPRESENTATIVE_LOCATIONS)
+                .map(|(path, result)| {
+                    let mut location = json!({ "line": result.line, "column": result.column });
+                    if include_path {
+                        location["path"] = Value::from(*path);
+                    }
+                    location
+                })
+                .collect();
+            let mut entry = json!({
+                "rule": first.rule_name,
+                "severity": first.severity,
+                "message": first.message,
+                "text": first.text,
+                "suggestion": first.suggestion,
+                "count": group.len(),
+                "score_impact": group.iter().map(|(_, result)| result.score_impact).sum::<f64>(),
+                "locations": locations,
+            });
+            if let Some(example) = &first.example_bad {
+                entry["example_bad"] = Value::from(example.as_str());
+            }
+            if let Some(example) = &first.example_good {
+                entry["example_good"] = Value::from(example.as_str());
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Replaces the `issues` of a single-file or aggregated report with grouped
+/// findings. [`OutputGrouping::Rule`] moves them from each file to a
+/// top-level `issues` array of an aggregated report.
+pub fn apply_grouping(report: &mut Value, files: &[FileReport], grouping: OutputGrouping) {
+    if grouping == OutputGrouping::None {
+        return;
+    }
+    report["group_by"] = Value::from(grouping.as_str());
+
+    let Some(entries) = report.get_mut("files").and_then(Value::as_array_mut) else {
+        let issues = files.iter().flat_map(|file| {
+            file.results
+                .iter()
+                .map(move |result| (file.path.as_str(), result))
+        });
+        report["issues"] = Value::from(group_issues(issues, false));
+        return;
+    };
+
+    match grouping {
+        OutputGrouping::File => {
+            for (entry, file) in entries.iter_mut().zip(files) {
+                let issues = file
+                    .results
+                    .iter()
+                    .map(|result| (file.path.as_str(), result));
+                entry["issues"] = Value::from(group_issues(issues, false));
+            }
+        }
+        _ => {
+            for entry in entries.iter_mut() {
+                if let Value::Object(map) = entry {
+                    map.remove("issues");
+                }
+            }
+            let issues = files.iter().flat_map(|file| {
+                file.results
+                    .iter()
+                    .map(move |result| (file.path.as_str(), result))
+            });
+            report["issues"] = Value::from(group_issues(issues, true));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Rule `scope`: which code of a file a rule looks at. A finding is kept
+//! or dropped by the node it flags: inside a function, inside test code,
+//! or outside test code. Test code is a whole test file (by its path) or a
+//! test construct within a file, such as a Rust `#[test]` function or
+//! `#[cfg(test)]` module, or a Java `@Test` method.
+
+use crate::hotspots::FUNCTION_KINDS;
+use crate::workspace::SourceSet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use tree_sitter::Node;
+
+/// The part of a file a rule applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleScope {
+    /// Code inside a function, method, or closure body.
+    Function,
+    /// Test files and test functions or modules.
+    Test,
+    /// Everything but test code.
+    NonTest,
+}
+
+/// Anonymous functions, on top of the named ones of the hotspots table.
+const CLOSURE_KINDS: &[&str] = &[
+    "closure_expression",
+    "arrow_function",
+    "function_expression",
+    "lambda_expression",
+];
+
+/// Directories holding only tests.
+const TEST_DIRS: &[&str] = &["tests", "__tests__"];
+
+/// Whether the file at `path` is test code by its name or location:
+/// `tests/` and `__tests__/` directories, Maven's `src/test`, Go's
+/// `*_test.go` (and the same suffix in other languages), JavaScript's
+/// `*.test.js` and `*.spec.js`, `test_*` files, and Java and Swift
+/// `*Test`/`*Tests` classes.
+pub fn is_test_file<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    if SourceSet::of(path) == SourceSet::Test {
+        return true;
+    }
+    let in_test_dir = path.parent().is_some_and(|parent| {
+        parent
+            .components()
+            .any(|component| TEST_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+    });
+    if in_test_dir {
+        return true;
+    }
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or(name);
+    let inner_extension = name
+        .split('.')
+        .nth(1)
+        .filter(|_| name.matches('.').count() > 1);
+    stem.ends_with("_test")
+        || stem.starts_with("test_")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+        || matches!(inner_extension, Some("test" | "spec"))
+}
+
+/// The functions and test constructs of one parsed file, to check
+/// findings against a rule's scope.
+#[derive(Debug, Clone, Default)]
+pub struct Scopes {
+    functions: Vec<Range<usize>>,
+    tests: Vec<Range<usize>>,
+    test_file: bool,
+}
+
+impl Scopes {
+    /// Collects the function and test byte ranges under `root`.
+    /// `test_file` marks the whole file as test code.
+    pub fn of(root: Node<'_>, source: &[u8], test_file: bool) -> Self {
+        let mut scopes = Scopes {
+            test_file,
+            ..Scopes::default()
+        };
+        let mut cursor = root.walk();
+        loop {
+            let node = cursor.node();
+            let kind = node.kind();
+            if CLOSURE_KINDS.contains(&kind)
+                || FUNCTION_KINDS
+                    .iter()
+                    .any(|(_, kinds)| kinds.contains(&kind))
+            {
+                scopes.functions.push(node.byte_range());
+            }
+            if is_test_item(node, source) {
+                scopes.tests.push(node.byte_range());
+            }
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            let mut done = true;
+            while cursor.goto_parent() {
+                if cursor.goto_next_sibling() {
+                    done = false;
+                    break;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+        scopes
+    }
+
+    /// Whether a node spanning `bytes` is within `scope`.
+    pub fn contains(&self, scope: RuleScope, bytes: Range<usize>) -> bool {
+        let within = |ranges: &[Range<usize>]| {
+            ranges
+                .iter()
+                .any(|range| range.start <= bytes.start && bytes.end <= range.end)
+        };
+        match scope {
+            RuleScope::Function => within(&self.functions),
+            RuleScope::Test => self.test_file || within(&self.tests),
+            RuleScope::NonTest => !self.test_file && !within(&self.tests),
+        }
+    }
+}
+
+/// A Rust function or module under a test attribute, or a Java method
+/// annotated as a test.
+fn is_test_item(node: Node<'_>, source: &[u8]) -> bool {
+    match node.kind() {
+        "function_item" | "mod_item" => {
+            let mut sibling = node.prev_named_sibling();
+            while let Some(attribute) = sibling.filter(|sibling| sibling.kind() == "attribute_item")
+            {
+                if attribute
+                    .utf8_text(source)
+                    .is_ok_and(|text| is_test_attribute(text, node.kind() == "function_item"))
+                {
+                    return true;
+                }
+                sibling = attribute.prev_named_sibling();
+            }
+            false
+        }
+        "method_declaration" => {
+            let Some(modifiers) = node
+                .named_child(0)
+                .filter(|child| child.kind() == "modifiers")
+            else {
+                return false;
+            };
+            let mut cursor = modifiers.walk();
+            let is_test = modifiers.named_children(&mut cursor).any(|modifier| {
+                matches!(modifier.kind(), "marker_annotation" | "annotation")
+                    && modifier
+                        .child_by_field_name("name")
+                        .and_then(|name| name.utf8_text(source).ok())
+                        .is_some_and(|name| {
+                            name.rsplit('.').next().unwrap_or(name).ends_with("Test")
+                        })
+            });
+            is_test
+        }
+        _ => false,
+    }
+}
+
+/// `#[cfg(test)]`, or on a function any attribute whose path ends in
+/// `test`: `#[test]`, `#[tokio::test]`, `#[test_case(..)]`.
+fn is_test_attribute(text: &str, function: bool) -> bool {
+    let inner = text.trim_start_matches("#[").trim_end_matches(']').trim();
+    if inner.replace(' ', "") == "cfg(test)" {
+        return true;
+    }
+    let path = inner.split('(').next().unwrap_or(inner).trim();
+    function
+        && path
+            .rsplit("::")
+            .next()
+            .is_some_and(|last| last.starts_with("test"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "lang-rust")]
+    use tree_sitter::Parser;
+
+    #[test]
+    fn test_test_files_are_recognized_by_name_and_directory() {
+        for path in [
+            "pkg/server_test.go",
+            "tests/integration.rs",
+            "web/__tests__/app.js",
+            "web/app.test.js",
+            "web/app.spec.ts",
+            "core/src/test/java/App.java",
+            "core/src/main/java/AppTest.java",
+            "Sources/AppTests.swift",
+            "test_parser.cpp",
+        ] {
+            assert!(is_test_file(path), "{}", path);
+        }
+        for path in [
+            "pkg/server.go",
+            "src/contest.rs",
+            "web/app.js",
+            "src/latest.rs",
+        ] {
+            assert!(!is_test_file(path), "{}", path);
+        }
+    }
+
+    #[cfg(feature = "lang-rust")]
+    #[test]
+    fn test_scopes_find_rust_test_functions_and_modules() {
+        let source = "fn main() { run(); }\nconst X: i32 = 1;\n#[test]\nfn checks() { run(); }\n#[cfg(test)]\nmod tests {\n    fn helper() {}\n}\n";
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_rust::LANGUAGE.into())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let scopes = Scopes::of(tree.root_node(), source.as_bytes(), false);
+        let at = |snippet: &str, nth: usize| {
+            let start = source.match_indices(snippet).nth(nth).unwrap().0;
+            start..start + snippet.len()
+        };
+
+        assert!(scopes.contains(RuleScope::Function, at("run()", 0)));
+        assert!(scopes.contains(RuleScope::NonTest, at("run()", 0)));
+        assert!(!scopes.contains(RuleScope::Function, at("1", 0)));
+        assert!(scopes.contains(RuleScope::Test, at("run()", 1)));
+        assert!(!scopes.contains(RuleScope::NonTest, at("run()", 1)));
+        assert!(scopes.contains(RuleScope::Test, at("helper", 0)));
+
+        let test_file = Scopes::of(tree.root_node(), source.as_bytes(), true);
+        assert!(test_file.contains(RuleScope::Test, at("run()", 0)));
+        assert!(!test_file.contains(RuleScope::NonTest, at("1", 0)));
+    }
+}
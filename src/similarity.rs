@@ -0,0 +1,161 @@
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use tree_sitter::{Language, Node, Parser};
+
+/// Smallest subtree, in named nodes, that counts as a fingerprint. Smaller
+/// fragments (a lone call or assignment) are common to unrelated code.
+const MIN_SUBTREE_NODES: usize = 10;
+
+/// Structural fingerprints of one submission.
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprints {
+    pub student: String,
+    hashes: HashSet<u64>,
+}
+
+impl Fingerprints {
+    pub fn new(student: &str) -> Self {
+        Fingerprints {
+            student: student.to_string(),
+            hashes: HashSet::new(),
+        }
+    }
+
+    /// Adds the subtree hashes of one source file. Hashes cover node kinds
+    /// only, so renaming identifiers or changing literals and comments does
+    /// not hide copied code.
+    pub fn add_source(
+        &mut self,
+        source: &str,
+        language: &Language,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut parser = Parser::new();
+        parser.set_language(language)?;
+        let tree = parser.parse(source, None).ok_or("failed to parse source")?;
+        hash_subtree(tree.root_node(), &mut self.hashes);
+        Ok(())
+    }
+}
+
+fn hash_subtree(node: Node<'_>, hashes: &mut HashSet<u64>) -> (u64, usize) {
+    let mut hasher = DefaultHasher::new();
+    node.kind_id().hash(&mut hasher);
+    let mut size = 1;
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        if child.kind().contains("comment") {
+            continue;
+        }
+        let (hash, child_size) = hash_subtree(child, hashes);
+        hash.hash(&mut hasher);
+        size += child_size;
+    }
+    let hash = hasher.finish();
+    if size >= MIN_SUBTREE_NODES {
+        hashes.insert(hash);
+    }
+    (hash, size)
+}
+
+/// Two submissions sharing an unusual share of their structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPair {
+    pub first: String,
+    pub second: String,
+    /// Jaccard similarity of the fingerprint sets, from 0 to 1.
+    pub similarity: f64,
+    pub shared_subtrees: usize,
+}
+
+impl SimilarPair {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "first": self.first,
+            "second": self.second,
+            "similarity": (self.similarity * 1000.0).round() / 1000.0,
+            "shared_subtrees": self.shared_subtrees,
+        })
+    }
+}
+
+/// Pairs of submissions at least `threshold` similar, most similar first.
+///
+/// Subtrees found in more than half of the submissions, and in at least
+/// three, are treated as starter code or boilerplate and ignored.
+pub fn similar_pairs(submissions: &[Fingerprints], threshold: f64) -> Vec<SimilarPair> {
+    let mut frequency: HashMap<u64, usize> = HashMap::new();
+    for submission in submissions {
+        for hash in &submission.hashes {
+            *frequency.entry(*hash).or_default() += 1;
+        }
+    }
+    let common = |hash: &u64| frequency[hash] >= 3 && frequency[hash] * 2 > submissions.len();
+    let distinctive: Vec<HashSet<u64>> = submissions
+        .iter()
+        .map(|submission| {
+            submission
+                .hashes
+                .iter()
+                .filter(|hash| !common(hash))
+                .copied()
+                .collect()
+        })
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, first) in distinctive.iter().enumerate() {
+        for (j, second) in distinctive.iter().enumerate().skip(i + 1) {
+            let shared = first.intersection(second).count();
+            let union = first.len() + second.len() - shared;
+            if union == 0 {
+                continue;
+            }
+            let similarity = shared as f64 / union as f64;
+            if similarity >= threshold {
+                pairs.push(SimilarPair {
+                    first: submissions[i].student.clone(),
+                    second: submissions[j].student.clone(),
+                    similarity,
+                    shared_subtrees: shared,
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    pairs
+}
+
+#[cfg(all(test, feature = "lang-go"))]
+mod tests {
+    use super::*;
+
+    fn fingerprints(student: &str, source: &str) -> Fingerprints {
+        let mut fingerprints = Fingerprints::new(student);
+        fingerprints
+            .add_source(source, &tree_sitter_go::LANGUAGE.into())
+            .unwrap();
+        fingerprints
+    }
+
+    #[test]
+    fn test_renamed_copy_is_flagged() {
+        let original = "package main\n\nfunc sum(values []int) int {\n\ttotal := 0\n\tfor _, v := range values {\n\t\tif v > 0 {\n\t\t\ttotal += v\n\t\t}\n\t}\n\treturn total\n}\n";
+        let renamed = "package main\n\n// Adds things up.\nfunc add(xs []int) int {\n\tacc := 0\n\tfor _, x := range xs {\n\t\tif x > 1 {\n\t\t\tacc += x\n\t\t}\n\t}\n\treturn acc\n}\n";
+        let different = "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tnames := map[string]int{\"a\": 1}\n\tfor name := range names {\n\t\tfmt.Println(name, len(name))\n\t}\n}\n";
+
+        let submissions = [
+            fingerprints("alice", original),
+            fingerprints("bob", renamed),
+            fingerprints("carol", different),
+        ];
+        let pairs = similar_pairs(&submissions, 0.8);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            (pairs[0].first.as_str(), pairs[0].second.as_str()),
+            ("alice", "bob")
+        );
+        assert_eq!(pairs[0].similarity, 1.0);
+    }
+}
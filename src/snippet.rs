@@ -0,0 +1,136 @@
+//! What a finding quotes of the code it flags: the `[snippets]` config
+//! table cuts [`AnalysisResult::text`](crate::analyzer::AnalysisResult)
+//! down to a first line or a number of characters, and can attach the
+//! lines around each finding for formats that show code.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Appended to text that was cut short.
+const ELLIPSIS: &str = "…";
+
+/// How much of the flagged text a finding keeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SnippetMode {
+    /// All of it, up to [`MAX_TEXT_LEN`](crate::analyzer::MAX_TEXT_LEN).
+    #[default]
+    Full,
+    /// The first line, with an ellipsis when there is more.
+    FirstLine,
+    /// The first `max_chars` characters, with an ellipsis when there is more.
+    Chars,
+}
+
+/// The `[snippets]` config table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnippetOptions {
+    pub mode: SnippetMode,
+    /// Characters kept in `chars` mode.
+    pub max_chars: usize,
+    /// Source lines attached before and after the line each finding starts
+    /// on; 0 attaches none.
+    pub context_lines: usize,
+}
+
+impl Default for SnippetOptions {
+    fn default() -> Self {
+        SnippetOptions {
+            mode: SnippetMode::Full,
+            max_chars: 80,
+            context_lines: 0,
+        }
+    }
+}
+
+impl SnippetOptions {
+    /// The part of `text` a finding keeps.
+    pub fn extract(&self, text: &str) -> String {
+        match self.mode {
+            SnippetMode::Full => text.to_string(),
+            SnippetMode::FirstLine => {
+                let first = text.lines().next().unwrap_or_default();
+                if first.len() < text.trim_end().len() {
+                    format!("{}{}", first.trim_end(), ELLIPSIS)
+                } else {
+                    first.to_string()
+                }
+            }
+            SnippetMode::Chars => match text.char_indices().nth(self.max_chars) {
+                Some((cut, _)) => format!("{}{}", &text[..cut], ELLIPSIS),
+                None => text.to_string(),
+            },
+        }
+    }
+}
+
+/// Characters of a redacted finding's text that are kept.
+const REDACTED_CHARS: usize = 4;
+
+/// The start of `text`, for findings whose text must not be repeated.
+pub fn redact(text: &str) -> String {
+    match text.char_indices().nth(REDACTED_CHARS) {
+        Some((cut, _)) => format!("{}{}", &text[..cut], ELLIPSIS),
+        None => ELLIPSIS.to_string(),
+    }
+}
+
+/// Source lines around a finding.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Context {
+    /// 1-based line number of `lines[0]`.
+    pub start_line: usize,
+    pub lines: Vec<String>,
+}
+
+/// Up to `radius` of `lines` on each side of 1-based `line`, and the line
+/// itself.
+pub fn context(lines: &[&str], line: usize, radius: usize) -> Context {
+    let start = line.saturating_sub(radius).max(1);
+    let end = (line + radius).min(lines.len());
+    Context {
+        start_line: start,
+        lines: lines
+            .get(start - 1..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|line| line.to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippets_cut_to_first_line_or_chars_and_take_context() {
+        let body = "fn main() {\n    run();\n}";
+        let first_line = SnippetOptions {
+            mode: SnippetMode::FirstLine,
+            ..SnippetOptions::default()
+        };
+        assert_eq!(first_line.extract(body), "fn main() {…");
+        assert_eq!(first_line.extract("run();\n"), "run();");
+
+        let chars = SnippetOptions {
+            mode: SnippetMode::Chars,
+            max_chars: 4,
+            ..SnippetOptions::default()
+        };
+        assert_eq!(chars.extract("é😀abc"), "é😀ab…");
+        assert_eq!(chars.extract("abcd"), "abcd");
+        assert_eq!(SnippetOptions::default().extract(body), body);
+
+        let lines: Vec<&str> = "a\nb\nc\nd\n".lines().collect();
+        assert_eq!(
+            context(&lines, 1, 1),
+            Context {
+                start_line: 1,
+                lines: vec!["a".to_string(), "b".to_string()]
+            }
+        );
+        assert_eq!(context(&lines, 3, 2).lines, ["a", "b", "c", "d"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Source files as text, whatever their encoding. Invalid UTF-8 is decoded
+//! lossily, with U+FFFD in place of each bad sequence, and reported as an
+//! `invalid_utf8` finding instead of failing the run.
+
+use crate::analyzer::{AnalysisResult, Confidence, Severity};
+use crate::position::Columns;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Rule name of the finding reported for files that are not valid UTF-8.
+pub const INVALID_UTF8_RULE: &str = "invalid_utf8";
+
+/// Leading bytes searched for NUL, which text files don't contain.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Default `max_file_size`, in bytes. Larger files are almost always
+/// generated, minified, or vendored.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// A decoded source file.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub text: String,
+    /// Byte offset of the first invalid UTF-8 sequence, if any.
+    pub invalid_at: Option<usize>,
+    /// Whether the file looks binary rather than text.
+    pub binary: bool,
+}
+
+impl SourceFile {
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let binary = bytes.iter().take(BINARY_SNIFF_LEN).any(|&byte| byte == 0);
+        match String::from_utf8(bytes) {
+            Ok(text) => SourceFile {
+                text,
+                invalid_at: None,
+                binary,
+            },
+            Err(e) => {
+                let invalid_at = e.utf8_error().valid_up_to();
+                SourceFile {
+                    text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                    invalid_at: Some(invalid_at),
+                    binary,
+                }
+            }
+        }
+    }
+
+    /// Whether the text differs from the bytes on disk or the file is
+    /// binary, so it should neither be rewritten nor, with
+    /// `skip_undecodable`, scanned.
+    pub fn is_undecodable(&self) -> bool {
+        self.binary || self.invalid_at.is_some()
+    }
+
+    /// The `invalid_utf8` warning for a lossily decoded file, at the first
+    /// invalid byte. It does not lower the score.
+    pub fn decoding_warning(&self) -> Option<AnalysisResult> {
+        let invalid_at = self.invalid_at?;
+        // Everything before the first invalid byte decoded unchanged.
+        let line = self.text[..invalid_at].matches('\n').count() + 1;
+        let columns = Columns::at(&self.text, invalid_at);
+        Some(AnalysisResult {
+            rule_name: INVALID_UTF8_RULE.to_string(),
+            severity: Severity::Warning,
+            message: "File is not valid UTF-8; invalid bytes were read as U+FFFD".to_string(),
+            line,
+            column: columns.byte,
+            columns,
+            end_line: line,
+            end_columns: columns,
+            start_byte: invalid_at,
+            end_byte: invalid_at,
+            text: String::new(),
+            context: None,
+            suggestion: Some(
+                "Save the file as UTF-8, or skip it with `exclude` or `skip_undecodable`."
+                    .to_string(),
+            ),
+            score_impact: 0.0,
+            confidence: Confidence::High,
+            labels: BTreeMap::new(),
+            example_bad: None,
+            example_good: None,
+            fix: None,
+        })
+    }
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<SourceFile> {
+    Ok(SourceFile::from_bytes(fs::read(path)?))
+}
+
+/// The size of the file at `path` if it is over `max_file_size` bytes,
+/// checked without reading it. A limit of 0 disables the check.
+pub fn oversized<P: AsRef<Path>>(path: P, max_file_size: u64) -> Option<u64> {
+    let size = fs::metadata(path).ok()?.len();
+    (max_file_size > 0 && size > max_file_size).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_utf8_is_decoded_lossily_and_located() {
+        let source = SourceFile::from_bytes(b"let a = 1;\nlet s = \"caf\xe9\";\n".to_vec());
+        assert_eq!(source.text, "let a = 1;\nlet s = \"caf\u{fffd}\";\n");
+        assert!(source.is_undecodable());
+        assert!(!source.binary);
+        let warning = source.decoding_warning().unwrap();
+        assert_eq!((warning.line, warning.column), (2, 13));
+
+        // Columns count bytes, like every other finding's
+        let source = SourceFile::from_bytes(b"\xc3\xa9 = \"\xe9\"".to_vec());
+        assert_eq!(source.decoding_warning().unwrap().column, 7);
+
+        let source = SourceFile::from_bytes("let s = \"café\";\n".as_bytes().to_vec());
+        assert!(!source.is_undecodable());
+        assert!(source.decoding_warning().is_none());
+
+        assert!(SourceFile::from_bytes(b"\x7fELF\x02\x01\x00\x00".to_vec()).binary);
+    }
+}
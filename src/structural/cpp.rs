@@ -0,0 +1,487 @@
+use super::{descendants, text};
+use std::collections::{BTreeMap, BTreeSet};
+use tree_sitter::Node;
+
+const SMART_POINTERS: &[&str] = &["unique_ptr", "shared_ptr"];
+
+/// `new` expressions whose result is not handed straight to a smart
+/// pointer (`std::unique_ptr<T>(new T)`, `std::shared_ptr<T> p(new T)`,
+/// or `ptr.reset(new T)`).
+pub(super) fn new_without_smart_pointer<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "new_expression")
+        .filter(|new| !wrapped_in_smart_pointer(*new, source))
+        .collect()
+}
+
+fn wrapped_in_smart_pointer(new: Node<'_>, source: &[u8]) -> bool {
+    let Some(arguments) = new
+        .parent()
+        .filter(|parent| parent.kind() == "argument_list")
+    else {
+        return false;
+    };
+    let Some(owner) = arguments.parent() else {
+        return false;
+    };
+
+    match owner.kind() {
+        "call_expression" => owner
+            .child_by_field_name("function")
+            .is_some_and(|function| match function.child_by_field_name("field") {
+                Some(field) => text(field, source) == "reset",
+                None => names_smart_pointer(function, source),
+            }),
+        "init_declarator" => owner
+            .parent()
+            .and_then(|declaration| declaration.child_by_field_name("type"))
+            .is_some_and(|ty| names_smart_pointer(ty, source)),
+        _ => false,
+    }
+}
+
+fn names_smart_pointer(node: Node<'_>, source: &[u8]) -> bool {
+    let name = text(node, source);
+    SMART_POINTERS.iter().any(|pointer| name.contains(pointer))
+}
+
+/// Member functions that override a virtual function of a base class
+/// defined in the same file without saying `override` (or `final`).
+pub(super) fn missing_override<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    let classes: Vec<Node<'tree>> = descendants(root)
+        .into_iter()
+        .filter(|node| is_class(*node) && node.child_by_field_name("body").is_some())
+        .collect();
+
+    let mut virtuals: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut bases: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for class in &classes {
+        let Some(name) = class.child_by_field_name("name") else {
+            continue;
+        };
+        let name = text(name, source);
+        let declared = virtuals.entry(name).or_default();
+        for member in members(*class) {
+            if has_child(member, "virtual") {
+                if let Some(method) = method_name(member, source) {
+                    declared.insert(method);
+                }
+            }
+        }
+        bases.insert(name, base_names(*class, source));
+    }
+
+    let mut flagged = Vec::new();
+    for class in &classes {
+        let Some(name) = class.child_by_field_name("name") else {
+            continue;
+        };
+        let inherited = inherited_virtuals(text(name, source), &bases, &virtuals);
+        if inherited.is_empty() {
+            continue;
+        }
+        for member in members(*class) {
+            let Some(declarator) = function_declarator(member) else {
+                continue;
+            };
+            let overrides =
+                method_name(member, source).is_some_and(|method| inherited.contains(method));
+            if overrides && !has_child(declarator, "virtual_specifier") {
+                flagged.push(member);
+            }
+        }
+    }
+    flagged
+}
+
+/// Virtual functions declared by the (transitive) bases of `class`.
+fn inherited_virtuals<'a>(
+    class: &str,
+    bases: &BTreeMap<&str, Vec<&'a str>>,
+    virtuals: &BTreeMap<&str, BTreeSet<&'a str>>,
+) -> BTreeSet<&'a str> {
+    let mut inherited = BTreeSet::new();
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<&str> = bases.get(class).cloned().unwrap_or_default();
+    while let Some(base) = pending.pop() {
+        if !seen.insert(base) {
+            continue;
+        }
+        if let Some(methods) = virtuals.get(base) {
+            inherited.extend(methods.iter().copied());
+        }
+        if let Some(grand_bases) = bases.get(base) {
+            pending.extend(grand_bases.iter().copied());
+        }
+    }
+    inherited
+}
+
+/// Classes that declare some of the special member functions that manage
+/// a resource but not the rest: a destructor, copy constructor, or copy
+/// assignment needs all three, and a move operation needs all five. A
+/// defaulted destructor on its own (the usual polymorphic base) is fine.
+pub(super) fn rule_of_five<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| is_class(*node))
+        .filter_map(|class| {
+            let name = class.child_by_field_name("name")?;
+            class.child_by_field_name("body")?;
+            let declared = special_members(class, text(name, source), source);
+            let copy_ops = [
+                declared.destructor,
+                declared.copy_constructor,
+                declared.copy_assignment,
+            ];
+            let move_ops = [declared.move_constructor, declared.move_assignment];
+
+            let incomplete = if move_ops.iter().any(|&op| op) {
+                !copy_ops.iter().chain(&move_ops).all(|&op| op)
+            } else {
+                copy_ops.iter().any(|&op| op) && !copy_ops.iter().all(|&op| op)
+            };
+            incomplete.then_some(name)
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct SpecialMembers {
+    destructor: bool,
+    copy_constructor: bool,
+    copy_assignment: bool,
+    move_constructor: bool,
+    move_assignment: bool,
+}
+
+fn special_members(class: Node<'_>, class_name: &str, source: &[u8]) -> SpecialMembers {
+    let mut declared = SpecialMembers::default();
+    for member in members(class) {
+        let Some(declarator) = function_declarator(member) else {
+            continue;
+        };
+        let Some(name) = declarator.child_by_field_name("declarator") else {
+            continue;
+        };
+
+        if name.kind() == "destructor_name" {
+            declared.destructor |= !has_child(member, "default_method_clause");
+            continue;
+        }
+
+        let is_constructor = matches!(name.kind(), "identifier" | "field_identifier")
+            && text(name, source) == class_name;
+        let is_assignment = name.kind() == "operator_name"
+            && text(name, source).split_whitespace().collect::<String>() == "operator=";
+        if !is_constructor && !is_assignment {
+            continue;
+        }
+
+        match own_type_reference(declarator, class_name, source) {
+            Some("&") if is_constructor => declared.copy_constructor = true,
+            Some("&") => declared.copy_assignment = true,
+            Some("&&") if is_constructor => declared.move_constructor = true,
+            Some("&&") => declared.move_assignment = true,
+            _ => {}
+        }
+    }
+    declared
+}
+
+/// `&` or `&&` when the function's only parameter is a reference to
+/// `class_name`.
+fn own_type_reference<'a>(
+    declarator: Node<'_>,
+    class_name: &str,
+    source: &'a [u8],
+) -> Option<&'a str> {
+    let parameters = declarator.child_by_field_name("parameters")?;
+    if parameters.named_child_count() != 1 {
+        return None;
+    }
+    let parameter = parameters.named_child(0)?;
+    let ty = parameter.child_by_field_name("type")?;
+    if text(ty, source) != class_name {
+        return None;
+    }
+    let reference = parameter.child_by_field_name("declarator")?;
+    if !matches!(
+        reference.kind(),
+        "reference_declarator" | "abstract_reference_declarator"
+    ) {
+        return None;
+    }
+    Some(text(reference.child(0)?, source))
+}
+
+/// Functions the header declares that neither it nor its source file
+/// defines. Pure virtual, template, and friend declarations are skipped.
+pub(super) fn declared_not_defined<'tree>(
+    header: Node<'tree>,
+    source: &[u8],
+    counterpart: Node<'_>,
+    counterpart_source: &[u8],
+) -> Vec<Node<'tree>> {
+    let declared = functions(header, source);
+    let defined: Vec<String> = declared
+        .iter()
+        .chain(&functions(counterpart, counterpart_source))
+        .filter(|function| function.defined)
+        .map(|function| function.name.clone())
+        .collect();
+    declared
+        .into_iter()
+        .filter(|function| function.required)
+        .filter(|function| {
+            !defined
+                .iter()
+                .any(|name| same_function(name, &function.name))
+        })
+        .map(|function| function.node)
+        .collect()
+}
+
+/// Functions the source file defines with external linkage that its header
+/// does not declare. `main`, `static` functions, anonymous namespaces,
+/// templates, and class bodies local to the source file are skipped.
+pub(super) fn defined_not_declared<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+    header: Node<'_>,
+    header_source: &[u8],
+) -> Vec<Node<'tree>> {
+    let declared: Vec<String> = functions(header, header_source)
+        .into_iter()
+        .map(|function| function.name)
+        .collect();
+    functions(root, source)
+        .into_iter()
+        .filter(|function| function.defined && function.exported && function.name != "main")
+        .filter(|function| {
+            !declared
+                .iter()
+                .any(|name| same_function(name, &function.name))
+        })
+        .map(|function| function.node)
+        .collect()
+}
+
+/// A function declaration or definition, named with its enclosing
+/// namespaces and classes (`ui::Widget::draw`).
+struct Function<'tree> {
+    name: String,
+    /// The declaration, or the declarator of a definition.
+    node: Node<'tree>,
+    defined: bool,
+    /// A declaration the pair must define: not a template or friend.
+    required: bool,
+    /// Defined at namespace scope with external linkage.
+    exported: bool,
+}
+
+fn functions<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Function<'tree>> {
+    let mut found = Vec::new();
+    collect_functions(root, source, &mut Vec::new(), false, &mut found);
+    found
+}
+
+/// `scope` holds enclosing namespace and class names with whether each is
+/// a class; an anonymous namespace is an empty name.
+fn collect_functions<'tree>(
+    node: Node<'tree>,
+    source: &[u8],
+    scope: &mut Vec<(String, bool)>,
+    template: bool,
+    found: &mut Vec<Function<'tree>>,
+) {
+    let scoped = match node.kind() {
+        "namespace_definition" => Some((
+            node.child_by_field_name("name")
+                .map(|name| text(name, source).to_string())
+                .unwrap_or_default(),
+            false,
+        )),
+        _ if is_class(node) => node
+            .child_by_field_name("name")
+            .map(|name| (text(name, source).to_string(), true)),
+        _ => None,
+    };
+    if let Some(entry) = scoped {
+        scope.push(entry);
+        if let Some(body) = node.child_by_field_name("body") {
+            collect_functions(body, source, scope, template, found);
+        }
+        scope.pop();
+        return;
+    }
+
+    match node.kind() {
+        "function_definition" => {
+            let Some(declarator) = function_declarator(node) else {
+                return;
+            };
+            let is_static = has_storage_class(node, "static", source);
+            let in_class = scope.iter().any(|(_, class)| *class);
+            let anonymous = scope.iter().any(|(name, _)| name.is_empty());
+            if let Some(name) = function_name(declarator, scope, source) {
+                found.push(Function {
+                    name,
+                    node: declarator,
+                    defined: true,
+                    required: false,
+                    exported: !is_static && !in_class && !anonymous && !template,
+                });
+            }
+        }
+        "declaration" | "field_declaration" => {
+            let pure_virtual = node.child_by_field_name("default_value").is_some();
+            if let Some(declarator) = function_declarator(node).filter(|_| !pure_virtual) {
+                if let Some(name) = function_name(declarator, scope, source) {
+                    found.push(Function {
+                        name,
+                        node,
+                        defined: false,
+                        required: !template,
+                        exported: false,
+                    });
+                }
+            }
+        }
+        // Friends are declared in the enclosing namespace, and may be
+        // defined anywhere.
+        "friend_declaration" => {
+            let mut cursor = node.walk();
+            for declaration in node.named_children(&mut cursor) {
+                if let Some(declarator) = function_declarator(declaration) {
+                    let outer: Vec<(String, bool)> =
+                        scope.iter().filter(|(_, class)| !class).cloned().collect();
+                    if let Some(name) = function_name(declarator, &outer, source) {
+                        found.push(Function {
+                            name,
+                            node: declaration,
+                            defined: false,
+                            required: false,
+                            exported: false,
+                        });
+                    }
+                }
+            }
+        }
+        "compound_statement" => {}
+        _ => {
+            let template = template || node.kind() == "template_declaration";
+            let mut cursor = node.walk();
+            let children: Vec<Node<'tree>> = node.named_children(&mut cursor).collect();
+            for child in children {
+                collect_functions(child, source, scope, template, found);
+            }
+        }
+    }
+}
+
+fn function_name(declarator: Node<'_>, scope: &[(String, bool)], source: &[u8]) -> Option<String> {
+    let name = declarator.child_by_field_name("declarator")?;
+    let name: String = text(name, source).split_whitespace().collect();
+    let mut qualified: Vec<&str> = scope
+        .iter()
+        .map(|(scope, _)| scope.as_str())
+        .filter(|scope| !scope.is_empty())
+        .collect();
+    qualified.push(&name);
+    Some(qualified.join("::"))
+}
+
+/// Whether two qualified names refer to the same function, letting the
+/// less qualified one (as written after `using namespace`) match as a
+/// suffix.
+fn same_function(a: &str, b: &str) -> bool {
+    let (longer, shorter) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    longer == shorter
+        || longer
+            .strip_suffix(shorter)
+            .is_some_and(|prefix| prefix.ends_with("::"))
+}
+
+fn has_storage_class(node: Node<'_>, class: &str, source: &[u8]) -> bool {
+    let mut cursor = node.walk();
+    let found = node
+        .children(&mut cursor)
+        .any(|child| child.kind() == "storage_class_specifier" && text(child, source) == class);
+    found
+}
+
+fn is_class(node: Node<'_>) -> bool {
+    matches!(node.kind(), "class_specifier" | "struct_specifier")
+}
+
+/// Direct member declarations and definitions of a class body.
+fn members(class: Node<'_>) -> Vec<Node<'_>> {
+    let Some(body) = class.child_by_field_name("body") else {
+        return Vec::new();
+    };
+    let mut cursor = body.walk();
+    body.named_children(&mut cursor)
+        .filter(|member| {
+            matches!(
+                member.kind(),
+                "field_declaration" | "function_definition" | "declaration"
+            )
+        })
+        .collect()
+}
+
+fn base_names<'a>(class: Node<'_>, source: &'a [u8]) -> Vec<&'a str> {
+    let mut cursor = class.walk();
+    let Some(clause) = class
+        .children(&mut cursor)
+        .find(|child| child.kind() == "base_class_clause")
+    else {
+        return Vec::new();
+    };
+    let mut cursor = clause.walk();
+    clause
+        .named_children(&mut cursor)
+        .filter(|base| matches!(base.kind(), "type_identifier" | "qualified_identifier"))
+        .map(|base| {
+            let name = text(base, source);
+            name.rsplit("::").next().unwrap_or(name)
+        })
+        .collect()
+}
+
+/// The `function_declarator` of a member, looking through pointer and
+/// reference return types.
+fn function_declarator(member: Node<'_>) -> Option<Node<'_>> {
+    let mut declarator = member.child_by_field_name("declarator")?;
+    loop {
+        if declarator.kind() == "function_declarator" {
+            return Some(declarator);
+        }
+        declarator = match declarator.child_by_field_name("declarator") {
+            Some(inner) => inner,
+            None => {
+                let mut cursor = declarator.walk();
+                let inner = declarator
+                    .named_children(&mut cursor)
+                    .find(|child| child.kind() == "function_declarator")?;
+                inner
+            }
+        };
+    }
+}
+
+fn method_name<'a>(member: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    let name = function_declarator(member)?.child_by_field_name("declarator")?;
+    (name.kind() == "field_identifier").then(|| text(name, source))
+}
+
+fn has_child(node: Node<'_>, kind: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|child| child.kind() == kind);
+    found
+}
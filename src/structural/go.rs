@@ -0,0 +1,134 @@
+use super::{descendants, text};
+use tree_sitter::Node;
+
+const FUNCTION_KINDS: &[&str] = &["function_declaration", "method_declaration", "func_literal"];
+
+/// Parameters of type `context.Context` that are not the first parameter.
+pub(super) fn context_not_first<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    let mut flagged = Vec::new();
+    for function in descendants(root)
+        .into_iter()
+        .filter(|node| FUNCTION_KINDS.contains(&node.kind()))
+    {
+        let Some(parameters) = function.child_by_field_name("parameters") else {
+            continue;
+        };
+        let mut cursor = parameters.walk();
+        let declarations: Vec<Node> = parameters
+            .named_children(&mut cursor)
+            .filter(|node| node.kind() == "parameter_declaration")
+            .collect();
+        flagged.extend(declarations.into_iter().skip(1).filter(|declaration| {
+            declaration
+                .child_by_field_name("type")
+                .is_some_and(|ty| text(ty, source) == "context.Context")
+        }));
+    }
+    flagged
+}
+
+/// `go func() { ... }()` inside a loop whose body uses a loop variable
+/// without receiving it as an argument.
+pub(super) fn goroutine_captures_loop_var<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+) -> Vec<Node<'tree>> {
+    let mut flagged = Vec::new();
+    for go_statement in descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "go_statement")
+    {
+        let Some(literal) = go_statement
+            .named_child(0)
+            .and_then(|call| call.child_by_field_name("function"))
+            .filter(|function| function.kind() == "func_literal")
+        else {
+            continue;
+        };
+
+        let parameters: Vec<&str> = literal
+            .child_by_field_name("parameters")
+            .map(|list| {
+                descendants(list)
+                    .into_iter()
+                    .filter(|node| node.kind() == "identifier")
+                    .map(|node| text(node, source))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let Some(body) = literal.child_by_field_name("body") else {
+            continue;
+        };
+        let used: Vec<&str> = descendants(body)
+            .into_iter()
+            .filter(|node| node.kind() == "identifier")
+            .map(|node| text(node, source))
+            .collect();
+
+        let captures_loop_var = enclosing_loops(go_statement).into_iter().any(|loop_node| {
+            loop_variables(loop_node, source)
+                .iter()
+                .any(|var| !parameters.contains(var) && used.contains(var))
+        });
+        if captures_loop_var {
+            flagged.push(go_statement);
+        }
+    }
+    flagged
+}
+
+/// `defer` directly inside a loop, which runs only when the surrounding
+/// function returns rather than at the end of each iteration.
+pub(super) fn defer_in_loop<'tree>(root: Node<'tree>, _source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "defer_statement")
+        .filter(|node| !enclosing_loops(*node).is_empty())
+        .collect()
+}
+
+/// `for` statements around `node`, innermost first, stopping at the
+/// nearest enclosing function.
+fn enclosing_loops(node: Node<'_>) -> Vec<Node<'_>> {
+    let mut loops = Vec::new();
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        if FUNCTION_KINDS.contains(&parent.kind()) {
+            break;
+        }
+        if parent.kind() == "for_statement" {
+            loops.push(parent);
+        }
+        current = parent.parent();
+    }
+    loops
+}
+
+/// Variables declared by a loop header (`for i := ...` or
+/// `for k, v := range ...`); `_` is never a variable.
+fn loop_variables<'a>(loop_node: Node<'_>, source: &'a [u8]) -> Vec<&'a str> {
+    let mut cursor = loop_node.walk();
+    let Some(header) = loop_node
+        .named_children(&mut cursor)
+        .find(|node| matches!(node.kind(), "for_clause" | "range_clause"))
+    else {
+        return Vec::new();
+    };
+
+    let left = match header.kind() {
+        "for_clause" => header
+            .child_by_field_name("initializer")
+            .filter(|init| init.kind() == "short_var_declaration")
+            .and_then(|init| init.child_by_field_name("left")),
+        _ => header.child_by_field_name("left"),
+    };
+    left.map(|list| {
+        descendants(list)
+            .into_iter()
+            .filter(|node| node.kind() == "identifier")
+            .map(|node| text(node, source))
+            .filter(|name| *name != "_")
+            .collect()
+    })
+    .unwrap_or_default()
+}
@@ -0,0 +1,304 @@
+use super::{descendants, text};
+use std::collections::HashSet;
+use tree_sitter::Node;
+
+/// One imported module or name: a Rust `use` path (each entry of a
+/// `{...}` list separately), a Go import spec, a Java, JavaScript, or
+/// Swift import, a C/C++ `#include` or `using namespace`, or a Zig
+/// `const x = @import("x")`.
+#[derive(Debug, Clone)]
+pub struct Import<'tree> {
+    /// What findings point at: the declaration, or its entry of a list.
+    pub node: Node<'tree>,
+    /// The whole import statement, whose identifiers are not uses.
+    pub declaration: Node<'tree>,
+    /// The module as written, with its own separators: `std::io::Write`,
+    /// `java.util`, `github.com/pkg/errors`, `./widget`, `vector`.
+    pub path: String,
+    /// The name the import binds, when uses of it must spell it out;
+    /// `None` for Go (whose compiler rejects unused imports), Swift, and
+    /// C/C++, whose imports bring in names implicitly.
+    pub name: Option<String>,
+    /// `use foo::*`, `import java.util.*`, `. "strings"`, or
+    /// `using namespace std`.
+    pub wildcard: bool,
+    /// A Rust `pub use`, which exports the name rather than using it.
+    pub reexport: bool,
+}
+
+impl Import<'_> {
+    /// Whether the import is of `module` or something inside it.
+    pub fn is_of(&self, module: &str) -> bool {
+        self.path
+            .strip_prefix(module)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '.', '/']))
+    }
+}
+
+/// Every import in the file, in document order. Works for every language.
+pub fn imports<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Import<'tree>> {
+    let mut imports = Vec::new();
+    for node in descendants(root) {
+        match node.kind() {
+            "use_declaration" => {
+                let reexport = node
+                    .named_child(0)
+                    .is_some_and(|child| child.kind() == "visibility_modifier");
+                if let Some(argument) = node.child_by_field_name("argument") {
+                    let import = Import {
+                        node: argument,
+                        declaration: node,
+                        path: String::new(),
+                        name: None,
+                        wildcard: false,
+                        reexport,
+                    };
+                    rust_use_tree(&import, argument, "", source, &mut imports);
+                }
+            }
+            "import_spec" => imports.push(go_import(node, source)),
+            "import_declaration" => imports.extend(java_or_swift_import(node, source)),
+            "import_statement" => js_imports(node, source, &mut imports),
+            "preproc_include" => {
+                if let Some(path) = node.child_by_field_name("path") {
+                    imports.push(plain(node, unquote(text(path, source)), None));
+                }
+            }
+            "using_declaration" if has_child(node, "namespace") => {
+                if let Some(name) = node.named_child(0) {
+                    let mut import = plain(node, text(name, source), None);
+                    import.wildcard = true;
+                    imports.push(import);
+                }
+            }
+            "variable_declaration" => imports.extend(zig_import(node, source)),
+            _ => {}
+        }
+    }
+    imports
+}
+
+/// The leaves of a Rust use tree under `prefix`, such as the entries of
+/// `std::collections::{HashMap, self}`.
+fn rust_use_tree<'tree>(
+    template: &Import<'tree>,
+    node: Node<'tree>,
+    prefix: &str,
+    source: &[u8],
+    imports: &mut Vec<Import<'tree>>,
+) {
+    let leaf = |path: String, name: Option<String>, wildcard: bool| Import {
+        node,
+        path,
+        name,
+        wildcard,
+        ..template.clone()
+    };
+    match node.kind() {
+        "scoped_use_list" => {
+            let path = node
+                .child_by_field_name("path")
+                .map(|path| format!("{}{}::", prefix, text(path, source)))
+                .unwrap_or_else(|| prefix.to_string());
+            if let Some(list) = node.child_by_field_name("list") {
+                rust_use_tree(template, list, &path, source, imports);
+            }
+        }
+        "use_list" => {
+            let mut cursor = node.walk();
+            let entries: Vec<_> = node.named_children(&mut cursor).collect();
+            for entry in entries {
+                rust_use_tree(template, entry, prefix, source, imports);
+            }
+        }
+        "use_as_clause" => {
+            let path = node
+                .child_by_field_name("path")
+                .map(|path| text(path, source))
+                .unwrap_or_default();
+            let alias = node
+                .child_by_field_name("alias")
+                .map(|alias| text(alias, source))
+                .filter(|alias| *alias != "_");
+            imports.push(leaf(
+                format!("{}{}", prefix, path),
+                alias.map(str::to_string),
+                false,
+            ));
+        }
+        "use_wildcard" => {
+            let path = node
+                .named_child(0)
+                .map(|path| format!("{}{}", prefix, text(path, source)))
+                .unwrap_or_else(|| prefix.trim_end_matches("::").to_string());
+            imports.push(leaf(path, None, true));
+        }
+        "self" => {
+            let path = prefix.trim_end_matches("::");
+            let name = path.rsplit("::").next().map(str::to_string);
+            imports.push(leaf(path.to_string(), name, false));
+        }
+        _ => {
+            let path = text(node, source);
+            let name = path.rsplit("::").next().map(str::to_string);
+            imports.push(leaf(format!("{}{}", prefix, path), name, false));
+        }
+    }
+}
+
+fn go_import<'tree>(spec: Node<'tree>, source: &[u8]) -> Import<'tree> {
+    let path = spec
+        .child_by_field_name("path")
+        .map(|path| unquote(text(path, source)))
+        .unwrap_or_default();
+    let mut import = plain(spec, path, None);
+    import.wildcard = spec
+        .child_by_field_name("name")
+        .is_some_and(|name| name.kind() == "dot");
+    import
+}
+
+/// A Java import, whose name is its last segment unless it ends in `.*`,
+/// or a Swift import, whose name is implicit.
+fn java_or_swift_import<'tree>(node: Node<'tree>, source: &[u8]) -> Option<Import<'tree>> {
+    let mut cursor = node.walk();
+    let path = node
+        .named_children(&mut cursor)
+        .find(|child| matches!(child.kind(), "scoped_identifier" | "identifier"))?;
+    let wildcard = has_child(node, "asterisk");
+    // Swift's `identifier` is a list of `simple_identifier`s, Java's a leaf.
+    let java = path.kind() == "scoped_identifier" || path.named_child_count() == 0;
+    let name = path.child_by_field_name("name").unwrap_or(path);
+    let name = (java && !wildcard).then(|| text(name, source).to_string());
+    let mut import = plain(node, text(path, source), name);
+    import.wildcard = wildcard;
+    Some(import)
+}
+
+/// One import per name a JavaScript `import` binds, or one without a name
+/// for `import "./side-effect"`.
+fn js_imports<'tree>(node: Node<'tree>, source: &[u8], imports: &mut Vec<Import<'tree>>) {
+    let path = node
+        .child_by_field_name("source")
+        .map(|path| unquote(text(path, source)))
+        .unwrap_or_default();
+    let before = imports.len();
+    let mut cursor = node.walk();
+    let clauses: Vec<_> = node
+        .named_children(&mut cursor)
+        .filter(|child| child.kind() == "import_clause")
+        .collect();
+    for clause in clauses {
+        for binding in descendants(clause) {
+            let name = match binding.kind() {
+                "import_specifier" => binding
+                    .child_by_field_name("alias")
+                    .or_else(|| binding.child_by_field_name("name")),
+                "identifier" if binding.parent() == Some(clause) => Some(binding),
+                "namespace_import" => binding.named_child(0),
+                _ => None,
+            };
+            if let Some(name) = name {
+                let mut import = plain(node, path, Some(text(name, source).to_string()));
+                import.node = binding;
+                imports.push(import);
+            }
+        }
+    }
+    if imports.len() == before {
+        imports.push(plain(node, path, None));
+    }
+}
+
+/// `const name = @import("path");`
+fn zig_import<'tree>(node: Node<'tree>, source: &[u8]) -> Option<Import<'tree>> {
+    let mut cursor = node.walk();
+    let children: Vec<_> = node.named_children(&mut cursor).collect();
+    let name = children.iter().find(|child| child.kind() == "identifier")?;
+    let call = children
+        .iter()
+        .find(|child| child.kind() == "builtin_function")?;
+    if call.named_child(0).map(|function| text(function, source)) != Some("@import") {
+        return None;
+    }
+    let argument = descendants(*call)
+        .into_iter()
+        .find(|child| child.kind() == "string")?;
+    Some(plain(
+        node,
+        unquote(text(argument, source)),
+        Some(text(*name, source).to_string()),
+    ))
+}
+
+fn plain<'tree>(node: Node<'tree>, path: &str, name: Option<String>) -> Import<'tree> {
+    Import {
+        node,
+        declaration: node,
+        path: path.split_whitespace().collect(),
+        name,
+        wildcard: false,
+        reexport: false,
+    }
+}
+
+fn has_child(node: Node<'_>, kind: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node.children(&mut cursor).any(|child| child.kind() == kind);
+    found
+}
+
+fn unquote(path: &str) -> &str {
+    path.trim_matches(['"', '\'', '`', '<', '>'])
+}
+
+/// `use foo::*`, `import java.util.*`, `import . "strings"`, and `using
+/// namespace std`, which bring in names a reader can't trace back. Rust
+/// `use super::*` and preludes are skipped, being the idiom for test
+/// modules and for crates meant to be glob-imported.
+pub(super) fn wildcard_imports<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    imports(root, source)
+        .into_iter()
+        .filter(|import| import.wildcard)
+        .filter(|import| {
+            let last = import.path.rsplit([':', '.']).next().unwrap_or_default();
+            !(import.path == "super" || last == "prelude")
+        })
+        .map(|import| import.node)
+        .collect()
+}
+
+/// Imports whose name appears nowhere else in the file. Names used only
+/// implicitly, such as a Rust trait for its methods, are flagged too, so
+/// the findings are only unused-looking; `React` counts as used by JSX,
+/// which needs it in scope before the automatic JSX runtime.
+pub(super) fn unused_imports<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    let imports = imports(root, source);
+    let declarations: Vec<_> = imports
+        .iter()
+        .map(|import| import.declaration.byte_range())
+        .collect();
+    let nodes = descendants(root);
+    let jsx = nodes.iter().any(|node| node.kind().starts_with("jsx_"));
+    let used: HashSet<&str> = nodes
+        .into_iter()
+        .filter(|node| node.kind().ends_with("identifier"))
+        .filter(|node| {
+            !declarations
+                .iter()
+                .any(|declaration| declaration.contains(&node.start_byte()))
+        })
+        .map(|node| text(node, source))
+        .collect();
+    imports
+        .into_iter()
+        .filter(|import| !import.reexport)
+        .filter(|import| {
+            import
+                .name
+                .as_deref()
+                .is_some_and(|name| !(used.contains(name) || jsx && name == "React"))
+        })
+        .map(|import| import.node)
+        .collect()
+}
@@ -0,0 +1,102 @@
+use super::{descendants, text};
+use tree_sitter::Node;
+
+/// `AutoCloseable` types from the JDK that are usually opened and closed
+/// by the same method.
+const CLOSEABLE_TYPES: &[&str] = &[
+    "BufferedInputStream",
+    "BufferedOutputStream",
+    "BufferedReader",
+    "BufferedWriter",
+    "DataInputStream",
+    "DataOutputStream",
+    "FileInputStream",
+    "FileOutputStream",
+    "FileReader",
+    "FileWriter",
+    "InputStreamReader",
+    "ObjectInputStream",
+    "ObjectOutputStream",
+    "OutputStreamWriter",
+    "PrintStream",
+    "PrintWriter",
+    "RandomAccessFile",
+    "Scanner",
+    "ServerSocket",
+    "Socket",
+    "ZipFile",
+    "ZipInputStream",
+    "ZipOutputStream",
+];
+
+/// Factory methods returning resources the caller must close (JDBC and
+/// `java.nio.file.Files`).
+const CLOSEABLE_FACTORIES: &[&str] = &[
+    "createStatement",
+    "executeQuery",
+    "getConnection",
+    "newBufferedReader",
+    "newBufferedWriter",
+    "newDirectoryStream",
+    "newInputStream",
+    "newOutputStream",
+    "prepareCall",
+    "prepareStatement",
+];
+
+/// `Files` methods returning a stream over an open file. The same names on
+/// other receivers, such as `String.lines()`, hold nothing open.
+const FILES_STREAM_FACTORIES: &[&str] = &["lines"];
+
+/// Local variables holding a closeable resource that are declared outside
+/// a try-with-resources header, so an exception can leak the resource.
+pub(super) fn resource_without_try_with_resources<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "local_variable_declaration")
+        .filter(|declaration| {
+            let mut cursor = declaration.walk();
+            let opens_resource = declaration
+                .children_by_field_name("declarator", &mut cursor)
+                .filter_map(|declarator| declarator.child_by_field_name("value"))
+                .any(|value| opens_resource(value, source));
+            opens_resource
+        })
+        .collect()
+}
+
+/// Catch blocks with no statements and no comment explaining why the
+/// exception can be ignored.
+pub(super) fn empty_catch<'tree>(root: Node<'tree>, _source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "catch_clause")
+        .filter(|catch| {
+            catch
+                .child_by_field_name("body")
+                .is_some_and(|body| body.named_child_count() == 0)
+        })
+        .collect()
+}
+
+fn opens_resource(value: Node<'_>, source: &[u8]) -> bool {
+    match value.kind() {
+        "object_creation_expression" => value.child_by_field_name("type").is_some_and(|ty| {
+            let name = text(ty, source);
+            let name = name.rsplit('.').next().unwrap_or(name);
+            CLOSEABLE_TYPES.contains(&name)
+        }),
+        "method_invocation" => value.child_by_field_name("name").is_some_and(|name| {
+            let name = text(name, source);
+            CLOSEABLE_FACTORIES.contains(&name)
+                || (FILES_STREAM_FACTORIES.contains(&name)
+                    && value.child_by_field_name("object").is_some_and(|object| {
+                        text(object, source).rsplit('.').next() == Some("Files")
+                    }))
+        }),
+        _ => false,
+    }
+}
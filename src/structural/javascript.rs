@@ -0,0 +1,258 @@
+use super::{descendants, text};
+use std::collections::BTreeSet;
+use tree_sitter::Node;
+
+const FUNCTION_KINDS: &[&str] = &[
+    "function_declaration",
+    "function_expression",
+    "function",
+    "arrow_function",
+    "method_definition",
+    "generator_function_declaration",
+];
+const LOOP_KINDS: &[&str] = &[
+    "for_statement",
+    "for_in_statement",
+    "while_statement",
+    "do_statement",
+];
+
+/// Promise-returning globals that are always worth awaiting.
+const PROMISE_GLOBALS: &[&str] = &["fetch"];
+
+/// Calls to async functions of this file (or `fetch`) made as bare
+/// statements inside an async function, so nothing awaits the promise.
+pub(super) fn unawaited_promise<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    let nodes = descendants(root);
+    let async_names: BTreeSet<&str> = nodes
+        .iter()
+        .filter(|node| FUNCTION_KINDS.contains(&node.kind()) && is_async(**node))
+        .filter_map(|function| function_name(*function, source))
+        .chain(PROMISE_GLOBALS.iter().copied())
+        .collect();
+
+    nodes
+        .into_iter()
+        .filter(|node| node.kind() == "call_expression")
+        .filter(|call| {
+            call.parent()
+                .is_some_and(|parent| parent.kind() == "expression_statement")
+        })
+        .filter(|call| callee_name(*call, source).is_some_and(|name| async_names.contains(name)))
+        .filter(|call| enclosing_function(*call).is_some_and(is_async))
+        .collect()
+}
+
+/// `await` inside a loop body, which serializes work that could often run
+/// concurrently. `for await (...)` headers are not flagged.
+pub(super) fn await_in_loop<'tree>(root: Node<'tree>, _source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "await_expression")
+        .filter(|node| {
+            let mut current = node.parent();
+            while let Some(parent) = current {
+                if FUNCTION_KINDS.contains(&parent.kind()) {
+                    return false;
+                }
+                if LOOP_KINDS.contains(&parent.kind()) {
+                    // The iterable of a `for ... of` runs once, not per iteration.
+                    return parent
+                        .child_by_field_name("body")
+                        .is_some_and(|body| body.byte_range().contains(&node.start_byte()));
+                }
+                current = parent.parent();
+            }
+            false
+        })
+        .collect()
+}
+
+/// Statement-level `.then(...)` chains with no `.catch(...)` and no
+/// rejection handler, whose errors become unhandled rejections.
+pub(super) fn floating_then<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "expression_statement")
+        .filter_map(|statement| statement.named_child(0))
+        .filter(|expression| expression.kind() == "call_expression")
+        .filter(|call| {
+            let chain = call_chain(*call, source);
+            let has_then = chain.iter().any(|(method, _)| *method == "then");
+            let handles_rejection = chain
+                .iter()
+                .any(|(method, args)| *method == "catch" || (*method == "then" && *args >= 2));
+            has_then && !handles_rejection
+        })
+        .collect()
+}
+
+/// Event handler props (`onClick`, `onChange`, ...) given an inline arrow
+/// function, function expression, or `.bind(...)` call, which creates a new
+/// function on every render and defeats memoized children.
+pub(super) fn jsx_inline_handler<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "jsx_attribute")
+        .filter(|attribute| {
+            let Some(name) = attribute.named_child(0) else {
+                return false;
+            };
+            let is_handler = text(name, source)
+                .strip_prefix("on")
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()));
+            let Some(value) = attribute
+                .named_child(1)
+                .filter(|value| value.kind() == "jsx_expression")
+                .and_then(|value| value.named_child(0))
+            else {
+                return false;
+            };
+            let is_inline = match value.kind() {
+                "arrow_function" | "function_expression" | "function" => true,
+                "call_expression" => callee_name(value, source) == Some("bind"),
+                _ => false,
+            };
+            is_handler && is_inline
+        })
+        .collect()
+}
+
+/// JSX elements returned from a `.map(...)` callback without a `key` prop,
+/// which React needs to track list items across renders. Fragments
+/// (`<>...</>`) can't take a key and are flagged too.
+pub(super) fn jsx_missing_key<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "call_expression")
+        .filter(|call| callee_name(*call, source) == Some("map"))
+        .filter_map(|call| {
+            let arguments = call.child_by_field_name("arguments")?;
+            let callback = arguments.named_child(0)?;
+            matches!(
+                callback.kind(),
+                "arrow_function" | "function_expression" | "function"
+            )
+            .then_some(callback)
+        })
+        .flat_map(|callback| returned_elements(callback))
+        .filter(|element| !has_key(*element, source))
+        .collect()
+}
+
+/// JSX elements a callback returns, from an expression body or from the
+/// `return` statements of its own body (not of nested functions).
+fn returned_elements(callback: Node<'_>) -> Vec<Node<'_>> {
+    let Some(body) = callback.child_by_field_name("body") else {
+        return Vec::new();
+    };
+    let returned: Vec<Node<'_>> = if body.kind() == "statement_block" {
+        descendants(body)
+            .into_iter()
+            .filter(|node| node.kind() == "return_statement")
+            .filter(|statement| enclosing_function(*statement) == Some(callback))
+            .filter_map(|statement| statement.named_child(0))
+            .collect()
+    } else {
+        vec![body]
+    };
+    returned
+        .into_iter()
+        .map(|mut node| {
+            while node.kind() == "parenthesized_expression" {
+                match node.named_child(0) {
+                    Some(inner) => node = inner,
+                    None => break,
+                }
+            }
+            node
+        })
+        .filter(|node| matches!(node.kind(), "jsx_element" | "jsx_self_closing_element"))
+        .collect()
+}
+
+fn has_key(element: Node<'_>, source: &[u8]) -> bool {
+    let tag = match element.kind() {
+        "jsx_element" => element.child_by_field_name("open_tag"),
+        _ => Some(element),
+    };
+    let Some(tag) = tag else {
+        return false;
+    };
+    let mut cursor = tag.walk();
+    let found = tag
+        .children_by_field_name("attribute", &mut cursor)
+        .any(|attribute| {
+            attribute.kind() == "jsx_attribute"
+                && attribute
+                    .named_child(0)
+                    .is_some_and(|name| text(name, source) == "key")
+        });
+    found
+}
+
+fn is_async(function: Node<'_>) -> bool {
+    let mut cursor = function.walk();
+    let is_async = function
+        .children(&mut cursor)
+        .any(|child| child.kind() == "async");
+    is_async
+}
+
+/// Declared name of a function: its own name, the method name, or the
+/// variable it is assigned to (`const load = async () => ...`).
+fn function_name<'a>(function: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    if let Some(name) = function.child_by_field_name("name") {
+        return Some(text(name, source));
+    }
+    function
+        .parent()
+        .filter(|parent| parent.kind() == "variable_declarator")
+        .and_then(|declarator| declarator.child_by_field_name("name"))
+        .map(|name| text(name, source))
+}
+
+fn callee_name<'a>(call: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    let function = call.child_by_field_name("function")?;
+    let name = match function.kind() {
+        "identifier" => function,
+        "member_expression" => function.child_by_field_name("property")?,
+        _ => return None,
+    };
+    Some(text(name, source))
+}
+
+fn enclosing_function(node: Node<'_>) -> Option<Node<'_>> {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        if FUNCTION_KINDS.contains(&parent.kind()) {
+            return Some(parent);
+        }
+        current = parent.parent();
+    }
+    None
+}
+
+/// `(method, argument count)` for each `.method(...)` call in a chain like
+/// `a().then(f).catch(g)`, outermost first.
+fn call_chain<'a>(call: Node<'_>, source: &'a [u8]) -> Vec<(&'a str, usize)> {
+    let mut chain = Vec::new();
+    let mut current = Some(call);
+    while let Some(call) = current.filter(|node| node.kind() == "call_expression") {
+        let Some(member) = call
+            .child_by_field_name("function")
+            .filter(|function| function.kind() == "member_expression")
+        else {
+            break;
+        };
+        let arguments = call
+            .child_by_field_name("arguments")
+            .map(|arguments| arguments.named_child_count())
+            .unwrap_or_default();
+        if let Some(property) = member.child_by_field_name("property") {
+            chain.push((text(property, source), arguments));
+        }
+        current = member.child_by_field_name("object");
+    }
+    chain
+}
@@ -0,0 +1,170 @@
+//! Built-in checks for patterns a single tree-sitter query can't express,
+//! such as correlating a loop variable with a closure that captures it.
+//!
+//! A rule opts in with `check = "<name>"` instead of `query`; everything
+//! else (severity, weight, message, scoring) works as for query rules.
+//!
+//! Pair checks correlate a C/C++ header with the source file next to it,
+//! or across `include/` and `src/`, and report against one side of the pair;
+//! they flag nothing in files without a counterpart.
+
+mod cpp;
+mod go;
+mod imports;
+mod java;
+mod javascript;
+mod rust;
+mod secrets;
+
+pub use imports::{imports, Import};
+use tree_sitter::Node;
+
+/// Returns the nodes under `root` that the check flags.
+pub type Check = for<'tree> fn(Node<'tree>, &[u8]) -> Vec<Node<'tree>>;
+
+/// Returns the nodes under `root` that the check flags, given the root and
+/// source of the file's counterpart.
+pub type PairCheck = for<'tree> fn(Node<'tree>, &[u8], Node<'_>, &[u8]) -> Vec<Node<'tree>>;
+
+/// The file of a header/source pair a pair check reports against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairSide {
+    Header,
+    Source,
+}
+
+const CHECKS: &[(&str, Check)] = &[
+    ("syntax_errors", syntax_errors),
+    ("hardcoded_secrets", secrets::hardcoded_secrets),
+    ("wildcard_imports", imports::wildcard_imports),
+    ("unused_imports", imports::unused_imports),
+    (
+        "cpp_new_without_smart_pointer",
+        cpp::new_without_smart_pointer,
+    ),
+    ("cpp_missing_override", cpp::missing_override),
+    ("cpp_rule_of_five", cpp::rule_of_five),
+    ("go_context_not_first", go::context_not_first),
+    (
+        "go_goroutine_captures_loop_var",
+        go::goroutine_captures_loop_var,
+    ),
+    ("go_defer_in_loop", go::defer_in_loop),
+    (
+        "java_resource_without_try_with_resources",
+        java::resource_without_try_with_resources,
+    ),
+    ("java_empty_catch", java::empty_catch),
+    ("js_unawaited_promise", javascript::unawaited_promise),
+    ("js_await_in_loop", javascript::await_in_loop),
+    ("js_floating_then", javascript::floating_then),
+    ("jsx_inline_handler", javascript::jsx_inline_handler),
+    ("jsx_missing_key", javascript::jsx_missing_key),
+    ("rust_ignored_result", rust::ignored_result),
+    ("rust_clone_in_loop", rust::clone_in_loop),
+    (
+        "rust_unsafe_without_safety_comment",
+        rust::unsafe_without_safety_comment,
+    ),
+];
+
+/// Checks whose flagged text is itself sensitive, so findings show only
+/// its start.
+const REDACTED_CHECKS: &[&str] = &["hardcoded_secrets"];
+
+const PAIR_CHECKS: &[(&str, PairSide, PairCheck)] = &[
+    (
+        "cpp_declared_not_defined",
+        PairSide::Header,
+        cpp::declared_not_defined,
+    ),
+    (
+        "cpp_defined_not_declared",
+        PairSide::Source,
+        cpp::defined_not_declared,
+    ),
+];
+
+pub fn lookup(name: &str) -> Option<Check> {
+    CHECKS
+        .iter()
+        .find(|(check_name, _)| *check_name == name)
+        .map(|(_, check)| *check)
+}
+
+pub fn lookup_pair(name: &str) -> Option<(PairSide, PairCheck)> {
+    PAIR_CHECKS
+        .iter()
+        .find(|(check_name, _, _)| *check_name == name)
+        .map(|(_, side, check)| (*side, *check))
+}
+
+/// Whether findings of check `name` have their text redacted.
+pub fn redacts(name: &str) -> bool {
+    REDACTED_CHECKS.contains(&name)
+}
+
+/// Names of every built-in check.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    CHECKS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(PAIR_CHECKS.iter().map(|(name, _, _)| *name))
+}
+
+/// Outermost `ERROR` nodes and `MISSING` nodes outside them: where the
+/// parser gave up on the source and where it inserted a token the source
+/// lacks. Works for every language.
+pub fn syntax_errors<'tree>(root: Node<'tree>, _source: &[u8]) -> Vec<Node<'tree>> {
+    let mut errors = Vec::new();
+    let mut pending = vec![root];
+    while let Some(node) = pending.pop() {
+        if node.is_error() || node.is_missing() {
+            errors.push(node);
+        } else if node.has_error() {
+            let mut cursor = node.walk();
+            let children: Vec<_> = node.children(&mut cursor).collect();
+            pending.extend(children.into_iter().rev());
+        }
+    }
+    errors
+}
+
+/// Comment nodes, outermost only, in document order: `line_comment`,
+/// `block_comment`, `comment`, and the like, but not the doc comment
+/// inside a Rust `line_comment`. Works for every language.
+pub fn comments(root: Node<'_>) -> Vec<Node<'_>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind().contains("comment"))
+        .filter(|node| {
+            node.parent()
+                .is_none_or(|parent| !parent.kind().contains("comment"))
+        })
+        .collect()
+}
+
+/// `node` and all of its descendants, in document order.
+fn descendants(node: Node<'_>) -> Vec<Node<'_>> {
+    let mut nodes = Vec::new();
+    let mut cursor = node.walk();
+    loop {
+        nodes.push(cursor.node());
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.node() == node {
+                return nodes;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            cursor.goto_parent();
+        }
+    }
+}
+
+fn text<'a>(node: Node<'_>, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or_default()
+}
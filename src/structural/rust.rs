@@ -0,0 +1,143 @@
+use super::{descendants, text};
+use std::collections::BTreeSet;
+use tree_sitter::Node;
+
+const LOOP_KINDS: &[&str] = &["for_expression", "while_expression", "loop_expression"];
+const FUNCTION_KINDS: &[&str] = &["function_item", "closure_expression"];
+
+/// Calls to functions of this file that return `Result` whose value is
+/// dropped: `f();` or `let _ = f();`. Functions are matched by name only.
+pub(super) fn ignored_result<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    let nodes = descendants(root);
+    let fallible: BTreeSet<&str> = nodes
+        .iter()
+        .filter(|node| node.kind() == "function_item")
+        .filter(|function| {
+            function
+                .child_by_field_name("return_type")
+                .is_some_and(|ty| returns_result(ty, source))
+        })
+        .filter_map(|function| function.child_by_field_name("name"))
+        .map(|name| text(name, source))
+        .collect();
+
+    nodes
+        .into_iter()
+        .filter(|node| node.kind() == "call_expression")
+        .filter(|call| {
+            callee_name(*call, source).is_some_and(|name| fallible.contains(name))
+                && is_discarded(*call, source)
+        })
+        .collect()
+}
+
+/// `.clone()` calls inside a loop body.
+pub(super) fn clone_in_loop<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "call_expression")
+        .filter(|call| {
+            call.child_by_field_name("function")
+                .filter(|function| function.kind() == "field_expression")
+                .and_then(|function| function.child_by_field_name("field"))
+                .is_some_and(|field| text(field, source) == "clone")
+        })
+        .filter(|call| in_loop(*call))
+        .collect()
+}
+
+/// `unsafe` blocks not preceded by a `// SAFETY:` comment.
+pub(super) fn unsafe_without_safety_comment<'tree>(
+    root: Node<'tree>,
+    source: &[u8],
+) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| node.kind() == "unsafe_block")
+        .filter(|block| {
+            let statement = enclosing_statement(*block);
+            !has_safety_comment(*block, source) && !has_safety_comment(statement, source)
+        })
+        .collect()
+}
+
+fn returns_result(ty: Node<'_>, source: &[u8]) -> bool {
+    let base = match ty.kind() {
+        "generic_type" => ty.child_by_field_name("type").unwrap_or(ty),
+        _ => ty,
+    };
+    text(base, source).rsplit("::").next() == Some("Result")
+}
+
+/// `save` for `save(..)`, `self.save(..)`, and `store::save(..)`.
+fn callee_name<'a>(call: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    let function = call.child_by_field_name("function")?;
+    let name = match function.kind() {
+        "identifier" => function,
+        "field_expression" => function.child_by_field_name("field")?,
+        "scoped_identifier" => function.child_by_field_name("name")?,
+        _ => return None,
+    };
+    Some(text(name, source))
+}
+
+fn is_discarded(call: Node<'_>, source: &[u8]) -> bool {
+    let Some(parent) = call.parent() else {
+        return false;
+    };
+    match parent.kind() {
+        "expression_statement" => {
+            let mut cursor = parent.walk();
+            let terminated = parent
+                .children(&mut cursor)
+                .any(|child| child.kind() == ";");
+            terminated
+        }
+        "let_declaration" => parent
+            .child_by_field_name("pattern")
+            .is_some_and(|pattern| text(pattern, source) == "_"),
+        _ => false,
+    }
+}
+
+fn in_loop(node: Node<'_>) -> bool {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        if FUNCTION_KINDS.contains(&parent.kind()) {
+            return false;
+        }
+        if LOOP_KINDS.contains(&parent.kind()) {
+            return true;
+        }
+        current = parent.parent();
+    }
+    false
+}
+
+/// The outermost ancestor of `node` that is still within the same block
+/// statement, e.g. the `let` declaration around `let x = unsafe { .. };`.
+fn enclosing_statement(node: Node<'_>) -> Node<'_> {
+    let mut statement = node;
+    while let Some(parent) = statement.parent() {
+        if matches!(parent.kind(), "block" | "source_file" | "declaration_list") {
+            break;
+        }
+        statement = parent;
+    }
+    statement
+}
+
+/// Whether the comments directly above `node` include `SAFETY:`.
+fn has_safety_comment(node: Node<'_>, source: &[u8]) -> bool {
+    let mut sibling = node.prev_sibling();
+    while let Some(comment) = sibling {
+        if !matches!(comment.kind(), "line_comment" | "block_comment") {
+            break;
+        }
+        if text(comment, source).contains("SAFETY:") {
+            return true;
+        }
+        sibling = comment.prev_sibling();
+    }
+    false
+}
@@ -0,0 +1,194 @@
+use super::{descendants, text};
+use tree_sitter::Node;
+
+/// String literal kinds across the built-in grammars.
+const STRING_KINDS: &[&str] = &[
+    "string",
+    "string_literal",
+    "raw_string_literal",
+    "interpreted_string_literal",
+    "line_string_literal",
+    "multi_line_string_literal",
+    "template_string",
+    "concatenated_string",
+];
+
+/// Fields naming what a declaration, assignment, or key/value pair binds.
+const NAME_FIELDS: &[&str] = &[
+    "name",
+    "left",
+    "key",
+    "pattern",
+    "declarator",
+    "field",
+    "target",
+];
+
+/// Parts of names, lowercased without `_` and `-`, that hold secrets.
+const SECRET_NAMES: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "apikey",
+    "accesskey",
+    "privatekey",
+    "credential",
+];
+
+/// Prefixes of AWS access key IDs, each followed by 16 more characters.
+const AWS_KEY_PREFIXES: &[&str] = &[
+    "AKIA", "ASIA", "AGPA", "AIDA", "AROA", "ANPA", "ANVA", "AIPA",
+];
+
+/// Prefixes of other providers' tokens, with the length a real token at
+/// least has.
+const TOKEN_PREFIXES: &[(&str, usize)] = &[
+    ("ghp_", 40),
+    ("gho_", 40),
+    ("ghs_", 40),
+    ("github_pat_", 60),
+    ("glpat-", 26),
+    ("xoxb-", 30),
+    ("xoxp-", 30),
+    ("sk_live_", 32),
+];
+
+/// Shortest value, and lowest Shannon entropy in bits per character, for
+/// a literal assigned to a secret-like name to count as a real secret
+/// rather than a placeholder such as `"changeme"`.
+const MIN_SECRET_LEN: usize = 16;
+const MIN_SECRET_ENTROPY: f64 = 3.5;
+
+/// String literals holding an AWS access key ID, a private key, a token
+/// with a known provider prefix, or a high-entropy value assigned to a
+/// name like `password` or `api_token`. Works for every language.
+pub(super) fn hardcoded_secrets<'tree>(root: Node<'tree>, source: &[u8]) -> Vec<Node<'tree>> {
+    descendants(root)
+        .into_iter()
+        .filter(|node| STRING_KINDS.contains(&node.kind()))
+        .filter(|literal| {
+            // The outermost literal only, e.g. not the parts of a
+            // concatenated C string.
+            literal
+                .parent()
+                .is_none_or(|parent| !STRING_KINDS.contains(&parent.kind()))
+        })
+        .filter(|literal| {
+            let value = unquote(text(*literal, source));
+            has_known_secret(value)
+                || (is_high_entropy(value)
+                    && assigned_name(*literal, source).is_some_and(is_secret_name))
+        })
+        .collect()
+}
+
+/// The text between a literal's first and last quote.
+fn unquote(literal: &str) -> &str {
+    let Some(start) = literal.find(['"', '\'', '`']) else {
+        return literal;
+    };
+    let end = literal.rfind(['"', '\'', '`']).unwrap_or(literal.len());
+    literal
+        .get(start..end)
+        .unwrap_or_default()
+        .trim_start_matches(['"', '\'', '`'])
+}
+
+fn has_known_secret(value: &str) -> bool {
+    if value.contains("-----BEGIN ") && value.contains("PRIVATE KEY") {
+        return true;
+    }
+    let aws_key = AWS_KEY_PREFIXES.iter().any(|prefix| {
+        value.match_indices(prefix).any(|(at, _)| {
+            let key = value.as_bytes().get(at..at + 20);
+            let after = value.as_bytes().get(at + 20);
+            key.is_some_and(|key| {
+                key.iter()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+            }) && after.is_none_or(|b| !b.is_ascii_alphanumeric())
+        })
+    });
+    aws_key
+        || TOKEN_PREFIXES.iter().any(|(prefix, len)| {
+            value.match_indices(prefix).any(|(at, _)| {
+                value[at..]
+                    .bytes()
+                    .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-'))
+                    .count()
+                    >= *len
+            })
+        })
+}
+
+/// Long enough and random enough to be a key rather than a word, and not
+/// a template or a sentence.
+fn is_high_entropy(value: &str) -> bool {
+    value.len() >= MIN_SECRET_LEN
+        && !value.contains(char::is_whitespace)
+        && !value.contains("${")
+        && !value.contains("{}")
+        && !value.contains('%')
+        && entropy(value) >= MIN_SECRET_ENTROPY
+}
+
+/// Shannon entropy of `value`'s bytes, in bits per byte.
+fn entropy(value: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in value.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = value.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// The name `literal` is assigned or bound to: `let name = ..`,
+/// `name := ..`, `obj.name = ..`, `{ name: .. }`, and so on. Climbs a few
+/// levels for values wrapped in expression lists or elements, but not out
+/// of calls, so arguments are not attributed to a name.
+fn assigned_name<'a>(literal: Node<'_>, source: &'a [u8]) -> Option<&'a str> {
+    let mut node = literal;
+    for _ in 0..3 {
+        let parent = node.parent()?;
+        let kind = parent.kind();
+        if kind.contains("call") || kind.contains("argument") || kind.contains("block") {
+            return None;
+        }
+        let field = NAME_FIELDS
+            .iter()
+            .find_map(|field| parent.child_by_field_name(field))
+            .filter(|name| name.id() != node.id());
+        let name = field.or_else(|| {
+            let mut cursor = parent.walk();
+            let first = parent
+                .named_children(&mut cursor)
+                .find(|child| child.kind().ends_with("identifier"));
+            first.filter(|_| kind.contains("declaration"))
+        });
+        if let Some(name) = name.filter(|name| name.end_byte() <= literal.start_byte()) {
+            return last_identifier(name).map(|name| text(name, source));
+        }
+        node = parent;
+    }
+    None
+}
+
+/// `node` if it is an identifier, else its last identifier, such as the
+/// property of `this.token` or the variable of `*password`.
+fn last_identifier(node: Node<'_>) -> Option<Node<'_>> {
+    descendants(node)
+        .into_iter()
+        .rfind(|node| node.kind().ends_with("identifier"))
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase().replace(['_', '-'], "");
+    SECRET_NAMES.iter().any(|part| name.contains(part))
+}
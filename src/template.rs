@@ -0,0 +1,55 @@
+use handlebars::{no_escape, Handlebars};
+use serde_json::Value;
+#[cfg(feature = "cli")]
+use std::fs;
+#[cfg(feature = "cli")]
+use std::path::Path;
+
+/// Renders a user-supplied Handlebars template over a report value.
+///
+/// The template sees the same fields as the JSON output (`score`, `rating`,
+/// `issues`, `breakdown`, ...) plus `file`, `language` and `config`. Values
+/// are inserted verbatim, without HTML escaping.
+pub fn render_template(
+    template: &str,
+    report: &Value,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(false);
+    handlebars.register_escape_fn(no_escape);
+    handlebars.register_template_string("report", template)?;
+    Ok(handlebars.render("report", report)?)
+}
+
+#[cfg(feature = "cli")]
+pub fn render_template_file<P: AsRef<Path>>(
+    path: P,
+    report: &Value,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let template = fs::read_to_string(path)?;
+    render_template(&template, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template_over_report() {
+        let report = json!({
+            "file": "main.go",
+            "score": 7.5,
+            "issues": [{ "rule": "panic_usage", "line": 3 }, { "rule": "missing_error_check", "line": 8 }]
+        });
+        let rendered = render_template(
+            "{{file}}: {{score}}\n{{#each issues}}- {{rule}} @ {{line}}\n{{/each}}",
+            &report,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            "main.go: 7.5\n- panic_usage @ 3\n- missing_error_check @ 8\n"
+        );
+    }
+}
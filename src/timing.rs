@@ -0,0 +1,148 @@
+//! `--timings`: how long parsing each file, and compiling and matching each
+//! rule's query, took, to find the pathological queries of a big config.
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Slowest rules and files listed by [`timings_table`].
+const TABLE_ROWS: usize = 10;
+
+/// Time one rule took on one file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleTiming {
+    pub rule: String,
+    /// Compiling the query; zero for structural checks.
+    pub compile: Duration,
+    /// Running the query or check over the tree.
+    pub matching: Duration,
+    pub matches: usize,
+}
+
+/// Time the analysis of one file took.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileTimings {
+    pub parse: Duration,
+    /// In rule order; rules skipped by their line limits are left out.
+    pub rules: Vec<RuleTiming>,
+}
+
+/// One rule's timings summed over every file it ran on.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct RuleTotal {
+    compile: Duration,
+    matching: Duration,
+    matches: usize,
+    files: usize,
+}
+
+impl RuleTotal {
+    fn total(&self) -> Duration {
+        self.compile + self.matching
+    }
+}
+
+/// Rule totals, most time first.
+fn rule_totals(files: &[(&str, &FileTimings)]) -> Vec<(String, RuleTotal)> {
+    let mut totals: BTreeMap<&str, RuleTotal> = BTreeMap::new();
+    for (_, timings) in files {
+        for rule in &timings.rules {
+            let total = totals.entry(&rule.rule).or_default();
+            total.compile += rule.compile;
+            total.matching += rule.matching;
+            total.matches += rule.matches;
+            total.files += 1;
+        }
+    }
+    let mut totals: Vec<(String, RuleTotal)> = totals
+        .into_iter()
+        .map(|(rule, total)| (rule.to_string(), total))
+        .collect();
+    totals.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// Files, slowest to parse first.
+fn slowest_parses<'a>(files: &[(&'a str, &'a FileTimings)]) -> Vec<(&'a str, &'a FileTimings)> {
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| b.1.parse.cmp(&a.1.parse).then_with(|| a.0.cmp(b.0)));
+    files
+}
+
+pub(crate) fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
+
+/// The `timings` section of the JSON output, in milliseconds.
+pub fn timings_report(files: &[(&str, &FileTimings)]) -> Value {
+    json!({
+        "rules": rule_totals(files).iter().map(|(rule, total)| json!({
+            "rule": rule,
+            "compile_ms": millis(total.compile),
+            "match_ms": millis(total.matching),
+            "total_ms": millis(total.total()),
+            "matches": total.matches,
+            "files": total.files,
+        })).collect::<Vec<_>>(),
+        "files": slowest_parses(files).iter().map(|(path, timings)| json!({
+            "path": path,
+            "parse_ms": millis(timings.parse),
+            "rules_ms": millis(timings.rules.iter().map(|rule| rule.compile + rule.matching).sum()),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+/// The slowest rules and parses as a plain-text table, for stderr.
+pub fn timings_table(files: &[(&str, &FileTimings)]) -> String {
+    let mut table = String::from("Slowest rules:\n");
+    table.push_str(&format!(
+        "  {:>10}  {:>10}  {:>8}  rule\n",
+        "compile ms", "match ms", "matches"
+    ));
+    for (rule, total) in rule_totals(files).iter().take(TABLE_ROWS) {
+        table.push_str(&format!(
+            "  {:>10.3}  {:>10.3}  {:>8}  {}\n",
+            millis(total.compile),
+            millis(total.matching),
+            total.matches,
+            rule
+        ));
+    }
+    table.push_str(&format!("Slowest parses:\n  {:>10}  path\n", "parse ms"));
+    for (path, timings) in slowest_parses(files).iter().take(TABLE_ROWS) {
+        table.push_str(&format!("  {:>10.3}  {}\n", millis(timings.parse), path));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_sum_rules_across_files_slowest_first() {
+        let rule = |name: &str, compile: u64, matching: u64| RuleTiming {
+            rule: name.to_string(),
+            compile: Duration::from_micros(compile),
+            matching: Duration::from_micros(matching),
+            matches: 1,
+        };
+        let a = FileTimings {
+            parse: Duration::from_micros(500),
+            rules: vec![rule("fast", 10, 10), rule("slow", 100, 900)],
+        };
+        let b = FileTimings {
+            parse: Duration::from_micros(2500),
+            rules: vec![rule("fast", 10, 10)],
+        };
+        let report = timings_report(&[("a.rs", &a), ("b.rs", &b)]);
+
+        assert_eq!(report["rules"][0]["rule"], "slow");
+        assert_eq!(report["rules"][0]["total_ms"], 1.0);
+        assert_eq!(report["rules"][1]["compile_ms"], 0.02);
+        assert_eq!(report["rules"][1]["files"], 2);
+        assert_eq!(report["files"][0]["path"], "b.rs");
+        assert_eq!(report["files"][0]["parse_ms"], 2.5);
+        assert_eq!(report["files"][1]["rules_ms"], 1.02);
+    }
+}
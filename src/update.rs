@@ -0,0 +1,186 @@
+use crate::fingerprint::sha256_hex;
+use crate::paths::cache_dir;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const REPO: &str = "lyledean1/compass";
+
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Release platform name matching the artifacts produced for `install.sh`.
+pub fn platform() -> Option<String> {
+    let os = match env::consts::OS {
+        "macos" => "macos",
+        "linux" => "linux",
+        _ => return None,
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+    Some(format!("{}-{}", os, arch))
+}
+
+/// Compares dotted numeric versions, ignoring a leading `v` and any
+/// pre-release suffix. Missing components count as 0, so `0.1` is `0.1.0`.
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    parse_version(candidate) > parse_version(current)
+}
+
+fn parse_version(version: &str) -> Vec<u64> {
+    let mut parts: Vec<u64> = version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    if parts.len() < 3 {
+        parts.resize(3, 0);
+    }
+    parts
+}
+
+/// The release archive for `platform` and the checksum file published
+/// next to it, as `.github/workflows/release.yml` uploads them.
+fn release_assets(platform: &str) -> (String, String) {
+    let archive = format!("compass-{}.tar.gz", platform);
+    let checksum = format!("{}.sha256", archive);
+    (archive, checksum)
+}
+
+/// The digest in a checksum file written by `shasum -a 256`.
+fn parse_checksum(content: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(std::str::from_utf8(content)?
+        .split_whitespace()
+        .next()
+        .ok_or("empty checksum file")?
+        .to_lowercase())
+}
+
+/// Fetches the tag of the latest published release.
+pub fn latest_release_tag() -> Result<String, Box<dyn std::error::Error>> {
+    let url = format!("https://api.github.com/repos/{}/releases/latest", REPO);
+    let body = download(&url)?;
+    let release: serde_json::Value = serde_json::from_slice(&body)?;
+    release["tag_name"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "release response has no tag_name".into())
+}
+
+/// Downloads the release archive for this platform, verifies it against the
+/// published `.sha256` checksum, and replaces the running executable.
+pub fn self_update(tag: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let platform = platform().ok_or("no prebuilt release for this platform")?;
+    let (archive_name, checksum_name) = release_assets(&platform);
+    let base_url = format!("https://github.com/{}/releases/download/{}", REPO, tag);
+
+    let archive = download(&format!("{}/{}", base_url, archive_name))?;
+    let expected = parse_checksum(&download(&format!("{}/{}", base_url, checksum_name))?)?;
+    verify_checksum(&archive, &expected)?;
+
+    let staging = cache_dir().join(format!("update-{}", std::process::id()));
+    fs::create_dir_all(&staging)?;
+    let archive_path = staging.join(&archive_name);
+    fs::write(&archive_path, &archive)?;
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(&staging)
+        .status()?;
+    if !status.success() {
+        return Err("failed to extract release archive".into());
+    }
+
+    let current_exe = env::current_exe()?;
+    replace_executable(&staging.join("compass"), &current_exe)?;
+    let _ = fs::remove_dir_all(&staging);
+    Ok(current_exe)
+}
+
+pub fn verify_checksum(data: &[u8], expected: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let actual = sha256_hex(data);
+    if actual != expected {
+        return Err(format!("checksum mismatch: expected {}, got {}", expected, actual).into());
+    }
+    Ok(())
+}
+
+fn replace_executable(
+    new_binary: &Path,
+    current_exe: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Copy next to the target first so the final rename is atomic and a
+    // failed copy never leaves a truncated executable behind.
+    let staged = current_exe.with_extension("new");
+    fs::copy(new_binary, &staged)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(&staged, current_exe)?;
+    Ok(())
+}
+
+/// Fetches `url` with `curl`, which every release platform ships.
+pub(crate) fn download(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "-H", "User-Agent: compass", url])
+        .output()
+        .map_err(|e| format!("failed to run curl (required for downloads): {}", e))?;
+    if !output.status.success() {
+        return Err(format!("download failed: {}", url).into());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_comparison() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.1.10", "0.1.9"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0-rc1", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.1"));
+        assert!(!is_newer("v0.1", "0.1.0"));
+        assert!(is_newer("0.2", "0.1.9"));
+    }
+
+    #[test]
+    fn test_release_workflow_publishes_the_assets_self_update_fetches() {
+        let workflow = include_str!("../.github/workflows/release.yml");
+        for platform in ["linux-amd64", "linux-arm64", "macos-amd64", "macos-arm64"] {
+            assert!(workflow.contains(&format!("asset_name: compass-{}\n", platform)));
+            let (archive, checksum) = release_assets(platform);
+            let uploaded = |name: &str| {
+                name.replace(&format!("compass-{}", platform), "${{ matrix.asset_name }}")
+            };
+            assert!(workflow.contains(&uploaded(&archive)), "{}", archive);
+            assert!(workflow.contains(&uploaded(&checksum)), "{}", checksum);
+        }
+
+        // The line `shasum -a 256 <archive>` writes.
+        let expected = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let line = format!("{}  compass-linux-amd64.tar.gz\n", expected.to_uppercase());
+        assert_eq!(parse_checksum(line.as_bytes()).unwrap(), expected);
+        assert!(parse_checksum(b"\n").is_err());
+    }
+
+    #[test]
+    fn test_checksum_verification() {
+        let expected = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(sha256_hex(b"hello"), expected);
+        assert!(verify_checksum(b"hello", expected).is_ok());
+        assert!(verify_checksum(b"hello!", expected).is_err());
+    }
+}
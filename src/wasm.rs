@@ -0,0 +1,87 @@
+//! JavaScript bindings for running compass in a browser or VS Code web,
+//! built with the `wasm` feature for `wasm32-unknown-unknown`:
+//!
+//! ```js
+//! import init, { analyzeSource } from "./pkg/compass.js";
+//! await init();
+//! const report = analyzeSource("fn main() {}", "rust", null);
+//! ```
+//!
+//! Only the built-in grammars are available, and nothing is read from disk
+//! beyond what `configToml` supplies.
+
+use crate::config::AnalyzerConfig;
+use crate::language::LanguageRegistry;
+use crate::output::json::format_score_as_json;
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+/// Path the analyzed source is reported under, for the config's path
+/// overrides and test-file scoping.
+const LABEL: &str = "input";
+
+/// Analyzes `code` as `language` with the rules of `configToml`, or the
+/// language's built-in rules when it is `null`, and returns the JSON report
+/// as a plain object. Throws on an unknown language, an invalid config, or
+/// a language the config has no rules for.
+#[wasm_bindgen(js_name = analyzeSource)]
+pub fn analyze_source(
+    code: &str,
+    language: &str,
+    config_toml: Option<String>,
+) -> Result<JsValue, JsError> {
+    let report = analyze(code, language, config_toml.as_deref())
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    report
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// The report behind [`analyze_source`].
+pub fn analyze(
+    code: &str,
+    language: &str,
+    config_toml: Option<&str>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let registry = LanguageRegistry::builtin();
+    let definition = registry
+        .get(language)
+        .ok_or_else(|| format!("unsupported language '{}'", language))?;
+    let config = config_toml.map(AnalyzerConfig::from_str).transpose()?;
+    let report = crate::api::analyze_source(LABEL, code, definition, config.as_ref())?
+        .ok_or_else(|| format!("no enabled rules for {}", definition.name))?;
+    Ok(format_score_as_json(&report.results, &report.score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_uses_builtin_rules_or_the_given_config() {
+        let code = "fn main() {\n    let x = Some(1).unwrap();\n}\n";
+        let builtin = analyze(code, "rust", None).unwrap();
+        assert!(builtin["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["rule"] == "no_unwrap_prefer_error_handling"));
+
+        let config = "[[rules]]\nname = \"no_main\"\nquery = \"(function_item name: (identifier) @name (#eq? @name \\\"main\\\"))\"\nseverity = \"warning\"\nmessage = \"main\"\nenabled = true\n";
+        let custom = analyze(code, "rust", Some(config)).unwrap();
+        let rules: Vec<_> = custom["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["rule"].clone())
+            .collect();
+        assert_eq!(rules, ["no_main"]);
+
+        assert_eq!(
+            analyze(code, "cobol", None).unwrap_err().to_string(),
+            "unsupported language 'cobol'"
+        );
+        assert!(analyze(code, "rust", Some("[[rules]]\nname = 1")).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::discovery::SKIPPED_DIRS;
+use crate::glob::glob_match;
+use crate::ratings::Ratings;
+use crate::report::{FileReport, ProjectScore, ProjectWeighting};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A package (or module) inside a multi-package repository.
+#[derive(Debug, Clone)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub root: PathBuf,
+    /// Build system that declared it: `npm`, `maven`, or `gradle`.
+    pub kind: &'static str,
+}
+
+/// Whether a file is production code or test code, following the Maven and
+/// Gradle convention of `src/main/...` and `src/test/...`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceSet {
+    Main,
+    Test,
+}
+
+impl SourceSet {
+    pub fn of<P: AsRef<Path>>(path: P) -> Self {
+        let components: Vec<_> = path
+            .as_ref()
+            .components()
+            .map(|component| component.as_os_str())
+            .collect();
+        let is_test = components
+            .windows(2)
+            .any(|pair| pair[0] == "src" && pair[1] == "test");
+        if is_test {
+            SourceSet::Test
+        } else {
+            SourceSet::Main
+        }
+    }
+}
+
+/// Detects npm/yarn workspaces, Maven modules, or Gradle subprojects under
+/// `root`, in that order.
+pub fn detect_packages(root: &Path) -> Option<Vec<WorkspacePackage>> {
+    detect_npm_workspaces(root)
+        .or_else(|| detect_maven_modules(root))
+        .or_else(|| detect_gradle_projects(root))
+}
+
+/// Reads `workspaces` from `root/package.json` (npm and yarn accept either
+/// an array or `{ "packages": [...] }`) and returns the matching packages.
+/// Returns `None` when the directory is not a JS workspace root.
+pub fn detect_npm_workspaces(root: &Path) -> Option<Vec<WorkspacePackage>> {
+    let manifest = read_package_json(&root.join("package.json"))?;
+    let patterns: Vec<String> = match &manifest["workspaces"] {
+        Value::Array(patterns) => patterns
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(String::from)
+            .collect(),
+        Value::Object(config) => config
+            .get("packages")?
+            .as_array()?
+            .iter()
+            .filter_map(|p| p.as_str())
+            .map(String::from)
+            .collect(),
+        _ => return None,
+    };
+
+    let (excludes, includes): (Vec<&String>, Vec<&String>) = patterns
+        .iter()
+        .partition(|pattern| pattern.starts_with('!'));
+
+    let mut packages = Vec::new();
+    for dir in candidate_dirs(root) {
+        let relative = relative_slash_path(&dir, root);
+        let included = includes
+            .iter()
+            .any(|pattern| glob_match(pattern, &relative));
+        let excluded = excludes
+            .iter()
+            .any(|pattern| glob_match(&pattern[1..], &relative));
+        if !included || excluded {
+            continue;
+        }
+
+        let Some(package) = read_package_json(&dir.join("package.json")) else {
+            continue;
+        };
+        let name = package["name"].as_str().unwrap_or(&relative).to_string();
+        packages.push(WorkspacePackage {
+            name,
+            root: dir,
+            kind: "npm",
+        });
+    }
+
+    Some(packages)
+}
+
+/// Follows `<modules>` from `root/pom.xml` recursively. Returns `None` when
+/// the root POM is missing or is not an aggregator.
+pub fn detect_maven_modules(root: &Path) -> Option<Vec<WorkspacePackage>> {
+    let pom = fs::read_to_string(root.join("pom.xml")).ok()?;
+    let modules = xml_values(&pom, "module");
+    if modules.is_empty() {
+        return None;
+    }
+
+    let mut packages = Vec::new();
+    let mut pending: Vec<PathBuf> = modules.iter().map(|module| root.join(module)).collect();
+    while let Some(dir) = pending.pop() {
+        let Ok(pom) = fs::read_to_string(dir.join("pom.xml")) else {
+            continue;
+        };
+        pending.extend(
+            xml_values(&pom, "module")
+                .iter()
+                .map(|module| dir.join(module)),
+        );
+
+        // The module's own artifactId comes after its <parent> block.
+        let own_section = pom.split("</parent>").last().unwrap_or(&pom);
+        let name = xml_values(own_section, "artifactId")
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| relative_slash_path(&dir, root));
+        packages.push(WorkspacePackage {
+            name,
+            root: dir,
+            kind: "maven",
+        });
+    }
+
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    Some(packages)
+}
+
+/// Reads `include` statements from `settings.gradle` or
+/// `settings.gradle.kts`. Project paths like `:libs:core` map to
+/// `libs/core`; custom `projectDir` assignments are not followed.
+pub fn detect_gradle_projects(root: &Path) -> Option<Vec<WorkspacePackage>> {
+    let settings = fs::read_to_string(root.join("settings.gradle"))
+        .or_else(|_| fs::read_to_string(root.join("settings.gradle.kts")))
+        .ok()?;
+
+    let mut packages = Vec::new();
+    for line in settings.lines().map(str::trim) {
+        let Some(arguments) = line.strip_prefix("include") else {
+            continue;
+        };
+        if !arguments.starts_with([' ', '(', '\'', '"']) {
+            continue;
+        }
+        for project in quoted_strings(arguments) {
+            let project = project.trim_start_matches(':');
+            let dir = root.join(project.replace(':', "/"));
+            if dir.is_dir() {
+                packages.push(WorkspacePackage {
+                    name: project.to_string(),
+                    root: dir,
+                    kind: "gradle",
+                });
+            }
+        }
+    }
+
+    if packages.is_empty() {
+        return None;
+    }
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    Some(packages)
+}
+
+/// Per-package score and rule statistics. Files are attributed to the
+/// deepest package containing them; anything outside every package is
+/// left out.
+pub fn package_breakdown(
+    packages: &[WorkspacePackage],
+    files: &[FileReport],
+    weighting: ProjectWeighting,
+    ratings: &Ratings,
+) -> Value {
+    let mut per_package: Vec<Vec<&FileReport>> = packages.iter().map(|_| Vec::new()).collect();
+    for file in files {
+        if let Some(index) = owning_package(packages, Path::new(&file.path)) {
+            per_package[index].push(file);
+        }
+    }
+
+    packages
+        .iter()
+        .zip(per_package)
+        .map(|(package, files)| {
+            let mut rule_counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for file in &files {
+                for result in &file.results {
+                    *rule_counts.entry(result.rule_name.as_str()).or_default() += 1;
+                }
+            }
+            let score = ProjectScore::from_file_refs(&files, weighting).with_ratings(ratings);
+
+            json!({
+                "name": package.name,
+                "kind": package.kind,
+                "path": package.root.display().to_string(),
+                "files_analyzed": score.files_analyzed,
+                "test_files": files.iter().filter(|file| SourceSet::of(&file.path) == SourceSet::Test).count(),
+                "average_score": score.overall_score,
+                "lowest_score": score.lowest_score,
+                "rating": score.rating,
+                "total_issues": score.total_issues,
+                "rule_counts": rule_counts,
+            })
+        })
+        .collect()
+}
+
+fn owning_package(packages: &[WorkspacePackage], path: &Path) -> Option<usize> {
+    packages
+        .iter()
+        .enumerate()
+        .filter(|(_, package)| path.starts_with(&package.root))
+        .max_by_key(|(_, package)| package.root.components().count())
+        .map(|(index, _)| index)
+}
+
+/// Text content of every `<tag>...</tag>` element. Good enough for the
+/// flat elements compass reads from POMs; comments are not stripped.
+fn xml_values(xml: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut values = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        values.push(rest[..end].trim().to_string());
+        rest = &rest[end + close.len()..];
+    }
+    values
+}
+
+fn quoted_strings(text: &str) -> Vec<&str> {
+    let mut strings = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['\'', '"']) {
+        let quote = rest.as_bytes()[start] as char;
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(quote) else {
+            break;
+        };
+        strings.push(&rest[..end]);
+        rest = &rest[end + 1..];
+    }
+    strings
+}
+
+fn read_package_json(path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Every directory under `root` that could be a package, excluding hidden
+/// and dependency/build directories.
+fn candidate_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !path.is_dir() || name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            dirs.push(path.clone());
+            pending.push(path);
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+fn relative_slash_path(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_set_follows_maven_layout() {
+        assert_eq!(
+            SourceSet::of("core/src/test/java/AppTest.java"),
+            SourceSet::Test
+        );
+        assert_eq!(
+            SourceSet::of("core/src/main/java/App.java"),
+            SourceSet::Main
+        );
+        assert_eq!(SourceSet::of("test/App.java"), SourceSet::Main);
+    }
+
+    #[test]
+    fn test_xml_values_and_quoted_strings() {
+        let pom = "<modules>\n  <module>core</module>\n  <module> web </module>\n</modules>";
+        assert_eq!(xml_values(pom, "module"), ["core", "web"]);
+        assert_eq!(
+            quoted_strings("(':app', \"libs:core\")"),
+            [":app", "libs:core"]
+        );
+    }
+}
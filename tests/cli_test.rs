@@ -0,0 +1,2413 @@
+use serde_json::Value;
+use std::process::Command;
+
+/// The compass binary, outside of any CI service so output defaults do not
+/// depend on where the tests run.
+fn command() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_compass"));
+    command
+        .env_remove("CI")
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("GITLAB_CI");
+    command
+}
+
+/// `cargo compass`, the way cargo runs it.
+fn cargo_compass(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_cargo-compass"))
+        .arg("compass")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn compass(args: &[&str]) -> std::process::Output {
+    command()
+        .args(args)
+        .output()
+        .expect("Failed to run compass binary")
+}
+
+#[test]
+fn test_json_output_is_the_only_thing_on_stdout() {
+    let output = compass(&["tests/fixtures/test.go"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let report: Value = serde_json::from_str(&stdout).expect("stdout should be pure JSON");
+    assert!(report["score"].is_number());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Analyzing Go file"),
+        "Banner should go to stderr"
+    );
+}
+
+#[test]
+fn test_template_output_is_the_only_thing_on_stdout() {
+    let template = std::env::temp_dir().join("compass_cli_test_template.hbs");
+    std::fs::write(&template, "{{rating}}").unwrap();
+
+    let output = compass(&[
+        "--format",
+        "template",
+        "--template",
+        template.to_str().unwrap(),
+        "tests/fixtures/test.js",
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        !stdout.contains("Analyzing"),
+        "Banner should not pollute stdout"
+    );
+    assert!(["Excellent", "Good", "Fair", "Poor", "Critical"].contains(&stdout.as_str()));
+}
+
+#[test]
+fn test_export_issues_output_is_pure_json() {
+    let state = std::env::temp_dir().join("compass_cli_test_export_state");
+    let _ = std::fs::remove_file(&state);
+
+    let output = compass(&[
+        "export-issues",
+        "--repo",
+        "org/name",
+        "--min-severity",
+        "warning",
+        "--state",
+        state.to_str().unwrap(),
+        "--dry-run",
+        "tests/fixtures/test.go",
+    ]);
+    assert!(output.status.success());
+    let payloads: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert!(!payloads.as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_errors_go_to_stderr() {
+    let output = compass(&["does/not/exist.rs"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("does not exist"));
+}
+
+#[test]
+fn test_porcelain_suppresses_banner() {
+    let output = compass(&["--porcelain", "tests/fixtures/test.go"]);
+    assert!(output.status.success());
+    assert!(
+        output.stderr.is_empty(),
+        "Porcelain mode should not print the banner"
+    );
+    let _: Value = serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+}
+
+#[test]
+fn test_directory_mode_with_env_config_and_summary_file() {
+    let summary = std::env::temp_dir().join("compass_cli_test_summary.json");
+    let _ = std::fs::remove_file(&summary);
+
+    let output = command()
+        .args([
+            "--porcelain",
+            "--summary-file",
+            summary.to_str().unwrap(),
+            "tests/fixtures",
+        ])
+        .env("COMPASS_CONFIG_TOML", include_str!("../config/config.toml"))
+        .output()
+        .expect("Failed to run compass binary");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let project: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert_eq!(project["schema_version"], 2);
+    let reports = project["files"].as_array().unwrap();
+    assert!(reports
+        .iter()
+        .any(|r| r["path"].as_str().unwrap().ends_with("test.go") && r["language"] == "go"));
+    assert!(reports
+        .iter()
+        .all(|r| r["score"].is_number() && r["issues"].is_array()));
+    assert_eq!(
+        project["summary"]["files_analyzed"].as_u64().unwrap() as usize,
+        reports.len()
+    );
+
+    let summary: Value = serde_json::from_str(&std::fs::read_to_string(&summary).unwrap()).unwrap();
+    assert_eq!(
+        summary["files_analyzed"].as_u64().unwrap() as usize,
+        reports.len()
+    );
+    assert_eq!(summary["exit_code"], 0);
+}
+
+#[test]
+fn test_stdin_input_requires_and_uses_language() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = command()
+        .args(["-", "--language", "go", "--porcelain"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run compass binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(include_bytes!("fixtures/test.go"))
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let report: Value = serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert!(report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|issue| issue["rule"] == "panic_usage"));
+
+    let missing_language = compass(&["--stdin"]);
+    assert!(!missing_language.status.success());
+    assert!(String::from_utf8(missing_language.stderr)
+        .unwrap()
+        .contains("--language"));
+}
+
+#[test]
+fn test_npm_workspaces_report_per_package_and_skip_build_output() {
+    let root = std::env::temp_dir().join("compass_cli_test_npm_workspace");
+    let _ = std::fs::remove_dir_all(&root);
+    for dir in [
+        "packages/web/src",
+        "packages/web/dist",
+        "packages/api/node_modules/dep",
+    ] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    std::fs::write(
+        root.join("package.json"),
+        r#"{ "private": true, "workspaces": ["packages/*"] }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("packages/web/package.json"),
+        r#"{ "name": "@acme/web" }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("packages/api/package.json"),
+        r#"{ "name": "@acme/api" }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("packages/web/src/index.js"),
+        "console.log('hi');\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("packages/web/dist/index.js"),
+        "console.log('built');\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("packages/api/server.js"), "var port = 80;\n").unwrap();
+    std::fs::write(
+        root.join("packages/api/node_modules/dep/index.js"),
+        "console.log('dep');\n",
+    )
+    .unwrap();
+
+    let output = compass(&["--porcelain", root.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let project: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert_eq!(
+        project["summary"]["files_analyzed"], 2,
+        "dist and node_modules are skipped"
+    );
+
+    let packages = project["packages"].as_array().unwrap();
+    let names: Vec<&str> = packages
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["@acme/api", "@acme/web"]);
+    assert_eq!(packages[1]["files_analyzed"], 1);
+    assert!(
+        packages[1]["rule_counts"]["console_log_usage"]
+            .as_u64()
+            .unwrap()
+            >= 1
+    );
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_maven_modules_report_per_module_with_test_profile() {
+    let root = std::env::temp_dir().join("compass_cli_test_maven");
+    let _ = std::fs::remove_dir_all(&root);
+    for dir in [
+        "core/src/main/java",
+        "core/src/test/java",
+        "core/target/classes",
+        "app/src/main/java",
+    ] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    std::fs::write(
+        root.join("pom.xml"),
+        "<project><modules><module>core</module><module>app</module></modules></project>",
+    )
+    .unwrap();
+    std::fs::write(root.join("core/pom.xml"), "<project><parent><artifactId>parent</artifactId></parent><artifactId>acme-core</artifactId></project>").unwrap();
+    std::fs::write(
+        root.join("app/pom.xml"),
+        "<project><artifactId>acme-app</artifactId></project>",
+    )
+    .unwrap();
+    let printing = "class A { void run() { System.out.println(\"hi\"); } }\n";
+    std::fs::write(root.join("core/src/main/java/A.java"), printing).unwrap();
+    std::fs::write(root.join("core/src/test/java/ATest.java"), printing).unwrap();
+    std::fs::write(root.join("core/target/classes/Gen.java"), printing).unwrap();
+    std::fs::write(root.join("app/src/main/java/App.java"), "class App {}\n").unwrap();
+
+    let output = compass(&["--porcelain", root.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let project: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert_eq!(
+        project["summary"]["files_analyzed"], 3,
+        "target/ is skipped"
+    );
+
+    let files = project["files"].as_array().unwrap();
+    let println_hits = |suffix: &str| {
+        let file = files
+            .iter()
+            .find(|f| f["path"].as_str().unwrap().ends_with(suffix))
+            .unwrap();
+        file["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|i| i["rule"] == "system_out_println")
+            .count()
+    };
+    assert!(println_hits("A.java") > 0);
+    assert_eq!(
+        println_hits("ATest.java"),
+        0,
+        "main-only rules skip src/test"
+    );
+
+    let modules = project["packages"].as_array().unwrap();
+    let names: Vec<&str> = modules
+        .iter()
+        .map(|m| m["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["acme-app", "acme-core"]);
+    assert!(modules.iter().all(|m| m["kind"] == "maven"));
+    assert_eq!(modules[1]["files_analyzed"], 2);
+    assert_eq!(modules[1]["test_files"], 1);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_recorded_history_is_printed_as_trend() {
+    let history = std::env::temp_dir().join("compass_cli_test_history.jsonl");
+    let _ = std::fs::remove_file(&history);
+    let history = history.to_str().unwrap();
+
+    for _ in 0..2 {
+        let output = compass(&[
+            "--porcelain",
+            "--history-file",
+            history,
+            "tests/fixtures/test.go",
+        ]);
+        assert!(output.status.success());
+    }
+
+    let output = compass(&["history", "--file", history, "--format", "json"]);
+    assert!(output.status.success());
+    let entries: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["files_analyzed"], 1);
+    assert!(entries[0]["score"].is_number() && entries[0]["recorded_at"].is_number());
+
+    let table = String::from_utf8(compass(&["history", "--file", history]).stdout).unwrap();
+    assert!(table.contains("Trend over 2 runs"));
+    let _ = std::fs::remove_file(history);
+}
+
+#[test]
+fn test_compare_reports_regressions_despite_line_drift() {
+    let dir = std::env::temp_dir().join("compass_cli_test_compare");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let original = std::fs::read_to_string("tests/fixtures/test.go").unwrap();
+    let shifted = format!(
+        "// header\n// header\n\n{}\nfunc extra() {{\n\tpanic(\"new\")\n}}\n",
+        original
+    );
+    std::fs::write(dir.join("old.go"), &original).unwrap();
+    std::fs::write(dir.join("new.go"), &shifted).unwrap();
+
+    let report = |name: &str| {
+        let output = compass(&[
+            "--porcelain",
+            dir.join(format!("{}.go", name)).to_str().unwrap(),
+        ]);
+        let path = dir.join(format!("{}.json", name));
+        std::fs::write(&path, &output.stdout).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+    let (old, new) = (report("old"), report("new"));
+
+    let output = compass(&["compare", &old, &new]);
+    assert!(
+        !output.status.success(),
+        "a new issue exceeds the default threshold"
+    );
+    let comparison: Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+    assert_eq!(
+        comparison["fixed_issue_count"], 0,
+        "shifted issues still match"
+    );
+    assert!(comparison["new_issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|issue| issue["rule"] == "panic_usage"));
+    assert_eq!(comparison["regressed"], true);
+
+    let allowed = compass(&["compare", "--max-new-issues", "10", &old, &new]);
+    assert!(allowed.status.success());
+    let unchanged = compass(&["compare", &old, &old]);
+    assert!(unchanged.status.success());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_group_by_collapses_identical_findings() {
+    let root = std::env::temp_dir().join("compass_cli_test_group_by");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    for name in ["a.js", "b.js"] {
+        std::fs::write(
+            root.join(name),
+            "function f() {\n".to_string() + &"  console.log(\"debug\");\n".repeat(7) + "}\n",
+        )
+        .unwrap();
+    }
+    let is_debug_call = |group: &&Value| group["text"] == "console.log(\"debug\")";
+
+    let output = compass(&["--porcelain", "--group-by", "rule", root.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["group_by"], "rule");
+    assert!(report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|file| file.get("issues").is_none()));
+    let group = report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(is_debug_call)
+        .expect("grouped console.log calls");
+    assert_eq!(group["rule"], "console_log_usage");
+    assert_eq!(group["count"], 14);
+    assert_eq!(group["locations"].as_array().unwrap().len(), 5);
+    assert!(group["locations"][0]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("a.js"));
+
+    let output = compass(&["--porcelain", "--group-by", "file", root.to_str().unwrap()]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    for file in report["files"].as_array().unwrap() {
+        let group = file["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(is_debug_call)
+            .unwrap();
+        assert_eq!(group["count"], 7);
+        assert_eq!(
+            file["total_issues"].as_u64().unwrap() as usize,
+            file["issues"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|g| g["count"].as_u64().unwrap() as usize)
+                .sum::<usize>()
+        );
+    }
+
+    let output = compass(&[
+        "--porcelain",
+        "--group-by",
+        "none",
+        root.join("a.js").to_str().unwrap(),
+    ]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.get("group_by").is_none());
+    assert!(report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|issue| issue.get("count").is_none()));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_summary_lists_hotspots_without_findings() {
+    let output = compass(&["summary", "--porcelain", "--top", "3", "tests/fixtures"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).expect("stdout should be pure JSON");
+
+    assert!(report.get("files").is_none() && report.get("issues").is_none());
+    assert!(report["summary"]["files_analyzed"].as_u64().unwrap() > 3);
+
+    let files = report["lowest_scoring_files"].as_array().unwrap();
+    assert_eq!(files.len(), 3);
+    assert!(files
+        .windows(2)
+        .all(|pair| pair[0]["score"].as_f64() <= pair[1]["score"].as_f64()));
+
+    let rules = report["top_rules"].as_array().unwrap();
+    assert_eq!(rules.len(), 3);
+    assert!(rules
+        .windows(2)
+        .all(|pair| pair[0]["count"].as_u64() >= pair[1]["count"].as_u64()));
+
+    let functions = report["worst_functions"].as_array().unwrap();
+    assert_eq!(functions.len(), 3);
+    assert!(functions[0]["issues"].as_u64().unwrap() > 0 && functions[0]["name"].is_string());
+
+    let flag = compass(&[
+        "--summary-only",
+        "--porcelain",
+        "--top",
+        "3",
+        "tests/fixtures",
+    ]);
+    assert_eq!(flag.stdout, output.stdout);
+}
+
+#[test]
+fn test_grade_scores_each_submission_with_feedback() {
+    let root = std::env::temp_dir().join("compass_cli_test_grade");
+    let feedback = root.join("feedback");
+    let submissions = root.join("submissions");
+    let _ = std::fs::remove_dir_all(&root);
+    for student in ["alice", "bob", "carol"] {
+        std::fs::create_dir_all(submissions.join(student)).unwrap();
+    }
+    std::fs::write(
+        submissions.join("alice/main.go"),
+        "package main\n\nfunc main() {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        submissions.join("bob/main.go"),
+        "package main\n\nfunc main() {\n\tpanic(\"boom\")\n}\n",
+    )
+    .unwrap();
+
+    let output = compass(&[
+        "grade",
+        "--submissions",
+        submissions.to_str().unwrap(),
+        "--per-student",
+        "--output-dir",
+        feedback.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let sheet = String::from_utf8(output.stdout).unwrap();
+    let rows: Vec<&str> = sheet.lines().collect();
+    assert!(rows[0].starts_with("student,score,rating"));
+    assert_eq!(rows.len(), 4);
+    assert!(rows[1].starts_with("alice,10,Excellent,1,"));
+    assert!(rows[2].starts_with("bob,") && !rows[2].starts_with("bob,10,"));
+    assert!(rows[3].starts_with("carol,,N/A,0,"));
+
+    let bob = std::fs::read_to_string(feedback.join("bob.md")).unwrap();
+    assert!(bob.starts_with("# Feedback for bob"));
+    assert!(bob.contains("## main.go"));
+    assert!(bob.contains("- Line 4: Use of panic() (Warning)"));
+    assert!(bob.contains("Suggestion: Prefer returning an error"));
+    assert!(std::fs::read_to_string(feedback.join("carol.md"))
+        .unwrap()
+        .contains("No source files"));
+
+    let output = compass(&[
+        "grade",
+        "--submissions",
+        submissions.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    let sheet: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(sheet[1]["student"], "bob");
+    assert!(sheet[1]["warnings"].as_u64().unwrap() > 0);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_junit_output_has_a_test_case_per_rule() {
+    let output = compass(&["--porcelain", "--format", "junit", "tests/fixtures/test.go"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let xml = String::from_utf8(output.stdout).unwrap();
+
+    assert!(
+        xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"compass\"")
+    );
+    let rules = include_str!("../config/go.toml")
+        .matches("[[rules]]")
+        .count();
+    assert_eq!(xml.matches("<testcase ").count(), rules);
+    assert!(xml.contains("<testcase name=\"syntax_error\" classname=\"tests/fixtures/test.go\"/>"));
+    assert!(xml.contains("<failure message=\"Use of panic() (1 finding)\" type=\"Warning\">tests/fixtures/test.go:17: Use of panic()\n</failure>"));
+    let failures = xml.matches("<failure ").count();
+    assert!(xml.contains(&format!("tests=\"{}\" failures=\"{}\"", rules, failures)));
+}
+
+#[test]
+fn test_rdjson_output_for_reviewdog() {
+    let output = compass(&[
+        "--porcelain",
+        "--format",
+        "rdjson",
+        "tests/fixtures/test.go",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["source"]["name"], "compass");
+    let panic = report["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|diagnostic| diagnostic["code"]["value"] == "panic_usage")
+        .unwrap();
+    assert_eq!(panic["severity"], "WARNING");
+    assert_eq!(panic["location"]["path"], "tests/fixtures/test.go");
+    assert_eq!(panic["location"]["range"]["start"]["line"], 17);
+    assert!(panic["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Use of panic()"));
+}
+
+#[test]
+fn test_xcode_and_android_lint_output() {
+    let output = compass(&["--porcelain", "--format", "xcode", "tests/fixtures/test.go"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text
+        .lines()
+        .any(|line| line.starts_with("tests/fixtures/test.go:17:")
+            && line.ends_with(": warning: Use of panic() [panic_usage]")));
+
+    let output = compass(&[
+        "--porcelain",
+        "--format",
+        "android-lint",
+        "tests/fixtures/test.go",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let xml = String::from_utf8(output.stdout).unwrap();
+    assert!(xml.starts_with(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<issues format=\"6\" by=\"compass\">\n"
+    ));
+    assert!(xml.contains("        id=\"panic_usage\"\n        severity=\"Warning\"\n        message=\"Use of panic()\"\n        category=\"Correctness\"\n        priority=\"5\"\n"));
+    assert!(xml.contains("            file=\"tests/fixtures/test.go\"\n            line=\"17\"\n"));
+    assert!(xml.trim_end().ends_with("</issues>"));
+}
+
+#[test]
+fn test_search_rule_index() {
+    let output = compass(&[
+        "search",
+        "panic",
+        "--index",
+        "rule-packs/index.json",
+        "--format",
+        "json",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let packs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<_> = packs
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pack| pack["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["rust-no-panics"]);
+    assert_eq!(packs[0]["file"], "rust-no-panics.toml");
+    assert!(packs[0]["install"]
+        .as_str()
+        .unwrap()
+        .ends_with("rule-packs/rust-no-panics.toml -o rust-no-panics.toml"));
+
+    // Installing again would overwrite the pack, so search says so
+    let root = std::env::temp_dir().join("compass_cli_test_search");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("rust-no-panics.toml"), "").unwrap();
+    let index = std::fs::canonicalize("rule-packs/index.json").unwrap();
+    let output = command()
+        .current_dir(&root)
+        .args(["search", "panic", "--index", index.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Then add \"rust-no-panics.toml\" to `include` in compass.toml"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Warning: rust-no-panics.toml already exists; installing rust-no-panics overwrites it"
+    ));
+    let _ = std::fs::remove_dir_all(&root);
+
+    let output = compass(&[
+        "search",
+        "panic",
+        "--index",
+        "http://example.com/index.json",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("plain HTTP"));
+}
+
+#[test]
+fn test_policy_gates_fail_the_run() {
+    let policy = std::env::temp_dir().join("compass_cli_test_policy.toml");
+    let summary = std::env::temp_dir().join("compass_cli_test_policy_summary.json");
+    std::fs::write(&policy, "[gates]\nmax_warnings = 0\n\n[[required_packs]]\nname = \"error-handling\"\nlanguage = \"go\"\nrules = [\"panic_usage\", \"unclosed_rows\"]\n\n[minimum_severities]\npanic_usage = \"error\"\n\n[protected_paths]\npaths = [\"tests/fixtures/**\"]\n").unwrap();
+
+    let output = compass(&[
+        "--porcelain",
+        "--policy",
+        policy.to_str().unwrap(),
+        "--summary-file",
+        summary.to_str().unwrap(),
+        "tests/fixtures/test.go",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Policy violation: required pack 'error-handling': rule 'unclosed_rows' is not enabled for go"), "{}", stderr);
+    assert!(stderr.contains("Policy violation: rule 'panic_usage' runs at Warning for go, policy requires at least Error"));
+    assert!(stderr
+        .contains("Policy violation: tests/fixtures/test.go:17: panic_usage in a protected path"));
+    assert!(stderr.contains("warnings found (allowed: 0)"));
+    let summary: Value = serde_json::from_str(&std::fs::read_to_string(&summary).unwrap()).unwrap();
+    assert_eq!(summary["exit_code"], 1);
+
+    std::fs::write(&policy, "[gates]\nmin_score = 0.0\n").unwrap();
+    assert!(compass(&[
+        "--porcelain",
+        "--policy",
+        policy.to_str().unwrap(),
+        "tests/fixtures/test.go"
+    ])
+    .status
+    .success());
+
+    std::fs::write(&policy, "[gates]\nmin_sore = 7.0\n").unwrap();
+    let output = compass(&[
+        "--porcelain",
+        "--policy",
+        policy.to_str().unwrap(),
+        "tests/fixtures/test.go",
+    ]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: invalid policy"));
+}
+
+#[test]
+fn test_audit_log_appends_gate_decisions() {
+    let root = std::env::temp_dir().join("compass_cli_test_audit");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let policy = root.join("policy.toml");
+    let log = root.join("logs/audit.jsonl");
+    std::fs::write(&policy, "[gates]\nmax_warnings = 0\n").unwrap();
+
+    let args = [
+        "--porcelain",
+        "--policy",
+        policy.to_str().unwrap(),
+        "--audit-log",
+        log.to_str().unwrap(),
+    ];
+    assert!(!compass(&[&args[..], &["tests/fixtures/test.go"]].concat())
+        .status
+        .success());
+    let second = compass(&[&args[..], &["tests/fixtures/test.js"]].concat());
+
+    let records: Vec<Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["decision"], "fail");
+    assert!(records[0]["violations"][0]
+        .as_str()
+        .unwrap()
+        .contains("warnings found (allowed: 0)"));
+    assert_eq!(
+        records[0]["inputs"]["tests/fixtures/test.go"]
+            .as_str()
+            .unwrap()
+            .len(),
+        64
+    );
+    assert_eq!(records[0]["policy"]["sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(records[0]["config_fingerprint"].as_str().unwrap().len(), 64);
+    assert!(records[1]["inputs"]["tests/fixtures/test.js"].is_string());
+    assert_eq!(
+        records[1]["decision"],
+        if second.status.success() {
+            "pass"
+        } else {
+            "fail"
+        }
+    );
+}
+
+#[test]
+fn test_exemption_token_lets_a_failing_gate_pass() {
+    let root = std::env::temp_dir().join("compass_cli_test_exemption");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let policy = root.join("policy.toml");
+    let log = root.join("audit.jsonl");
+    std::fs::write(&policy, "[gates]\nmax_warnings = 0\n").unwrap();
+    let with_key = |key: &str, args: &[&str]| {
+        command()
+            .env("COMPASS_EXEMPTION_KEY", key)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let issued = with_key(
+        "s3cret",
+        &["exempt", "--reason", "hotfix for outage", "--ttl", "1h"],
+    );
+    assert!(
+        issued.status.success(),
+        "{}",
+        String::from_utf8_lossy(&issued.stderr)
+    );
+    let token = String::from_utf8(issued.stdout).unwrap().trim().to_string();
+    assert!(token.starts_with("compass-exempt-v1."));
+
+    // --commit is signed as the full hash it names, or refused
+    let head = String::from_utf8(
+        std::process::Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .unwrap()
+            .stdout,
+    )
+    .unwrap();
+    let named = with_key(
+        "s3cret",
+        &[
+            "exempt",
+            "--reason",
+            "hotfix",
+            "--ttl",
+            "1h",
+            "--commit",
+            &head[..7],
+        ],
+    );
+    assert!(String::from_utf8_lossy(&named.stderr)
+        .contains(&format!("Exemption for {} valid until", head.trim())));
+    let unknown = with_key(
+        "s3cret",
+        &[
+            "exempt",
+            "--reason",
+            "hotfix",
+            "--ttl",
+            "1h",
+            "--commit",
+            "not-a-commit",
+        ],
+    );
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr)
+        .contains("'not-a-commit' is not a commit in this repository"));
+
+    let gated = [
+        "--porcelain",
+        "--policy",
+        policy.to_str().unwrap(),
+        "--audit-log",
+        log.to_str().unwrap(),
+        "--allow-failure-token",
+        &token,
+        "tests/fixtures/test.go",
+    ];
+    let exempted = with_key("s3cret", &gated);
+    assert!(
+        exempted.status.success(),
+        "{}",
+        String::from_utf8_lossy(&exempted.stderr)
+    );
+    assert!(String::from_utf8_lossy(&exempted.stderr).contains("Gate failure exempted until"));
+
+    let rejected = with_key("wrong", &gated);
+    assert_eq!(rejected.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&rejected.stderr)
+        .contains("exemption token rejected: exemption token signature is invalid"));
+
+    let records: Vec<Value> = std::fs::read_to_string(&log)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records[0]["decision"], "exempted");
+    assert_eq!(records[0]["exemption"]["reason"], "hotfix for outage");
+    assert_eq!(records[1]["decision"], "fail");
+    assert!(records[1].get("exemption").is_none());
+}
+
+#[test]
+fn test_min_severity_hides_findings_and_score_min_severity_rescores() {
+    let report = |args: &[&str]| -> Value {
+        serde_json::from_slice(
+            &compass(&[args, &["--porcelain", "tests/fixtures/test.js"]].concat()).stdout,
+        )
+        .unwrap()
+    };
+    let all = report(&[]);
+    let shown = report(&["--min-severity", "warning"]);
+    let rescored = report(&[
+        "--min-severity",
+        "warning",
+        "--score-min-severity",
+        "warning",
+    ]);
+
+    assert!(all["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|issue| issue["severity"] == "info"));
+    assert!(shown["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|issue| issue["severity"] == "warning"));
+    assert_eq!(shown["score"], all["score"]);
+    assert!(rescored["score"].as_f64().unwrap() > all["score"].as_f64().unwrap());
+
+    let output = compass(&["--min-severity", "fatal", "tests/fixtures/test.js"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("invalid value 'fatal' for '--min-severity <SEVERITY>'"));
+}
+
+#[test]
+fn test_explain_prints_rule_documentation() {
+    let output = compass(&["explain", "panic_usage"]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("panic_usage (warning, go)\nUse of panic()\n"));
+    assert!(text.contains("Docs: https://go.dev/doc/effective_go#panic"));
+    assert!(text.contains("Avoid:\n    if cfg == nil {\n        panic(\"missing config\")"));
+
+    let config = std::env::temp_dir().join("compass_cli_test_explain.toml");
+    std::fs::write(&config, "[severity_overrides]\nlocal_rule = \"error\"\n\n[[rules]]\nname = \"local_rule\"\nquery = \"(identifier) @id\"\nseverity = \"info\"\nmessage = \"Local\"\ndescription = \"Explained.\"\n").unwrap();
+    let json: Value = serde_json::from_slice(
+        &compass(&[
+            "explain",
+            "local_rule",
+            config.to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .stdout,
+    )
+    .unwrap();
+    assert_eq!(json["severity"], "error");
+    assert_eq!(json["description"], "Explained.");
+    assert_eq!(json["enabled"], false);
+
+    let missing = compass(&["explain", "no_such_rule"]);
+    assert!(
+        String::from_utf8_lossy(&missing.stderr).contains("Error: no rule named 'no_such_rule'")
+    );
+}
+
+#[test]
+fn test_rules_lists_the_resolved_config() {
+    let output = compass(&["rules", "--language", "go"]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.lines().any(|line| line.starts_with("panic_usage ")
+        && line.contains(" go ")
+        && line.contains(" warning enabled  1.6")));
+    assert!(!text.contains("no_unwrap_prefer_error_handling"));
+
+    let config = std::env::temp_dir().join("compass_cli_test_rules.toml");
+    std::fs::write(&config, "[[rules]]\nname = \"scoped\"\nlanguage = \"rust\"\nquery = \"(identifier) @id\"\nseverity = \"info\"\nmessage = \"m\"\nlabels = { team = \"core\" }\n\n[[rules]]\nname = \"everywhere\"\nquery = \"(identifier) @id\"\nseverity = \"error\"\nmessage = \"m\"\nenabled = true\nweight = 2.5\n").unwrap();
+    let rules: Value = serde_json::from_slice(
+        &compass(&[
+            "rules",
+            config.to_str().unwrap(),
+            "--language",
+            "go",
+            "--format",
+            "json",
+        ])
+        .stdout,
+    )
+    .unwrap();
+    assert_eq!(rules.as_array().unwrap().len(), 1);
+    assert_eq!(rules[0]["name"], "everywhere");
+    assert_eq!(rules[0]["weight"], 2.5);
+    let rules: Value = serde_json::from_slice(
+        &compass(&["rules", config.to_str().unwrap(), "--format", "json"]).stdout,
+    )
+    .unwrap();
+    assert_eq!(rules[0]["tags"]["team"], "core");
+    assert_eq!(rules[0]["enabled"], false);
+}
+
+#[test]
+fn test_init_writes_a_config_for_the_languages_found() {
+    let root = std::env::temp_dir().join("compass_cli_test_init");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("vendor/dep")).unwrap();
+    std::fs::copy("tests/fixtures/test.go", root.join("main.go")).unwrap();
+    std::fs::copy("tests/fixtures/test.go", root.join("vendor/dep/dep.go")).unwrap();
+
+    let output = compass(&["init", root.to_str().unwrap()]);
+    assert!(output.status.success());
+    let config = root.join("compass.toml");
+    assert!(std::fs::read_to_string(&config)
+        .unwrap()
+        .contains("extends = [\"go\"]"));
+    assert!(
+        !compass(&["init", root.to_str().unwrap()]).status.success(),
+        "refuses to overwrite"
+    );
+
+    let report: Value = serde_json::from_slice(
+        &compass(&[
+            "--porcelain",
+            root.to_str().unwrap(),
+            config.to_str().unwrap(),
+        ])
+        .stdout,
+    )
+    .unwrap();
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1, "vendor/ is excluded");
+    assert!(files[0]["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|issue| issue["rule"] == "panic_usage"));
+}
+
+#[test]
+fn test_reports_carry_grammar_versions_and_requirements_are_enforced() {
+    let report: Value =
+        serde_json::from_slice(&compass(&["--porcelain", "tests/fixtures/test.rs"]).stdout)
+            .unwrap();
+    assert_eq!(report["grammar"]["name"], "tree-sitter-rust");
+    assert!(report["grammar"]["version"].is_string());
+
+    let report: Value = serde_json::from_slice(
+        &compass(&[
+            "--porcelain",
+            "--require-grammar",
+            "go>=0.20",
+            "tests/fixtures",
+        ])
+        .stdout,
+    )
+    .unwrap();
+    assert_eq!(report["grammars"]["go"]["name"], "tree-sitter-go");
+    assert_eq!(
+        report["files"][0]["grammar"],
+        report["grammars"][report["files"][0]["language"].as_str().unwrap()]
+    );
+
+    let output = compass(&["--require-grammar", "go>=999", "tests/fixtures/test.go"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: grammar requirement 'go>=999' not met: tree-sitter-go is"));
+
+    let config = std::env::temp_dir().join("compass_cli_test_require_grammar.toml");
+    std::fs::write(
+        &config,
+        "extends = [\"go\"]\nrequire_grammar = [\"go<0.1\"]\n",
+    )
+    .unwrap();
+    assert!(
+        !compass(&["tests/fixtures/test.go", config.to_str().unwrap()])
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn test_profiles_switch_rules_per_environment() {
+    let config = std::env::temp_dir().join("compass_cli_test_profiles.toml");
+    std::fs::write(&config, "extends = [\"go\"]\n\n[profile.local]\ndisable = [\"panic_usage\"]\n\n[profile.ci]\nseverity_overrides = { panic_usage = \"error\" }\n").unwrap();
+    let config = config.to_str().unwrap();
+    let severity = |profile: &str| -> Option<String> {
+        let report: Value = serde_json::from_slice(
+            &compass(&[
+                "--porcelain",
+                "--profile",
+                profile,
+                "tests/fixtures/test.go",
+                config,
+            ])
+            .stdout,
+        )
+        .unwrap();
+        report["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|issue| issue["rule"] == "panic_usage")
+            .map(|issue| issue["severity"].as_str().unwrap().to_string())
+    };
+    assert_eq!(severity("local"), None);
+    assert_eq!(severity("ci").as_deref(), Some("error"));
+
+    let output = compass(&["--profile", "strict", "tests/fixtures/test.go", config]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: unknown profile 'strict' (defined: ci, local)"));
+    assert!(!compass(&["--profile", "ci", "tests/fixtures/test.go"])
+        .status
+        .success());
+}
+
+#[test]
+fn test_test_rule_shows_captures_and_query_errors() {
+    let output = compass(&[
+        "test-rule",
+        "no_unwrap_prefer_error_handling",
+        "tests/fixtures/test.rs",
+        "--format",
+        "json",
+    ]);
+    assert!(output.status.success());
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["language"], "rust");
+    let captures = json["captures"].as_array().unwrap();
+    assert!(captures.iter().any(|capture| capture["capture"] == "call"
+        && capture["kind"] == "call_expression"
+        && capture["text"].as_str().unwrap().ends_with(".unwrap()")));
+    assert!(captures
+        .iter()
+        .any(|capture| capture["capture"] == "method" && capture["text"] == "unwrap"));
+
+    let text =
+        String::from_utf8(compass(&["test-rule", "panic_usage", "tests/fixtures/test.go"]).stdout)
+            .unwrap();
+    assert!(text.starts_with("panic_usage (go): query captured "));
+    assert!(text
+        .lines()
+        .any(|line| line.contains(" pattern 0 @") && line.trim_end().ends_with("panic")));
+
+    let config = std::env::temp_dir().join("compass_cli_test_test_rule.toml");
+    std::fs::write(&config, "[[rules]]\nname = \"draft\"\nquery = \"(call_expression function: (identifer) @fn)\"\nseverity = \"info\"\nmessage = \"m\"\n").unwrap();
+    let output = compass(&[
+        "test-rule",
+        "draft",
+        "tests/fixtures/test.rs",
+        config.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: rule 'draft' does not compile for rust: Query error at 1:29. Invalid node type identifer"));
+}
+
+#[test]
+fn test_ast_dumps_the_tree_with_query_captures() {
+    let output = compass(&[
+        "ast",
+        "tests/fixtures/test.go",
+        "--query",
+        "(call_expression function: (identifier) @fn (#eq? @fn \"panic\"))",
+    ]);
+    assert!(output.status.success());
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.starts_with("(source_file [1:1-"));
+    assert_eq!(text.lines().filter(|line| line.contains("@fn")).count(), 1);
+    assert!(text.lines().any(
+        |line| line.trim_start().starts_with("function: (identifier [")
+            && line.contains("\"panic\" @fn)")
+    ));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "1 match(es)\n");
+
+    let output = compass(&["ast", "tests/fixtures/test.go", "--at", "5:9"]);
+    assert!(String::from_utf8(output.stdout).unwrap().starts_with("; in source_file > import_declaration > import_spec > interpreted_string_literal\n(interpreted_string_literal_content [5:9-5:12] \"fmt\")"));
+
+    let output = compass(&[
+        "ast",
+        "tests/fixtures/test.go",
+        "--query",
+        "(no_such_node) @x",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Error: query does not compile for go: Query error at 1:2. Invalid node type no_such_node"
+    ));
+}
+
+#[test]
+fn test_check_config_runs_embedded_tests() {
+    let output = compass(&["check-config"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("ok    panic and defer in a loop (go)\n"));
+    assert!(text.ends_with(" passed, 0 failed\n"));
+
+    let config = std::env::temp_dir().join("compass_cli_test_check_config.toml");
+    std::fs::write(&config, "[[rules]]\nname = \"calls\"\nquery = \"(call_expression) @call\"\nseverity = \"info\"\nmessage = \"m\"\nenabled = true\n\n[[tests]]\nname = \"one call\"\nlanguage = \"javascript\"\ncode = \"f();\\ng();\\n\"\nexpect = [{ rule = \"calls\", line = 1 }]\n").unwrap();
+    let output = compass(&["check-config", config.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let outcomes: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(outcomes[0]["name"], "one call");
+    assert_eq!(outcomes[0]["missing"].as_array().unwrap().len(), 0);
+    assert_eq!(
+        outcomes[0]["unexpected"][0],
+        serde_json::json!({"rule": "calls", "line": 2})
+    );
+}
+
+#[test]
+fn test_fail_on_parse_error() {
+    let broken = std::env::temp_dir().join("compass_cli_test_broken.go");
+    std::fs::write(&broken, "package main\n\nfunc main() {\n\tbar(1,\n}\n").unwrap();
+    let path = broken.to_str().unwrap();
+
+    let output = compass(&["--porcelain", "--format", "text", path]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(":4:8: error [syntax_error] Syntax error"));
+
+    let output = compass(&[
+        "--porcelain",
+        "--format",
+        "text",
+        "--fail-on-parse-error",
+        path,
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("Parse error: {}:4:8: missing `)`", path)),
+        "{}",
+        stderr
+    );
+
+    let output = compass(&[
+        "--porcelain",
+        "--fail-on-parse-error",
+        "tests/fixtures/test.go",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_fix_prints_diffs_and_workspace_edits_and_writes_with_flag() {
+    let dir = std::env::temp_dir().join("compass_cli_test_fix");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("app.js");
+    std::fs::write(&file, "var a = 1;\nlet b = 2;\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let output = compass(&["fix", path]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let diff = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        diff,
+        format!(
+            "--- a/{0}\n+++ b/{0}\n@@ -1,2 +1,2 @@\n-var a = 1;\n+let a = 1;\n let b = 2;\n",
+            path
+        )
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "var a = 1;\nlet b = 2;\n"
+    );
+
+    let output = compass(&["fix", path, "--format", "workspace-edit"]);
+    let edit: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let change = &edit["documentChanges"][0];
+    assert!(change["textDocument"]["uri"]
+        .as_str()
+        .unwrap()
+        .starts_with("file:///"));
+    assert_eq!(change["edits"][0]["newText"], "let");
+    assert_eq!(change["edits"][0]["annotationId"], "var_keyword_usage");
+
+    let output = compass(&["fix", path, "--write"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "let a = 1;\nlet b = 2;\n"
+    );
+    let output = compass(&["fix", path]);
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Nothing to fix"));
+}
+
+#[test]
+fn test_fix_reports_conflicts_and_unstable_fixes_and_is_idempotent() {
+    let dir = std::env::temp_dir().join("compass_cli_test_fix_conflicts");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("app.js");
+    std::fs::write(&file, "var a = 1;\n").unwrap();
+    let config = dir.join("fixes.toml");
+    std::fs::write(&config, "[[rules]]\nname = \"to_let\"\nquery = '(variable_declaration \"var\" @keyword)'\nseverity = \"warning\"\nmessage = \"m\"\nfix = \"let\"\nenabled = true\n\n[[rules]]\nname = \"to_const\"\nquery = '(variable_declaration \"var\" @keyword)'\nseverity = \"warning\"\nmessage = \"m\"\nfix = \"const\"\nenabled = true\n\n[[rules]]\nname = \"wrap\"\nquery = \"(number) @number\"\nseverity = \"info\"\nmessage = \"m\"\nfix = \"(1)\"\nenabled = true\n").unwrap();
+    let (path, config) = (file.to_str().unwrap(), config.to_str().unwrap());
+
+    let output = compass(&["fix", path, config, "--write"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "Skipped fix of to_let at {}:1:1: overlaps a fix of to_const",
+            path
+        )),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(&format!(
+            "Skipped fix of wrap at {}:1:9: the rule flags its own replacement",
+            path
+        )),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("Applied 1 fix(es) to 1 file(s)")
+            && stderr.contains("Skipped 2 conflicting or unstable fix(es)")
+    );
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "const a = 1;\n");
+
+    let output = compass(&["fix", path, config, "--write"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Applied 0 fix(es) to 0 file(s)"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "const a = 1;\n");
+
+    // A fix that breaks the syntax is rolled back instead of written.
+    std::fs::write(config, "[[rules]]\nname = \"to_paren\"\nquery = '(lexical_declaration \"const\" @keyword)'\nseverity = \"warning\"\nmessage = \"m\"\nfix = \"(\"\nenabled = true\n").unwrap();
+    let output = compass(&["fix", path, config, "--write"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Skipped fix of to_paren at {}:1:1: rolled back, the fixed file has a syntax error at 1:", path)), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "const a = 1;\n");
+}
+
+#[test]
+fn test_column_encoding_counts_bytes_chars_or_utf16_units() {
+    // `é` is 2 bytes and 1 UTF-16 unit; `😀` is 4 bytes and 2 units.
+    for (encoding, column) in [("byte", 21), ("char", 17), ("utf-16", 18)] {
+        let output = compass(&[
+            "--format",
+            "text",
+            "--column-encoding",
+            encoding,
+            "tests/fixtures/multibyte.js",
+        ]);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let expected = format!(
+            "tests/fixtures/multibyte.js:1:{}: warning [var_keyword_usage]",
+            column
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stdout).contains(&expected),
+            "{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+
+    let sarif: Value = serde_json::from_slice(
+        &compass(&[
+            "--format",
+            "sarif",
+            "--column-encoding",
+            "byte",
+            "tests/fixtures/multibyte.js",
+        ])
+        .stdout,
+    )
+    .unwrap();
+    assert_eq!(sarif["runs"][0]["columnKind"], "utf16CodeUnits");
+    assert_eq!(
+        sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"]["startColumn"],
+        18
+    );
+
+    let rdjson: Value = serde_json::from_slice(
+        &compass(&["--format", "rdjson", "tests/fixtures/multibyte.js"]).stdout,
+    )
+    .unwrap();
+    assert_eq!(
+        rdjson["diagnostics"][0]["location"]["range"]["start"]["column"],
+        21
+    );
+
+    // Bytes by default; each --column-encoding goes with the --format before it.
+    let json = std::env::temp_dir().join("compass_cli_test_columns.json");
+    let output = compass(&[
+        "--format",
+        "text",
+        "--format",
+        "json",
+        "--output",
+        json.to_str().unwrap(),
+        "--column-encoding",
+        "utf-16",
+        "tests/fixtures/multibyte.js",
+    ]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("multibyte.js:1:21: warning"));
+    let report: Value = serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    assert_eq!(report["issues"][0]["column"], 18);
+    let output = compass(&[
+        "--column-encoding",
+        "char",
+        "--format",
+        "text",
+        "tests/fixtures/multibyte.js",
+    ]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("multibyte.js:1:17: warning"));
+    let output = compass(&[
+        "--format",
+        "text",
+        "--column-encoding",
+        "char",
+        "--column-encoding",
+        "byte",
+        "tests/fixtures/multibyte.js",
+    ]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("each --column-encoding must follow its own --format"));
+
+    let output = compass(&["--column-encoding", "utf-32", "tests/fixtures/multibyte.js"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("tip: a similar value exists: 'utf-16'")
+    );
+}
+
+#[test]
+fn test_timings_report_parse_and_rule_times() {
+    let output = compass(&["--porcelain", "--timings", "tests/fixtures/test.go"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let rules = report["timings"]["rules"].as_array().unwrap();
+    assert!(rules.iter().any(|rule| rule["rule"] == "panic_usage"
+        && rule["files"] == 1
+        && rule["compile_ms"].as_f64().unwrap() > 0.0));
+    assert!(rules
+        .windows(2)
+        .all(|pair| pair[0]["total_ms"].as_f64() >= pair[1]["total_ms"].as_f64()));
+    assert_eq!(
+        report["timings"]["files"][0]["path"],
+        "tests/fixtures/test.go"
+    );
+    assert!(report["timings"]["files"][0]["parse_ms"].is_number());
+
+    let output = compass(&[
+        "--porcelain",
+        "--timings",
+        "--format",
+        "text",
+        "tests/fixtures/test.go",
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Slowest rules:") && stderr.contains("panic_usage"),
+        "{}",
+        stderr
+    );
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Slowest"));
+
+    let output = compass(&["--porcelain", "tests/fixtures/test.go"]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.get("timings").is_none());
+}
+
+#[test]
+fn test_directory_runs_reuse_cached_results_until_cleared() {
+    let root = std::env::temp_dir().join("compass_cli_test_cache");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::copy("tests/fixtures/test.go", root.join("src/test.go")).unwrap();
+    let src = root.join("src");
+    let cache = root.join("cache");
+    let (src, cache) = (src.to_str().unwrap(), cache.to_str().unwrap());
+
+    let first = compass(&[src, "--cache-dir", cache]);
+    assert!(first.status.success());
+    let entries: Vec<_> = std::fs::read_dir(cache)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+
+    // A hit is served from the entry without re-analyzing the file.
+    let entry = std::fs::read_to_string(&entries[0]).unwrap();
+    let mut cached: Value = serde_json::from_str(&entry).unwrap();
+    cached["results"][0]["message"] = Value::from("served from the cache");
+    std::fs::write(&entries[0], cached.to_string()).unwrap();
+    let second = String::from_utf8(compass(&[src, "--cache-dir", cache]).stdout).unwrap();
+    assert!(second.contains("served from the cache"));
+    let uncached =
+        String::from_utf8(compass(&[src, "--cache-dir", cache, "--no-cache"]).stdout).unwrap();
+    assert_eq!(uncached, String::from_utf8(first.stdout).unwrap());
+
+    let cleared = compass(&["cache", "clear", "--cache-dir", cache]);
+    assert!(cleared.status.success());
+    assert!(String::from_utf8(cleared.stdout)
+        .unwrap()
+        .starts_with("Removed 1 cached file result(s)"));
+    assert!(!std::path::Path::new(cache).exists());
+}
+
+#[test]
+fn test_one_run_writes_every_format_to_its_output() {
+    let dir = std::env::temp_dir().join("compass_cli_test_outputs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let json = dir.join("compass.json");
+    let sarif = dir.join("compass.sarif");
+    let output = compass(&[
+        "--porcelain",
+        "tests/fixtures/test.go",
+        "--format",
+        "json",
+        "--output",
+        json.to_str().unwrap(),
+        "--format",
+        "sarif",
+        "--output",
+        sarif.to_str().unwrap(),
+        "--format",
+        "github",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: Value = serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+    let log: Value = serde_json::from_str(&std::fs::read_to_string(&sarif).unwrap()).unwrap();
+    assert!(report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|issue| issue["rule"] == "panic_usage"));
+    assert!(log["runs"][0]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|result| result["ruleId"] == "panic_usage"));
+    let annotations = String::from_utf8(output.stdout).unwrap();
+    assert!(annotations.lines().any(|line| line
+        .starts_with("::warning file=tests/fixtures/test.go,line=17,")
+        && line.ends_with(",title=panic_usage::Use of panic()")));
+
+    let output = compass(&[
+        "tests/fixtures/test.go",
+        "--format",
+        "json",
+        "--format",
+        "github",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only one --format can go to stdout"));
+}
+
+#[test]
+fn test_discovered_config_needs_opt_in_to_load_grammar_libraries() {
+    let root = std::env::temp_dir().join("compass_cli_test_project_grammars");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::copy("tests/fixtures/test.go", root.join("test.go")).unwrap();
+    std::fs::write(
+        root.join("compass.toml"),
+        "extends = [\"go\"]\n\n[languages.elixir]\nlibrary = \"grammars/elixir.so\"\nextensions = [\".ex\"]\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        command()
+            .current_dir(&root)
+            .env_remove("COMPASS_CONFIG_TOML")
+            .env_remove("COMPASS_TRUST_PROJECT_GRAMMARS")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["test.go"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("loads grammar libraries") && stderr.contains("--trust-project-grammars"),
+        "{}",
+        stderr
+    );
+
+    // Trusted, the library is loaded, and fails only because it's missing
+    let output = run(&["test.go", "--trust-project-grammars"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("loads grammar libraries"), "{}", stderr);
+    assert!(stderr.contains("elixir.so"), "{}", stderr);
+
+    // A config passed explicitly is trusted as it is
+    let config = root.join("compass.toml");
+    let output = run(&["test.go", config.to_str().unwrap()]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("loads grammar libraries"));
+}
+
+#[test]
+fn test_project_config_layers_over_user_config() {
+    let root = std::env::temp_dir().join("compass_cli_test_layers");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::copy("tests/fixtures/test.go", root.join("src/test.go")).unwrap();
+    std::fs::write(root.join("user.toml"), "extends = [\"go\"]\n\n[scoring]\nrepeat_damping = \"none\"\nweight_by_confidence = true\n\n[severity_overrides]\npanic_usage = \"error\"\n").unwrap();
+    std::fs::write(
+        root.join("compass.toml"),
+        "exclude = [\"vendor/**\"]\n\n[scoring]\nrepeat_damping = \"logarithmic\"\n",
+    )
+    .unwrap();
+    let user = root.join("user.toml");
+    let run = |user: &std::path::Path, args: &[&str]| {
+        command()
+            .current_dir(root.join("src"))
+            .env("COMPASS_USER_CONFIG", user)
+            .env_remove("COMPASS_CONFIG_TOML")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let layers = String::from_utf8(run(&user, &["config", "show"]).stdout).unwrap();
+    assert!(
+        layers.contains(&format!("user      {}", user.display())),
+        "{}",
+        layers
+    );
+    assert!(
+        layers.contains(&format!(
+            "project   {}",
+            root.join("compass.toml").display()
+        )),
+        "{}",
+        layers
+    );
+    let resolved = String::from_utf8(run(&user, &["config", "show", "--resolved"]).stdout).unwrap();
+    assert!(
+        resolved.contains("repeat_damping = \"logarithmic\"")
+            && resolved.contains("weight_by_confidence = true"),
+        "{}",
+        resolved
+    );
+    assert!(resolved.contains("panic_usage = \"error\"") && resolved.contains("\"vendor/**\""));
+
+    let output = run(&user, &["test.go"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|issue| issue["rule"] == "panic_usage" && issue["severity"] == "error"));
+
+    let layers =
+        String::from_utf8(run(std::path::Path::new(""), &["config", "show"]).stdout).unwrap();
+    assert!(layers.contains("user      (none)"), "{}", layers);
+
+    // A bad value is reported where it is, though a higher layer overrides it.
+    std::fs::write(
+        &user,
+        "extends = [\"go\"]\n\n[scoring]\nrepeat_damping = \"linear\"\n",
+    )
+    .unwrap();
+    let stderr = String::from_utf8(run(&user, &["test.go"]).stderr).unwrap();
+    assert!(
+        stderr.contains(&user.display().to_string()) && stderr.contains("line 4"),
+        "{}",
+        stderr
+    );
+
+    let schema: Value = serde_json::from_slice(&compass(&["config", "schema"]).stdout).unwrap();
+    assert_eq!(schema["title"], "compass.toml");
+    assert_eq!(
+        schema["properties"]["rules"]["items"]["$ref"],
+        "#/$defs/RuleConfig"
+    );
+}
+
+#[test]
+fn test_ci_services_pick_the_default_format_and_color_follows_the_flag() {
+    let github = command()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["tests/fixtures/test.go"])
+        .output()
+        .unwrap();
+    assert!(github.status.success());
+    assert!(String::from_utf8(github.stdout)
+        .unwrap()
+        .lines()
+        .all(|line| line.starts_with("::")));
+    assert!(String::from_utf8_lossy(&github.stderr)
+        .contains("Detected GitHub Actions: defaulting to --format github"));
+
+    let gitlab = command()
+        .env("GITLAB_CI", "true")
+        .args(["--porcelain", "tests/fixtures/test.go"])
+        .output()
+        .unwrap();
+    let issues: Value = serde_json::from_slice(&gitlab.stdout).unwrap();
+    assert!(issues.as_array().unwrap()[0]["fingerprint"].is_string());
+    assert!(gitlab.stderr.is_empty());
+
+    let explicit = command()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--format", "json", "tests/fixtures/test.go"])
+        .output()
+        .unwrap();
+    assert!(serde_json::from_slice::<Value>(&explicit.stdout).unwrap()["score"].is_number());
+
+    let colored = String::from_utf8(
+        compass(&[
+            "--porcelain",
+            "--format",
+            "text",
+            "--color",
+            "always",
+            "tests/fixtures/test.go",
+        ])
+        .stdout,
+    )
+    .unwrap();
+    assert!(colored.contains("\x1b[33mwarning\x1b[0m"), "{}", colored);
+    let plain = String::from_utf8(
+        compass(&["--porcelain", "--format", "text", "tests/fixtures/test.go"]).stdout,
+    )
+    .unwrap();
+    assert!(!plain.contains('\x1b'));
+    assert!(
+        !compass(&["--color", "sometimes", "tests/fixtures/test.go"])
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn test_presets_narrow_or_tighten_the_built_in_rules() {
+    let issues = |preset: &str| {
+        let output = compass(&["--porcelain", "--preset", preset, "tests/fixtures/test.go"]);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+        report["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|issue| {
+                (
+                    issue["rule"].as_str().unwrap().to_string(),
+                    issue["severity"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let minimal = issues("minimal");
+    let strict = issues("strict");
+    assert!(minimal
+        .iter()
+        .all(|(rule, _)| rule == "missing_error_check"));
+    assert!(strict.iter().any(|(rule, _)| rule == "panic_usage"));
+    assert!(strict.contains(&("missing_error_check".to_string(), "error".to_string())));
+
+    let unknown = compass(&["--preset", "lenient", "tests/fixtures/test.go"]);
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr)
+        .contains("unknown preset 'lenient' (defined: minimal, recommended, security, strict"));
+}
+
+#[test]
+fn test_comment_rules_date_markers_with_git_blame() {
+    let root = std::env::temp_dir().join("compass_cli_test_comment_age");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .current_dir(&root)
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", "2020-01-01T00:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2020-01-01T00:00:00Z")
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    std::fs::write(
+        root.join("old.rs"),
+        "// TODO: handle errors\nfn main() {}\n",
+    )
+    .unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "old.rs"]);
+    git(&["commit", "-q", "-m", "old"]);
+    std::fs::write(
+        root.join("old.rs"),
+        "// TODO: handle errors\nfn main() {}\n// FIXME: added today\n",
+    )
+    .unwrap();
+    std::fs::write(root.join("new.rs"), "/* hack: untracked */\nfn run() {}\n").unwrap();
+    let config = root.join("comments.toml");
+    std::fs::write(&config, "[[rules]]\nname = \"stale_marker\"\ncomment = '(?i)\\b(TODO|FIXME|HACK)\\b'\nolder_than = \"30d\"\nseverity = \"info\"\nmessage = \"Stale marker\"\nenabled = true\n").unwrap();
+
+    let output = compass(&[
+        "--porcelain",
+        root.to_str().unwrap(),
+        config.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = report["files"].as_array().unwrap();
+    let old = files
+        .iter()
+        .find(|file| file["path"].as_str().unwrap().ends_with("old.rs"))
+        .unwrap();
+    assert_eq!(old["issues"].as_array().unwrap().len(), 1);
+    assert_eq!(old["issues"][0]["line"], 1);
+    assert_eq!(old["issues"][0]["labels"]["marker"], "TODO");
+    assert!(
+        old["issues"][0]["labels"]["age_days"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap()
+            > 1000
+    );
+    let new = files
+        .iter()
+        .find(|file| file["path"].as_str().unwrap().ends_with("new.rs"))
+        .unwrap();
+    assert_eq!(new["issues"][0]["labels"]["marker"], "hack");
+    assert!(new["issues"][0]["labels"].get("age_days").is_none());
+    assert_eq!(
+        report["summary"]["comment_markers"],
+        serde_json::json!({ "HACK": 1, "TODO": 1 })
+    );
+
+    // The library dates findings the same way
+    let options = compass::AnalyzeOptions::new()
+        .with_config_file(&config)
+        .unwrap();
+    let report = compass::analyze_path(root.join("old.rs"), &options).unwrap();
+    let lines: Vec<_> = report.files[0]
+        .results
+        .iter()
+        .map(|result| result.line)
+        .collect();
+    assert_eq!(lines, [1]);
+    assert!(report.files[0].results[0].labels.contains_key("age_days"));
+
+    let invalid = root.join("invalid.toml");
+    std::fs::write(&invalid, "[[rules]]\nname = \"stale_marker\"\ncomment = 'TODO'\nolder_than = \"soon\"\nseverity = \"info\"\nmessage = \"m\"\nenabled = true\n").unwrap();
+    let output = compass(&[
+        "--porcelain",
+        root.join("old.rs").to_str().unwrap(),
+        invalid.to_str().unwrap(),
+    ]);
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("rule 'stale_marker': invalid older_than 'soon'"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_metrics_report_maintainability_index_per_file() {
+    let output = compass(&["--porcelain", "--metrics", "tests/fixtures"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let metrics = &report["metrics"];
+    let files = metrics["files"].as_array().unwrap();
+    assert_eq!(files.len(), report["files"].as_array().unwrap().len());
+    assert!(files
+        .iter()
+        .all(|file| file["halstead"]["volume"].as_f64().unwrap() > 0.0
+            && file["complexity"].as_u64().unwrap() >= 1));
+    assert!(files
+        .windows(2)
+        .all(|pair| pair[0]["maintainability_index"].as_f64()
+            <= pair[1]["maintainability_index"].as_f64()));
+    let index = metrics["maintainability_index"].as_f64().unwrap();
+    assert!(index > 0.0 && index <= 100.0, "{}", index);
+    assert_eq!(
+        metrics["code_lines"],
+        files
+            .iter()
+            .map(|file| file["code_lines"].as_u64().unwrap())
+            .sum::<u64>()
+    );
+
+    let output = compass(&[
+        "--porcelain",
+        "--metrics",
+        "--format",
+        "text",
+        "tests/fixtures/test.go",
+    ]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Maintainability:") && stderr.contains("tests/fixtures/test.go"),
+        "{}",
+        stderr
+    );
+
+    let output = compass(&["--porcelain", "tests/fixtures/test.go"]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report.get("metrics").is_none());
+}
+
+#[test]
+fn test_badge_renders_project_score_as_svg_or_shields_json() {
+    let svg = std::env::temp_dir().join("compass_cli_test_badge.svg");
+    let output = compass(&[
+        "badge",
+        "--porcelain",
+        "tests/fixtures",
+        "--output",
+        svg.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty());
+    let report: Value =
+        serde_json::from_slice(&compass(&["--porcelain", "tests/fixtures"]).stdout).unwrap();
+    let message = format!(
+        "{:.1} {}",
+        report["summary"]["average_score"].as_f64().unwrap(),
+        report["summary"]["rating"].as_str().unwrap()
+    );
+    let badge = std::fs::read_to_string(&svg).unwrap();
+    assert!(
+        badge.starts_with("<svg") && badge.contains(&format!(">{}</text>", message)),
+        "{}",
+        badge
+    );
+    let _ = std::fs::remove_file(&svg);
+
+    let output = compass(&[
+        "badge",
+        "--porcelain",
+        "--format",
+        "shields",
+        "tests/fixtures",
+    ]);
+    let endpoint: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(endpoint["schemaVersion"], 1);
+    assert_eq!(endpoint["message"], message.as_str());
+    assert!(endpoint["color"].is_string());
+}
+
+#[test]
+fn test_hook_gates_commits_on_staged_content() {
+    let root = std::env::temp_dir().join("compass_cli_test_hook");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(&root)
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let hook = |args: &[&str]| {
+        command()
+            .current_dir(&root)
+            .arg("hook")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    assert!(git(&["init", "-q"]).status.success());
+
+    let installed = hook(&["install", "--min-score", "9"]);
+    assert!(
+        installed.status.success(),
+        "{}",
+        String::from_utf8_lossy(&installed.stderr)
+    );
+    let script = std::fs::read_to_string(root.join(".git/hooks/pre-commit")).unwrap();
+    assert!(script.ends_with(" hook run --min-score 9\n"), "{}", script);
+
+    // The index holds the unwraps; the working tree is clean.
+    std::fs::write(
+        root.join("main.rs"),
+        "fn main() {\n    let a = Some(1).unwrap();\n    let b = Some(2).unwrap();\n}\n",
+    )
+    .unwrap();
+    assert!(git(&["add", "main.rs"]).status.success());
+    std::fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+    let blocked = git(&["commit", "-q", "-m", "unwraps"]);
+    assert!(!blocked.status.success());
+    let stderr = String::from_utf8_lossy(&blocked.stderr);
+    assert!(
+        stderr.contains("main.rs:2:") && stderr.contains("compass: commit blocked: score"),
+        "{}",
+        stderr
+    );
+
+    assert!(git(&["add", "main.rs"]).status.success());
+    let passed = hook(&["run", "--min-score", "9"]);
+    assert!(
+        passed.status.success(),
+        "{}",
+        String::from_utf8_lossy(&passed.stderr)
+    );
+    assert!(git(&["commit", "-q", "-m", "clean"]).status.success());
+
+    std::fs::write(root.join(".git/hooks/pre-commit"), "#!/bin/sh\nexit 0\n").unwrap();
+    let refused = hook(&["install"]);
+    assert!(String::from_utf8_lossy(&refused.stderr)
+        .contains("was not installed by compass; pass --force"));
+    assert!(hook(&["install", "--force"]).status.success());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_jsonl_streams_findings_and_files_then_a_summary() {
+    let output = compass(&["--porcelain", "--format", "jsonl", "tests/fixtures"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let lines: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is one JSON object"))
+        .collect();
+    let report: Value =
+        serde_json::from_slice(&compass(&["--porcelain", "tests/fixtures"]).stdout).unwrap();
+
+    let of_type = |kind: &str| {
+        lines
+            .iter()
+            .filter(|line| line["type"] == kind)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        of_type("finding").len() as u64,
+        report["summary"]["total_issues"].as_u64().unwrap()
+    );
+    assert_eq!(
+        of_type("file").len(),
+        report["files"].as_array().unwrap().len()
+    );
+    assert!(of_type("file")
+        .iter()
+        .all(|file| file.get("issues").is_none() && file["score"].is_number()));
+    // A file's findings come right before its file line.
+    let first_file = lines
+        .iter()
+        .position(|line| line["type"] == "file")
+        .unwrap();
+    assert!(lines[..first_file]
+        .iter()
+        .all(|line| line["type"] == "finding"
+            && line["path"] == lines[first_file]["path"]
+            && line["rule"].is_string()));
+
+    let summary = lines.last().unwrap();
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["average_score"], report["summary"]["average_score"]);
+    assert_eq!(of_type("summary").len(), 1);
+    // Totals kept while streaming match those of the collected files.
+    for (key, value) in report["summary"].as_object().unwrap() {
+        assert_eq!(&summary[key], value, "summary.{}", key);
+    }
+}
+
+#[test]
+fn test_progress_bar_stays_off_stderr_when_it_is_not_a_terminal() {
+    let piped = compass(&["--format", "text", "tests/fixtures"]);
+    let stderr = String::from_utf8_lossy(&piped.stderr);
+    assert!(
+        stderr.contains("Analyzing") && !stderr.contains('\r'),
+        "{}",
+        stderr
+    );
+
+    let quiet = compass(&["--quiet", "--format", "text", "tests/fixtures"]);
+    assert_eq!(quiet.stdout, piped.stdout);
+}
+
+#[test]
+fn test_timeouts_take_durations_and_leave_fast_runs_alone() {
+    let bounded = compass(&[
+        "--porcelain",
+        "--timeout",
+        "30s",
+        "--total-timeout",
+        "10m",
+        "tests/fixtures",
+    ]);
+    assert!(
+        bounded.status.success(),
+        "{}",
+        String::from_utf8_lossy(&bounded.stderr)
+    );
+    let report: Value = serde_json::from_slice(&bounded.stdout).unwrap();
+    assert!(report["summary"].get("timed_out").is_none());
+    assert_eq!(
+        report,
+        serde_json::from_slice::<Value>(&compass(&["--porcelain", "tests/fixtures"]).stdout)
+            .unwrap()
+    );
+
+    let invalid = compass(&["--timeout", "30", "tests/fixtures"]);
+    assert!(!invalid.status.success());
+    assert!(String::from_utf8_lossy(&invalid.stderr).contains("invalid value '30' for '--timeout <DURATION>': expected a duration such as 30s, 5m, 12h or 7d"));
+}
+
+#[test]
+fn test_verbose_logs_go_to_stderr_as_text_or_json() {
+    let quiet = compass(&["--porcelain", "tests/fixtures"]);
+    let verbose = compass(&["-v", "--porcelain", "tests/fixtures"]);
+    assert!(
+        verbose.status.success(),
+        "{}",
+        String::from_utf8_lossy(&verbose.stderr)
+    );
+    assert_eq!(verbose.stdout, quiet.stdout);
+    let stderr = String::from_utf8_lossy(&verbose.stderr);
+    assert!(
+        stderr.contains("loaded config")
+            && stderr.contains("analyzed files")
+            && !stderr.contains("ran rule"),
+        "{}",
+        stderr
+    );
+    assert!(String::from_utf8_lossy(&quiet.stderr).is_empty());
+
+    let debug = compass(&[
+        "-vv",
+        "--log-format",
+        "json",
+        "--porcelain",
+        "tests/fixtures/test.go",
+    ]);
+    let events: Vec<Value> = String::from_utf8_lossy(&debug.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each log line is JSON"))
+        .collect();
+    let rule = events
+        .iter()
+        .find(|event| event["fields"]["message"] == "ran rule")
+        .expect("-vv logs each rule");
+    assert_eq!(rule["level"], "DEBUG");
+    assert_eq!(rule["span"]["path"], "tests/fixtures/test.go");
+    assert!(rule["fields"]["match_ms"].is_number());
+
+    let unknown = compass(&["--log-format", "xml", "tests/fixtures"]);
+    assert!(String::from_utf8_lossy(&unknown.stderr)
+        .contains("invalid value 'xml' for '--log-format <LOG_FORMAT>'"));
+}
+
+#[test]
+fn test_subcommands_have_help_aliases_and_typo_suggestions() {
+    let help = compass(&["--help"]);
+    assert!(help.status.success());
+    let help = String::from_utf8_lossy(&help.stdout);
+    assert!(
+        help.contains("Usage: compass [OPTIONS] [SOURCE] [CONFIG]")
+            && help.contains("Run the [[tests]] of a config [aliases: validate]")
+            && help.contains("Supported extensions:"),
+        "{}",
+        help
+    );
+    let rules_help = String::from_utf8_lossy(&compass(&["rules", "--help"]).stdout).to_string();
+    assert!(
+        rules_help.contains("Usage: compass rules [OPTIONS] [CONFIG]"),
+        "{}",
+        rules_help
+    );
+
+    let validate = compass(&["validate"]);
+    assert!(validate.status.success());
+    assert_eq!(validate.stdout, compass(&["check-config"]).stdout);
+    let analyze = compass(&["analyze", "--porcelain", "tests/fixtures/test.go"]);
+    assert!(analyze.status.success());
+    assert_eq!(
+        analyze.stdout,
+        compass(&["--porcelain", "tests/fixtures/test.go"]).stdout
+    );
+
+    let flag = compass(&["--formt", "json", "tests/fixtures/test.go"]);
+    assert_eq!(flag.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&flag.stderr)
+        .contains("tip: a similar argument exists: '--format'"));
+    let subcommand = compass(&["chek-config"]);
+    assert_eq!(subcommand.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&subcommand.stderr)
+        .contains("tip: a similar subcommand exists: 'check-config'"));
+    let format = compass(&["--format", "sarf", "tests/fixtures/test.go"]);
+    assert!(
+        String::from_utf8_lossy(&format.stderr).contains("tip: a similar value exists: 'sarif'")
+    );
+}
+
+#[test]
+fn test_grade_similarity_report_flags_renamed_copies() {
+    let root = std::env::temp_dir().join("compass_cli_test_similarity");
+    let submissions = root.join("submissions");
+    let report = root.join("similarity.json");
+    let _ = std::fs::remove_dir_all(&root);
+    let programs = [
+        ("alice", "package main\n\nfunc sum(values []int) int {\n\ttotal := 0\n\tfor _, v := range values {\n\t\tif v > 0 {\n\t\t\ttotal += v\n\t\t}\n\t}\n\treturn total\n}\n"),
+        ("bob", "package main\n\nfunc add(xs []int) int {\n\tacc := 0\n\tfor _, x := range xs {\n\t\tif x > 0 {\n\t\t\tacc += x\n\t\t}\n\t}\n\treturn acc\n}\n"),
+        ("carol", "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tnames := map[string]int{\"a\": 1}\n\tfor name := range names {\n\t\tfmt.Println(name, len(name))\n\t}\n}\n"),
+    ];
+    for (student, source) in programs {
+        std::fs::create_dir_all(submissions.join(student)).unwrap();
+        std::fs::write(submissions.join(student).join("main.go"), source).unwrap();
+    }
+
+    let output = compass(&[
+        "grade",
+        "--submissions",
+        submissions.to_str().unwrap(),
+        "--similarity-report",
+        report.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_str(&std::fs::read_to_string(&report).unwrap()).unwrap();
+    assert_eq!(report["threshold"], 0.8);
+    let pairs = report["pairs"].as_array().unwrap();
+    assert_eq!(pairs.len(), 1);
+    assert_eq!(
+        (pairs[0]["first"].as_str(), pairs[0]["second"].as_str()),
+        (Some("alice"), Some("bob"))
+    );
+    assert_eq!(pairs[0]["similarity"], 1.0);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_cargo_compass_reads_sources_that_are_not_utf8() {
+    let root = std::env::temp_dir().join("compass_cli_test_cargo_latin1");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"latin1\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        b"// caf\xe9\npub fn answer() -> u32 {\n    42\n}\n",
+    )
+    .unwrap();
+
+    let manifest = root.join("Cargo.toml");
+    let output = cargo_compass(&["--manifest-path", manifest.to_str().unwrap()]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files_analyzed"], 1);
+    assert_eq!(report["crates"][0]["name"], "latin1");
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_cargo_compass_scores_each_crate_of_a_workspace() {
+    let root = std::env::temp_dir().join("compass_cli_test_cargo_workspace");
+    let _ = std::fs::remove_dir_all(&root);
+    for member in ["app", "util"] {
+        std::fs::create_dir_all(root.join(member).join("src")).unwrap();
+        std::fs::write(
+            root.join(member).join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+                member
+            ),
+        )
+        .unwrap();
+    }
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"app\", \"util\"]\nresolver = \"2\"\n",
+    )
+    .unwrap();
+    std::fs::copy("tests/fixtures/test.rs", root.join("app/src/main.rs")).unwrap();
+    std::fs::write(
+        root.join("util/src/lib.rs"),
+        "pub fn add(a: u32, b: u32) -> u32 {\n    a + b\n}\n",
+    )
+    .unwrap();
+    let manifest = root.join("Cargo.toml");
+
+    let output = cargo_compass(&["--manifest-path", manifest.to_str().unwrap(), "--files"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files_analyzed"], 2);
+    let crates: Vec<(&str, &str, u64)> = report["crates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|krate| {
+            (
+                krate["name"].as_str().unwrap(),
+                krate["files"][0]["path"].as_str().unwrap(),
+                krate["total_issues"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(crates.len(), 2);
+    assert_eq!((crates[0].0, crates[0].1), ("app", "app/src/main.rs"));
+    assert!(crates[0].2 > 0);
+    assert_eq!(crates[1], ("util", "util/src/lib.rs", 0));
+
+    // A missing value is an error rather than cargo's default workspace
+    let output = cargo_compass(&["--manifest-path"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("a value is required for '--manifest-path <PATH>'"));
+    let _ = std::fs::remove_dir_all(&root);
+}
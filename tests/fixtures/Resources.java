@@ -0,0 +1,47 @@
+// Java fixture for the resource and exception hygiene checks
+
+import java.io.*;
+import java.sql.*;
+
+public class Resources {
+    String firstLine(String path) throws IOException {
+        try (BufferedReader reader = new BufferedReader(new FileReader(path))) {
+            return reader.readLine();
+        }
+    }
+
+    String leakyFirstLine(String path) throws IOException {
+        BufferedReader reader = new BufferedReader(new FileReader(path));
+        return reader.readLine();
+    }
+
+    void query(String url) throws SQLException {
+        Connection connection = DriverManager.getConnection(url);
+        connection.close();
+    }
+
+    void ignore(Runnable task) {
+        try {
+            task.run();
+        } catch (IllegalStateException e) {
+        }
+
+        try {
+            task.run();
+        } catch (IllegalArgumentException e) {
+            // Retrying is pointless; the caller already logged the input.
+        }
+
+        try {
+            task.run();
+        } catch (Throwable t) {
+            throw new RuntimeException(t);
+        }
+    }
+
+    long countLines(String text, java.nio.file.Path path) throws IOException {
+        java.util.stream.Stream<String> words = text.lines();
+        java.util.stream.Stream<String> lines = java.nio.file.Files.lines(path);
+        return words.count() + lines.count();
+    }
+}
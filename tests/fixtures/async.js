@@ -0,0 +1,32 @@
+// JavaScript fixture for the async correctness checks
+
+async function loadUser(id) {
+  const response = await fetch(`/users/${id}`);
+  return response.json();
+}
+
+const saveUser = async (user) => {
+  await fetch("/users", { method: "POST", body: JSON.stringify(user) });
+};
+
+async function syncAll(ids) {
+  saveUser({ id: 0 });
+  await saveUser({ id: 1 });
+  const pending = saveUser({ id: 2 });
+
+  for (const id of ids) {
+    await loadUser(id);
+  }
+  for await (const user of ids.map(loadUser)) {
+    console.info(user);
+  }
+
+  await Promise.all(ids.map(async (id) => await loadUser(id)));
+  return pending;
+}
+
+function refresh() {
+  loadUser(1).then((user) => console.info(user));
+  loadUser(2).then((user) => console.info(user)).catch(console.error);
+  loadUser(3).then(console.info, console.error);
+}
@@ -0,0 +1,40 @@
+// Go fixture for the structural concurrency checks
+
+package main
+
+import (
+	"context"
+	"os"
+)
+
+func fetch(id int, ctx context.Context) error {
+	return ctx.Err()
+}
+
+func fetchAll(ctx context.Context, ids []int) {
+	for _, id := range ids {
+		go func() {
+			fetch(id, ctx)
+		}()
+	}
+
+	for _, id := range ids {
+		go func(id int) {
+			fetch(id, ctx)
+		}(id)
+	}
+}
+
+func closeAll(paths []string) {
+	for _, path := range paths {
+		f, _ := os.Open(path)
+		defer f.Close()
+	}
+
+	for _, path := range paths {
+		func() {
+			f, _ := os.Open(path)
+			defer f.Close()
+		}()
+	}
+}
@@ -0,0 +1,53 @@
+#include <memory>
+
+class Shape {
+public:
+    virtual ~Shape() = default;
+    virtual double area() const = 0;
+    virtual void draw() const;
+};
+
+class Circle : public Shape {
+public:
+    double area() const override;
+    void draw() const;
+};
+
+class Square final : public Shape {
+public:
+    double area() const final;
+    virtual void draw() const;
+};
+
+class Buffer {
+public:
+    Buffer(const Buffer& other);
+    ~Buffer();
+
+private:
+    int* data_;
+};
+
+class Handle {
+public:
+    Handle(const Handle&) = delete;
+    Handle& operator=(const Handle&) = delete;
+    Handle(Handle&& other) noexcept;
+    ~Handle();
+};
+
+class Owner {
+public:
+    ~Owner();
+    Owner(const Owner& other);
+    Owner& operator=(const Owner& other);
+};
+
+void build() {
+    auto circle = std::unique_ptr<Shape>(new Circle());
+    std::shared_ptr<Shape> square(new Square());
+    std::unique_ptr<int> value;
+    value.reset(new int(3));
+    Shape* leaked = new Circle();
+    int* numbers = new int[4];
+}
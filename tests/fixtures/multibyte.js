@@ -0,0 +1 @@
+const s = "é😀"; var y = 2;
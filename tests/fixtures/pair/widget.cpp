@@ -0,0 +1,14 @@
+#include "widget.h"
+namespace {
+int helper() { return 1; }
+}
+static int also_private() { return 2; }
+namespace ui {
+int area(int w, int h) { return w * h; }
+Widget::Widget() {}
+Widget::~Widget() {}
+int Widget::size() const { return helper(); }
+}
+ui::Widget* ui::Widget::make() { return nullptr; }
+int stray(int x) { return x; }
+int main() { return 0; }
@@ -0,0 +1,19 @@
+#pragma once
+namespace ui {
+int area(int w, int h);
+void unused();
+inline int twice(int x) { return 2 * x; }
+class Widget {
+public:
+    Widget();
+    ~Widget();
+    Widget(const Widget&) = delete;
+    virtual void draw() = 0;
+    int size() const;
+    static Widget* make();
+    int inline_size() const { return 1; }
+    friend bool operator==(const Widget&, const Widget&);
+    void missing();
+};
+template <typename T> T clamp(T v);
+}
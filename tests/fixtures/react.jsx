@@ -0,0 +1,21 @@
+import { useCallback } from "react";
+
+function List({ items, onPick, html }) {
+  const pick = useCallback((item) => onPick(item), [onPick]);
+  return (
+    <ul>
+      {items.map((item) => <li onClick={() => onPick(item)}>{item.name}</li>)}
+      {items.map((item) => (
+        <Row key={item.id} item={item} onHover={handle.bind(this)} />
+      ))}
+      {items.map(function (item) {
+        const render = () => <span>{item.name}</span>;
+        return <>{render()}</>;
+      })}
+      <button onClick={pick} title={() => "not a handler"}>Pick</button>
+      <div dangerouslySetInnerHTML={{ __html: html }} />
+    </ul>
+  );
+}
+
+export default List;
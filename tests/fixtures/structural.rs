@@ -0,0 +1,35 @@
+// Rust fixture for the structural checks
+
+use std::fs;
+
+fn save(path: &str, data: &str) -> std::io::Result<()> {
+    fs::write(path, data)
+}
+
+fn describe(name: &String) -> String {
+    format!("item {}", name)
+}
+
+fn store_all(names: &[String]) -> Result<(), String> {
+    save("all.txt", "data");
+    let _ = save("backup.txt", "data");
+    save("checked.txt", "data").map_err(|e| e.to_string())?;
+    describe(&names[0]);
+
+    let mut copies = Vec::new();
+    for name in names {
+        copies.push(name.clone());
+    }
+    let first = names[0].clone();
+    copies.push(first);
+    Ok(())
+}
+
+fn raw(bytes: &[u8]) -> u8 {
+    let first = unsafe { *bytes.as_ptr() };
+
+    // SAFETY: `bytes` is non-empty, checked by the caller.
+    let second = unsafe { *bytes.as_ptr().add(1) };
+
+    first + second
+}
@@ -0,0 +1,28 @@
+const std = @import("std");
+
+const ParseError = error{InvalidInput};
+
+fn parse(input: []const u8) ParseError!u32 {
+    if (input.len == 0) return error.InvalidInput;
+    return 42;
+}
+
+fn load(path: []const u8) !void {
+    _ = path;
+}
+
+pub fn main() void {
+    const value = parse("12") catch unreachable;
+    std.debug.print("{}\n", .{value});
+
+    _ = parse("") catch {};
+
+    load("config.json") catch |err| {
+        std.debug.print("failed: {}\n", .{err});
+        @panic("cannot continue");
+    };
+
+    const maybe = std.os.getenv("HOME");
+    if (maybe == null) unreachable;
+    _ = load("data.bin");
+}